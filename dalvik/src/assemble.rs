@@ -0,0 +1,1412 @@
+//! Smali-style text assembler
+//!
+//! [`assemble`] accepts one instruction per line, in the syntax printed by
+//! [`Instruction`]'s [`Display`][`std::fmt::Display`] or by
+//! [`PrettyPrint`][`crate::PrettyPrint`], plus:
+//!  - labels on their own line (`:cond_0`), usable wherever a branch offset is
+//!    expected
+//!  - the `.packed-switch`, `.sparse-switch` and `.array-data` payload
+//!    directives, as written by smali
+//!  - the `.registers`, `.catch` and `.catchall` directives, which only
+//!    [`assemble_method`] returns
+//!  - `#` comments
+//!
+//! Symbolic references such as `Ljava/lang/Object;-><init>()V` are resolved
+//! through [`Resolve`]. Payloads are aligned to 32 bits with a `nop` as needed.
+//! Together, this accepts the output of [`listing`][`crate::smali::listing`].
+//!
+//! ```
+//! use dalvik::{assemble::assemble, Instruction};
+//!
+//! let insts = assemble(
+//!     "
+//!     if-eqz v0, :done
+//!     invoke-static {v0, v3}, method@4455
+//! :done
+//!     return-void
+//!     ",
+//!     &(),
+//! )
+//! .unwrap();
+//! assert_eq!(insts[0], Instruction::IfEqz(0, 5));
+//! ```
+
+use std::collections::HashMap;
+
+use crate::opcode::{IndexKind, Opcode};
+use crate::patch::TryBlock;
+use crate::Instruction;
+
+/// Assembly error, located by 1-based line and column
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Error {
+    /// Line of the offending input
+    pub line: usize,
+    /// Column of the offending input, in characters
+    pub column: usize,
+    /// What went wrong
+    pub kind: ErrorKind,
+}
+
+/// Kind of an assembly [`Error`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Unexpected input, the payload describes what was expected instead
+    Expected(&'static str),
+    /// No standard opcode has this mnemonic
+    UnknownMnemonic(String),
+    /// Unsupported directive, e.g. `.locals`
+    UnknownDirective(String),
+    /// An operand does not fit its field in the instruction format, e.g. `v16`
+    /// in a 4-bit register field or a branch too far for `goto`
+    OutOfRange,
+    /// A branch to a label that is never defined
+    UnknownLabel(String),
+    /// A label defined twice
+    DuplicateLabel(String),
+    /// A symbolic reference [`Resolve`] does not know
+    Unresolved(String),
+    /// A switch payload branching to labels that no switch instruction
+    /// references, so its targets cannot be made relative
+    UnreferencedPayload,
+    /// A try block ending before it starts
+    EmptyTry,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}:{}: ", self.line, self.column))?;
+        match &self.kind {
+            ErrorKind::Expected(what) => f.write_fmt(format_args!("expected {what}")),
+            ErrorKind::UnknownMnemonic(m) => f.write_fmt(format_args!("unknown mnemonic `{m}`")),
+            ErrorKind::UnknownDirective(d) => f.write_fmt(format_args!("unknown directive `{d}`")),
+            ErrorKind::OutOfRange => f.write_str("operand out of range"),
+            ErrorKind::UnknownLabel(l) => f.write_fmt(format_args!("unknown label `:{l}`")),
+            ErrorKind::DuplicateLabel(l) => f.write_fmt(format_args!("duplicate label `:{l}`")),
+            ErrorKind::Unresolved(r) => f.write_fmt(format_args!("unresolved reference `{r}`")),
+            ErrorKind::UnreferencedPayload => f.write_str("payload is not referenced by a switch"),
+            ErrorKind::EmptyTry => f.write_str("try block ends before it starts"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Symbolic reference lookup, the inverse of [`PrettyPrint`][`crate::PrettyPrint`]
+///
+/// Every lookup defaults to `None`, so `()` only accepts numeric references as
+/// printed by [`Display`][`std::fmt::Display`], e.g. `method@4455`.
+pub trait Resolve {
+    /// String index of the given contents
+    fn string(&self, _contents: &str) -> Option<u32> {
+        None
+    }
+    /// Type index of the given descriptor, e.g. `Ljava/lang/String;`
+    fn type_index(&self, _descriptor: &str) -> Option<u16> {
+        None
+    }
+    /// Field index, from (Class, Name, Type)
+    fn field(&self, _class: &str, _name: &str, _ty: &str) -> Option<u16> {
+        None
+    }
+    /// Method index, from (Class, Name, Params, Return)
+    fn method(&self, _class: &str, _name: &str, _params: &str, _ret: &str) -> Option<u16> {
+        None
+    }
+    /// Prototype index of the method descriptor, e.g. `(I)V`
+    fn proto(&self, _descriptor: &str) -> Option<u16> {
+        None
+    }
+}
+
+impl Resolve for () {}
+
+/// Assemble smali-style text into [`Instruction`]s
+pub fn assemble(text: &str, lookup: &impl Resolve) -> Result<Vec<Instruction>, Error> {
+    Ok(assemble_inner(text, lookup)?.insts.into_iter().map(|a| a.inst).collect())
+}
+
+/// Assemble smali-style text into encoded u16 codepoints
+pub fn assemble_units(text: &str, lookup: &impl Resolve) -> Result<Vec<u16>, Error> {
+    encode(assemble_inner(text, lookup)?.insts)
+}
+
+/// A method body, as assembled by [`assemble_method`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Method {
+    /// Register count, from the `.registers` directive
+    pub registers: Option<u16>,
+    /// Encoded u16 codepoints
+    pub insns: Vec<u16>,
+    /// Try blocks from the `.catch` and `.catchall` directives, by start
+    /// address, with the handlers of each in the order they are given
+    pub tries: Vec<TryBlock>,
+}
+
+/// Assemble a smali method body, such as printed by
+/// [`listing`][`crate::smali::listing`], along with its register count and try
+/// blocks
+///
+/// ```
+/// use dalvik::assemble::assemble_method;
+///
+/// let method = assemble_method(
+///     "
+///     .registers 2
+/// :try_start_0
+///     div-int/2addr v0, v1
+/// :try_end_0
+///     .catchall {:try_start_0 .. :try_end_0} :catchall_0
+///     return v0
+/// :catchall_0
+///     return v1
+///     ",
+///     &(),
+/// )
+/// .unwrap();
+/// assert_eq!(method.registers, Some(2));
+/// assert_eq!(method.insns, [0x10b3, 0x000f, 0x010f]);
+/// assert_eq!((method.tries[0].start_addr, method.tries[0].insn_count), (0, 1));
+/// assert_eq!(method.tries[0].catch_all_addr, Some(2));
+/// ```
+pub fn assemble_method(text: &str, lookup: &impl Resolve) -> Result<Method, Error> {
+    let Body { insts, registers, tries } = assemble_inner(text, lookup)?;
+    Ok(Method {
+        registers,
+        insns: encode(insts)?,
+        tries,
+    })
+}
+
+fn encode(insts: Vec<Assembled>) -> Result<Vec<u16>, Error> {
+    let mut units = Vec::new();
+    for Assembled { line, column, inst } in insts {
+        // operands are range checked while parsing, this is only a safety net
+        inst.encode(&mut units).map_err(|_| Error {
+            line,
+            column,
+            kind: ErrorKind::OutOfRange,
+        })?;
+    }
+    Ok(units)
+}
+
+/// Everything parsed from the text, with labels resolved
+struct Body {
+    insts: Vec<Assembled>,
+    registers: Option<u16>,
+    tries: Vec<TryBlock>,
+}
+
+/// An instruction, along with the line and column it was parsed from
+struct Assembled {
+    line: usize,
+    column: usize,
+    inst: Instruction,
+}
+
+fn assemble_inner(text: &str, lookup: &impl Resolve) -> Result<Body, Error> {
+    let mut items = Vec::new();
+    let mut labels = HashMap::new();
+    let mut address = 0;
+    let mut registers = None;
+    let mut catches = Vec::new();
+
+    let mut lines = text.lines().enumerate().map(|(n, text)| Cursor::new(n + 1, text));
+    while let Some(mut cur) = lines.next() {
+        cur.skip_ws();
+        if cur.at_end() {
+            continue;
+        }
+
+        if cur.eat(":") {
+            let column = cur.column();
+            let label = cur.label()?;
+            if labels.insert(label.to_string(), address).is_some() {
+                return Err(Error {
+                    line: cur.line,
+                    column,
+                    kind: ErrorKind::DuplicateLabel(label.to_string()),
+                });
+            }
+            cur.end()?;
+            continue;
+        }
+
+        let column = cur.column();
+        let word = cur.word();
+        let item = match word {
+            ".registers" => {
+                registers = Some(cur.int(Kind::Unsigned, 16, Cursor::number)? as u16);
+                cur.end()?;
+                continue;
+            }
+            ".catch" | ".catchall" => {
+                let ty = match word {
+                    ".catch" => Some(cur.index(IndexKind::Type, 16, lookup)?),
+                    _ => None,
+                };
+                catches.push(cur.catch(ty)?);
+                cur.end()?;
+                continue;
+            }
+            ".packed-switch" | ".sparse-switch" | ".array-data" => directive(word, &mut cur, &mut lines)?,
+            "packed-switch-payload" | "sparse-switch-payload" | "array-data-payload" => inline_payload(word, &mut cur)?,
+            _ if word.starts_with('.') => {
+                return Err(Error {
+                    line: cur.line,
+                    column,
+                    kind: ErrorKind::UnknownDirective(word.to_string()),
+                });
+            }
+            _ => {
+                let Some(op) = Opcode::from_mnemonic(word) else {
+                    return Err(Error {
+                        line: cur.line,
+                        column,
+                        kind: ErrorKind::UnknownMnemonic(word.to_string()),
+                    });
+                };
+                let (inst, target) = instruction(&mut cur, op, lookup)?;
+                cur.end()?;
+                Item::Insn { inst, target }
+            }
+        };
+
+        // payloads must be 32-bit aligned
+        if matches!(item, Item::Payload(_)) && address % 2 == 1 {
+            // labels just before the payload move along with it
+            labels.values_mut().filter(|target| **target == address).for_each(|target| *target += 1);
+            let nop = Item::Insn {
+                inst: Instruction::Nop,
+                target: None,
+            };
+            items.push((cur.line, column, address, nop));
+            address += 1;
+        }
+        let len = item.len();
+        items.push((cur.line, column, address, item));
+        address += len;
+    }
+
+    // the offset of a branch operand from the instruction at `from`
+    let resolve = |line: usize, from: usize, field: &Field| -> Result<i32, Error> {
+        let off = match &field.value {
+            Value::Int(v) => *v,
+            Value::Label(label) => match labels.get(label) {
+                Some(target) => *target as i128 - from as i128,
+                None => {
+                    return Err(Error {
+                        line,
+                        column: field.column,
+                        kind: ErrorKind::UnknownLabel(label.clone()),
+                    })
+                }
+            },
+        };
+        match Kind::Branch.fits(off, field.bits) {
+            true => Ok(off as i32),
+            false => Err(Error {
+                line,
+                column: field.column,
+                kind: ErrorKind::OutOfRange,
+            }),
+        }
+    };
+
+    // switch targets are relative to the switch instruction, not the payload
+    let mut switches = HashMap::new();
+    for (line, _, address, item) in &items {
+        if let Item::Insn {
+            inst: Instruction::PackedSwitch(..) | Instruction::SparseSwitch(..),
+            target: Some(target),
+        } = item
+        {
+            let payload = *address as i128 + resolve(*line, *address, target)? as i128;
+            switches.insert(payload, *address);
+        }
+    }
+
+    let mut assembled = Vec::with_capacity(items.len());
+    for (line, column, address, item) in items {
+        let inst = match item {
+            Item::Insn { mut inst, target } => {
+                if let Some(target) = target {
+                    set_offset(&mut inst, resolve(line, address, &target)?);
+                }
+                inst
+            }
+            Item::Payload(payload) => {
+                let base = switches.get(&(address as i128)).copied();
+                let targets = |targets: &[Field]| -> Result<Vec<i32>, Error> {
+                    targets
+                        .iter()
+                        .map(|t| match (&t.value, base) {
+                            (Value::Int(v), _) => Ok(*v as i32),
+                            (Value::Label(_), Some(base)) => resolve(line, base, t),
+                            (Value::Label(_), None) => Err(Error {
+                                line,
+                                column: t.column,
+                                kind: ErrorKind::UnreferencedPayload,
+                            }),
+                        })
+                        .collect()
+                };
+                match payload {
+                    Payload::Packed { first_key, targets: t } => Instruction::PackedSwitchPayload {
+                        first_key,
+                        targets: targets(&t)?,
+                    },
+                    Payload::Sparse { keys, targets: t } => Instruction::SparseSwitchPayload { keys, targets: targets(&t)? },
+                    Payload::Array { width, data } => Instruction::FillArrayDataPayload { element_width: width, data },
+                }
+            }
+        };
+        assembled.push(Assembled { line, column, inst });
+    }
+
+    // catches of the same range make up one try block
+    let mut tries: Vec<TryBlock> = Vec::new();
+    for catch in catches {
+        let address = |(column, label): &(usize, String)| match labels.get(label) {
+            Some(address) => Ok(*address as u32),
+            None => Err(Error {
+                line: catch.line,
+                column: *column,
+                kind: ErrorKind::UnknownLabel(label.clone()),
+            }),
+        };
+        let (start, end, handler) = (address(&catch.start)?, address(&catch.end)?, address(&catch.handler)?);
+        let Some(insn_count) = end.checked_sub(start).filter(|count| *count > 0) else {
+            return Err(Error {
+                line: catch.line,
+                column: catch.end.0,
+                kind: ErrorKind::EmptyTry,
+            });
+        };
+        let Ok(insn_count) = u16::try_from(insn_count) else {
+            return Err(Error {
+                line: catch.line,
+                column: catch.end.0,
+                kind: ErrorKind::OutOfRange,
+            });
+        };
+        let index = match tries.iter().position(|t| t.start_addr == start && t.insn_count == insn_count) {
+            Some(index) => index,
+            None => {
+                tries.push(TryBlock {
+                    start_addr: start,
+                    insn_count,
+                    handlers: Vec::new(),
+                    catch_all_addr: None,
+                });
+                tries.len() - 1
+            }
+        };
+        match catch.ty {
+            Some(ty) => tries[index].handlers.push((ty, handler)),
+            None => tries[index].catch_all_addr = Some(handler),
+        }
+    }
+    tries.sort_by_key(|t| t.start_addr);
+
+    Ok(Body {
+        insts: assembled,
+        registers,
+        tries,
+    })
+}
+
+/// A `.catch` or `.catchall` directive, with the column of each of its labels
+struct Catch {
+    line: usize,
+    /// Exception type, `None` for `.catchall`
+    ty: Option<u32>,
+    start: (usize, String),
+    end: (usize, String),
+    handler: (usize, String),
+}
+
+enum Item {
+    /// An instruction, with its branch operand if any, to be resolved once
+    /// labels are laid out
+    Insn {
+        inst: Instruction,
+        target: Option<Field>,
+    },
+    Payload(Payload),
+}
+
+impl Item {
+    fn len(&self) -> usize {
+        match self {
+            Item::Insn { inst, .. } => inst.len(),
+            Item::Payload(Payload::Packed { targets, .. }) => 4 + 2 * targets.len(),
+            Item::Payload(Payload::Sparse { targets, .. }) => 2 + 4 * targets.len(),
+            Item::Payload(Payload::Array { data, .. }) => 4 + data.len().div_ceil(2),
+        }
+    }
+}
+
+enum Payload {
+    Packed { first_key: i32, targets: Vec<Field> },
+    Sparse { keys: Vec<i32>, targets: Vec<Field> },
+    Array { width: u16, data: Vec<u8> },
+}
+
+/// A branch operand, `bits` wide
+struct Field {
+    column: usize,
+    value: Value,
+    bits: u32,
+}
+
+enum Value {
+    Int(i128),
+    Label(String),
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    /// Unsigned: registers, indices and counts
+    Unsigned,
+    /// Signed branch offset
+    Branch,
+    /// Literal, accepting both the signed and unsigned range of its width
+    Literal,
+}
+
+impl Kind {
+    fn fits(self, value: i128, bits: u32) -> bool {
+        let (min, max) = match self {
+            Kind::Unsigned => (0, (1 << bits) - 1),
+            Kind::Branch => (-(1 << (bits - 1)), (1 << (bits - 1)) - 1),
+            Kind::Literal => (-(1 << (bits - 1)), (1 << bits) - 1),
+        };
+        (min..=max).contains(&value)
+    }
+}
+
+// parse the operands of `op`, with a zero offset in place of its branch operand
+fn instruction(cur: &mut Cursor, op: Opcode, lookup: &impl Resolve) -> Result<(Instruction, Option<Field>), Error> {
+    let kind = op.index_kind();
+    let mut target = None;
+
+    let inst = match op {
+        Opcode::Nop => Instruction::Nop,
+        Opcode::Move => cur.f12x(Instruction::Move)?,
+        Opcode::MoveFrom16 => cur.f22x(Instruction::MoveFrom16)?,
+        Opcode::Move16 => cur.f32x(Instruction::Move16)?,
+        Opcode::MoveWide => cur.f12x(Instruction::MoveWide)?,
+        Opcode::MoveWideFrom16 => cur.f22x(Instruction::MoveWideFrom16)?,
+        Opcode::MoveWide16 => cur.f32x(Instruction::MoveWide16)?,
+        Opcode::MoveObject => cur.f12x(Instruction::MoveObject)?,
+        Opcode::MoveObjectFrom16 => cur.f22x(Instruction::MoveObjectFrom16)?,
+        Opcode::MoveObject16 => cur.f32x(Instruction::MoveObject16)?,
+        Opcode::MoveResult => cur.f11x(Instruction::MoveResult)?,
+        Opcode::MoveResultWide => cur.f11x(Instruction::MoveResultWide)?,
+        Opcode::MoveResultObject => cur.f11x(Instruction::MoveResultObject)?,
+        Opcode::MoveException => cur.f11x(Instruction::MoveException)?,
+        Opcode::ReturnVoid => Instruction::ReturnVoid,
+        Opcode::Return => cur.f11x(Instruction::Return)?,
+        Opcode::ReturnWide => cur.f11x(Instruction::ReturnWide)?,
+        Opcode::ReturnObject => cur.f11x(Instruction::ReturnObject)?,
+        Opcode::Const4 => {
+            let reg = cur.register(4)? as u8;
+            Instruction::Const4(reg, cur.comma()?.literal4()?)
+        }
+        Opcode::Const16 => {
+            let reg = cur.register(8)? as u8;
+            Instruction::Const16(reg, cur.comma()?.literal(16)? as i16)
+        }
+        Opcode::Const => {
+            let reg = cur.register(8)? as u8;
+            Instruction::Const(reg, cur.comma()?.literal(32)? as u32)
+        }
+        Opcode::ConstHigh16 => {
+            let reg = cur.register(8)? as u8;
+            Instruction::ConstHigh16(reg, cur.comma()?.high16(op)? as i16)
+        }
+        Opcode::ConstWide16 => {
+            let reg = cur.register(8)? as u8;
+            Instruction::ConstWide16(reg, cur.comma()?.wide(16)? as i16)
+        }
+        Opcode::ConstWide32 => {
+            let reg = cur.register(8)? as u8;
+            Instruction::ConstWide32(reg, cur.comma()?.wide(32)? as u32)
+        }
+        Opcode::ConstWide => {
+            let reg = cur.register(8)? as u8;
+            Instruction::ConstWide(reg, cur.comma()?.wide(64)? as u64)
+        }
+        Opcode::ConstWideHigh16 => {
+            let reg = cur.register(8)? as u8;
+            Instruction::ConstWideHigh16(reg, cur.comma()?.high16(op)? as u16)
+        }
+        Opcode::ConstString => cur.f21c(Instruction::ConstString, kind, lookup)?,
+        Opcode::ConstStringJumbo => {
+            let reg = cur.register(8)? as u8;
+            Instruction::ConstStringJumbo(reg, cur.comma()?.index(kind, 32, lookup)?)
+        }
+        Opcode::ConstClass => cur.f21c(Instruction::ConstClass, kind, lookup)?,
+        Opcode::MonitorEnter => cur.f11x(Instruction::MonitorEnter)?,
+        Opcode::MonitorExit => cur.f11x(Instruction::MonitorExit)?,
+        Opcode::CheckCast => cur.f21c(Instruction::CheckCast, kind, lookup)?,
+        Opcode::InstanceOf => cur.f22c(Instruction::InstanceOf, kind, lookup)?,
+        Opcode::ArrayLength => cur.f12x(Instruction::ArrayLength)?,
+        Opcode::NewInstance => cur.f21c(Instruction::NewInstance, kind, lookup)?,
+        Opcode::NewArray => cur.f22c(Instruction::NewArray, kind, lookup)?,
+        Opcode::FilledNewArray => cur.f35c(kind, lookup, |ty, nargs, args| Instruction::FilledNewArray { ty, nargs, args })?,
+        Opcode::FilledNewArrayRange => cur.f3rc(kind, lookup, |ty, args| Instruction::FilledNewArrayRange { ty, args })?,
+        Opcode::FillArrayData => cur.f31t(Instruction::FillArrayData, &mut target)?,
+        Opcode::Throw => cur.f11x(Instruction::Throw)?,
+        Opcode::Goto => {
+            target = Some(cur.branch(8)?);
+            Instruction::Goto(0)
+        }
+        Opcode::Goto16 => {
+            target = Some(cur.branch(16)?);
+            Instruction::Goto16(0)
+        }
+        Opcode::Goto32 => {
+            target = Some(cur.branch(32)?);
+            Instruction::Goto32(0)
+        }
+        Opcode::PackedSwitch => cur.f31t(Instruction::PackedSwitch, &mut target)?,
+        Opcode::SparseSwitch => cur.f31t(Instruction::SparseSwitch, &mut target)?,
+        Opcode::CmplFloat => cur.f23x(Instruction::CmplFloat)?,
+        Opcode::CmpgFloat => cur.f23x(Instruction::CmpgFloat)?,
+        Opcode::CmplDouble => cur.f23x(Instruction::CmplDouble)?,
+        Opcode::CmpgDouble => cur.f23x(Instruction::CmpgDouble)?,
+        Opcode::CmpLong => cur.f23x(Instruction::CmpLong)?,
+        Opcode::IfEq => cur.f22t(Instruction::IfEq, &mut target)?,
+        Opcode::IfNe => cur.f22t(Instruction::IfNe, &mut target)?,
+        Opcode::IfLt => cur.f22t(Instruction::IfLt, &mut target)?,
+        Opcode::IfGe => cur.f22t(Instruction::IfGe, &mut target)?,
+        Opcode::IfGt => cur.f22t(Instruction::IfGt, &mut target)?,
+        Opcode::IfLe => cur.f22t(Instruction::IfLe, &mut target)?,
+        Opcode::IfEqz => cur.f21t(Instruction::IfEqz, &mut target)?,
+        Opcode::IfNez => cur.f21t(Instruction::IfNez, &mut target)?,
+        Opcode::IfLtz => cur.f21t(Instruction::IfLtz, &mut target)?,
+        Opcode::IfGez => cur.f21t(Instruction::IfGez, &mut target)?,
+        Opcode::IfGtz => cur.f21t(Instruction::IfGtz, &mut target)?,
+        Opcode::IfLez => cur.f21t(Instruction::IfLez, &mut target)?,
+        Opcode::AGet => cur.f23x(Instruction::AGet)?,
+        Opcode::AGetWide => cur.f23x(Instruction::AGetWide)?,
+        Opcode::AGetObject => cur.f23x(Instruction::AGetObject)?,
+        Opcode::AGetBoolean => cur.f23x(Instruction::AGetBoolean)?,
+        Opcode::AGetByte => cur.f23x(Instruction::AGetByte)?,
+        Opcode::AGetChar => cur.f23x(Instruction::AGetChar)?,
+        Opcode::AGetShort => cur.f23x(Instruction::AGetShort)?,
+        Opcode::APut => cur.f23x(Instruction::APut)?,
+        Opcode::APutWide => cur.f23x(Instruction::APutWide)?,
+        Opcode::APutObject => cur.f23x(Instruction::APutObject)?,
+        Opcode::APutBoolean => cur.f23x(Instruction::APutBoolean)?,
+        Opcode::APutByte => cur.f23x(Instruction::APutByte)?,
+        Opcode::APutChar => cur.f23x(Instruction::APutChar)?,
+        Opcode::APutShort => cur.f23x(Instruction::APutShort)?,
+        Opcode::IGet => cur.f22c(Instruction::IGet, kind, lookup)?,
+        Opcode::IGetWide => cur.f22c(Instruction::IGetWide, kind, lookup)?,
+        Opcode::IGetObject => cur.f22c(Instruction::IGetObject, kind, lookup)?,
+        Opcode::IGetBoolean => cur.f22c(Instruction::IGetBoolean, kind, lookup)?,
+        Opcode::IGetByte => cur.f22c(Instruction::IGetByte, kind, lookup)?,
+        Opcode::IGetChar => cur.f22c(Instruction::IGetChar, kind, lookup)?,
+        Opcode::IGetShort => cur.f22c(Instruction::IGetShort, kind, lookup)?,
+        Opcode::IPut => cur.f22c(Instruction::IPut, kind, lookup)?,
+        Opcode::IPutWide => cur.f22c(Instruction::IPutWide, kind, lookup)?,
+        Opcode::IPutObject => cur.f22c(Instruction::IPutObject, kind, lookup)?,
+        Opcode::IPutBoolean => cur.f22c(Instruction::IPutBoolean, kind, lookup)?,
+        Opcode::IPutByte => cur.f22c(Instruction::IPutByte, kind, lookup)?,
+        Opcode::IPutChar => cur.f22c(Instruction::IPutChar, kind, lookup)?,
+        Opcode::IPutShort => cur.f22c(Instruction::IPutShort, kind, lookup)?,
+        Opcode::SGet => cur.f21c(Instruction::SGet, kind, lookup)?,
+        Opcode::SGetWide => cur.f21c(Instruction::SGetWide, kind, lookup)?,
+        Opcode::SGetObject => cur.f21c(Instruction::SGetObject, kind, lookup)?,
+        Opcode::SGetBoolean => cur.f21c(Instruction::SGetBoolean, kind, lookup)?,
+        Opcode::SGetByte => cur.f21c(Instruction::SGetByte, kind, lookup)?,
+        Opcode::SGetChar => cur.f21c(Instruction::SGetChar, kind, lookup)?,
+        Opcode::SGetShort => cur.f21c(Instruction::SGetShort, kind, lookup)?,
+        Opcode::SPut => cur.f21c(Instruction::SPut, kind, lookup)?,
+        Opcode::SPutWide => cur.f21c(Instruction::SPutWide, kind, lookup)?,
+        Opcode::SPutObject => cur.f21c(Instruction::SPutObject, kind, lookup)?,
+        Opcode::SPutBoolean => cur.f21c(Instruction::SPutBoolean, kind, lookup)?,
+        Opcode::SPutByte => cur.f21c(Instruction::SPutByte, kind, lookup)?,
+        Opcode::SPutChar => cur.f21c(Instruction::SPutChar, kind, lookup)?,
+        Opcode::SPutShort => cur.f21c(Instruction::SPutShort, kind, lookup)?,
+        Opcode::InvokeVirtual => cur.f35c(kind, lookup, |method, nargs, args| Instruction::InvokeVirtual { method, nargs, args })?,
+        Opcode::InvokeSuper => cur.f35c(kind, lookup, |method, nargs, args| Instruction::InvokeSuper { method, nargs, args })?,
+        Opcode::InvokeDirect => cur.f35c(kind, lookup, |method, nargs, args| Instruction::InvokeDirect { method, nargs, args })?,
+        Opcode::InvokeStatic => cur.f35c(kind, lookup, |method, nargs, args| Instruction::InvokeStatic { method, nargs, args })?,
+        Opcode::InvokeInterface => cur.f35c(kind, lookup, |method, nargs, args| Instruction::InvokeInterface { method, nargs, args })?,
+        Opcode::InvokeVirtualRange => cur.f3rc(kind, lookup, |method, args| Instruction::InvokeVirtualRange { method, args })?,
+        Opcode::InvokeSuperRange => cur.f3rc(kind, lookup, |method, args| Instruction::InvokeSuperRange { method, args })?,
+        Opcode::InvokeDirectRange => cur.f3rc(kind, lookup, |method, args| Instruction::InvokeDirectRange { method, args })?,
+        Opcode::InvokeStaticRange => cur.f3rc(kind, lookup, |method, args| Instruction::InvokeStaticRange { method, args })?,
+        Opcode::InvokeInterfaceRange => cur.f3rc(kind, lookup, |method, args| Instruction::InvokeInterfaceRange { method, args })?,
+        Opcode::NegInt => cur.f12x(Instruction::NegInt)?,
+        Opcode::NotInt => cur.f12x(Instruction::NotInt)?,
+        Opcode::NegLong => cur.f12x(Instruction::NegLong)?,
+        Opcode::NotLong => cur.f12x(Instruction::NotLong)?,
+        Opcode::NegFloat => cur.f12x(Instruction::NegFloat)?,
+        Opcode::NegDouble => cur.f12x(Instruction::NegDouble)?,
+        Opcode::IntToLong => cur.f12x(Instruction::IntToLong)?,
+        Opcode::IntToFloat => cur.f12x(Instruction::IntToFloat)?,
+        Opcode::IntToDouble => cur.f12x(Instruction::IntToDouble)?,
+        Opcode::LongToInt => cur.f12x(Instruction::LongToInt)?,
+        Opcode::LongToFloat => cur.f12x(Instruction::LongToFloat)?,
+        Opcode::LongToDouble => cur.f12x(Instruction::LongToDouble)?,
+        Opcode::FloatToInt => cur.f12x(Instruction::FloatToInt)?,
+        Opcode::FloatToLong => cur.f12x(Instruction::FloatToLong)?,
+        Opcode::FloatToDouble => cur.f12x(Instruction::FloatToDouble)?,
+        Opcode::DoubleToInt => cur.f12x(Instruction::DoubleToInt)?,
+        Opcode::DoubleToLong => cur.f12x(Instruction::DoubleToLong)?,
+        Opcode::DoubleToFloat => cur.f12x(Instruction::DoubleToFloat)?,
+        Opcode::IntTobyte => cur.f12x(Instruction::IntTobyte)?,
+        Opcode::IntTochar => cur.f12x(Instruction::IntTochar)?,
+        Opcode::IntToshort => cur.f12x(Instruction::IntToshort)?,
+        Opcode::AddInt => cur.f23x(Instruction::AddInt)?,
+        Opcode::SubInt => cur.f23x(Instruction::SubInt)?,
+        Opcode::MulInt => cur.f23x(Instruction::MulInt)?,
+        Opcode::DivInt => cur.f23x(Instruction::DivInt)?,
+        Opcode::RemInt => cur.f23x(Instruction::RemInt)?,
+        Opcode::AndInt => cur.f23x(Instruction::AndInt)?,
+        Opcode::OrInt => cur.f23x(Instruction::OrInt)?,
+        Opcode::XorInt => cur.f23x(Instruction::XorInt)?,
+        Opcode::ShlInt => cur.f23x(Instruction::ShlInt)?,
+        Opcode::ShrInt => cur.f23x(Instruction::ShrInt)?,
+        Opcode::UshrInt => cur.f23x(Instruction::UshrInt)?,
+        Opcode::AddLong => cur.f23x(Instruction::AddLong)?,
+        Opcode::SubLong => cur.f23x(Instruction::SubLong)?,
+        Opcode::MulLong => cur.f23x(Instruction::MulLong)?,
+        Opcode::DivLong => cur.f23x(Instruction::DivLong)?,
+        Opcode::RemLong => cur.f23x(Instruction::RemLong)?,
+        Opcode::AndLong => cur.f23x(Instruction::AndLong)?,
+        Opcode::OrLong => cur.f23x(Instruction::OrLong)?,
+        Opcode::XorLong => cur.f23x(Instruction::XorLong)?,
+        Opcode::ShlLong => cur.f23x(Instruction::ShlLong)?,
+        Opcode::ShrLong => cur.f23x(Instruction::ShrLong)?,
+        Opcode::UshrLong => cur.f23x(Instruction::UshrLong)?,
+        Opcode::AddFloat => cur.f23x(Instruction::AddFloat)?,
+        Opcode::SubFloat => cur.f23x(Instruction::SubFloat)?,
+        Opcode::MulFloat => cur.f23x(Instruction::MulFloat)?,
+        Opcode::DivFloat => cur.f23x(Instruction::DivFloat)?,
+        Opcode::RemFloat => cur.f23x(Instruction::RemFloat)?,
+        Opcode::AddDouble => cur.f23x(Instruction::AddDouble)?,
+        Opcode::SubDouble => cur.f23x(Instruction::SubDouble)?,
+        Opcode::MulDouble => cur.f23x(Instruction::MulDouble)?,
+        Opcode::DivDouble => cur.f23x(Instruction::DivDouble)?,
+        Opcode::RemDouble => cur.f23x(Instruction::RemDouble)?,
+        Opcode::AddInt2 => cur.f12x(Instruction::AddInt2)?,
+        Opcode::SubInt2 => cur.f12x(Instruction::SubInt2)?,
+        Opcode::MulInt2 => cur.f12x(Instruction::MulInt2)?,
+        Opcode::DivInt2 => cur.f12x(Instruction::DivInt2)?,
+        Opcode::RemInt2 => cur.f12x(Instruction::RemInt2)?,
+        Opcode::AndInt2 => cur.f12x(Instruction::AndInt2)?,
+        Opcode::OrInt2 => cur.f12x(Instruction::OrInt2)?,
+        Opcode::XorInt2 => cur.f12x(Instruction::XorInt2)?,
+        Opcode::ShlInt2 => cur.f12x(Instruction::ShlInt2)?,
+        Opcode::ShrInt2 => cur.f12x(Instruction::ShrInt2)?,
+        Opcode::UShrInt2 => cur.f12x(Instruction::UShrInt2)?,
+        Opcode::AddLong2 => cur.f12x(Instruction::AddLong2)?,
+        Opcode::SubLong2 => cur.f12x(Instruction::SubLong2)?,
+        Opcode::MulLong2 => cur.f12x(Instruction::MulLong2)?,
+        Opcode::DivLong2 => cur.f12x(Instruction::DivLong2)?,
+        Opcode::RemLong2 => cur.f12x(Instruction::RemLong2)?,
+        Opcode::AndLong2 => cur.f12x(Instruction::AndLong2)?,
+        Opcode::OrLong2 => cur.f12x(Instruction::OrLong2)?,
+        Opcode::XorLong2 => cur.f12x(Instruction::XorLong2)?,
+        Opcode::ShlLong2 => cur.f12x(Instruction::ShlLong2)?,
+        Opcode::ShrLong2 => cur.f12x(Instruction::ShrLong2)?,
+        Opcode::UShrLong2 => cur.f12x(Instruction::UShrLong2)?,
+        Opcode::AddFloat2 => cur.f12x(Instruction::AddFloat2)?,
+        Opcode::SubFloat2 => cur.f12x(Instruction::SubFloat2)?,
+        Opcode::MulFloat2 => cur.f12x(Instruction::MulFloat2)?,
+        Opcode::DivFloat2 => cur.f12x(Instruction::DivFloat2)?,
+        Opcode::RemFloat2 => cur.f12x(Instruction::RemFloat2)?,
+        Opcode::AddDouble2 => cur.f12x(Instruction::AddDouble2)?,
+        Opcode::SubDouble2 => cur.f12x(Instruction::SubDouble2)?,
+        Opcode::MulDouble2 => cur.f12x(Instruction::MulDouble2)?,
+        Opcode::DivDouble2 => cur.f12x(Instruction::DivDouble2)?,
+        Opcode::RemDouble2 => cur.f12x(Instruction::RemDouble2)?,
+        Opcode::AddInt16 => cur.f22s(Instruction::AddInt16)?,
+        Opcode::RsubInt16 => cur.f22s(Instruction::RsubInt16)?,
+        Opcode::MulInt16 => cur.f22s(Instruction::MulInt16)?,
+        Opcode::DivInt16 => cur.f22s(Instruction::DivInt16)?,
+        Opcode::RemInt16 => cur.f22s(Instruction::RemInt16)?,
+        Opcode::AndInt16 => cur.f22s(Instruction::AndInt16)?,
+        Opcode::OrInt16 => cur.f22s(Instruction::OrInt16)?,
+        Opcode::XorInt16 => cur.f22s(Instruction::XorInt16)?,
+        Opcode::AddInt8 => cur.f22b(Instruction::AddInt8)?,
+        Opcode::RsubInt8 => cur.f22b(Instruction::RsubInt8)?,
+        Opcode::MulInt8 => cur.f22b(Instruction::MulInt8)?,
+        Opcode::DivInt8 => cur.f22b(Instruction::DivInt8)?,
+        Opcode::RemInt8 => cur.f22b(Instruction::RemInt8)?,
+        Opcode::AndInt8 => cur.f22b(Instruction::AndInt8)?,
+        Opcode::OrInt8 => cur.f22b(Instruction::OrInt8)?,
+        Opcode::XorInt8 => cur.f22b(Instruction::XorInt8)?,
+        Opcode::ShlInt8 => cur.f22b(Instruction::ShlInt8)?,
+        Opcode::ShrInt8 => cur.f22b(Instruction::ShrInt8)?,
+        Opcode::UshrInt8 => cur.f22b(Instruction::UshrInt8)?,
+        Opcode::InvokePolymorphic => {
+            let (nargs, args) = cur.register_list()?;
+            let method = cur.comma()?.index(IndexKind::Method, 16, lookup)? as u16;
+            let proto = cur.comma()?.index(IndexKind::Proto, 16, lookup)? as u16;
+            Instruction::InvokePolymorphic { method, proto, nargs, args }
+        }
+        Opcode::InvokePolymorphicRange => {
+            let args = cur.register_range()?;
+            let method = cur.comma()?.index(IndexKind::Method, 16, lookup)? as u16;
+            let proto = cur.comma()?.index(IndexKind::Proto, 16, lookup)? as u16;
+            Instruction::InvokePolymorphicRange { method, proto, args }
+        }
+        Opcode::InvokeCustom => cur.f35c(kind, lookup, |call_site, nargs, args| Instruction::InvokeCustom { call_site, nargs, args })?,
+        Opcode::InvokeCustomRange => cur.f3rc(kind, lookup, |call_site, args| Instruction::InvokeCustomRange { call_site, args })?,
+        Opcode::ConstMethodHandle => cur.f21c(Instruction::ConstMethodHandle, kind, lookup)?,
+        Opcode::ConstMethodType => cur.f21c(Instruction::ConstMethodType, kind, lookup)?,
+        // not returned by `Opcode::from_mnemonic`
+        _ => return Err(cur.error(ErrorKind::UnknownMnemonic(op.mnemonic().to_string()))),
+    };
+
+    Ok((inst, target))
+}
+
+// set the branch offset of an instruction parsed with a zero offset
+fn set_offset(inst: &mut Instruction, off: i32) {
+    match inst {
+        Instruction::Goto(o) => *o = off as i8,
+        Instruction::Goto16(o)
+        | Instruction::IfEq(_, _, o)
+        | Instruction::IfNe(_, _, o)
+        | Instruction::IfLt(_, _, o)
+        | Instruction::IfGe(_, _, o)
+        | Instruction::IfGt(_, _, o)
+        | Instruction::IfLe(_, _, o)
+        | Instruction::IfEqz(_, o)
+        | Instruction::IfNez(_, o)
+        | Instruction::IfLtz(_, o)
+        | Instruction::IfGez(_, o)
+        | Instruction::IfGtz(_, o)
+        | Instruction::IfLez(_, o) => *o = off as i16,
+        Instruction::Goto32(o) | Instruction::PackedSwitch(_, o) | Instruction::SparseSwitch(_, o) | Instruction::FillArrayData(_, o) => *o = off,
+        _ => {}
+    }
+}
+
+// parse a multiline payload directive, up to its `.end`
+fn directive<'a>(name: &str, cur: &mut Cursor, lines: &mut impl Iterator<Item = Cursor<'a>>) -> Result<Item, Error> {
+    let payload = match name {
+        ".packed-switch" => Payload::Packed {
+            first_key: cur.int32()?,
+            targets: Vec::new(),
+        },
+        ".sparse-switch" => Payload::Sparse {
+            keys: Vec::new(),
+            targets: Vec::new(),
+        },
+        _ => Payload::Array {
+            width: cur.element_width()?,
+            data: Vec::new(),
+        },
+    };
+    cur.end()?;
+    let mut payload = payload;
+    let end = format!(".end {}", &name[1..]);
+
+    loop {
+        let Some(mut line) = lines.next() else {
+            return Err(cur.error(ErrorKind::Expected("a matching .end directive")));
+        };
+        line.skip_ws();
+        if line.at_end() {
+            continue;
+        }
+        if line.eat(&end) {
+            line.end()?;
+            break;
+        }
+
+        match &mut payload {
+            Payload::Packed { targets, .. } => targets.push(line.branch(32)?),
+            Payload::Sparse { keys, targets } => {
+                keys.push(line.int32()?);
+                line.expect("->")?;
+                targets.push(line.branch(32)?);
+            }
+            Payload::Array { width, data } => {
+                while !line.at_end() {
+                    line.element(*width, data)?;
+                    line.eat(",");
+                    line.skip_ws();
+                }
+            }
+        }
+        line.end()?;
+    }
+
+    Ok(Item::Payload(payload))
+}
+
+// parse a payload as printed by `Display`
+fn inline_payload(name: &str, cur: &mut Cursor) -> Result<Item, Error> {
+    let payload = match name {
+        "packed-switch-payload" => {
+            let first_key = cur.int32()?;
+            let targets = cur.comma()?.list(|cur| cur.branch(32))?;
+            Payload::Packed { first_key, targets }
+        }
+        "sparse-switch-payload" => {
+            let pairs = cur.list(|cur| {
+                let key = cur.int32()?;
+                cur.expect(":")?;
+                Ok((key, cur.branch(32)?))
+            })?;
+            let (keys, targets) = pairs.into_iter().unzip();
+            Payload::Sparse { keys, targets }
+        }
+        _ => {
+            let width = cur.element_width()?;
+            let mut data = Vec::new();
+            cur.comma()?.list(|cur| cur.element(width, &mut data))?;
+            Payload::Array { width, data }
+        }
+    };
+    cur.end()?;
+
+    Ok(Item::Payload(payload))
+}
+
+/// Parsing position within a line
+struct Cursor<'a> {
+    line: usize,
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(line: usize, text: &'a str) -> Self {
+        // strip comments outside of string literals
+        let mut quoted = false;
+        let mut escaped = false;
+        let mut end = text.len();
+        for (i, c) in text.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if quoted => escaped = true,
+                '"' => quoted = !quoted,
+                '#' if !quoted => {
+                    end = i;
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        Cursor {
+            line,
+            text: &text[..end],
+            pos: 0,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn column(&self) -> usize {
+        self.text[..self.pos].chars().count() + 1
+    }
+
+    fn error(&self, kind: ErrorKind) -> Error {
+        Error {
+            line: self.line,
+            column: self.column(),
+            kind,
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        self.pos = self.text.len() - self.rest().trim_start().len();
+    }
+
+    fn at_end(&self) -> bool {
+        self.rest().trim().is_empty()
+    }
+
+    fn end(&mut self) -> Result<(), Error> {
+        self.skip_ws();
+        match self.at_end() {
+            true => Ok(()),
+            false => Err(self.error(ErrorKind::Expected("end of line"))),
+        }
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_ws();
+        let found = self.rest().starts_with(token);
+        if found {
+            self.pos += token.len();
+        }
+        found
+    }
+
+    fn expect(&mut self, token: &'static str) -> Result<&mut Self, Error> {
+        match self.eat(token) {
+            true => Ok(self),
+            false => Err(self.error(ErrorKind::Expected(token))),
+        }
+    }
+
+    fn comma(&mut self) -> Result<&mut Self, Error> {
+        self.expect(",")
+    }
+
+    // up to the next whitespace, separator or brace
+    fn word(&mut self) -> &'a str {
+        self.skip_ws();
+        let rest = self.rest();
+        let len = rest.find(|c: char| c.is_whitespace() || matches!(c, ',' | '{' | '}')).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    // `{a, b, ...}`
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T, Error>) -> Result<Vec<T>, Error> {
+        self.expect("{")?;
+        let mut items = Vec::new();
+        if self.eat("}") {
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            if self.eat("}") {
+                return Ok(items);
+            }
+            self.comma()?;
+        }
+    }
+
+    fn number(&mut self) -> Result<i128, Error> {
+        self.skip_ws();
+        let column = self.column();
+        let rest = self.rest();
+        let (negative, unsigned) = match rest.as_bytes().first() {
+            Some(b'-') => (true, &rest[1..]),
+            Some(b'+') => (false, &rest[1..]),
+            _ => (false, rest),
+        };
+        let (radix, digits) = match unsigned.strip_prefix("0x") {
+            Some(hex) => (16, hex),
+            None => (10, unsigned),
+        };
+        let len = digits.find(|c: char| !c.is_digit(radix)).unwrap_or(digits.len());
+        let value = match i128::from_str_radix(&digits[..len], radix) {
+            Ok(v) if v <= u64::MAX as i128 => v,
+            Ok(_) => {
+                return Err(Error {
+                    line: self.line,
+                    column,
+                    kind: ErrorKind::OutOfRange,
+                })
+            }
+            Err(_) => return Err(self.error(ErrorKind::Expected("a number"))),
+        };
+        self.pos += rest.len() - digits.len() + len;
+
+        Ok(if negative { -value } else { value })
+    }
+
+    // a number of the given kind, fitting `bits`
+    fn int(&mut self, kind: Kind, bits: u32, parse: impl FnOnce(&mut Self) -> Result<i128, Error>) -> Result<i128, Error> {
+        self.skip_ws();
+        let column = self.column();
+        let value = parse(self)?;
+        match kind.fits(value, bits) {
+            true => Ok(value),
+            false => Err(Error {
+                line: self.line,
+                column,
+                kind: ErrorKind::OutOfRange,
+            }),
+        }
+    }
+
+    fn register(&mut self, bits: u32) -> Result<u16, Error> {
+        let reg = self.int(Kind::Unsigned, bits, |cur| {
+            if !cur.rest().starts_with('v') {
+                return Err(cur.error(ErrorKind::Expected("a register")));
+            }
+            cur.pos += 1;
+            match cur.rest().starts_with(|c: char| c.is_ascii_digit()) {
+                true => cur.number(),
+                false => Err(cur.error(ErrorKind::Expected("a register"))),
+            }
+        })?;
+        Ok(reg as u16)
+    }
+
+    fn literal(&mut self, bits: u32) -> Result<i128, Error> {
+        self.int(Kind::Literal, bits, Self::number)
+    }
+
+    // a wide literal, with smali's optional `L` suffix
+    fn wide(&mut self, bits: u32) -> Result<i128, Error> {
+        let value = self.literal(bits)?;
+        self.long_suffix();
+        Ok(value)
+    }
+
+    fn long_suffix(&mut self) {
+        if self.rest().starts_with(['l', 'L']) {
+            self.pos += 1;
+        }
+    }
+
+    // `Display` prints the sign-extended byte
+    fn literal4(&mut self) -> Result<i8, Error> {
+        let value = self.int(Kind::Branch, 4, |cur| match cur.number()? {
+            v @ 0x8..=0xf => Ok(v - 0x10),
+            v @ 0xf8..=0xff => Ok(v - 0x100),
+            v => Ok(v),
+        })?;
+        Ok(value as i8)
+    }
+
+    // an offset or a label, resolved once laid out
+    fn branch(&mut self, bits: u32) -> Result<Field, Error> {
+        self.skip_ws();
+        let column = self.column();
+        let value = match self.eat(":") {
+            true => Value::Label(self.label()?.to_string()),
+            false => Value::Int(self.int(Kind::Branch, bits, Self::number)?),
+        };
+        Ok(Field { column, value, bits })
+    }
+
+    // the name of a label, after its `:`
+    fn label(&mut self) -> Result<&'a str, Error> {
+        match self.word() {
+            "" => Err(self.error(ErrorKind::Expected("a label name"))),
+            label => Ok(label),
+        }
+    }
+
+    // `{:start .. :end} :handler`, after the exception type if any
+    fn catch(&mut self, ty: Option<u32>) -> Result<Catch, Error> {
+        let label = |cur: &mut Self| -> Result<(usize, String), Error> {
+            cur.skip_ws();
+            let column = cur.column();
+            cur.expect(":")?;
+            Ok((column, cur.label()?.to_string()))
+        };
+        self.expect("{")?;
+        let start = label(self)?;
+        self.expect("..")?;
+        let end = label(self)?;
+        self.expect("}")?;
+        let handler = label(self)?;
+        Ok(Catch {
+            line: self.line,
+            ty,
+            start,
+            end,
+            handler,
+        })
+    }
+
+    fn int32(&mut self) -> Result<i32, Error> {
+        Ok(self.literal(32)? as i32)
+    }
+
+    // the literal of `const/high16` is printed shifted, that of
+    // `const-wide/high16` either way
+    fn high16(&mut self, op: Opcode) -> Result<i128, Error> {
+        self.int(Kind::Literal, 16, |cur| {
+            let column = cur.column();
+            let value = cur.number()?;
+            let shift = match op {
+                Opcode::ConstHigh16 => 16,
+                _ if Kind::Literal.fits(value, 16) => 0,
+                _ => 48,
+            };
+            if value & ((1 << shift) - 1) != 0 || !Kind::Literal.fits(value, 16 + shift) {
+                return Err(Error {
+                    line: cur.line,
+                    column,
+                    kind: ErrorKind::OutOfRange,
+                });
+            }
+            if let Opcode::ConstWideHigh16 = op {
+                cur.long_suffix();
+            }
+            Ok(value >> shift)
+        })
+    }
+
+    // `{vC, vD, ...}` as the count and the 5 register slots
+    fn register_list(&mut self) -> Result<(u8, [u8; 5]), Error> {
+        self.skip_ws();
+        let column = self.column();
+        let regs = self.list(|cur| cur.register(4))?;
+        if regs.len() > 5 {
+            return Err(Error {
+                line: self.line,
+                column,
+                kind: ErrorKind::OutOfRange,
+            });
+        }
+        let mut args = [0; 5];
+        for (arg, reg) in args.iter_mut().zip(&regs) {
+            *arg = *reg as u8;
+        }
+        Ok((regs.len() as u8, args))
+    }
+
+    // `{vCCCC .. vNNNN}` or every register spelled out
+    fn register_range(&mut self) -> Result<Vec<u16>, Error> {
+        self.skip_ws();
+        let column = self.column();
+        let braces = self.rest().split('}').next().unwrap_or_default();
+        let regs: Vec<u16> = match braces.contains("..") {
+            true => {
+                self.expect("{")?;
+                let first = self.register(16)?;
+                self.expect("..")?;
+                self.skip_ws();
+                let last_column = self.column();
+                let last = self.register(16)?;
+                self.expect("}")?;
+                if last < first {
+                    return Err(Error {
+                        line: self.line,
+                        column: last_column,
+                        kind: ErrorKind::Expected("a last register no lower than the first"),
+                    });
+                }
+                (first..=last).collect()
+            }
+            false => self.list(|cur| cur.register(16))?,
+        };
+
+        let start = regs.first().copied().unwrap_or(0);
+        if regs.iter().zip(start..).any(|(r, n)| *r != n) {
+            return Err(Error {
+                line: self.line,
+                column,
+                kind: ErrorKind::Expected("consecutive registers"),
+            });
+        }
+        // the range must end within the 16-bit register space
+        if regs.len() > 0xff || start as usize + regs.len() > 0xffff {
+            return Err(Error {
+                line: self.line,
+                column,
+                kind: ErrorKind::OutOfRange,
+            });
+        }
+        Ok(regs)
+    }
+
+    // `kind@hex` or a symbolic reference
+    fn index(&mut self, kind: IndexKind, bits: u32, lookup: &impl Resolve) -> Result<u32, Error> {
+        let index = self.int(Kind::Unsigned, bits, |cur| {
+            let prefix = match kind {
+                IndexKind::String => "string@",
+                IndexKind::Type => "type@",
+                IndexKind::Field => "field@",
+                IndexKind::Proto => "proto@",
+                IndexKind::CallSite => "call_site@",
+                IndexKind::MethodHandle => "method_handle@",
+                _ => "method@",
+            };
+            if cur.eat(prefix) {
+                let rest = cur.rest();
+                let len = rest.find(|c: char| !c.is_ascii_hexdigit()).unwrap_or(rest.len());
+                let index = u64::from_str_radix(&rest[..len], 16).map_err(|_| cur.error(ErrorKind::Expected("a hexadecimal index")))?;
+                cur.pos += len;
+                return Ok(index.into());
+            }
+
+            let column = cur.column();
+            let symbol = match kind {
+                IndexKind::String => cur.string()?,
+                _ => cur.word().to_string(),
+            };
+            let resolved = match kind {
+                IndexKind::String => lookup.string(&symbol),
+                IndexKind::Type => lookup.type_index(&symbol).map(u32::from),
+                IndexKind::Field => symbol
+                    .split_once("->")
+                    .and_then(|(class, rest)| rest.split_once(':').map(|(name, ty)| (class, name, ty)))
+                    .and_then(|(class, name, ty)| lookup.field(class, name, ty))
+                    .map(u32::from),
+                IndexKind::Method => symbol
+                    .split_once("->")
+                    .and_then(|(class, rest)| rest.split_once('(').map(|(name, rest)| (class, name, rest)))
+                    .and_then(|(class, name, rest)| rest.split_once(')').map(|(params, ret)| (class, name, params, ret)))
+                    .and_then(|(class, name, params, ret)| lookup.method(class, name, params, ret))
+                    .map(u32::from),
+                IndexKind::Proto => lookup.proto(&symbol).map(u32::from),
+                _ => None,
+            };
+            match resolved {
+                Some(index) => Ok(index.into()),
+                None if symbol.is_empty() => Err(cur.error(ErrorKind::Expected(prefix))),
+                None => Err(Error {
+                    line: cur.line,
+                    column,
+                    kind: ErrorKind::Unresolved(symbol),
+                }),
+            }
+        })?;
+        Ok(index as u32)
+    }
+
+    // vA, vB
+    fn f12x(&mut self, make: fn(u8, u8) -> Instruction) -> Result<Instruction, Error> {
+        let a = self.register(4)? as u8;
+        Ok(make(a, self.comma()?.register(4)? as u8))
+    }
+
+    // vAA
+    fn f11x(&mut self, make: fn(u8) -> Instruction) -> Result<Instruction, Error> {
+        Ok(make(self.register(8)? as u8))
+    }
+
+    // vAA, vBBBB
+    fn f22x(&mut self, make: fn(u8, u16) -> Instruction) -> Result<Instruction, Error> {
+        let a = self.register(8)? as u8;
+        Ok(make(a, self.comma()?.register(16)?))
+    }
+
+    // vAAAA, vBBBB
+    fn f32x(&mut self, make: fn(u16, u16) -> Instruction) -> Result<Instruction, Error> {
+        let a = self.register(16)?;
+        Ok(make(a, self.comma()?.register(16)?))
+    }
+
+    // vAA, vBB, vCC
+    fn f23x(&mut self, make: fn(u8, u8, u8) -> Instruction) -> Result<Instruction, Error> {
+        let a = self.register(8)? as u8;
+        let b = self.comma()?.register(8)? as u8;
+        Ok(make(a, b, self.comma()?.register(8)? as u8))
+    }
+
+    // vAA, vBB, #+CC
+    fn f22b(&mut self, make: fn(u8, u8, i8) -> Instruction) -> Result<Instruction, Error> {
+        let a = self.register(8)? as u8;
+        let b = self.comma()?.register(8)? as u8;
+        Ok(make(a, b, self.comma()?.literal(8)? as i8))
+    }
+
+    // vA, vB, #+CCCC
+    fn f22s(&mut self, make: fn(u8, u8, i16) -> Instruction) -> Result<Instruction, Error> {
+        let a = self.register(4)? as u8;
+        let b = self.comma()?.register(4)? as u8;
+        Ok(make(a, b, self.comma()?.literal(16)? as i16))
+    }
+
+    // vAA, kind@BBBB
+    fn f21c(&mut self, make: fn(u8, u16) -> Instruction, kind: IndexKind, lookup: &impl Resolve) -> Result<Instruction, Error> {
+        let a = self.register(8)? as u8;
+        Ok(make(a, self.comma()?.index(kind, 16, lookup)? as u16))
+    }
+
+    // vA, vB, kind@CCCC
+    fn f22c(&mut self, make: fn(u8, u8, u16) -> Instruction, kind: IndexKind, lookup: &impl Resolve) -> Result<Instruction, Error> {
+        let a = self.register(4)? as u8;
+        let b = self.comma()?.register(4)? as u8;
+        Ok(make(a, b, self.comma()?.index(kind, 16, lookup)? as u16))
+    }
+
+    // vAA, +BBBB
+    fn f21t(&mut self, make: fn(u8, i16) -> Instruction, target: &mut Option<Field>) -> Result<Instruction, Error> {
+        let a = self.register(8)? as u8;
+        *target = Some(self.comma()?.branch(16)?);
+        Ok(make(a, 0))
+    }
+
+    // vA, vB, +CCCC
+    fn f22t(&mut self, make: fn(u8, u8, i16) -> Instruction, target: &mut Option<Field>) -> Result<Instruction, Error> {
+        let a = self.register(4)? as u8;
+        let b = self.comma()?.register(4)? as u8;
+        *target = Some(self.comma()?.branch(16)?);
+        Ok(make(a, b, 0))
+    }
+
+    // vAA, +BBBBBBBB
+    fn f31t(&mut self, make: fn(u8, i32) -> Instruction, target: &mut Option<Field>) -> Result<Instruction, Error> {
+        let a = self.register(8)? as u8;
+        *target = Some(self.comma()?.branch(32)?);
+        Ok(make(a, 0))
+    }
+
+    // {vC, vD, vE, vF, vG}, kind@BBBB
+    fn f35c(&mut self, kind: IndexKind, lookup: &impl Resolve, make: impl FnOnce(u16, u8, [u8; 5]) -> Instruction) -> Result<Instruction, Error> {
+        let (nargs, args) = self.register_list()?;
+        Ok(make(self.comma()?.index(kind, 16, lookup)? as u16, nargs, args))
+    }
+
+    // {vCCCC .. vNNNN}, kind@BBBB
+    fn f3rc(&mut self, kind: IndexKind, lookup: &impl Resolve, make: impl FnOnce(u16, Vec<u16>) -> Instruction) -> Result<Instruction, Error> {
+        let args = self.register_range()?;
+        Ok(make(self.comma()?.index(kind, 16, lookup)? as u16, args))
+    }
+
+    // a double quoted string literal, unescaped
+    fn string(&mut self) -> Result<String, Error> {
+        self.expect("\"")?;
+        let mut s = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            let c = match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Ok(s);
+                }
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('0') => '\0',
+                    Some('u') => {
+                        let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                        let code = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+                        code.ok_or_else(|| self.error(ErrorKind::Expected("a \\u escape")))?
+                    }
+                    Some(c) => c,
+                    None => break,
+                },
+                c => c,
+            };
+            s.push(c);
+        }
+
+        self.pos = self.text.len();
+        Err(self.error(ErrorKind::Expected("\"")))
+    }
+
+    fn element_width(&mut self) -> Result<u16, Error> {
+        self.skip_ws();
+        let column = self.column();
+        match self.number()? {
+            width @ (1 | 2 | 4 | 8) => Ok(width as u16),
+            _ => Err(Error {
+                line: self.line,
+                column,
+                kind: ErrorKind::Expected("an element width of 1, 2, 4 or 8"),
+            }),
+        }
+    }
+
+    // an array-data element, appended as little-endian bytes
+    fn element(&mut self, width: u16, data: &mut Vec<u8>) -> Result<(), Error> {
+        let value = self.literal(8 * width as u32)?;
+        // smali's type suffixes
+        if self.rest().starts_with(['t', 's', 'l', 'L']) {
+            self.pos += 1;
+        }
+        data.extend(&(value as u64).to_le_bytes()[..width as usize]);
+        Ok(())
+    }
+}
@@ -2,7 +2,7 @@
 
 use std::collections::{BTreeMap, BTreeSet};
 
-use crate::{ControlFlow, Instruction};
+use crate::{decode::Error, ControlFlow, Instruction};
 
 /// A simple basic block of sequential instructions.
 ///
@@ -13,7 +13,8 @@ use crate::{ControlFlow, Instruction};
 /// (outside of this basic block), or it may have multiple jump locations based
 /// on a conditional, or it may even return from the method, terminating local
 /// control flow. The `next` Vec stores this information.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BasicBlock {
     /// Instructions contained in this basic block
     pub instructions: Vec<Instruction>,
@@ -22,7 +23,8 @@ pub struct BasicBlock {
 }
 
 /// Possible branch targets finalizing a basic block
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(tag = "kind", content = "args"))]
 pub enum NextBranch {
     /// Termination (e.g. return or throw)
     None,
@@ -53,10 +55,13 @@ impl NextBranch {
 /// within the method, for example the offsets of the exception handling catch
 /// blocks, parsed from the relevant [dex table].
 ///
-/// [dex]: https://source.android.com/docs/core/runtime/dex-format#type-item
-
+/// Fails if any reachable instruction cannot be decoded, or if a branch leaves
+/// the bounds of `bytecode`.
+///
+/// [dex table]: https://source.android.com/docs/core/runtime/dex-format#type-item
+//
 // TODO: implement block splitting to reduce total blocks returned
-pub fn basic_blocks(bytecode: &[u16], entries: &[usize]) -> BTreeMap<usize, BasicBlock> {
+pub fn basic_blocks(bytecode: &[u16], entries: &[usize]) -> Result<BTreeMap<usize, BasicBlock>, Error> {
     let mut bbs = BTreeMap::new();
     let mut search_next = BTreeSet::from([0]);
     for e in entries {
@@ -64,7 +69,7 @@ pub fn basic_blocks(bytecode: &[u16], entries: &[usize]) -> BTreeMap<usize, Basi
     }
 
     while let Some(start_addr) = search_next.pop_first() {
-        let bb = decode_bb(bytecode, start_addr, &search_next);
+        let bb = decode_bb(bytecode, start_addr, &search_next)?;
         for next in bb.next.iter() {
             if !bbs.contains_key(&next) {
                 search_next.insert(next);
@@ -73,18 +78,19 @@ pub fn basic_blocks(bytecode: &[u16], entries: &[usize]) -> BTreeMap<usize, Basi
         bbs.insert(start_addr, bb);
     }
 
-    bbs
+    Ok(bbs)
 }
 
 // decode a single basic block starting at entry_point, and stopping before any other known entry_points
-fn decode_bb(bytecode: &[u16], entry_point: usize, avoid: &BTreeSet<usize>) -> BasicBlock {
+fn decode_bb(bytecode: &[u16], entry_point: usize, avoid: &BTreeSet<usize>) -> Result<BasicBlock, Error> {
     let mut instructions = Vec::new();
     let next;
 
     let mut cursor = entry_point;
 
     loop {
-        let inst = crate::decode::decode_one(&mut &bytecode[cursor..]).unwrap();
+        let mut rest = bytecode.get(cursor..).ok_or(Error::Truncated)?;
+        let inst = crate::decode::decode_one(&mut rest).map_err(|e| e.rebase(cursor))?;
         let cf = inst.control_flow();
         let len = inst.len();
         instructions.push(inst);
@@ -97,9 +103,9 @@ fn decode_bb(bytecode: &[u16], entry_point: usize, avoid: &BTreeSet<usize>) -> B
                 }
                 NextBranch::Goto(cursor)
             }
-            ControlFlow::GoTo(t) => NextBranch::Goto((cursor as i32 + t) as usize),
+            ControlFlow::GoTo(t) => NextBranch::Goto(branch_target(bytecode, cursor, t)?),
             ControlFlow::Branch(t) => NextBranch::Cond {
-                t: (cursor as i32 + t as i32) as usize,
+                t: branch_target(bytecode, cursor, t.into())?,
                 f: cursor + len,
             },
 
@@ -109,5 +115,15 @@ fn decode_bb(bytecode: &[u16], entry_point: usize, avoid: &BTreeSet<usize>) -> B
         break;
     }
 
-    BasicBlock { instructions, next }
+    Ok(BasicBlock { instructions, next })
+}
+
+// resolve a relative branch, making sure it lands within the bytecode
+fn branch_target(bytecode: &[u16], from: usize, offset: i32) -> Result<usize, Error> {
+    let to = from as isize + offset as isize;
+    if to < 0 || to as usize >= bytecode.len() {
+        return Err(Error::BadBranchTarget { from, to });
+    }
+
+    Ok(to as usize)
 }
@@ -0,0 +1,446 @@
+//! Label-based code generation
+//!
+//! [`CodeBuilder`] takes abstract operations (branch to a label, load a
+//! constant, move a register) and picks the smallest encoding for each:
+//!  - `goto` grows to `goto/16` and `goto/32` as needed, and conditional
+//!    branches out of 16-bit range become an inverted branch over a `goto/32`.
+//!    Branch sizes are relaxed until every offset is stable.
+//!  - constants use `const/4`, `const/16`, `const/high16` or `const` (and their
+//!    wide counterparts) depending on the value and destination register
+//!  - moves use `move`, `move/from16` or `move/16` depending on the registers
+//!
+//! Switch and array data payloads are placed after the code, 32-bit aligned
+//! with `nop` padding.
+//!
+//! ```
+//! use dalvik::builder::{CodeBuilder, Condition};
+//! use dalvik::Instruction;
+//!
+//! let mut code = CodeBuilder::new();
+//! let done = code.new_label();
+//! code.if_testz(Condition::Eq, 0, done).constant(0, 0x10000).bind(done).push(Instruction::Return(0));
+//!
+//! let insts = code.build().unwrap();
+//! assert_eq!(insts, [Instruction::IfEqz(0, 4), Instruction::ConstHigh16(0, 1), Instruction::Return(0)]);
+//! ```
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub use crate::Condition;
+use crate::Instruction;
+
+/// Code building error
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Error {
+    /// A branch targets a label that was never bound
+    UnboundLabel,
+    /// A label was bound more than once
+    DuplicateLabel,
+    /// A label created by another [`CodeBuilder`] was bound or branched to
+    ForeignLabel,
+    /// A register does not fit any encoding of the operation, e.g. `v16` in a
+    /// two-register conditional branch
+    OutOfRange,
+    /// An instruction added through [`CodeBuilder::push`] could not be encoded
+    Encode(crate::encode::Error),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::UnboundLabel => f.write_str("branch to an unbound label"),
+            Error::DuplicateLabel => f.write_str("label bound more than once"),
+            Error::ForeignLabel => f.write_str("label of another builder"),
+            Error::OutOfRange => f.write_str("register out of range"),
+            Error::Encode(e) => f.write_fmt(format_args!("encoding failed: {e}")),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A branch target, bound to a position in the code with [`CodeBuilder::bind`]
+///
+/// Labels are only valid in the builder that created them (and its clones).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Label {
+    builder: usize,
+    index: usize,
+}
+
+/// Register kind of a move
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MoveKind {
+    /// `move`, for 32-bit values
+    Single,
+    /// `move-wide`, for register pairs
+    Wide,
+    /// `move-object`, for references
+    Object,
+}
+
+/// Builder of a method's bytecode from abstract operations
+#[derive(Debug, Clone)]
+pub struct CodeBuilder {
+    /// Identifies the labels of this builder
+    id: usize,
+    ops: Vec<Op>,
+    payloads: Vec<Payload>,
+    /// Index of the op each label is bound before, and how many times
+    labels: Vec<(usize, usize)>,
+    /// Whether a label of another builder was bound
+    foreign: bool,
+}
+
+impl Default for CodeBuilder {
+    fn default() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        CodeBuilder {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            ops: Vec::new(),
+            payloads: Vec::new(),
+            labels: Vec::new(),
+            foreign: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    Raw(Instruction),
+    Goto(Label),
+    If {
+        cond: Condition,
+        a: u8,
+        b: Option<u8>,
+        target: Label,
+    },
+    Const {
+        dst: u8,
+        value: i32,
+    },
+    ConstWide {
+        dst: u8,
+        value: i64,
+    },
+    Move {
+        kind: MoveKind,
+        dst: u16,
+        src: u16,
+    },
+    /// A 31t instruction referencing the payload of that index
+    Payload {
+        reg: u8,
+        payload: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum Payload {
+    Packed { first_key: i32, targets: Vec<Label> },
+    Sparse { keys: Vec<i32>, targets: Vec<Label> },
+    Array { element_width: u16, data: Vec<u8> },
+}
+
+impl CodeBuilder {
+    /// Create an empty builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new label, to be bound later
+    pub fn new_label(&mut self) -> Label {
+        self.labels.push((0, 0));
+        Label {
+            builder: self.id,
+            index: self.labels.len() - 1,
+        }
+    }
+
+    /// Bind `label` to the next operation added
+    ///
+    /// Binding a label of another builder makes [`build`][`Self::build`] fail
+    /// with [`Error::ForeignLabel`].
+    pub fn bind(&mut self, label: Label) -> &mut Self {
+        match self.labels.get_mut(label.index) {
+            Some((op, bound)) if label.builder == self.id => {
+                *op = self.ops.len();
+                *bound += 1;
+            }
+            _ => self.foreign = true,
+        }
+        self
+    }
+
+    /// Add an instruction as is
+    ///
+    /// Branch offsets in the instruction are not adjusted, so use the label
+    /// based operations for branches and payload references instead.
+    pub fn push(&mut self, inst: Instruction) -> &mut Self {
+        self.ops.push(Op::Raw(inst));
+        self
+    }
+
+    /// Unconditionally branch to `target`
+    pub fn goto(&mut self, target: Label) -> &mut Self {
+        self.ops.push(Op::Goto(target));
+        self
+    }
+
+    /// Branch to `target` if `cond` holds between `a` and `b`, e.g. `if-lt`
+    pub fn if_test(&mut self, cond: Condition, a: u8, b: u8, target: Label) -> &mut Self {
+        self.ops.push(Op::If { cond, a, b: Some(b), target });
+        self
+    }
+
+    /// Branch to `target` if `cond` holds between `a` and zero, e.g. `if-ltz`
+    pub fn if_testz(&mut self, cond: Condition, a: u8, target: Label) -> &mut Self {
+        self.ops.push(Op::If { cond, a, b: None, target });
+        self
+    }
+
+    /// Load a 32-bit constant
+    pub fn constant(&mut self, dst: u8, value: i32) -> &mut Self {
+        self.ops.push(Op::Const { dst, value });
+        self
+    }
+
+    /// Load a 64-bit constant into a register pair
+    pub fn constant_wide(&mut self, dst: u8, value: i64) -> &mut Self {
+        self.ops.push(Op::ConstWide { dst, value });
+        self
+    }
+
+    /// Move a register of the given kind
+    pub fn mov(&mut self, kind: MoveKind, dst: u16, src: u16) -> &mut Self {
+        self.ops.push(Op::Move { kind, dst, src });
+        self
+    }
+
+    /// `packed-switch` on `reg`, with cases from `first_key` up branching to
+    /// `targets`
+    pub fn packed_switch(&mut self, reg: u8, first_key: i32, targets: &[Label]) -> &mut Self {
+        self.payload(
+            reg,
+            Payload::Packed {
+                first_key,
+                targets: targets.to_vec(),
+            },
+        )
+    }
+
+    /// `sparse-switch` on `reg`, with `(key, target)` cases
+    pub fn sparse_switch(&mut self, reg: u8, cases: &[(i32, Label)]) -> &mut Self {
+        let (keys, targets) = cases.iter().copied().unzip();
+        self.payload(reg, Payload::Sparse { keys, targets })
+    }
+
+    /// `fill-array-data` of the array in `reg`, from little-endian elements of
+    /// `element_width` bytes each
+    pub fn fill_array_data(&mut self, reg: u8, element_width: u16, data: Vec<u8>) -> &mut Self {
+        self.payload(reg, Payload::Array { element_width, data })
+    }
+
+    fn payload(&mut self, reg: u8, payload: Payload) -> &mut Self {
+        self.ops.push(Op::Payload {
+            reg,
+            payload: self.payloads.len(),
+        });
+        self.payloads.push(payload);
+        self
+    }
+
+    /// Lay out the code, choosing an encoding for every operation
+    pub fn build(self) -> Result<Vec<Instruction>, Error> {
+        let CodeBuilder {
+            id,
+            ops,
+            payloads,
+            labels,
+            foreign,
+        } = self;
+        if foreign {
+            return Err(Error::ForeignLabel);
+        }
+        if labels.iter().any(|(_, bound)| *bound > 1) {
+            return Err(Error::DuplicateLabel);
+        }
+        let offset = |addresses: &[usize], label: Label, from: usize| -> Result<i32, Error> {
+            match labels.get(label.index) {
+                _ if label.builder != id => Err(Error::ForeignLabel),
+                Some((_, 0)) => Err(Error::UnboundLabel),
+                Some((op, _)) => Ok(addresses[*op] as i32 - from as i32),
+                None => Err(Error::ForeignLabel),
+            }
+        };
+
+        // start from the smallest encodings, and only ever grow them so the
+        // relaxation terminates
+        let mut sizes = ops.iter().map(initial_size).collect::<Result<Vec<_>, _>>()?;
+        let addresses = loop {
+            let addresses = prefix_sums(&sizes);
+            let mut stable = true;
+            for (i, op) in ops.iter().enumerate() {
+                let size = match op {
+                    Op::Goto(target) => match offset(&addresses, *target, addresses[i])? {
+                        0 => 3,
+                        off if i8::try_from(off).is_ok() => 1,
+                        off if i16::try_from(off).is_ok() => 2,
+                        _ => 3,
+                    },
+                    Op::If { target, .. } => match offset(&addresses, *target, addresses[i])? {
+                        off if off != 0 && i16::try_from(off).is_ok() => 2,
+                        _ => 5,
+                    },
+                    _ => continue,
+                };
+                if size > sizes[i] {
+                    sizes[i] = size;
+                    stable = false;
+                }
+            }
+            if stable {
+                break addresses;
+            }
+        };
+
+        // payloads follow the code, 32-bit aligned, and their switch targets
+        // are relative to the referencing instruction
+        let referrers = ops
+            .iter()
+            .zip(&addresses)
+            .filter_map(|(op, addr)| matches!(op, Op::Payload { .. }).then_some(*addr));
+        let mut end = addresses[ops.len()];
+        let mut laid_out = Vec::with_capacity(payloads.len());
+        for (payload, referrer) in payloads.into_iter().zip(referrers) {
+            let targets = |targets: Vec<Label>| -> Result<Vec<i32>, Error> { targets.into_iter().map(|t| offset(&addresses, t, referrer)).collect() };
+            let inst = match payload {
+                Payload::Packed { first_key, targets: t } => Instruction::PackedSwitchPayload {
+                    first_key,
+                    targets: targets(t)?,
+                },
+                Payload::Sparse { keys, targets: t } => Instruction::SparseSwitchPayload { keys, targets: targets(t)? },
+                Payload::Array { element_width, data } => Instruction::FillArrayDataPayload { element_width, data },
+            };
+            let padding = end % 2;
+            end += padding;
+            laid_out.push((end, padding, inst));
+            end += laid_out[laid_out.len() - 1].2.len();
+        }
+
+        let mut insts = Vec::with_capacity(ops.len() + 2 * laid_out.len());
+        for (i, op) in ops.into_iter().enumerate() {
+            let addr = addresses[i];
+            let inst = match op {
+                Op::Raw(inst) => inst,
+                Op::Goto(target) => {
+                    let off = offset(&addresses, target, addr)?;
+                    match sizes[i] {
+                        1 => Instruction::Goto(off as i8),
+                        2 => Instruction::Goto16(off as i16),
+                        _ => Instruction::Goto32(off),
+                    }
+                }
+                Op::If { cond, a, b, target } => {
+                    let off = offset(&addresses, target, addr)?;
+                    if sizes[i] == 2 {
+                        Instruction::conditional(cond, a, b, off as i16)
+                    } else {
+                        // branch over a goto/32 to the target
+                        insts.push(Instruction::conditional(cond.inverse(), a, b, 5));
+                        Instruction::Goto32(off - 2)
+                    }
+                }
+                Op::Const { dst, value } => const_inst(dst, value),
+                Op::ConstWide { dst, value } => const_wide_inst(dst, value),
+                Op::Move { kind, dst, src } => move_inst(kind, dst, src),
+                Op::Payload { reg, payload } => {
+                    let off = (laid_out[payload].0 - addr) as i32;
+                    match laid_out[payload].2 {
+                        Instruction::PackedSwitchPayload { .. } => Instruction::PackedSwitch(reg, off),
+                        Instruction::SparseSwitchPayload { .. } => Instruction::SparseSwitch(reg, off),
+                        _ => Instruction::FillArrayData(reg, off),
+                    }
+                }
+            };
+            insts.push(inst);
+        }
+
+        for (_, padding, payload) in laid_out {
+            if padding > 0 {
+                insts.push(Instruction::Nop);
+            }
+            insts.push(payload);
+        }
+
+        Ok(insts)
+    }
+
+    /// Lay out the code as with [`build`][`Self::build`], and encode it
+    pub fn build_units(self) -> Result<Vec<u16>, Error> {
+        let mut units = Vec::new();
+        for inst in self.build()? {
+            inst.encode(&mut units).map_err(Error::Encode)?;
+        }
+        Ok(units)
+    }
+}
+
+// size of the smallest encoding of an operation, in u16 codepoints
+fn initial_size(op: &Op) -> Result<usize, Error> {
+    Ok(match op {
+        Op::Raw(inst) => inst.len(),
+        Op::Goto(_) => 1,
+        Op::If { a, b: Some(b), .. } if *a > 0xf || *b > 0xf => return Err(Error::OutOfRange),
+        Op::If { .. } => 2,
+        Op::Const { dst, value } => const_inst(*dst, *value).len(),
+        Op::ConstWide { dst, value } => const_wide_inst(*dst, *value).len(),
+        Op::Move { kind, dst, src } => move_inst(*kind, *dst, *src).len(),
+        Op::Payload { .. } => 3,
+    })
+}
+
+// addresses of each operation, followed by the end address
+fn prefix_sums(sizes: &[usize]) -> Vec<usize> {
+    let mut addresses = Vec::with_capacity(sizes.len() + 1);
+    let mut addr = 0;
+    addresses.push(addr);
+    for size in sizes {
+        addr += size;
+        addresses.push(addr);
+    }
+    addresses
+}
+
+fn const_inst(dst: u8, value: i32) -> Instruction {
+    match value {
+        -8..=7 if dst <= 0xf => Instruction::Const4(dst, value as i8),
+        _ if i16::try_from(value).is_ok() => Instruction::Const16(dst, value as i16),
+        _ if value & 0xffff == 0 => Instruction::ConstHigh16(dst, (value >> 16) as i16),
+        _ => Instruction::Const(dst, value as u32),
+    }
+}
+
+fn const_wide_inst(dst: u8, value: i64) -> Instruction {
+    match value {
+        _ if i16::try_from(value).is_ok() => Instruction::ConstWide16(dst, value as i16),
+        _ if i32::try_from(value).is_ok() => Instruction::ConstWide32(dst, value as u32),
+        _ if value & 0xffff_ffff_ffff == 0 => Instruction::ConstWideHigh16(dst, (value >> 48) as u16),
+        _ => Instruction::ConstWide(dst, value as u64),
+    }
+}
+
+fn move_inst(kind: MoveKind, dst: u16, src: u16) -> Instruction {
+    match kind {
+        MoveKind::Single if dst <= 0xf && src <= 0xf => Instruction::Move(dst as u8, src as u8),
+        MoveKind::Single if dst <= 0xff => Instruction::MoveFrom16(dst as u8, src),
+        MoveKind::Single => Instruction::Move16(dst, src),
+        MoveKind::Wide if dst <= 0xf && src <= 0xf => Instruction::MoveWide(dst as u8, src as u8),
+        MoveKind::Wide if dst <= 0xff => Instruction::MoveWideFrom16(dst as u8, src),
+        MoveKind::Wide => Instruction::MoveWide16(dst, src),
+        MoveKind::Object if dst <= 0xf && src <= 0xf => Instruction::MoveObject(dst as u8, src as u8),
+        MoveKind::Object if dst <= 0xff => Instruction::MoveObjectFrom16(dst as u8, src),
+        MoveKind::Object => Instruction::MoveObject16(dst, src),
+    }
+}
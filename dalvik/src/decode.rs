@@ -3,7 +3,10 @@
 use crate::Instruction;
 
 /// Decoding error
-#[derive(Debug)]
+///
+/// Offsets carried by the variants are in u16 codepoints, relative to the start
+/// of the bytecode handed to the decoding function.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub enum Error {
     /// An instruction was truncated
@@ -12,43 +15,348 @@ pub enum Error {
     Truncated,
     /// Instruction was not encoded correctly
     Encoding,
+    /// The opcode is unassigned, e.g. 0x3e-0x43, 0x73, 0x79-0x7a or 0xe3-0xf9
+    UnknownOpcode {
+        /// The unassigned opcode
+        opcode: u8,
+        /// Offset of the instruction
+        offset: usize,
+    },
+    /// A branch leaves the bounds of the method's bytecode
+    BadBranchTarget {
+        /// Offset of the branching instruction
+        from: usize,
+        /// Offset the instruction branches to
+        to: isize,
+    },
+    /// The opcode is newer than the dex format version being decoded, e.g.
+    /// `const-method-handle` (039) in a 035 file
+    UnsupportedOpcode {
+        /// The offending opcode
+        opcode: u8,
+        /// Offset of the instruction
+        offset: usize,
+        /// Dex format version given in [`DecodeOptions::dex_version`]
+        version: u32,
+    },
     /// The bytecode was inline metadata and should be skipped over
     ///
-    /// Possible tables formats:
-    ///  - [Packed Switch Payload][1]
-    ///  - [Sparse Switch Payload][2]
-    ///  - [Fill Array Data Payload][3]
-    ///
-    /// [1]: https://source.android.com/docs/core/runtime/dalvik-bytecode#packed-switch
-    /// [2]: https://source.android.com/docs/core/runtime/dalvik-bytecode#sparse-switch
-    /// [3]: https://source.android.com/docs/core/runtime/dalvik-bytecode#fill-array
+    /// Payload tables are now decoded as [`Instruction::PackedSwitchPayload`],
+    /// [`Instruction::SparseSwitchPayload`] and
+    /// [`Instruction::FillArrayDataPayload`], so this is never returned.
+    #[deprecated(note = "payload tables are decoded into their own `Instruction` variants")]
     Metadata {
         /// Length (in u16 codepoints) of the table
         length: usize,
     },
 }
 
+impl Error {
+    /// Shift any offsets in the error by `base`, for errors raised while
+    /// decoding a subslice starting at `base`
+    pub(crate) fn rebase(self, base: usize) -> Self {
+        match self {
+            Error::UnknownOpcode { opcode, offset } => Error::UnknownOpcode { opcode, offset: offset + base },
+            Error::UnsupportedOpcode { opcode, offset, version } => Error::UnsupportedOpcode {
+                opcode,
+                offset: offset + base,
+                version,
+            },
+            Error::BadBranchTarget { from, to } => Error::BadBranchTarget {
+                from: from + base,
+                to: to + base as isize,
+            },
+            e => e,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Truncated => f.write_str("instruction truncated"),
+            Error::Encoding => f.write_str("invalid instruction encoding"),
+            Error::UnknownOpcode { opcode, offset } => f.write_fmt(format_args!("unknown opcode {opcode:#04x} at {offset:#x}")),
+            Error::BadBranchTarget { from, to } => f.write_fmt(format_args!("branch at {from:#x} targets out of bounds offset {to:#x}")),
+            Error::UnsupportedOpcode { opcode, offset, version } => {
+                f.write_fmt(format_args!("opcode {opcode:#04x} at {offset:#x} is not valid in dex version {version:03}"))
+            }
+            #[allow(deprecated)]
+            Error::Metadata { length } => f.write_fmt(format_args!("inline metadata of {length} codepoints")),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 /// Decode all [`Instructions`][`Instruction`] from a slice of codepoints
-pub fn decode_all(mut bytecode: &[u16]) -> Result<Vec<Instruction>, Error> {
-    let mut ins = Vec::new();
-    while !bytecode.is_empty() {
-        dbg!(bytecode.len());
-        ins.push(match decode_one(&mut bytecode) {
-            Ok(i) => i,
-            // skip over metadata tables
-            Err(Error::Metadata { length }) => {
-                bytecode = &bytecode[length..];
-                continue;
+///
+/// Inline payload tables (switch and array data) are returned in sequence as
+/// their own [`Instruction`] variants.
+pub fn decode_all(bytecode: &[u16]) -> Result<Vec<Instruction>, Error> {
+    Decoder::new(bytecode).map(|r| r.map(|(_, i)| i).map_err(|(_, e)| e)).collect()
+}
+
+/// Decode all [`Instructions`][`Instruction`] from raw dex bytes
+///
+/// `bytecode` is the `insns` array of a code_item as it appears in the dex
+/// file, and `endian` the byte order declared by the dex header.
+pub fn decode_all_bytes(bytecode: &[u8], endian: Endian) -> Result<Vec<Instruction>, Error> {
+    Decoder::from_bytes(bytecode, endian).map(|r| r.map(|(_, i)| i).map_err(|(_, e)| e)).collect()
+}
+
+/// Decode all [`Instructions`][`Instruction`] from a slice of codepoints, with
+/// the given [`DecodeOptions`]
+pub fn decode_all_with(bytecode: &[u16], options: &DecodeOptions) -> Result<Vec<Instruction>, Error> {
+    Decoder::new(bytecode)
+        .with_options(*options)
+        .map(|r| r.map(|(_, i)| i).map_err(|(_, e)| e))
+        .collect()
+}
+
+/// Options controlling which instructions are accepted while decoding
+///
+/// The default decodes exactly the instructions of the standard dex format.
+///
+/// ```
+/// use dalvik::decode::{decode_all_with, DecodeOptions};
+/// use dalvik::Instruction;
+///
+/// // return-void-no-barrier
+/// let options = DecodeOptions::new().with_api_level(26).with_dex_version(35);
+/// assert_eq!(decode_all_with(&[0x0073], &options), Ok(vec![Instruction::ReturnVoidNoBarrier]));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct DecodeOptions {
+    /// Android API level the bytecode was optimized for
+    ///
+    /// When set, the runtime-private opcodes of the targeted runtime are
+    /// decoded into dedicated [`Instruction`] variants instead of being
+    /// rejected as [`Error::UnknownOpcode`]:
+    ///  - API levels below 21 select Dalvik odex opcodes (`execute-inline`,
+    ///    `iget-quick`, `return-void-barrier`, ...)
+    ///  - API levels 21 through 30 select ART quickened opcodes
+    ///    (`return-void-no-barrier`, `iget-quick`, ...) as found in vdex files
+    ///  - later API levels no longer quicken bytecode
+    pub api_level: Option<u32>,
+    /// Dex format version of the file the bytecode comes from, e.g. `35` for
+    /// the `dex\n035\0` magic
+    ///
+    /// When set, opcodes introduced in later versions (see
+    /// [`Opcode::dex_version`][`crate::opcode::Opcode::dex_version`]) are
+    /// refused with [`Error::UnsupportedOpcode`]. Leave it unset to decode
+    /// them regardless and inspect [`Instruction::opcode`] instead.
+    pub dex_version: Option<u32>,
+}
+
+impl DecodeOptions {
+    /// Options decoding the standard dex format, same as the default
+    pub const fn new() -> Self {
+        DecodeOptions {
+            api_level: None,
+            dex_version: None,
+        }
+    }
+
+    /// Set [`api_level`][`DecodeOptions::api_level`]
+    pub const fn with_api_level(self, api_level: u32) -> Self {
+        DecodeOptions {
+            api_level: Some(api_level),
+            ..self
+        }
+    }
+
+    /// Set [`dex_version`][`DecodeOptions::dex_version`]
+    pub const fn with_dex_version(self, dex_version: u32) -> Self {
+        DecodeOptions {
+            dex_version: Some(dex_version),
+            ..self
+        }
+    }
+
+    /// First API level running ART rather than Dalvik
+    pub(crate) const ART: u32 = 21;
+    /// First API level where ART stopped quickening bytecode
+    pub(crate) const NO_QUICKENING: u32 = 31;
+    /// API level replacing `invoke-direct-empty` with `invoke-object-init/range`
+    pub(crate) const OBJECT_INIT: u32 = 14;
+}
+
+/// Byte order of the codepoints in a dex file, as declared by the `endian_tag`
+/// of its header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endian {
+    /// `ENDIAN_CONSTANT`, used by all standard dex files
+    Little,
+    /// `REVERSE_ENDIAN_CONSTANT`
+    Big,
+}
+
+/// Lazy [`Instruction`] decoder over a method's bytecode
+///
+/// Yields each instruction along with its address, in u16 codepoints from the
+/// start of the bytecode. Inline payload tables are yielded in sequence like
+/// any other instruction (see [`Instruction::is_payload`]).
+///
+/// Decoding cannot continue past a malformed instruction, so the first error is
+/// yielded along with the address of the faulting instruction and the iterator
+/// ends.
+///
+/// Raw dex bytes can be decoded in place with [`Decoder::from_bytes`].
+///
+/// ```
+/// use dalvik::{decode::Decoder, Instruction};
+///
+/// // const/4 v1, 0x0; return v1
+/// let mut decoder = Decoder::new(&[0x0112, 0x010f]);
+/// assert_eq!(decoder.next(), Some(Ok((0, Instruction::Const4(1, 0)))));
+/// assert_eq!(decoder.next(), Some(Ok((1, Instruction::Return(1)))));
+/// assert_eq!(decoder.next(), None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Decoder<'a> {
+    bytecode: Source<'a>,
+    options: DecodeOptions,
+    address: usize,
+    failed: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Source<'a> {
+    Units(&'a [u16]),
+    Bytes(d::Bytes<'a>),
+}
+
+impl<'a> Decoder<'a> {
+    /// Decode `bytecode`, starting at address 0
+    pub fn new(bytecode: &'a [u16]) -> Self {
+        Self {
+            bytecode: Source::Units(bytecode),
+            options: DecodeOptions::default(),
+            address: 0,
+            failed: false,
+        }
+    }
+
+    /// Decode raw dex bytes of the given byte order, starting at address 0
+    ///
+    /// See [`decode_all_bytes`].
+    pub fn from_bytes(bytecode: &'a [u8], endian: Endian) -> Self {
+        Self {
+            bytecode: Source::Bytes(d::Bytes { bytes: bytecode, endian }),
+            options: DecodeOptions::default(),
+            address: 0,
+            failed: false,
+        }
+    }
+
+    /// Decode with the given [`DecodeOptions`]
+    pub fn with_options(mut self, options: DecodeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Address of the next instruction to be decoded
+    pub fn address(&self) -> usize {
+        self.address
+    }
+}
+
+impl Iterator for Decoder<'_> {
+    type Item = Result<(usize, Instruction), (usize, Error)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let decoded = match &mut self.bytecode {
+            _ if self.failed => return None,
+            Source::Units([]) => return None,
+            Source::Bytes(d::Bytes { bytes: [], .. }) => return None,
+            Source::Units(units) => decode_from(units, &self.options),
+            Source::Bytes(bytes) => decode_from(bytes, &self.options),
+        };
+
+        let address = self.address;
+        match decoded {
+            Ok(inst) => {
+                self.address += inst.len();
+                Some(Ok((address, inst)))
+            }
+            Err(e) => {
+                self.failed = true;
+                Some(Err((address, e.rebase(address))))
             }
-            Err(e) => return Err(e),
-        });
+        }
     }
-    Ok(ins)
 }
 
+impl std::iter::FusedIterator for Decoder<'_> {}
+
 /// Decode one [`Instruction`], advancing the given slice to the next instruction
+///
+/// On error the slice is left untouched.
 pub fn decode_one(bytecode: &mut &[u16]) -> Result<Instruction, Error> {
-    let op = bytecode[0] as u8;
+    decode_from(bytecode, &DecodeOptions::default())
+}
+
+/// Decode one [`Instruction`] with the given [`DecodeOptions`], advancing the
+/// given slice to the next instruction
+///
+/// On error the slice is left untouched.
+pub fn decode_one_with(bytecode: &mut &[u16], options: &DecodeOptions) -> Result<Instruction, Error> {
+    decode_from(bytecode, options)
+}
+
+/// Decode one [`Instruction`] from raw dex bytes of the given byte order,
+/// advancing the given slice to the next instruction
+///
+/// On error the slice is left untouched.
+pub fn decode_one_bytes(bytecode: &mut &[u8], endian: Endian) -> Result<Instruction, Error> {
+    let mut bytes = d::Bytes { bytes: bytecode, endian };
+    let inst = decode_from(&mut bytes, &DecodeOptions::default())?;
+    *bytecode = bytes.bytes;
+
+    Ok(inst)
+}
+
+fn decode_from(bytecode: &mut impl d::CodeUnits, options: &DecodeOptions) -> Result<Instruction, Error> {
+    let mut cursor = *bytecode;
+    let inst = decode_inner(&mut cursor, options)?;
+    *bytecode = cursor;
+
+    Ok(inst)
+}
+
+fn decode_inner(bytecode: &mut impl d::CodeUnits, options: &DecodeOptions) -> Result<Instruction, Error> {
+    let Some(first) = bytecode.peek() else {
+        return Err(Error::Truncated);
+    };
+    let op = first as u8;
+
+    // optimized opcodes shadow both unused and (for odex) standard opcodes
+    match options.api_level {
+        Some(api) if api < DecodeOptions::ART => {
+            if let Some(inst) = decode_dalvik_odex(op, bytecode, api)? {
+                return Ok(inst);
+            }
+        }
+        Some(api) if api < DecodeOptions::NO_QUICKENING => {
+            if let Some(inst) = decode_art_quickened(op, bytecode)? {
+                return Ok(inst);
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(version) = options.dex_version {
+        if crate::opcode::Opcode::from_u8(op).dex_version() > version {
+            return Err(Error::UnsupportedOpcode {
+                opcode: op,
+                offset: 0,
+                version,
+            });
+        }
+    }
+
     let inst = match op {
         opcode::NOP => match d::aa_op(bytecode)? {
             0x00 => Instruction::Nop,
@@ -59,14 +367,9 @@ pub fn decode_one(bytecode: &mut &[u16]) -> Result<Instruction, Error> {
             // targets   int[]  list of `size` relative branch targets
             0x01 => {
                 let size = d::consume_u16(bytecode)?;
-                let _first_key = d::consume_u32(bytecode)?;
-                // skip the targets table
-                let num_codes = 2                // 2 u16 per u32
-                                * size as usize; // length of each table;
-                if bytecode.len() < num_codes {
-                    return Err(Error::Truncated);
-                }
-                todo!("handle inline metadata?");
+                let first_key = d::consume_u32(bytecode)? as i32;
+                let targets = d::consume_i32s(bytecode, size as usize)?;
+                Instruction::PackedSwitchPayload { first_key, targets }
             }
             // sparse-switch-payload
             // ident    ushort  opcode, already parsed
@@ -75,14 +378,13 @@ pub fn decode_one(bytecode: &mut &[u16]) -> Result<Instruction, Error> {
             // targets  int[]   list of `size` relative branch targets
             0x02 => {
                 let size = d::consume_u16(bytecode)?;
-                // skip the keys and targets tables
-                let num_codes = 2                // 2 u16 per u32
-                                * 2              // 2 tables of equal length
-                                * size as usize; // length of each table;
-                if bytecode.len() < num_codes {
+                // both tables must be present before consuming either
+                if bytecode.remaining() < 2 * 2 * size as usize {
                     return Err(Error::Truncated);
                 }
-                todo!("handle inline metadata?");
+                let keys = d::consume_i32s(bytecode, size as usize)?;
+                let targets = d::consume_i32s(bytecode, size as usize)?;
+                Instruction::SparseSwitchPayload { keys, targets }
             }
             // fill-array-data-payload
             // element_width  ushort   number of bytes in each element
@@ -94,12 +396,13 @@ pub fn decode_one(bytecode: &mut &[u16]) -> Result<Instruction, Error> {
             // ERRATA: The note in the reference miscalculates the size
             0x03 => {
                 let element_width = d::consume_u16(bytecode)?;
-                let size = d::consume_u32(bytecode)?;
-                let code_size = (element_width as usize * size as usize + 1) / 2;
-                if bytecode.len() < code_size {
-                    return Err(Error::Truncated);
+                if !matches!(element_width, 1 | 2 | 4 | 8) {
+                    return Err(Error::Encoding);
                 }
-                todo!("handle inline metadata?");
+                let size = d::consume_u32(bytecode)?;
+                let data_len = element_width as usize * size as usize;
+                let data = d::consume_bytes(bytecode, data_len)?;
+                Instruction::FillArrayDataPayload { element_width, data }
             }
             _ => return Err(Error::Encoding),
         },
@@ -175,7 +478,7 @@ pub fn decode_one(bytecode: &mut &[u16]) -> Result<Instruction, Error> {
             let (mut src, dst) = d::ba_op(bytecode)?;
             // sign extend the 4-bit literal to i8
             if src & 0b1000 > 0 {
-                src = src | 0xf0;
+                src |= 0xf0;
             }
             Instruction::Const4(dst, src as i8)
         }
@@ -236,7 +539,7 @@ pub fn decode_one(bytecode: &mut &[u16]) -> Result<Instruction, Error> {
             Instruction::InstanceOf(dst, src, ty)
         }
         opcode::ARRAYLENGTH => {
-            let (dst, src) = d::ba_op(bytecode)?;
+            let (src, dst) = d::ba_op(bytecode)?;
             Instruction::ArrayLength(dst, src)
         }
         opcode::NEWINSTANCE => {
@@ -254,10 +557,7 @@ pub fn decode_one(bytecode: &mut &[u16]) -> Result<Instruction, Error> {
         }
         opcode::FILLEDNEWARRAYRANGE => {
             let (count, ty, start) = d::aa_op_ccccbbbb(bytecode)?;
-            let mut args = Vec::with_capacity(count as usize);
-            for r in start..start + count as u16 {
-                args.push(r);
-            }
+            let args = d::register_range(start, count)?;
             Instruction::FilledNewArrayRange { ty, args }
         }
         opcode::FILLARRAYDATA => {
@@ -538,10 +838,7 @@ pub fn decode_one(bytecode: &mut &[u16]) -> Result<Instruction, Error> {
         }
         opcode::INVOKEVIRTUALRANGE | opcode::INVOKESUPERRANGE | opcode::INVOKEDIRECTRANGE | opcode::INVOKESTATICRANGE | opcode::INVOKEINTERFACERANGE => {
             let (count, method, start) = d::aa_op_ccccbbbb(bytecode)?;
-            let mut args = Vec::with_capacity(count as usize);
-            for r in start..start + count as u16 {
-                args.push(r);
-            }
+            let args = d::register_range(start, count)?;
             match op {
                 opcode::INVOKEVIRTUALRANGE => Instruction::InvokeVirtualRange { method, args },
                 opcode::INVOKESUPERRANGE => Instruction::InvokeSuperRange { method, args },
@@ -967,14 +1264,263 @@ pub fn decode_one(bytecode: &mut &[u16]) -> Result<Instruction, Error> {
             let (dst, lit, src) = d::aa_op_ccbb(bytecode)?;
             Instruction::UshrInt8(dst, src, lit as i8)
         }
-        unk => todo!("handle opcode {unk:#x?}"),
+        opcode::INVOKEPOLYMORPHIC => {
+            let (nargs, g, method, f, e, d, c, proto) = d::ag_op_bbbbfedc_hhhh(bytecode)?;
+            let args = [c, d, e, f, g];
+            Instruction::InvokePolymorphic { method, proto, nargs, args }
+        }
+        opcode::INVOKEPOLYMORPHICRANGE => {
+            let (count, method, start, proto) = d::aa_op_ccccbbbb_hhhh(bytecode)?;
+            let args = d::register_range(start, count)?;
+            Instruction::InvokePolymorphicRange { method, proto, args }
+        }
+        opcode::INVOKECUSTOM => {
+            let (nargs, g, call_site, f, e, d, c) = d::ag_op_bbbbfedc(bytecode)?;
+            let args = [c, d, e, f, g];
+            Instruction::InvokeCustom { call_site, nargs, args }
+        }
+        opcode::INVOKECUSTOMRANGE => {
+            let (count, call_site, start) = d::aa_op_ccccbbbb(bytecode)?;
+            let args = d::register_range(start, count)?;
+            Instruction::InvokeCustomRange { call_site, args }
+        }
+        opcode::CONSTMETHODHANDLE => {
+            let (dst, idx) = d::aa_op_bbbb(bytecode)?;
+            Instruction::ConstMethodHandle(dst, idx)
+        }
+        opcode::CONSTMETHODTYPE => {
+            let (dst, idx) = d::aa_op_bbbb(bytecode)?;
+            Instruction::ConstMethodType(dst, idx)
+        }
+        unk => return Err(Error::UnknownOpcode { opcode: unk, offset: 0 }),
     };
 
     Ok(inst)
 }
 
+// Decode the runtime-private opcodes of Dalvik's odex files
+fn decode_dalvik_odex(op: u8, bytecode: &mut impl d::CodeUnits, api: u32) -> Result<Option<Instruction>, Error> {
+    let inst = match op {
+        opcode::dalvik::IGETVOLATILE => {
+            let (obj, dst, field) = d::ba_op_cccc(bytecode)?;
+            Instruction::IGetVolatile(dst, obj, field)
+        }
+        opcode::dalvik::IPUTVOLATILE => {
+            let (obj, dst, field) = d::ba_op_cccc(bytecode)?;
+            Instruction::IPutVolatile(dst, obj, field)
+        }
+        opcode::dalvik::SGETVOLATILE => {
+            let (dst, field) = d::aa_op_bbbb(bytecode)?;
+            Instruction::SGetVolatile(dst, field)
+        }
+        opcode::dalvik::SPUTVOLATILE => {
+            let (dst, field) = d::aa_op_bbbb(bytecode)?;
+            Instruction::SPutVolatile(dst, field)
+        }
+        opcode::dalvik::IGETOBJECTVOLATILE => {
+            let (obj, dst, field) = d::ba_op_cccc(bytecode)?;
+            Instruction::IGetObjectVolatile(dst, obj, field)
+        }
+        opcode::dalvik::IGETWIDEVOLATILE => {
+            let (obj, dst, field) = d::ba_op_cccc(bytecode)?;
+            Instruction::IGetWideVolatile(dst, obj, field)
+        }
+        opcode::dalvik::IPUTWIDEVOLATILE => {
+            let (obj, dst, field) = d::ba_op_cccc(bytecode)?;
+            Instruction::IPutWideVolatile(dst, obj, field)
+        }
+        opcode::dalvik::SGETWIDEVOLATILE => {
+            let (dst, field) = d::aa_op_bbbb(bytecode)?;
+            Instruction::SGetWideVolatile(dst, field)
+        }
+        opcode::dalvik::SPUTWIDEVOLATILE => {
+            let (dst, field) = d::aa_op_bbbb(bytecode)?;
+            Instruction::SPutWideVolatile(dst, field)
+        }
+        opcode::dalvik::BREAKPOINT => {
+            d::zz_op(bytecode)?;
+            Instruction::Breakpoint
+        }
+        opcode::dalvik::THROWVERIFICATIONERROR => {
+            let (kind, idx) = d::aa_op_bbbb(bytecode)?;
+            Instruction::ThrowVerificationError(kind, idx)
+        }
+        opcode::dalvik::EXECUTEINLINE => {
+            let (nargs, g, inline, f, e, d, c) = d::ag_op_bbbbfedc(bytecode)?;
+            let args = [c, d, e, f, g];
+            Instruction::ExecuteInline { inline, nargs, args }
+        }
+        opcode::dalvik::EXECUTEINLINERANGE => {
+            let (count, inline, start) = d::aa_op_ccccbbbb(bytecode)?;
+            let args = d::register_range(start, count)?;
+            Instruction::ExecuteInlineRange { inline, args }
+        }
+        opcode::dalvik::INVOKEOBJECTINITRANGE if api < DecodeOptions::OBJECT_INIT => {
+            let (nargs, g, method, f, e, d, c) = d::ag_op_bbbbfedc(bytecode)?;
+            let args = [c, d, e, f, g];
+            Instruction::InvokeDirectEmpty { method, nargs, args }
+        }
+        opcode::dalvik::INVOKEOBJECTINITRANGE => {
+            let (count, method, start) = d::aa_op_ccccbbbb(bytecode)?;
+            let args = d::register_range(start, count)?;
+            Instruction::InvokeObjectInitRange { method, args }
+        }
+        opcode::dalvik::RETURNVOIDBARRIER => {
+            d::zz_op(bytecode)?;
+            Instruction::ReturnVoidBarrier
+        }
+        opcode::dalvik::IGETQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IGetQuick(dst, obj, offset)
+        }
+        opcode::dalvik::IGETWIDEQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IGetWideQuick(dst, obj, offset)
+        }
+        opcode::dalvik::IGETOBJECTQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IGetObjectQuick(dst, obj, offset)
+        }
+        opcode::dalvik::IPUTQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IPutQuick(dst, obj, offset)
+        }
+        opcode::dalvik::IPUTWIDEQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IPutWideQuick(dst, obj, offset)
+        }
+        opcode::dalvik::IPUTOBJECTQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IPutObjectQuick(dst, obj, offset)
+        }
+        opcode::dalvik::INVOKEVIRTUALQUICK => {
+            let (nargs, g, vtable, f, e, d, c) = d::ag_op_bbbbfedc(bytecode)?;
+            let args = [c, d, e, f, g];
+            Instruction::InvokeVirtualQuick { vtable, nargs, args }
+        }
+        opcode::dalvik::INVOKEVIRTUALQUICKRANGE => {
+            let (count, vtable, start) = d::aa_op_ccccbbbb(bytecode)?;
+            let args = d::register_range(start, count)?;
+            Instruction::InvokeVirtualQuickRange { vtable, args }
+        }
+        opcode::dalvik::INVOKESUPERQUICK => {
+            let (nargs, g, vtable, f, e, d, c) = d::ag_op_bbbbfedc(bytecode)?;
+            let args = [c, d, e, f, g];
+            Instruction::InvokeSuperQuick { vtable, nargs, args }
+        }
+        opcode::dalvik::INVOKESUPERQUICKRANGE => {
+            let (count, vtable, start) = d::aa_op_ccccbbbb(bytecode)?;
+            let args = d::register_range(start, count)?;
+            Instruction::InvokeSuperQuickRange { vtable, args }
+        }
+        opcode::dalvik::IPUTOBJECTVOLATILE => {
+            let (obj, dst, field) = d::ba_op_cccc(bytecode)?;
+            Instruction::IPutObjectVolatile(dst, obj, field)
+        }
+        opcode::dalvik::SGETOBJECTVOLATILE => {
+            let (dst, field) = d::aa_op_bbbb(bytecode)?;
+            Instruction::SGetObjectVolatile(dst, field)
+        }
+        opcode::dalvik::SPUTOBJECTVOLATILE => {
+            let (dst, field) = d::aa_op_bbbb(bytecode)?;
+            Instruction::SPutObjectVolatile(dst, field)
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(inst))
+}
+
+// Decode the quickened opcodes of ART's vdex files
+fn decode_art_quickened(op: u8, bytecode: &mut impl d::CodeUnits) -> Result<Option<Instruction>, Error> {
+    let inst = match op {
+        opcode::art::RETURNVOIDNOBARRIER => {
+            d::zz_op(bytecode)?;
+            Instruction::ReturnVoidNoBarrier
+        }
+        opcode::art::IGETQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IGetQuick(dst, obj, offset)
+        }
+        opcode::art::IGETWIDEQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IGetWideQuick(dst, obj, offset)
+        }
+        opcode::art::IGETOBJECTQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IGetObjectQuick(dst, obj, offset)
+        }
+        opcode::art::IPUTQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IPutQuick(dst, obj, offset)
+        }
+        opcode::art::IPUTWIDEQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IPutWideQuick(dst, obj, offset)
+        }
+        opcode::art::IPUTOBJECTQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IPutObjectQuick(dst, obj, offset)
+        }
+        opcode::art::INVOKEVIRTUALQUICK => {
+            let (nargs, g, vtable, f, e, d, c) = d::ag_op_bbbbfedc(bytecode)?;
+            let args = [c, d, e, f, g];
+            Instruction::InvokeVirtualQuick { vtable, nargs, args }
+        }
+        opcode::art::INVOKEVIRTUALQUICKRANGE => {
+            let (count, vtable, start) = d::aa_op_ccccbbbb(bytecode)?;
+            let args = d::register_range(start, count)?;
+            Instruction::InvokeVirtualQuickRange { vtable, args }
+        }
+        opcode::art::IPUTBOOLEANQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IPutBooleanQuick(dst, obj, offset)
+        }
+        opcode::art::IPUTBYTEQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IPutByteQuick(dst, obj, offset)
+        }
+        opcode::art::IPUTCHARQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IPutCharQuick(dst, obj, offset)
+        }
+        opcode::art::IPUTSHORTQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IPutShortQuick(dst, obj, offset)
+        }
+        opcode::art::IGETBOOLEANQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IGetBooleanQuick(dst, obj, offset)
+        }
+        opcode::art::IGETBYTEQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IGetByteQuick(dst, obj, offset)
+        }
+        opcode::art::IGETCHARQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IGetCharQuick(dst, obj, offset)
+        }
+        opcode::art::IGETSHORTQUICK => {
+            let (obj, dst, offset) = d::ba_op_cccc(bytecode)?;
+            Instruction::IGetShortQuick(dst, obj, offset)
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(inst))
+}
+
 impl Instruction {
+    /// Whether this is an inline data table rather than an executable instruction
+    pub fn is_payload(&self) -> bool {
+        matches!(
+            self,
+            Instruction::PackedSwitchPayload { .. } | Instruction::SparseSwitchPayload { .. } | Instruction::FillArrayDataPayload { .. }
+        )
+    }
+
     /// Length in u16 codepoints needed to encode/decode
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
         match self {
             Instruction::Nop => 1,
@@ -1195,6 +1741,53 @@ impl Instruction {
             Instruction::ShlInt8(_, _, _) => 2,
             Instruction::ShrInt8(_, _, _) => 2,
             Instruction::UshrInt8(_, _, _) => 2,
+            Instruction::InvokePolymorphic { .. } => 4,
+            Instruction::InvokePolymorphicRange { .. } => 4,
+            Instruction::InvokeCustom { .. } => 3,
+            Instruction::InvokeCustomRange { .. } => 3,
+            Instruction::ConstMethodHandle(_, _) => 2,
+            Instruction::ConstMethodType(_, _) => 2,
+            Instruction::ReturnVoidNoBarrier => 1,
+            Instruction::IGetQuick(_, _, _) => 2,
+            Instruction::IGetWideQuick(_, _, _) => 2,
+            Instruction::IGetObjectQuick(_, _, _) => 2,
+            Instruction::IPutQuick(_, _, _) => 2,
+            Instruction::IPutWideQuick(_, _, _) => 2,
+            Instruction::IPutObjectQuick(_, _, _) => 2,
+            Instruction::InvokeVirtualQuick { .. } => 3,
+            Instruction::InvokeVirtualQuickRange { .. } => 3,
+            Instruction::IPutBooleanQuick(_, _, _) => 2,
+            Instruction::IPutByteQuick(_, _, _) => 2,
+            Instruction::IPutCharQuick(_, _, _) => 2,
+            Instruction::IPutShortQuick(_, _, _) => 2,
+            Instruction::IGetBooleanQuick(_, _, _) => 2,
+            Instruction::IGetByteQuick(_, _, _) => 2,
+            Instruction::IGetCharQuick(_, _, _) => 2,
+            Instruction::IGetShortQuick(_, _, _) => 2,
+            Instruction::InvokeSuperQuick { .. } => 3,
+            Instruction::InvokeSuperQuickRange { .. } => 3,
+            Instruction::IGetVolatile(_, _, _) => 2,
+            Instruction::IPutVolatile(_, _, _) => 2,
+            Instruction::SGetVolatile(_, _) => 2,
+            Instruction::SPutVolatile(_, _) => 2,
+            Instruction::IGetObjectVolatile(_, _, _) => 2,
+            Instruction::IGetWideVolatile(_, _, _) => 2,
+            Instruction::IPutWideVolatile(_, _, _) => 2,
+            Instruction::SGetWideVolatile(_, _) => 2,
+            Instruction::SPutWideVolatile(_, _) => 2,
+            Instruction::Breakpoint => 1,
+            Instruction::ThrowVerificationError(_, _) => 2,
+            Instruction::ExecuteInline { .. } => 3,
+            Instruction::ExecuteInlineRange { .. } => 3,
+            Instruction::InvokeDirectEmpty { .. } => 3,
+            Instruction::InvokeObjectInitRange { .. } => 3,
+            Instruction::ReturnVoidBarrier => 1,
+            Instruction::IPutObjectVolatile(_, _, _) => 2,
+            Instruction::SGetObjectVolatile(_, _) => 2,
+            Instruction::SPutObjectVolatile(_, _) => 2,
+            Instruction::PackedSwitchPayload { targets, .. } => 4 + 2 * targets.len(),
+            Instruction::SparseSwitchPayload { targets, .. } => 2 + 4 * targets.len(),
+            Instruction::FillArrayDataPayload { data, .. } => 4 + data.len().div_ceil(2),
         }
     }
 }
@@ -1423,47 +2016,198 @@ pub(crate) mod opcode {
     mkop!(0xe0 => SHLINT8);
     mkop!(0xe1 => SHRINT8);
     mkop!(0xe2 => USHRINT8);
+    mkop!(0xfa => INVOKEPOLYMORPHIC);
+    mkop!(0xfb => INVOKEPOLYMORPHICRANGE);
+    mkop!(0xfc => INVOKECUSTOM);
+    mkop!(0xfd => INVOKECUSTOMRANGE);
+    mkop!(0xfe => CONSTMETHODHANDLE);
+    mkop!(0xff => CONSTMETHODTYPE);
+
+    /// Dalvik odex opcodes, API levels < 21
+    pub(crate) mod dalvik {
+        mkop!(0xe3 => IGETVOLATILE);
+        mkop!(0xe4 => IPUTVOLATILE);
+        mkop!(0xe5 => SGETVOLATILE);
+        mkop!(0xe6 => SPUTVOLATILE);
+        mkop!(0xe7 => IGETOBJECTVOLATILE);
+        mkop!(0xe8 => IGETWIDEVOLATILE);
+        mkop!(0xe9 => IPUTWIDEVOLATILE);
+        mkop!(0xea => SGETWIDEVOLATILE);
+        mkop!(0xeb => SPUTWIDEVOLATILE);
+        mkop!(0xec => BREAKPOINT);
+        mkop!(0xed => THROWVERIFICATIONERROR);
+        mkop!(0xee => EXECUTEINLINE);
+        mkop!(0xef => EXECUTEINLINERANGE);
+        // invoke-direct-empty below API level 14
+        mkop!(0xf0 => INVOKEOBJECTINITRANGE);
+        mkop!(0xf1 => RETURNVOIDBARRIER);
+        mkop!(0xf2 => IGETQUICK);
+        mkop!(0xf3 => IGETWIDEQUICK);
+        mkop!(0xf4 => IGETOBJECTQUICK);
+        mkop!(0xf5 => IPUTQUICK);
+        mkop!(0xf6 => IPUTWIDEQUICK);
+        mkop!(0xf7 => IPUTOBJECTQUICK);
+        mkop!(0xf8 => INVOKEVIRTUALQUICK);
+        mkop!(0xf9 => INVOKEVIRTUALQUICKRANGE);
+        mkop!(0xfa => INVOKESUPERQUICK);
+        mkop!(0xfb => INVOKESUPERQUICKRANGE);
+        mkop!(0xfc => IPUTOBJECTVOLATILE);
+        mkop!(0xfd => SGETOBJECTVOLATILE);
+        mkop!(0xfe => SPUTOBJECTVOLATILE);
+    }
+
+    /// ART quickened opcodes, API levels 21 through 30
+    pub(crate) mod art {
+        mkop!(0x73 => RETURNVOIDNOBARRIER);
+        mkop!(0xe3 => IGETQUICK);
+        mkop!(0xe4 => IGETWIDEQUICK);
+        mkop!(0xe5 => IGETOBJECTQUICK);
+        mkop!(0xe6 => IPUTQUICK);
+        mkop!(0xe7 => IPUTWIDEQUICK);
+        mkop!(0xe8 => IPUTOBJECTQUICK);
+        mkop!(0xe9 => INVOKEVIRTUALQUICK);
+        mkop!(0xea => INVOKEVIRTUALQUICKRANGE);
+        mkop!(0xeb => IPUTBOOLEANQUICK);
+        mkop!(0xec => IPUTBYTEQUICK);
+        mkop!(0xed => IPUTCHARQUICK);
+        mkop!(0xee => IPUTSHORTQUICK);
+        mkop!(0xef => IGETBOOLEANQUICK);
+        mkop!(0xf0 => IGETBYTEQUICK);
+        mkop!(0xf1 => IGETCHARQUICK);
+        mkop!(0xf2 => IGETSHORTQUICK);
+    }
 }
 
 /// Decoders for various instruction formats
+#[allow(clippy::identity_op, clippy::erasing_op)]
 mod d {
-    use super::Error;
+    use super::{Endian, Error};
+
+    /// A source of u16 codepoints
+    pub(crate) trait CodeUnits: Copy {
+        /// Number of whole codepoints left
+        fn remaining(&self) -> usize;
+
+        /// Consume the next `N` codepoints, advancing the source
+        fn take<const N: usize>(&mut self) -> Result<[u16; N], Error>;
+
+        /// Look at the next codepoint without consuming it
+        fn peek(&self) -> Option<u16> {
+            let mut copy = *self;
+            copy.take::<1>().ok().map(|[a]| a)
+        }
+    }
+
+    impl CodeUnits for &[u16] {
+        fn remaining(&self) -> usize {
+            self.len()
+        }
+
+        fn take<const N: usize>(&mut self) -> Result<[u16; N], Error> {
+            let Some((units, rest)) = self.split_first_chunk::<N>() else {
+                return Err(Error::Truncated);
+            };
+            *self = rest;
+
+            Ok(*units)
+        }
+    }
+
+    /// Raw dex bytes, read as codepoints of the given byte order
+    #[derive(Debug, Clone, Copy)]
+    pub(crate) struct Bytes<'a> {
+        pub(crate) bytes: &'a [u8],
+        pub(crate) endian: Endian,
+    }
+
+    impl CodeUnits for Bytes<'_> {
+        fn remaining(&self) -> usize {
+            self.bytes.len() / 2
+        }
+
+        fn take<const N: usize>(&mut self) -> Result<[u16; N], Error> {
+            if self.bytes.len() < 2 * N {
+                return Err(Error::Truncated);
+            }
+            let (units, rest) = self.bytes.split_at(2 * N);
+            self.bytes = rest;
+
+            let mut out = [0; N];
+            for (o, unit) in out.iter_mut().zip(units.chunks_exact(2)) {
+                let unit = [unit[0], unit[1]];
+                *o = match self.endian {
+                    Endian::Little => u16::from_le_bytes(unit),
+                    Endian::Big => u16::from_be_bytes(unit),
+                };
+            }
+
+            Ok(out)
+        }
+    }
 
     /// Helper to consume a u16 and advance the slice
-    pub(crate) fn consume_u16(bytecode: &mut &[u16]) -> Result<u16, Error> {
-        let (a, rest) = match *bytecode {
-            [a, rest @ ..] => (*a, rest),
-            _ => return Err(Error::Truncated),
-        };
-        *bytecode = rest;
+    pub(crate) fn consume_u16(bytecode: &mut impl CodeUnits) -> Result<u16, Error> {
+        let [a] = bytecode.take()?;
 
         Ok(a)
     }
 
     /// Helper to consume a u32 and advance the slice
-    pub(crate) fn consume_u32(bytecode: &mut &[u16]) -> Result<u32, Error> {
-        let (al, ah, rest) = match *bytecode {
-            [al, ah, rest @ ..] => (*al, *ah, rest),
-            _ => return Err(Error::Truncated),
-        };
-        *bytecode = rest;
+    pub(crate) fn consume_u32(bytecode: &mut impl CodeUnits) -> Result<u32, Error> {
+        let [al, ah] = bytecode.take()?;
 
         let a = (ah as u32) << 16 | al as u32;
 
         Ok(a)
     }
 
+    /// Helper to consume `count` little-endian i32s and advance the slice
+    pub(crate) fn consume_i32s(bytecode: &mut impl CodeUnits, count: usize) -> Result<Vec<i32>, Error> {
+        if bytecode.remaining() < 2 * count {
+            return Err(Error::Truncated);
+        }
+        let mut values = Vec::with_capacity(count);
+        for _ in 0..count {
+            values.push(consume_u32(bytecode)? as i32);
+        }
+
+        Ok(values)
+    }
+
+    /// Helper to consume `count` bytes packed little-endian into u16s, and
+    /// advance the slice past the final (possibly padded) u16
+    pub(crate) fn consume_bytes(bytecode: &mut impl CodeUnits, count: usize) -> Result<Vec<u8>, Error> {
+        let units = count.div_ceil(2);
+        if bytecode.remaining() < units {
+            return Err(Error::Truncated);
+        }
+
+        let mut bytes = Vec::with_capacity(2 * units);
+        for _ in 0..units {
+            let [unit] = bytecode.take()?;
+            bytes.extend(unit.to_le_bytes());
+        }
+        bytes.truncate(count);
+
+        Ok(bytes)
+    }
+
+    /// Helper to expand a `count` long register range beginning at `start`
+    pub(crate) fn register_range(start: u16, count: u8) -> Result<Vec<u16>, Error> {
+        let Some(end) = start.checked_add(count as u16) else {
+            return Err(Error::Encoding);
+        };
+
+        Ok((start..end).collect())
+    }
+
     /// AA|op
     ///
     /// returns AA
     ///
     /// decodes formats 11x, 10t
-    pub(crate) fn aa_op(bytecode: &mut &[u16]) -> Result<u8, Error> {
-        let (a, rest) = match *bytecode {
-            [a, rest @ ..] => (*a, rest),
-            _ => return Err(Error::Truncated),
-        };
-        *bytecode = rest;
+    pub(crate) fn aa_op(bytecode: &mut impl CodeUnits) -> Result<u8, Error> {
+        let [a] = bytecode.take()?;
 
         let a = (a >> 8) as u8;
 
@@ -1475,7 +2219,7 @@ mod d {
     /// returns (B, A)
     ///
     /// decodes formats 11x, 10t
-    pub(crate) fn ba_op(bytecode: &mut &[u16]) -> Result<(u8, u8), Error> {
+    pub(crate) fn ba_op(bytecode: &mut impl CodeUnits) -> Result<(u8, u8), Error> {
         let ab = aa_op(bytecode)?;
         let b = ab >> 4;
         let a = ab & 0xf;
@@ -1488,7 +2232,7 @@ mod d {
     /// returns ()
     ///
     /// decodes formats 10x
-    pub(crate) fn zz_op(bytecode: &mut &[u16]) -> Result<(), Error> {
+    pub(crate) fn zz_op(bytecode: &mut impl CodeUnits) -> Result<(), Error> {
         let aa = aa_op(bytecode)?;
 
         if aa != 0 {
@@ -1503,12 +2247,8 @@ mod d {
     /// returns (AA, BBBB)
     ///
     /// decodes formats 20bc, 22x, 21t, 21s, 21h, 21c
-    pub(crate) fn aa_op_bbbb(bytecode: &mut &[u16]) -> Result<(u8, u16), Error> {
-        let (a, bbbb, rest) = match *bytecode {
-            [a, bbbb, rest @ ..] => (*a, *bbbb, rest),
-            _ => return Err(Error::Truncated),
-        };
-        *bytecode = rest;
+    pub(crate) fn aa_op_bbbb(bytecode: &mut impl CodeUnits) -> Result<(u8, u16), Error> {
+        let [a, bbbb] = bytecode.take()?;
 
         let a = (a >> 8) as u8;
 
@@ -1520,7 +2260,7 @@ mod d {
     /// returns (AA, CC, BB)
     ///
     /// decodes formats 23x, 22b
-    pub(crate) fn aa_op_ccbb(bytecode: &mut &[u16]) -> Result<(u8, u8, u8), Error> {
+    pub(crate) fn aa_op_ccbb(bytecode: &mut impl CodeUnits) -> Result<(u8, u8, u8), Error> {
         let (aa, ccbb) = aa_op_bbbb(bytecode)?;
 
         let cc = (ccbb >> 8) as u8;
@@ -1534,7 +2274,7 @@ mod d {
     /// returns (B, A, CCCC)
     ///
     /// decodes formats 22t, 22s, 22c, 22cs
-    pub(crate) fn ba_op_cccc(bytecode: &mut &[u16]) -> Result<(u8, u8, u16), Error> {
+    pub(crate) fn ba_op_cccc(bytecode: &mut impl CodeUnits) -> Result<(u8, u8, u16), Error> {
         let (ba, cccc) = aa_op_bbbb(bytecode)?;
         let b = ba >> 4;
         let a = ba & 0xf;
@@ -1547,7 +2287,7 @@ mod d {
     /// returns (AAAA)
     ///
     /// decodes formats 20t
-    pub(crate) fn zz_op_aaaa(bytecode: &mut &[u16]) -> Result<u16, Error> {
+    pub(crate) fn zz_op_aaaa(bytecode: &mut impl CodeUnits) -> Result<u16, Error> {
         let (zz, aaaa) = aa_op_bbbb(bytecode)?;
 
         if zz != 0 {
@@ -1562,12 +2302,8 @@ mod d {
     /// returns (AA, BBBBBBBB)
     ///
     /// decodes formats 31i, 31t, 31c
-    pub(crate) fn aa_op_bbbbbbbb(bytecode: &mut &[u16]) -> Result<(u8, u32), Error> {
-        let (a, bl, bh, rest) = match *bytecode {
-            [a, bl, bh, rest @ ..] => (*a, *bl, *bh, rest),
-            _ => return Err(Error::Truncated),
-        };
-        *bytecode = rest;
+    pub(crate) fn aa_op_bbbbbbbb(bytecode: &mut impl CodeUnits) -> Result<(u8, u32), Error> {
+        let [a, bl, bh] = bytecode.take()?;
 
         let a = (a >> 8) as u8;
         let b = (bh as u32) << 16 | bl as u32;
@@ -1580,7 +2316,7 @@ mod d {
     /// returns (AAAAAAAA)
     ///
     /// decodes formats 30t
-    pub(crate) fn zz_op_aaaaaaaa(bytecode: &mut &[u16]) -> Result<u32, Error> {
+    pub(crate) fn zz_op_aaaaaaaa(bytecode: &mut impl CodeUnits) -> Result<u32, Error> {
         let (zz, aaaaaaaa) = aa_op_bbbbbbbb(bytecode)?;
 
         if zz != 0 {
@@ -1598,7 +2334,7 @@ mod d {
     ///
     /// ERRATA: This instruction format is documented incorrectly in the "Dalvik
     /// executable instruction formats" manual as "AA|op BBBB|CCCC"
-    pub(crate) fn aa_op_ccccbbbb(bytecode: &mut &[u16]) -> Result<(u8, u16, u16), Error> {
+    pub(crate) fn aa_op_ccccbbbb(bytecode: &mut impl CodeUnits) -> Result<(u8, u16, u16), Error> {
         let (aa, ccccbbbb) = aa_op_bbbbbbbb(bytecode)?;
 
         let cccc = (ccccbbbb >> 16) as u16;
@@ -1612,12 +2348,9 @@ mod d {
     /// returns (A, G, BBBB, F, E, D, C)
     ///
     /// decodes formats 35c, 35ms, 35mi
-    pub(crate) fn ag_op_bbbbfedc(bytecode: &mut &[u16]) -> Result<(u8, u8, u16, u8, u8, u8, u8), Error> {
-        let (agop, bbbb, fedc, rest) = match *bytecode {
-            [agop, b, fedc, rest @ ..] => (*agop, *b, *fedc, rest),
-            _ => return Err(Error::Truncated),
-        };
-        *bytecode = rest;
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn ag_op_bbbbfedc(bytecode: &mut impl CodeUnits) -> Result<(u8, u8, u16, u8, u8, u8, u8), Error> {
+        let [agop, bbbb, fedc] = bytecode.take()?;
 
         let a = ((agop >> 12) & 0xf) as u8;
         let g = ((agop >> 8) & 0xf) as u8;
@@ -1626,15 +2359,51 @@ mod d {
         let d = ((fedc >> 4) & 0xf) as u8;
         let c = ((fedc >> 0) & 0xf) as u8;
 
+        // at most 5 argument registers are encodable
+        if a > 5 {
+            return Err(Error::Encoding);
+        }
+
         Ok((a, g, bbbb, f, e, d, c))
     }
 
+    /// A|G|op BBBB F|E|D|C HHHH
+    ///
+    /// returns (A, G, BBBB, F, E, D, C, HHHH)
+    ///
+    /// decodes formats 45cc
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn ag_op_bbbbfedc_hhhh(bytecode: &mut impl CodeUnits) -> Result<(u8, u8, u16, u8, u8, u8, u8, u16), Error> {
+        if bytecode.remaining() < 4 {
+            return Err(Error::Truncated);
+        }
+        let (a, g, bbbb, f, e, d, c) = ag_op_bbbbfedc(bytecode)?;
+        let hhhh = consume_u16(bytecode)?;
+
+        Ok((a, g, bbbb, f, e, d, c, hhhh))
+    }
+
+    /// AA|op BBBB CCCC HHHH
+    ///
+    /// returns (AA, BBBB, CCCC, HHHH)
+    ///
+    /// decodes formats 4rcc
+    pub(crate) fn aa_op_ccccbbbb_hhhh(bytecode: &mut impl CodeUnits) -> Result<(u8, u16, u16, u16), Error> {
+        if bytecode.remaining() < 4 {
+            return Err(Error::Truncated);
+        }
+        let (aa, bbbb, cccc) = aa_op_ccccbbbb(bytecode)?;
+        let hhhh = consume_u16(bytecode)?;
+
+        Ok((aa, bbbb, cccc, hhhh))
+    }
+
     /// ØØ|op AAAA BBBB
     ///
     /// returns (AAAA, BBBB)
     ///
     /// decodes formats 32x
-    pub(crate) fn zz_op_aaaabbbb(bytecode: &mut &[u16]) -> Result<(u16, u16), Error> {
+    pub(crate) fn zz_op_aaaabbbb(bytecode: &mut impl CodeUnits) -> Result<(u16, u16), Error> {
         let (zz, aaaa, bbbb) = aa_op_ccccbbbb(bytecode)?;
 
         if zz != 0 {
@@ -1649,12 +2418,8 @@ mod d {
     /// returns (AA, BBBBBBBBBBBBBBBB)
     ///
     /// decodes formats 51l
-    pub(crate) fn aa_op_bbbbbbbbbbbbbbbb(bytecode: &mut &[u16]) -> Result<(u8, u64), Error> {
-        let (aa, b0, b1, b2, b3, rest) = match *bytecode {
-            [a, b0, b1, b2, b3, rest @ ..] => (*a, *b0, *b1, *b2, *b3, rest),
-            _ => return Err(Error::Truncated),
-        };
-        *bytecode = rest;
+    pub(crate) fn aa_op_bbbbbbbbbbbbbbbb(bytecode: &mut impl CodeUnits) -> Result<(u8, u64), Error> {
+        let [aa, b0, b1, b2, b3] = bytecode.take()?;
 
         let aa = (aa >> 8) as u8;
         #[rustfmt::skip]
@@ -0,0 +1,674 @@
+//! Dalvik bytecode instruction encoding, the inverse of [`decode`][`crate::decode`]
+
+use crate::decode::{opcode, DecodeOptions};
+use crate::Instruction;
+
+/// Encoding error
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Error {
+    /// An operand does not fit its field in the instruction format, e.g. `v16`
+    /// in the 4-bit register field of a 12x instruction
+    OutOfRange {
+        /// The offending operand value
+        value: i64,
+        /// Width of the field, in bits
+        bits: u32,
+    },
+    /// The operands are inconsistent, e.g. more than 5 arguments to a non-range
+    /// invoke, a non-contiguous register range or switch tables of different
+    /// lengths
+    Malformed,
+    /// The instruction does not exist for the targeted runtime or dex version
+    Unsupported,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::OutOfRange { value, bits } => f.write_fmt(format_args!("operand {value} does not fit in {bits} bits")),
+            Error::Malformed => f.write_str("inconsistent instruction operands"),
+            Error::Unsupported => f.write_str("instruction not supported by the target"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Instruction {
+    /// Encode the instruction, appending its codepoints to `out`
+    ///
+    /// This is the inverse of [`decode_one`][`crate::decode::decode_one`]:
+    /// decoding the appended codepoints yields an instruction equal to `self`.
+    /// The optimized variants are refused with [`Error::Unsupported`], see
+    /// [`encode_with`][`Instruction::encode_with`] to target a runtime.
+    ///
+    /// On error `out` is left untouched.
+    pub fn encode(&self, out: &mut Vec<u16>) -> Result<(), Error> {
+        self.encode_with(out, &DecodeOptions::default())
+    }
+
+    /// Encode the instruction for the target described by `options`,
+    /// appending its codepoints to `out`
+    ///
+    /// This is the inverse of
+    /// [`decode_one_with`][`crate::decode::decode_one_with`] given the same
+    /// `options`. Instructions the target would decode differently, such as an
+    /// optimized variant of another runtime or an opcode newer than
+    /// [`DecodeOptions::dex_version`], are refused with [`Error::Unsupported`].
+    ///
+    /// On error `out` is left untouched.
+    pub fn encode_with(&self, out: &mut Vec<u16>, options: &DecodeOptions) -> Result<(), Error> {
+        let start = out.len();
+        let result = encode_inner(self, out, options);
+        if result.is_err() {
+            out.truncate(start);
+        }
+
+        result
+    }
+}
+
+fn encode_inner(inst: &Instruction, out: &mut Vec<u16>, options: &DecodeOptions) -> Result<(), Error> {
+    let op = opcode_for(inst, options)?;
+
+    match inst {
+        Instruction::Nop => e::zz_op(out, op),
+        // packed-switch-payload, see `decode_inner` for the layout
+        Instruction::PackedSwitchPayload { first_key, targets } => {
+            e::aa_op(out, op, 0x01)?;
+            out.push(e::size(targets.len(), 16)? as u16);
+            e::push_u32(out, *first_key as u32);
+            targets.iter().for_each(|t| e::push_u32(out, *t as u32));
+            Ok(())
+        }
+        // sparse-switch-payload
+        Instruction::SparseSwitchPayload { keys, targets } => {
+            if keys.len() != targets.len() {
+                return Err(Error::Malformed);
+            }
+            e::aa_op(out, op, 0x02)?;
+            out.push(e::size(keys.len(), 16)? as u16);
+            keys.iter().chain(targets).for_each(|v| e::push_u32(out, *v as u32));
+            Ok(())
+        }
+        // fill-array-data-payload
+        Instruction::FillArrayDataPayload { element_width, data } => {
+            if !matches!(element_width, 1 | 2 | 4 | 8) || data.len() % *element_width as usize != 0 {
+                return Err(Error::Malformed);
+            }
+            e::aa_op(out, op, 0x03)?;
+            out.push(*element_width);
+            e::push_u32(out, e::size(data.len() / *element_width as usize, 32)?);
+            // an odd trailing byte is padded
+            out.extend(data.chunks(2).map(|c| u16::from_le_bytes([c[0], c.get(1).copied().unwrap_or(0)])));
+            Ok(())
+        }
+        Instruction::Move(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::MoveFrom16(dst, src) => e::aa_op_bbbb(out, op, *dst, *src),
+        Instruction::Move16(dst, src) => e::zz_op_aaaabbbb(out, op, *dst, *src),
+        Instruction::MoveWide(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::MoveWideFrom16(dst, src) => e::aa_op_bbbb(out, op, *dst, *src),
+        Instruction::MoveWide16(dst, src) => e::zz_op_aaaabbbb(out, op, *dst, *src),
+        Instruction::MoveObject(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::MoveObjectFrom16(dst, src) => e::aa_op_bbbb(out, op, *dst, *src),
+        Instruction::MoveObject16(dst, src) => e::zz_op_aaaabbbb(out, op, *dst, *src),
+        Instruction::MoveResult(dst) => e::aa_op(out, op, *dst),
+        Instruction::MoveResultWide(dst) => e::aa_op(out, op, *dst),
+        Instruction::MoveResultObject(dst) => e::aa_op(out, op, *dst),
+        Instruction::MoveException(dst) => e::aa_op(out, op, *dst),
+        Instruction::ReturnVoid => e::zz_op(out, op),
+        Instruction::Return(reg) => e::aa_op(out, op, *reg),
+        Instruction::ReturnWide(reg) => e::aa_op(out, op, *reg),
+        Instruction::ReturnObject(reg) => e::aa_op(out, op, *reg),
+        Instruction::Const4(dst, lit) => e::ba_op(out, op, e::literal4(*lit)?, *dst),
+        Instruction::Const16(dst, src) => e::aa_op_bbbb(out, op, *dst, *src as u16),
+        Instruction::Const(dst, src) => e::aa_op_bbbbbbbb(out, op, *dst, *src),
+        Instruction::ConstHigh16(dst, src) => e::aa_op_bbbb(out, op, *dst, *src as u16),
+        Instruction::ConstWide16(dst, src) => e::aa_op_bbbb(out, op, *dst, *src as u16),
+        Instruction::ConstWide32(dst, src) => e::aa_op_bbbbbbbb(out, op, *dst, *src),
+        Instruction::ConstWide(dst, src) => e::aa_op_bbbbbbbbbbbbbbbb(out, op, *dst, *src),
+        Instruction::ConstWideHigh16(dst, src) => e::aa_op_bbbb(out, op, *dst, *src),
+        Instruction::ConstString(dst, src) => e::aa_op_bbbb(out, op, *dst, *src),
+        Instruction::ConstStringJumbo(dst, src) => e::aa_op_bbbbbbbb(out, op, *dst, *src),
+        Instruction::ConstClass(dst, class) => e::aa_op_bbbb(out, op, *dst, *class),
+        Instruction::MonitorEnter(reg) => e::aa_op(out, op, *reg),
+        Instruction::MonitorExit(reg) => e::aa_op(out, op, *reg),
+        Instruction::CheckCast(reg, ty) => e::aa_op_bbbb(out, op, *reg, *ty),
+        Instruction::InstanceOf(dst, src, ty) => e::ba_op_cccc(out, op, *src, *dst, *ty),
+        Instruction::ArrayLength(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::NewInstance(reg, ty) => e::aa_op_bbbb(out, op, *reg, *ty),
+        Instruction::NewArray(dst, size, ty) => e::ba_op_cccc(out, op, *size, *dst, *ty),
+        Instruction::FilledNewArray { ty, nargs, args } => {
+            let [c, d, e, f, g] = *args;
+            e::ag_op_bbbbfedc(out, op, *nargs, g, *ty, f, e, d, c)
+        }
+        Instruction::FilledNewArrayRange { ty, args } => {
+            let (start, count) = e::register_range(args)?;
+            e::aa_op_ccccbbbb(out, op, count, *ty, start)
+        }
+        Instruction::FillArrayData(dst, table) => e::aa_op_bbbbbbbb(out, op, *dst, *table as u32),
+        Instruction::Throw(reg) => e::aa_op(out, op, *reg),
+        Instruction::Goto(dst) => e::aa_op(out, op, *dst as u8),
+        Instruction::Goto16(dst) => e::zz_op_aaaa(out, op, *dst as u16),
+        Instruction::Goto32(dst) => e::zz_op_aaaaaaaa(out, op, *dst as u32),
+        Instruction::PackedSwitch(reg, table) => e::aa_op_bbbbbbbb(out, op, *reg, *table as u32),
+        Instruction::SparseSwitch(reg, table) => e::aa_op_bbbbbbbb(out, op, *reg, *table as u32),
+        Instruction::CmplFloat(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::CmpgFloat(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::CmplDouble(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::CmpgDouble(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::CmpLong(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::IfEq(a, b, off) => e::ba_op_cccc(out, op, *b, *a, *off as u16),
+        Instruction::IfNe(a, b, off) => e::ba_op_cccc(out, op, *b, *a, *off as u16),
+        Instruction::IfLt(a, b, off) => e::ba_op_cccc(out, op, *b, *a, *off as u16),
+        Instruction::IfGe(a, b, off) => e::ba_op_cccc(out, op, *b, *a, *off as u16),
+        Instruction::IfGt(a, b, off) => e::ba_op_cccc(out, op, *b, *a, *off as u16),
+        Instruction::IfLe(a, b, off) => e::ba_op_cccc(out, op, *b, *a, *off as u16),
+        Instruction::IfEqz(reg, off) => e::aa_op_bbbb(out, op, *reg, *off as u16),
+        Instruction::IfNez(reg, off) => e::aa_op_bbbb(out, op, *reg, *off as u16),
+        Instruction::IfLtz(reg, off) => e::aa_op_bbbb(out, op, *reg, *off as u16),
+        Instruction::IfGez(reg, off) => e::aa_op_bbbb(out, op, *reg, *off as u16),
+        Instruction::IfGtz(reg, off) => e::aa_op_bbbb(out, op, *reg, *off as u16),
+        Instruction::IfLez(reg, off) => e::aa_op_bbbb(out, op, *reg, *off as u16),
+        Instruction::AGet(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::AGetWide(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::AGetObject(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::AGetBoolean(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::AGetByte(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::AGetChar(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::AGetShort(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::APut(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::APutWide(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::APutObject(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::APutBoolean(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::APutByte(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::APutChar(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::APutShort(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::IGet(dst, src, ty) => e::ba_op_cccc(out, op, *src, *dst, *ty),
+        Instruction::IGetWide(dst, src, ty) => e::ba_op_cccc(out, op, *src, *dst, *ty),
+        Instruction::IGetObject(dst, src, ty) => e::ba_op_cccc(out, op, *src, *dst, *ty),
+        Instruction::IGetBoolean(dst, src, ty) => e::ba_op_cccc(out, op, *src, *dst, *ty),
+        Instruction::IGetByte(dst, src, ty) => e::ba_op_cccc(out, op, *src, *dst, *ty),
+        Instruction::IGetChar(dst, src, ty) => e::ba_op_cccc(out, op, *src, *dst, *ty),
+        Instruction::IGetShort(dst, src, ty) => e::ba_op_cccc(out, op, *src, *dst, *ty),
+        Instruction::IPut(dst, src, ty) => e::ba_op_cccc(out, op, *src, *dst, *ty),
+        Instruction::IPutWide(dst, src, ty) => e::ba_op_cccc(out, op, *src, *dst, *ty),
+        Instruction::IPutObject(dst, src, ty) => e::ba_op_cccc(out, op, *src, *dst, *ty),
+        Instruction::IPutBoolean(dst, src, ty) => e::ba_op_cccc(out, op, *src, *dst, *ty),
+        Instruction::IPutByte(dst, src, ty) => e::ba_op_cccc(out, op, *src, *dst, *ty),
+        Instruction::IPutChar(dst, src, ty) => e::ba_op_cccc(out, op, *src, *dst, *ty),
+        Instruction::IPutShort(dst, src, ty) => e::ba_op_cccc(out, op, *src, *dst, *ty),
+        Instruction::SGet(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::SGetWide(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::SGetObject(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::SGetBoolean(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::SGetByte(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::SGetChar(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::SGetShort(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::SPut(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::SPutWide(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::SPutObject(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::SPutBoolean(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::SPutByte(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::SPutChar(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::SPutShort(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::InvokeVirtual { method, nargs, args } => {
+            let [c, d, e, f, g] = *args;
+            e::ag_op_bbbbfedc(out, op, *nargs, g, *method, f, e, d, c)
+        }
+        Instruction::InvokeSuper { method, nargs, args } => {
+            let [c, d, e, f, g] = *args;
+            e::ag_op_bbbbfedc(out, op, *nargs, g, *method, f, e, d, c)
+        }
+        Instruction::InvokeDirect { method, nargs, args } => {
+            let [c, d, e, f, g] = *args;
+            e::ag_op_bbbbfedc(out, op, *nargs, g, *method, f, e, d, c)
+        }
+        Instruction::InvokeStatic { method, nargs, args } => {
+            let [c, d, e, f, g] = *args;
+            e::ag_op_bbbbfedc(out, op, *nargs, g, *method, f, e, d, c)
+        }
+        Instruction::InvokeInterface { method, nargs, args } => {
+            let [c, d, e, f, g] = *args;
+            e::ag_op_bbbbfedc(out, op, *nargs, g, *method, f, e, d, c)
+        }
+        Instruction::InvokeVirtualRange { method, args } => {
+            let (start, count) = e::register_range(args)?;
+            e::aa_op_ccccbbbb(out, op, count, *method, start)
+        }
+        Instruction::InvokeSuperRange { method, args } => {
+            let (start, count) = e::register_range(args)?;
+            e::aa_op_ccccbbbb(out, op, count, *method, start)
+        }
+        Instruction::InvokeDirectRange { method, args } => {
+            let (start, count) = e::register_range(args)?;
+            e::aa_op_ccccbbbb(out, op, count, *method, start)
+        }
+        Instruction::InvokeStaticRange { method, args } => {
+            let (start, count) = e::register_range(args)?;
+            e::aa_op_ccccbbbb(out, op, count, *method, start)
+        }
+        Instruction::InvokeInterfaceRange { method, args } => {
+            let (start, count) = e::register_range(args)?;
+            e::aa_op_ccccbbbb(out, op, count, *method, start)
+        }
+        Instruction::NegInt(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::NotInt(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::NegLong(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::NotLong(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::NegFloat(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::NegDouble(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::IntToLong(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::IntToFloat(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::IntToDouble(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::LongToInt(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::LongToFloat(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::LongToDouble(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::FloatToInt(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::FloatToLong(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::FloatToDouble(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::DoubleToInt(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::DoubleToLong(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::DoubleToFloat(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::IntTobyte(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::IntTochar(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::IntToshort(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::AddInt(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::SubInt(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::MulInt(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::DivInt(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::RemInt(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::AndInt(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::OrInt(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::XorInt(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::ShlInt(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::ShrInt(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::UshrInt(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::AddLong(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::SubLong(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::MulLong(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::DivLong(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::RemLong(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::AndLong(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::OrLong(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::XorLong(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::ShlLong(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::ShrLong(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::UshrLong(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::AddFloat(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::SubFloat(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::MulFloat(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::DivFloat(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::RemFloat(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::AddDouble(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::SubDouble(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::MulDouble(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::DivDouble(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::RemDouble(dst, src1, src2) => e::aa_op_ccbb(out, op, *dst, *src2, *src1),
+        Instruction::AddInt2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::SubInt2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::MulInt2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::DivInt2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::RemInt2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::AndInt2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::OrInt2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::XorInt2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::ShlInt2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::ShrInt2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::UShrInt2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::AddLong2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::SubLong2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::MulLong2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::DivLong2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::RemLong2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::AndLong2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::OrLong2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::XorLong2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::ShlLong2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::ShrLong2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::UShrLong2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::AddFloat2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::SubFloat2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::MulFloat2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::DivFloat2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::RemFloat2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::AddDouble2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::SubDouble2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::MulDouble2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::DivDouble2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::RemDouble2(dst, src) => e::ba_op(out, op, *src, *dst),
+        Instruction::AddInt16(dst, src, lit) => e::ba_op_cccc(out, op, *src, *dst, *lit as u16),
+        Instruction::RsubInt16(dst, src, lit) => e::ba_op_cccc(out, op, *src, *dst, *lit as u16),
+        Instruction::MulInt16(dst, src, lit) => e::ba_op_cccc(out, op, *src, *dst, *lit as u16),
+        Instruction::DivInt16(dst, src, lit) => e::ba_op_cccc(out, op, *src, *dst, *lit as u16),
+        Instruction::RemInt16(dst, src, lit) => e::ba_op_cccc(out, op, *src, *dst, *lit as u16),
+        Instruction::AndInt16(dst, src, lit) => e::ba_op_cccc(out, op, *src, *dst, *lit as u16),
+        Instruction::OrInt16(dst, src, lit) => e::ba_op_cccc(out, op, *src, *dst, *lit as u16),
+        Instruction::XorInt16(dst, src, lit) => e::ba_op_cccc(out, op, *src, *dst, *lit as u16),
+        Instruction::AddInt8(dst, src, lit) => e::aa_op_ccbb(out, op, *dst, *lit as u8, *src),
+        Instruction::RsubInt8(dst, src, lit) => e::aa_op_ccbb(out, op, *dst, *lit as u8, *src),
+        Instruction::MulInt8(dst, src, lit) => e::aa_op_ccbb(out, op, *dst, *lit as u8, *src),
+        Instruction::DivInt8(dst, src, lit) => e::aa_op_ccbb(out, op, *dst, *lit as u8, *src),
+        Instruction::RemInt8(dst, src, lit) => e::aa_op_ccbb(out, op, *dst, *lit as u8, *src),
+        Instruction::AndInt8(dst, src, lit) => e::aa_op_ccbb(out, op, *dst, *lit as u8, *src),
+        Instruction::OrInt8(dst, src, lit) => e::aa_op_ccbb(out, op, *dst, *lit as u8, *src),
+        Instruction::XorInt8(dst, src, lit) => e::aa_op_ccbb(out, op, *dst, *lit as u8, *src),
+        Instruction::ShlInt8(dst, src, lit) => e::aa_op_ccbb(out, op, *dst, *lit as u8, *src),
+        Instruction::ShrInt8(dst, src, lit) => e::aa_op_ccbb(out, op, *dst, *lit as u8, *src),
+        Instruction::UshrInt8(dst, src, lit) => e::aa_op_ccbb(out, op, *dst, *lit as u8, *src),
+        Instruction::InvokePolymorphic { method, proto, nargs, args } => {
+            let [c, d, e, f, g] = *args;
+            e::ag_op_bbbbfedc_hhhh(out, op, *nargs, g, *method, f, e, d, c, *proto)
+        }
+        Instruction::InvokePolymorphicRange { method, proto, args } => {
+            let (start, count) = e::register_range(args)?;
+            e::aa_op_ccccbbbb_hhhh(out, op, count, *method, start, *proto)
+        }
+        Instruction::InvokeCustom { call_site, nargs, args } => {
+            let [c, d, e, f, g] = *args;
+            e::ag_op_bbbbfedc(out, op, *nargs, g, *call_site, f, e, d, c)
+        }
+        Instruction::InvokeCustomRange { call_site, args } => {
+            let (start, count) = e::register_range(args)?;
+            e::aa_op_ccccbbbb(out, op, count, *call_site, start)
+        }
+        Instruction::ConstMethodHandle(dst, idx) => e::aa_op_bbbb(out, op, *dst, *idx),
+        Instruction::ConstMethodType(dst, idx) => e::aa_op_bbbb(out, op, *dst, *idx),
+        Instruction::IGetVolatile(dst, obj, field) => e::ba_op_cccc(out, op, *obj, *dst, *field),
+        Instruction::IPutVolatile(dst, obj, field) => e::ba_op_cccc(out, op, *obj, *dst, *field),
+        Instruction::SGetVolatile(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::SPutVolatile(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::IGetObjectVolatile(dst, obj, field) => e::ba_op_cccc(out, op, *obj, *dst, *field),
+        Instruction::IGetWideVolatile(dst, obj, field) => e::ba_op_cccc(out, op, *obj, *dst, *field),
+        Instruction::IPutWideVolatile(dst, obj, field) => e::ba_op_cccc(out, op, *obj, *dst, *field),
+        Instruction::SGetWideVolatile(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::SPutWideVolatile(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::Breakpoint => e::zz_op(out, op),
+        Instruction::ThrowVerificationError(kind, idx) => e::aa_op_bbbb(out, op, *kind, *idx),
+        Instruction::ExecuteInline { inline, nargs, args } => {
+            let [c, d, e, f, g] = *args;
+            e::ag_op_bbbbfedc(out, op, *nargs, g, *inline, f, e, d, c)
+        }
+        Instruction::ExecuteInlineRange { inline, args } => {
+            let (start, count) = e::register_range(args)?;
+            e::aa_op_ccccbbbb(out, op, count, *inline, start)
+        }
+        Instruction::InvokeDirectEmpty { method, nargs, args } => {
+            let [c, d, e, f, g] = *args;
+            e::ag_op_bbbbfedc(out, op, *nargs, g, *method, f, e, d, c)
+        }
+        Instruction::InvokeObjectInitRange { method, args } => {
+            let (start, count) = e::register_range(args)?;
+            e::aa_op_ccccbbbb(out, op, count, *method, start)
+        }
+        Instruction::ReturnVoidBarrier => e::zz_op(out, op),
+        Instruction::IGetQuick(dst, obj, offset) => e::ba_op_cccc(out, op, *obj, *dst, *offset),
+        Instruction::IGetWideQuick(dst, obj, offset) => e::ba_op_cccc(out, op, *obj, *dst, *offset),
+        Instruction::IGetObjectQuick(dst, obj, offset) => e::ba_op_cccc(out, op, *obj, *dst, *offset),
+        Instruction::IPutQuick(dst, obj, offset) => e::ba_op_cccc(out, op, *obj, *dst, *offset),
+        Instruction::IPutWideQuick(dst, obj, offset) => e::ba_op_cccc(out, op, *obj, *dst, *offset),
+        Instruction::IPutObjectQuick(dst, obj, offset) => e::ba_op_cccc(out, op, *obj, *dst, *offset),
+        Instruction::InvokeVirtualQuick { vtable, nargs, args } => {
+            let [c, d, e, f, g] = *args;
+            e::ag_op_bbbbfedc(out, op, *nargs, g, *vtable, f, e, d, c)
+        }
+        Instruction::InvokeVirtualQuickRange { vtable, args } => {
+            let (start, count) = e::register_range(args)?;
+            e::aa_op_ccccbbbb(out, op, count, *vtable, start)
+        }
+        Instruction::InvokeSuperQuick { vtable, nargs, args } => {
+            let [c, d, e, f, g] = *args;
+            e::ag_op_bbbbfedc(out, op, *nargs, g, *vtable, f, e, d, c)
+        }
+        Instruction::InvokeSuperQuickRange { vtable, args } => {
+            let (start, count) = e::register_range(args)?;
+            e::aa_op_ccccbbbb(out, op, count, *vtable, start)
+        }
+        Instruction::IPutObjectVolatile(dst, obj, field) => e::ba_op_cccc(out, op, *obj, *dst, *field),
+        Instruction::SGetObjectVolatile(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::SPutObjectVolatile(dst, field) => e::aa_op_bbbb(out, op, *dst, *field),
+        Instruction::ReturnVoidNoBarrier => e::zz_op(out, op),
+        Instruction::IPutBooleanQuick(dst, obj, offset) => e::ba_op_cccc(out, op, *obj, *dst, *offset),
+        Instruction::IPutByteQuick(dst, obj, offset) => e::ba_op_cccc(out, op, *obj, *dst, *offset),
+        Instruction::IPutCharQuick(dst, obj, offset) => e::ba_op_cccc(out, op, *obj, *dst, *offset),
+        Instruction::IPutShortQuick(dst, obj, offset) => e::ba_op_cccc(out, op, *obj, *dst, *offset),
+        Instruction::IGetBooleanQuick(dst, obj, offset) => e::ba_op_cccc(out, op, *obj, *dst, *offset),
+        Instruction::IGetByteQuick(dst, obj, offset) => e::ba_op_cccc(out, op, *obj, *dst, *offset),
+        Instruction::IGetCharQuick(dst, obj, offset) => e::ba_op_cccc(out, op, *obj, *dst, *offset),
+        Instruction::IGetShortQuick(dst, obj, offset) => e::ba_op_cccc(out, op, *obj, *dst, *offset),
+    }
+}
+
+// the opcode the target decodes into `inst`
+fn opcode_for(inst: &Instruction, options: &DecodeOptions) -> Result<u8, Error> {
+    let optimized = match options.api_level {
+        Some(api) if api < DecodeOptions::ART => dalvik_opcode(inst, api),
+        Some(api) if api < DecodeOptions::NO_QUICKENING => art_opcode(inst),
+        _ => None,
+    };
+    if let Some(op) = optimized {
+        return Ok(op);
+    }
+
+    // optimized for a runtime other than the target
+    if art_opcode(inst).is_some() || dalvik_opcode(inst, 0).is_some() || dalvik_opcode(inst, DecodeOptions::OBJECT_INIT).is_some() {
+        return Err(Error::Unsupported);
+    }
+
+    let op = inst.opcode();
+    let Some(code) = op.to_u8() else {
+        return Err(Error::Unsupported);
+    };
+    if options.dex_version.is_some_and(|version| op.dex_version() > version) {
+        return Err(Error::Unsupported);
+    }
+    // Dalvik odex opcodes shadow the standard ones from e3 up
+    if options.api_level.is_some_and(|api| api < DecodeOptions::ART) && matches!(code, 0xe3..=0xfe) {
+        return Err(Error::Unsupported);
+    }
+
+    Ok(code)
+}
+
+fn dalvik_opcode(inst: &Instruction, api: u32) -> Option<u8> {
+    let op = match inst {
+        Instruction::IGetVolatile(..) => opcode::dalvik::IGETVOLATILE,
+        Instruction::IPutVolatile(..) => opcode::dalvik::IPUTVOLATILE,
+        Instruction::SGetVolatile(..) => opcode::dalvik::SGETVOLATILE,
+        Instruction::SPutVolatile(..) => opcode::dalvik::SPUTVOLATILE,
+        Instruction::IGetObjectVolatile(..) => opcode::dalvik::IGETOBJECTVOLATILE,
+        Instruction::IGetWideVolatile(..) => opcode::dalvik::IGETWIDEVOLATILE,
+        Instruction::IPutWideVolatile(..) => opcode::dalvik::IPUTWIDEVOLATILE,
+        Instruction::SGetWideVolatile(..) => opcode::dalvik::SGETWIDEVOLATILE,
+        Instruction::SPutWideVolatile(..) => opcode::dalvik::SPUTWIDEVOLATILE,
+        Instruction::Breakpoint => opcode::dalvik::BREAKPOINT,
+        Instruction::ThrowVerificationError(..) => opcode::dalvik::THROWVERIFICATIONERROR,
+        Instruction::ExecuteInline { .. } => opcode::dalvik::EXECUTEINLINE,
+        Instruction::ExecuteInlineRange { .. } => opcode::dalvik::EXECUTEINLINERANGE,
+        Instruction::InvokeDirectEmpty { .. } if api < DecodeOptions::OBJECT_INIT => opcode::dalvik::INVOKEOBJECTINITRANGE,
+        Instruction::InvokeObjectInitRange { .. } if api >= DecodeOptions::OBJECT_INIT => opcode::dalvik::INVOKEOBJECTINITRANGE,
+        Instruction::ReturnVoidBarrier => opcode::dalvik::RETURNVOIDBARRIER,
+        Instruction::IGetQuick(..) => opcode::dalvik::IGETQUICK,
+        Instruction::IGetWideQuick(..) => opcode::dalvik::IGETWIDEQUICK,
+        Instruction::IGetObjectQuick(..) => opcode::dalvik::IGETOBJECTQUICK,
+        Instruction::IPutQuick(..) => opcode::dalvik::IPUTQUICK,
+        Instruction::IPutWideQuick(..) => opcode::dalvik::IPUTWIDEQUICK,
+        Instruction::IPutObjectQuick(..) => opcode::dalvik::IPUTOBJECTQUICK,
+        Instruction::InvokeVirtualQuick { .. } => opcode::dalvik::INVOKEVIRTUALQUICK,
+        Instruction::InvokeVirtualQuickRange { .. } => opcode::dalvik::INVOKEVIRTUALQUICKRANGE,
+        Instruction::InvokeSuperQuick { .. } => opcode::dalvik::INVOKESUPERQUICK,
+        Instruction::InvokeSuperQuickRange { .. } => opcode::dalvik::INVOKESUPERQUICKRANGE,
+        Instruction::IPutObjectVolatile(..) => opcode::dalvik::IPUTOBJECTVOLATILE,
+        Instruction::SGetObjectVolatile(..) => opcode::dalvik::SGETOBJECTVOLATILE,
+        Instruction::SPutObjectVolatile(..) => opcode::dalvik::SPUTOBJECTVOLATILE,
+        _ => return None,
+    };
+
+    Some(op)
+}
+
+fn art_opcode(inst: &Instruction) -> Option<u8> {
+    let op = match inst {
+        Instruction::ReturnVoidNoBarrier => opcode::art::RETURNVOIDNOBARRIER,
+        Instruction::IGetQuick(..) => opcode::art::IGETQUICK,
+        Instruction::IGetWideQuick(..) => opcode::art::IGETWIDEQUICK,
+        Instruction::IGetObjectQuick(..) => opcode::art::IGETOBJECTQUICK,
+        Instruction::IPutQuick(..) => opcode::art::IPUTQUICK,
+        Instruction::IPutWideQuick(..) => opcode::art::IPUTWIDEQUICK,
+        Instruction::IPutObjectQuick(..) => opcode::art::IPUTOBJECTQUICK,
+        Instruction::InvokeVirtualQuick { .. } => opcode::art::INVOKEVIRTUALQUICK,
+        Instruction::InvokeVirtualQuickRange { .. } => opcode::art::INVOKEVIRTUALQUICKRANGE,
+        Instruction::IPutBooleanQuick(..) => opcode::art::IPUTBOOLEANQUICK,
+        Instruction::IPutByteQuick(..) => opcode::art::IPUTBYTEQUICK,
+        Instruction::IPutCharQuick(..) => opcode::art::IPUTCHARQUICK,
+        Instruction::IPutShortQuick(..) => opcode::art::IPUTSHORTQUICK,
+        Instruction::IGetBooleanQuick(..) => opcode::art::IGETBOOLEANQUICK,
+        Instruction::IGetByteQuick(..) => opcode::art::IGETBYTEQUICK,
+        Instruction::IGetCharQuick(..) => opcode::art::IGETCHARQUICK,
+        Instruction::IGetShortQuick(..) => opcode::art::IGETSHORTQUICK,
+        _ => return None,
+    };
+
+    Some(op)
+}
+
+/// Inverses of the `decode::d` helpers, named after the same format strings
+mod e {
+    use super::Error;
+
+    fn unit(op: u8, aa: u8) -> u16 {
+        (aa as u16) << 8 | op as u16
+    }
+
+    fn nibble(value: u8) -> Result<u16, Error> {
+        if value > 0xf {
+            return Err(Error::OutOfRange { value: value.into(), bits: 4 });
+        }
+        Ok(value.into())
+    }
+
+    /// Sign-truncate a 4-bit literal
+    pub(crate) fn literal4(value: i8) -> Result<u8, Error> {
+        if !(-8..=7).contains(&value) {
+            return Err(Error::OutOfRange { value: value.into(), bits: 4 });
+        }
+        Ok(value as u8 & 0xf)
+    }
+
+    /// Table or register count fitting in `bits`
+    pub(crate) fn size(len: usize, bits: u32) -> Result<u32, Error> {
+        if len >> bits != 0 {
+            return Err(Error::OutOfRange { value: len as i64, bits });
+        }
+        Ok(len as u32)
+    }
+
+    pub(crate) fn push_u32(out: &mut Vec<u16>, value: u32) {
+        out.extend([value as u16, (value >> 16) as u16]);
+    }
+
+    /// Start and count of a contiguous register range
+    pub(crate) fn register_range(args: &[u16]) -> Result<(u16, u8), Error> {
+        let count = size(args.len(), 8)? as u8;
+        let start = args.first().copied().unwrap_or(0);
+        if start.checked_add(count.into()).is_none() || args.iter().zip(start..).any(|(a, r)| *a != r) {
+            return Err(Error::Malformed);
+        }
+        Ok((start, count))
+    }
+
+    /// AA|op
+    pub(crate) fn aa_op(out: &mut Vec<u16>, op: u8, aa: u8) -> Result<(), Error> {
+        out.push(unit(op, aa));
+        Ok(())
+    }
+
+    /// B|A|op
+    pub(crate) fn ba_op(out: &mut Vec<u16>, op: u8, b: u8, a: u8) -> Result<(), Error> {
+        out.push(unit(op, (nibble(b)? << 4 | nibble(a)?) as u8));
+        Ok(())
+    }
+
+    /// ØØ|op
+    pub(crate) fn zz_op(out: &mut Vec<u16>, op: u8) -> Result<(), Error> {
+        aa_op(out, op, 0)
+    }
+
+    /// AA|op BBBB
+    pub(crate) fn aa_op_bbbb(out: &mut Vec<u16>, op: u8, aa: u8, bbbb: u16) -> Result<(), Error> {
+        out.extend([unit(op, aa), bbbb]);
+        Ok(())
+    }
+
+    /// AA|op CC|BB
+    pub(crate) fn aa_op_ccbb(out: &mut Vec<u16>, op: u8, aa: u8, cc: u8, bb: u8) -> Result<(), Error> {
+        aa_op_bbbb(out, op, aa, (cc as u16) << 8 | bb as u16)
+    }
+
+    /// B|A|op CCCC
+    pub(crate) fn ba_op_cccc(out: &mut Vec<u16>, op: u8, b: u8, a: u8, cccc: u16) -> Result<(), Error> {
+        ba_op(out, op, b, a)?;
+        out.push(cccc);
+        Ok(())
+    }
+
+    /// ØØ|op AAAA
+    pub(crate) fn zz_op_aaaa(out: &mut Vec<u16>, op: u8, aaaa: u16) -> Result<(), Error> {
+        aa_op_bbbb(out, op, 0, aaaa)
+    }
+
+    /// AA|op BBBBlo BBBBhi
+    pub(crate) fn aa_op_bbbbbbbb(out: &mut Vec<u16>, op: u8, aa: u8, bbbbbbbb: u32) -> Result<(), Error> {
+        out.push(unit(op, aa));
+        push_u32(out, bbbbbbbb);
+        Ok(())
+    }
+
+    /// ØØ|op AAAAlo AAAAhi
+    pub(crate) fn zz_op_aaaaaaaa(out: &mut Vec<u16>, op: u8, aaaaaaaa: u32) -> Result<(), Error> {
+        aa_op_bbbbbbbb(out, op, 0, aaaaaaaa)
+    }
+
+    /// AA|op BBBB CCCC
+    pub(crate) fn aa_op_ccccbbbb(out: &mut Vec<u16>, op: u8, aa: u8, bbbb: u16, cccc: u16) -> Result<(), Error> {
+        out.extend([unit(op, aa), bbbb, cccc]);
+        Ok(())
+    }
+
+    /// A|G|op BBBB F|E|D|C
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn ag_op_bbbbfedc(out: &mut Vec<u16>, op: u8, a: u8, g: u8, bbbb: u16, f: u8, e: u8, d: u8, c: u8) -> Result<(), Error> {
+        if a > 5 {
+            return Err(Error::Malformed);
+        }
+        let fedc = nibble(f)? << 12 | nibble(e)? << 8 | nibble(d)? << 4 | nibble(c)?;
+        out.extend([unit(op, (nibble(a)? << 4 | nibble(g)?) as u8), bbbb, fedc]);
+        Ok(())
+    }
+
+    /// A|G|op BBBB F|E|D|C HHHH
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn ag_op_bbbbfedc_hhhh(out: &mut Vec<u16>, op: u8, a: u8, g: u8, bbbb: u16, f: u8, e: u8, d: u8, c: u8, hhhh: u16) -> Result<(), Error> {
+        ag_op_bbbbfedc(out, op, a, g, bbbb, f, e, d, c)?;
+        out.push(hhhh);
+        Ok(())
+    }
+
+    /// AA|op BBBB CCCC HHHH
+    pub(crate) fn aa_op_ccccbbbb_hhhh(out: &mut Vec<u16>, op: u8, aa: u8, bbbb: u16, cccc: u16, hhhh: u16) -> Result<(), Error> {
+        out.extend([unit(op, aa), bbbb, cccc, hhhh]);
+        Ok(())
+    }
+
+    /// ØØ|op AAAA BBBB
+    pub(crate) fn zz_op_aaaabbbb(out: &mut Vec<u16>, op: u8, aaaa: u16, bbbb: u16) -> Result<(), Error> {
+        aa_op_ccccbbbb(out, op, 0, aaaa, bbbb)
+    }
+
+    /// AA|op BBBBlo BBBB BBBB BBBBhi
+    pub(crate) fn aa_op_bbbbbbbbbbbbbbbb(out: &mut Vec<u16>, op: u8, aa: u8, b: u64) -> Result<(), Error> {
+        out.extend([unit(op, aa), b as u16, (b >> 16) as u16, (b >> 32) as u16, (b >> 48) as u16]);
+        Ok(())
+    }
+}
@@ -0,0 +1,327 @@
+//! Constant folding of arithmetic, conversion and comparison instructions
+//!
+//! [`Instruction::evaluate`] computes the value an instruction writes to its
+//! destination register given the values of its source registers, following
+//! the Java semantics the runtime implements: integer arithmetic wraps, shift
+//! distances are masked to the width of the operand, float to integer
+//! conversions saturate (NaN becomes 0), and `cmpl`/`cmpg` differ only in the
+//! result for NaN.
+
+use crate::{exceptions::Exception, Instruction};
+use std::ops::{Add, Div, Mul, Rem, Sub};
+
+/// The contents of a register, or of a register pair for wide values
+///
+/// Registers are untyped, so a narrow value is read as either an int or a
+/// float by reinterpreting its bits, and likewise for wide values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    /// 32-bit integer
+    Int(i32),
+    /// 64-bit integer
+    Long(i64),
+    /// 32-bit float
+    Float(f32),
+    /// 64-bit float
+    Double(f64),
+}
+
+impl Value {
+    /// Whether the value takes a register pair
+    pub fn is_wide(self) -> bool {
+        matches!(self, Value::Long(_) | Value::Double(_))
+    }
+}
+
+impl From<i32> for Value {
+    fn from(v: i32) -> Self {
+        Value::Int(v)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(v: i64) -> Self {
+        Value::Long(v)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(v: f32) -> Self {
+        Value::Float(v)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(v: f64) -> Self {
+        Value::Double(v)
+    }
+}
+
+/// Evaluation error
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Error {
+    /// Not an arithmetic, conversion or comparison instruction
+    Unsupported,
+    /// The value of the register is not known
+    Unknown(u16),
+    /// The register holds a narrow value where a wide one is expected, or the
+    /// other way around
+    Width(u16),
+    /// The instruction throws instead of producing a value
+    Throws(Exception),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Unsupported => f.write_str("instruction cannot be evaluated"),
+            Error::Unknown(reg) => f.write_fmt(format_args!("value of v{reg} is unknown")),
+            Error::Width(reg) => f.write_fmt(format_args!("v{reg} has a value of the wrong width")),
+            Error::Throws(exc) => f.write_fmt(format_args!("instruction throws {exc:?}")),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+enum IntOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+    Ushr,
+}
+
+enum FloatOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+struct Inputs<F>(F);
+
+impl<F: FnMut(u16) -> Option<Value>> Inputs<F> {
+    fn narrow(&mut self, reg: u8) -> Result<u32, Error> {
+        match (self.0)(reg.into()) {
+            Some(Value::Int(v)) => Ok(v as u32),
+            Some(Value::Float(v)) => Ok(v.to_bits()),
+            Some(_) => Err(Error::Width(reg.into())),
+            None => Err(Error::Unknown(reg.into())),
+        }
+    }
+
+    fn wide(&mut self, reg: u8) -> Result<u64, Error> {
+        match (self.0)(reg.into()) {
+            Some(Value::Long(v)) => Ok(v as u64),
+            Some(Value::Double(v)) => Ok(v.to_bits()),
+            Some(_) => Err(Error::Width(reg.into())),
+            None => Err(Error::Unknown(reg.into())),
+        }
+    }
+
+    fn int(&mut self, reg: u8) -> Result<i32, Error> {
+        self.narrow(reg).map(|v| v as i32)
+    }
+
+    fn float(&mut self, reg: u8) -> Result<f32, Error> {
+        self.narrow(reg).map(f32::from_bits)
+    }
+
+    fn long(&mut self, reg: u8) -> Result<i64, Error> {
+        self.wide(reg).map(|v| v as i64)
+    }
+
+    fn double(&mut self, reg: u8) -> Result<f64, Error> {
+        self.wide(reg).map(f64::from_bits)
+    }
+}
+
+fn int_op(op: IntOp, x: i32, y: i32) -> Result<Value, Error> {
+    if matches!(op, IntOp::Div | IntOp::Rem) && y == 0 {
+        return Err(Error::Throws(Exception::Arithmetic));
+    }
+    // the shift distance is masked to 5 bits by wrapping_sh*
+    Ok(Value::Int(match op {
+        IntOp::Add => x.wrapping_add(y),
+        IntOp::Sub => x.wrapping_sub(y),
+        IntOp::Mul => x.wrapping_mul(y),
+        IntOp::Div => x.wrapping_div(y),
+        IntOp::Rem => x.wrapping_rem(y),
+        IntOp::And => x & y,
+        IntOp::Or => x | y,
+        IntOp::Xor => x ^ y,
+        IntOp::Shl => x.wrapping_shl(y as u32),
+        IntOp::Shr => x.wrapping_shr(y as u32),
+        IntOp::Ushr => (x as u32).wrapping_shr(y as u32) as i32,
+    }))
+}
+
+fn long_op(op: IntOp, x: i64, y: i64) -> Result<Value, Error> {
+    if matches!(op, IntOp::Div | IntOp::Rem) && y == 0 {
+        return Err(Error::Throws(Exception::Arithmetic));
+    }
+    // the shift distance is masked to 6 bits by wrapping_sh*
+    Ok(Value::Long(match op {
+        IntOp::Add => x.wrapping_add(y),
+        IntOp::Sub => x.wrapping_sub(y),
+        IntOp::Mul => x.wrapping_mul(y),
+        IntOp::Div => x.wrapping_div(y),
+        IntOp::Rem => x.wrapping_rem(y),
+        IntOp::And => x & y,
+        IntOp::Or => x | y,
+        IntOp::Xor => x ^ y,
+        IntOp::Shl => x.wrapping_shl(y as u32),
+        IntOp::Shr => x.wrapping_shr(y as u32),
+        IntOp::Ushr => (x as u64).wrapping_shr(y as u32) as i64,
+    }))
+}
+
+// `%` on floats is the truncating remainder, as in Java
+fn float_op<T>(op: FloatOp, x: T, y: T) -> T
+where
+    T: Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Div<Output = T> + Rem<Output = T>,
+{
+    match op {
+        FloatOp::Add => x + y,
+        FloatOp::Sub => x - y,
+        FloatOp::Mul => x * y,
+        FloatOp::Div => x / y,
+        FloatOp::Rem => x % y,
+    }
+}
+
+// -1, 0 or 1, with `nan` when either operand is NaN
+fn compare<T: PartialOrd>(x: T, y: T, nan: i32) -> Value {
+    Value::Int(x.partial_cmp(&y).map_or(nan, |ord| ord as i32))
+}
+
+impl Instruction {
+    /// Compute the value the instruction writes to its destination register
+    ///
+    /// `value` gives the contents of a source register, or of the register
+    /// pair starting at it for wide operands, or `None` if it is not known.
+    /// Only the arithmetic, conversion and comparison instructions (`neg-*`
+    /// through `ushr-int/lit8`) can be evaluated; integer division or
+    /// remainder by zero results in [`Error::Throws`].
+    ///
+    /// ```
+    /// use dalvik::eval::{Error, Value};
+    /// use dalvik::exceptions::Exception;
+    /// use dalvik::Instruction;
+    ///
+    /// let regs = |reg| match reg {
+    ///     0 => Some(Value::Int(1)),
+    ///     1 => Some(Value::Int(33)),
+    ///     2 => Some(Value::Float(f32::NAN)),
+    ///     _ => None,
+    /// };
+    /// assert_eq!(Instruction::ShlInt(3, 0, 1).evaluate(regs), Ok(Value::Int(2)));
+    /// assert_eq!(Instruction::FloatToInt(3, 2).evaluate(regs), Ok(Value::Int(0)));
+    /// assert_eq!(Instruction::CmpgFloat(3, 2, 0).evaluate(regs), Ok(Value::Int(1)));
+    /// assert_eq!(Instruction::DivInt8(3, 0, 0).evaluate(regs), Err(Error::Throws(Exception::Arithmetic)));
+    /// assert_eq!(Instruction::AddInt(3, 0, 4).evaluate(regs), Err(Error::Unknown(4)));
+    /// ```
+    pub fn evaluate(&self, value: impl FnMut(u16) -> Option<Value>) -> Result<Value, Error> {
+        let mut r = Inputs(value);
+
+        match *self {
+            Self::CmplFloat(_, a, b) => Ok(compare(r.float(a)?, r.float(b)?, -1)),
+            Self::CmpgFloat(_, a, b) => Ok(compare(r.float(a)?, r.float(b)?, 1)),
+            Self::CmplDouble(_, a, b) => Ok(compare(r.double(a)?, r.double(b)?, -1)),
+            Self::CmpgDouble(_, a, b) => Ok(compare(r.double(a)?, r.double(b)?, 1)),
+            Self::CmpLong(_, a, b) => Ok(Value::Int(r.long(a)?.cmp(&r.long(b)?) as i32)),
+
+            Self::NegInt(_, a) => Ok(Value::Int(r.int(a)?.wrapping_neg())),
+            Self::NotInt(_, a) => Ok(Value::Int(!r.int(a)?)),
+            Self::NegLong(_, a) => Ok(Value::Long(r.long(a)?.wrapping_neg())),
+            Self::NotLong(_, a) => Ok(Value::Long(!r.long(a)?)),
+            Self::NegFloat(_, a) => Ok(Value::Float(-r.float(a)?)),
+            Self::NegDouble(_, a) => Ok(Value::Double(-r.double(a)?)),
+
+            // `as` rounds to nearest, and saturates from floats with NaN as 0
+            Self::IntToLong(_, a) => Ok(Value::Long(r.int(a)?.into())),
+            Self::IntToFloat(_, a) => Ok(Value::Float(r.int(a)? as f32)),
+            Self::IntToDouble(_, a) => Ok(Value::Double(r.int(a)?.into())),
+            Self::LongToInt(_, a) => Ok(Value::Int(r.long(a)? as i32)),
+            Self::LongToFloat(_, a) => Ok(Value::Float(r.long(a)? as f32)),
+            Self::LongToDouble(_, a) => Ok(Value::Double(r.long(a)? as f64)),
+            Self::FloatToInt(_, a) => Ok(Value::Int(r.float(a)? as i32)),
+            Self::FloatToLong(_, a) => Ok(Value::Long(r.float(a)? as i64)),
+            Self::FloatToDouble(_, a) => Ok(Value::Double(r.float(a)?.into())),
+            Self::DoubleToInt(_, a) => Ok(Value::Int(r.double(a)? as i32)),
+            Self::DoubleToLong(_, a) => Ok(Value::Long(r.double(a)? as i64)),
+            Self::DoubleToFloat(_, a) => Ok(Value::Float(r.double(a)? as f32)),
+            Self::IntTobyte(_, a) => Ok(Value::Int((r.int(a)? as i8).into())),
+            Self::IntTochar(_, a) => Ok(Value::Int((r.int(a)? as u16).into())),
+            Self::IntToshort(_, a) => Ok(Value::Int((r.int(a)? as i16).into())),
+
+            Self::AddInt(_, a, b) | Self::AddInt2(a, b) => int_op(IntOp::Add, r.int(a)?, r.int(b)?),
+            Self::SubInt(_, a, b) | Self::SubInt2(a, b) => int_op(IntOp::Sub, r.int(a)?, r.int(b)?),
+            Self::MulInt(_, a, b) | Self::MulInt2(a, b) => int_op(IntOp::Mul, r.int(a)?, r.int(b)?),
+            Self::DivInt(_, a, b) | Self::DivInt2(a, b) => int_op(IntOp::Div, r.int(a)?, r.int(b)?),
+            Self::RemInt(_, a, b) | Self::RemInt2(a, b) => int_op(IntOp::Rem, r.int(a)?, r.int(b)?),
+            Self::AndInt(_, a, b) | Self::AndInt2(a, b) => int_op(IntOp::And, r.int(a)?, r.int(b)?),
+            Self::OrInt(_, a, b) | Self::OrInt2(a, b) => int_op(IntOp::Or, r.int(a)?, r.int(b)?),
+            Self::XorInt(_, a, b) | Self::XorInt2(a, b) => int_op(IntOp::Xor, r.int(a)?, r.int(b)?),
+            Self::ShlInt(_, a, b) | Self::ShlInt2(a, b) => int_op(IntOp::Shl, r.int(a)?, r.int(b)?),
+            Self::ShrInt(_, a, b) | Self::ShrInt2(a, b) => int_op(IntOp::Shr, r.int(a)?, r.int(b)?),
+            Self::UshrInt(_, a, b) | Self::UShrInt2(a, b) => int_op(IntOp::Ushr, r.int(a)?, r.int(b)?),
+
+            // the shift distance of a long shift is an int
+            Self::AddLong(_, a, b) | Self::AddLong2(a, b) => long_op(IntOp::Add, r.long(a)?, r.long(b)?),
+            Self::SubLong(_, a, b) | Self::SubLong2(a, b) => long_op(IntOp::Sub, r.long(a)?, r.long(b)?),
+            Self::MulLong(_, a, b) | Self::MulLong2(a, b) => long_op(IntOp::Mul, r.long(a)?, r.long(b)?),
+            Self::DivLong(_, a, b) | Self::DivLong2(a, b) => long_op(IntOp::Div, r.long(a)?, r.long(b)?),
+            Self::RemLong(_, a, b) | Self::RemLong2(a, b) => long_op(IntOp::Rem, r.long(a)?, r.long(b)?),
+            Self::AndLong(_, a, b) | Self::AndLong2(a, b) => long_op(IntOp::And, r.long(a)?, r.long(b)?),
+            Self::OrLong(_, a, b) | Self::OrLong2(a, b) => long_op(IntOp::Or, r.long(a)?, r.long(b)?),
+            Self::XorLong(_, a, b) | Self::XorLong2(a, b) => long_op(IntOp::Xor, r.long(a)?, r.long(b)?),
+            Self::ShlLong(_, a, b) | Self::ShlLong2(a, b) => long_op(IntOp::Shl, r.long(a)?, r.int(b)?.into()),
+            Self::ShrLong(_, a, b) | Self::ShrLong2(a, b) => long_op(IntOp::Shr, r.long(a)?, r.int(b)?.into()),
+            Self::UshrLong(_, a, b) | Self::UShrLong2(a, b) => long_op(IntOp::Ushr, r.long(a)?, r.int(b)?.into()),
+
+            Self::AddFloat(_, a, b) | Self::AddFloat2(a, b) => Ok(Value::Float(float_op(FloatOp::Add, r.float(a)?, r.float(b)?))),
+            Self::SubFloat(_, a, b) | Self::SubFloat2(a, b) => Ok(Value::Float(float_op(FloatOp::Sub, r.float(a)?, r.float(b)?))),
+            Self::MulFloat(_, a, b) | Self::MulFloat2(a, b) => Ok(Value::Float(float_op(FloatOp::Mul, r.float(a)?, r.float(b)?))),
+            Self::DivFloat(_, a, b) | Self::DivFloat2(a, b) => Ok(Value::Float(float_op(FloatOp::Div, r.float(a)?, r.float(b)?))),
+            Self::RemFloat(_, a, b) | Self::RemFloat2(a, b) => Ok(Value::Float(float_op(FloatOp::Rem, r.float(a)?, r.float(b)?))),
+            Self::AddDouble(_, a, b) | Self::AddDouble2(a, b) => Ok(Value::Double(float_op(FloatOp::Add, r.double(a)?, r.double(b)?))),
+            Self::SubDouble(_, a, b) | Self::SubDouble2(a, b) => Ok(Value::Double(float_op(FloatOp::Sub, r.double(a)?, r.double(b)?))),
+            Self::MulDouble(_, a, b) | Self::MulDouble2(a, b) => Ok(Value::Double(float_op(FloatOp::Mul, r.double(a)?, r.double(b)?))),
+            Self::DivDouble(_, a, b) | Self::DivDouble2(a, b) => Ok(Value::Double(float_op(FloatOp::Div, r.double(a)?, r.double(b)?))),
+            Self::RemDouble(_, a, b) | Self::RemDouble2(a, b) => Ok(Value::Double(float_op(FloatOp::Rem, r.double(a)?, r.double(b)?))),
+
+            Self::AddInt16(_, a, lit) => int_op(IntOp::Add, r.int(a)?, lit.into()),
+            Self::RsubInt16(_, a, lit) => int_op(IntOp::Sub, lit.into(), r.int(a)?),
+            Self::MulInt16(_, a, lit) => int_op(IntOp::Mul, r.int(a)?, lit.into()),
+            Self::DivInt16(_, a, lit) => int_op(IntOp::Div, r.int(a)?, lit.into()),
+            Self::RemInt16(_, a, lit) => int_op(IntOp::Rem, r.int(a)?, lit.into()),
+            Self::AndInt16(_, a, lit) => int_op(IntOp::And, r.int(a)?, lit.into()),
+            Self::OrInt16(_, a, lit) => int_op(IntOp::Or, r.int(a)?, lit.into()),
+            Self::XorInt16(_, a, lit) => int_op(IntOp::Xor, r.int(a)?, lit.into()),
+
+            Self::AddInt8(_, a, lit) => int_op(IntOp::Add, r.int(a)?, lit.into()),
+            Self::RsubInt8(_, a, lit) => int_op(IntOp::Sub, lit.into(), r.int(a)?),
+            Self::MulInt8(_, a, lit) => int_op(IntOp::Mul, r.int(a)?, lit.into()),
+            Self::DivInt8(_, a, lit) => int_op(IntOp::Div, r.int(a)?, lit.into()),
+            Self::RemInt8(_, a, lit) => int_op(IntOp::Rem, r.int(a)?, lit.into()),
+            Self::AndInt8(_, a, lit) => int_op(IntOp::And, r.int(a)?, lit.into()),
+            Self::OrInt8(_, a, lit) => int_op(IntOp::Or, r.int(a)?, lit.into()),
+            Self::XorInt8(_, a, lit) => int_op(IntOp::Xor, r.int(a)?, lit.into()),
+            Self::ShlInt8(_, a, lit) => int_op(IntOp::Shl, r.int(a)?, lit.into()),
+            Self::ShrInt8(_, a, lit) => int_op(IntOp::Shr, r.int(a)?, lit.into()),
+            Self::UshrInt8(_, a, lit) => int_op(IntOp::Ushr, r.int(a)?, lit.into()),
+
+            _ => Err(Error::Unsupported),
+        }
+    }
+}
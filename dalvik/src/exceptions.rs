@@ -0,0 +1,323 @@
+//! Implicit exceptions raised by instructions
+//!
+//! [`Instruction::throws`] gives the exceptions an instruction may raise on
+//! its own, so that only those instructions in a try range get an edge to its
+//! catch handlers. Asynchronous errors that may happen anywhere, like
+//! `StackOverflowError` or `ThreadDeath`, are not modelled.
+
+use crate::Instruction;
+
+/// An exception an instruction may raise
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Exception {
+    /// `NullPointerException`, from a null object, array or monitor
+    NullPointer,
+    /// `ArithmeticException`, from an integer division or remainder by zero
+    Arithmetic,
+    /// `ArrayIndexOutOfBoundsException`
+    ArrayIndexOutOfBounds,
+    /// `ArrayStoreException`, from `aput-object` of an incompatible reference
+    ArrayStore,
+    /// `NegativeArraySizeException`
+    NegativeArraySize,
+    /// `ClassCastException`
+    ClassCast,
+    /// `IllegalMonitorStateException`, from `monitor-exit` of a monitor not
+    /// held
+    IllegalMonitorState,
+    /// `OutOfMemoryError`, from an allocation
+    OutOfMemory,
+    /// A `LinkageError` or `ExceptionInInitializerError` from resolving the
+    /// referenced class, field, method or call site, e.g. `NoSuchFieldError`
+    Linkage,
+    /// Any `Throwable`: whatever an invoked method throws, or the object
+    /// thrown by `throw`
+    Any,
+}
+
+impl Instruction {
+    /// The exceptions the instruction may raise, none if it cannot throw
+    ///
+    /// ```
+    /// use dalvik::exceptions::Exception;
+    /// use dalvik::Instruction;
+    ///
+    /// assert_eq!(Instruction::DivInt(0, 1, 2).throws(), [Exception::Arithmetic]);
+    /// assert_eq!(Instruction::DivInt8(0, 1, 2).throws(), []);
+    /// assert_eq!(Instruction::AddInt(0, 1, 2).throws(), []);
+    /// ```
+    pub fn throws(&self) -> &'static [Exception] {
+        use Exception::*;
+
+        match self {
+            Self::MonitorEnter(_) | Self::ArrayLength(_, _) => &[NullPointer],
+            Self::MonitorExit(_) => &[NullPointer, IllegalMonitorState],
+
+            // resolving the string on first use allocates it
+            Self::ConstString(_, _) | Self::ConstStringJumbo(_, _) => &[OutOfMemory],
+            Self::ConstClass(_, _) | Self::ConstMethodHandle(_, _) | Self::ConstMethodType(_, _) | Self::InstanceOf(_, _, _) => &[Linkage],
+            Self::CheckCast(_, _) => &[Linkage, ClassCast],
+            Self::NewInstance(_, _) | Self::FilledNewArray { .. } | Self::FilledNewArrayRange { .. } => &[Linkage, OutOfMemory],
+            Self::NewArray(_, _, _) => &[Linkage, NegativeArraySize, OutOfMemory],
+            Self::FillArrayData(_, _) => &[NullPointer, ArrayIndexOutOfBounds],
+            Self::Throw(_) => &[NullPointer, Any],
+            Self::ThrowVerificationError(_, _) => &[Linkage],
+
+            Self::AGet(_, _, _)
+            | Self::AGetWide(_, _, _)
+            | Self::AGetObject(_, _, _)
+            | Self::AGetBoolean(_, _, _)
+            | Self::AGetByte(_, _, _)
+            | Self::AGetChar(_, _, _)
+            | Self::AGetShort(_, _, _)
+            | Self::APut(_, _, _)
+            | Self::APutWide(_, _, _)
+            | Self::APutBoolean(_, _, _)
+            | Self::APutByte(_, _, _)
+            | Self::APutChar(_, _, _)
+            | Self::APutShort(_, _, _) => &[NullPointer, ArrayIndexOutOfBounds],
+            Self::APutObject(_, _, _) => &[NullPointer, ArrayIndexOutOfBounds, ArrayStore],
+
+            Self::IGet(_, _, _)
+            | Self::IGetWide(_, _, _)
+            | Self::IGetObject(_, _, _)
+            | Self::IGetBoolean(_, _, _)
+            | Self::IGetByte(_, _, _)
+            | Self::IGetChar(_, _, _)
+            | Self::IGetShort(_, _, _)
+            | Self::IPut(_, _, _)
+            | Self::IPutWide(_, _, _)
+            | Self::IPutObject(_, _, _)
+            | Self::IPutBoolean(_, _, _)
+            | Self::IPutByte(_, _, _)
+            | Self::IPutChar(_, _, _)
+            | Self::IPutShort(_, _, _) => &[Linkage, NullPointer],
+
+            // also runs the static initializer of the class on first access
+            Self::SGet(_, _)
+            | Self::SGetWide(_, _)
+            | Self::SGetObject(_, _)
+            | Self::SGetBoolean(_, _)
+            | Self::SGetByte(_, _)
+            | Self::SGetChar(_, _)
+            | Self::SGetShort(_, _)
+            | Self::SPut(_, _)
+            | Self::SPutWide(_, _)
+            | Self::SPutObject(_, _)
+            | Self::SPutBoolean(_, _)
+            | Self::SPutByte(_, _)
+            | Self::SPutChar(_, _)
+            | Self::SPutShort(_, _)
+            | Self::SGetVolatile(_, _)
+            | Self::SPutVolatile(_, _)
+            | Self::SGetWideVolatile(_, _)
+            | Self::SPutWideVolatile(_, _)
+            | Self::SGetObjectVolatile(_, _)
+            | Self::SPutObjectVolatile(_, _) => &[Linkage],
+
+            Self::InvokeVirtual { .. }
+            | Self::InvokeSuper { .. }
+            | Self::InvokeDirect { .. }
+            | Self::InvokeInterface { .. }
+            | Self::InvokePolymorphic { .. }
+            | Self::InvokeVirtualRange { .. }
+            | Self::InvokeSuperRange { .. }
+            | Self::InvokeDirectRange { .. }
+            | Self::InvokeInterfaceRange { .. }
+            | Self::InvokePolymorphicRange { .. } => &[Linkage, NullPointer, Any],
+            Self::InvokeStatic { .. } | Self::InvokeStaticRange { .. } | Self::InvokeCustom { .. } | Self::InvokeCustomRange { .. } => &[Linkage, Any],
+
+            Self::DivInt(_, _, _)
+            | Self::RemInt(_, _, _)
+            | Self::DivLong(_, _, _)
+            | Self::RemLong(_, _, _)
+            | Self::DivInt2(_, _)
+            | Self::RemInt2(_, _)
+            | Self::DivLong2(_, _)
+            | Self::RemLong2(_, _) => &[Arithmetic],
+            Self::DivInt16(_, _, 0) | Self::RemInt16(_, _, 0) | Self::DivInt8(_, _, 0) | Self::RemInt8(_, _, 0) => &[Arithmetic],
+            Self::DivInt16(_, _, _) | Self::RemInt16(_, _, _) | Self::DivInt8(_, _, _) | Self::RemInt8(_, _, _) => &[],
+
+            // optimized instructions refer to already resolved fields and
+            // methods
+            Self::IGetQuick(_, _, _)
+            | Self::IGetWideQuick(_, _, _)
+            | Self::IGetObjectQuick(_, _, _)
+            | Self::IPutQuick(_, _, _)
+            | Self::IPutWideQuick(_, _, _)
+            | Self::IPutObjectQuick(_, _, _)
+            | Self::IPutBooleanQuick(_, _, _)
+            | Self::IPutByteQuick(_, _, _)
+            | Self::IPutCharQuick(_, _, _)
+            | Self::IPutShortQuick(_, _, _)
+            | Self::IGetBooleanQuick(_, _, _)
+            | Self::IGetByteQuick(_, _, _)
+            | Self::IGetCharQuick(_, _, _)
+            | Self::IGetShortQuick(_, _, _)
+            | Self::IGetVolatile(_, _, _)
+            | Self::IPutVolatile(_, _, _)
+            | Self::IGetObjectVolatile(_, _, _)
+            | Self::IGetWideVolatile(_, _, _)
+            | Self::IPutWideVolatile(_, _, _)
+            | Self::IPutObjectVolatile(_, _, _)
+            | Self::InvokeDirectEmpty { .. }
+            | Self::InvokeObjectInitRange { .. } => &[NullPointer],
+            Self::InvokeVirtualQuick { .. } | Self::InvokeVirtualQuickRange { .. } | Self::InvokeSuperQuick { .. } | Self::InvokeSuperQuickRange { .. } => {
+                &[NullPointer, Any]
+            }
+            Self::ExecuteInline { .. } | Self::ExecuteInlineRange { .. } => &[Any],
+
+            Self::Nop
+            | Self::Move(_, _)
+            | Self::MoveFrom16(_, _)
+            | Self::Move16(_, _)
+            | Self::MoveWide(_, _)
+            | Self::MoveWideFrom16(_, _)
+            | Self::MoveWide16(_, _)
+            | Self::MoveObject(_, _)
+            | Self::MoveObjectFrom16(_, _)
+            | Self::MoveObject16(_, _)
+            | Self::MoveResult(_)
+            | Self::MoveResultWide(_)
+            | Self::MoveResultObject(_)
+            | Self::MoveException(_)
+            | Self::ReturnVoid
+            | Self::Return(_)
+            | Self::ReturnWide(_)
+            | Self::ReturnObject(_)
+            | Self::Const4(_, _)
+            | Self::Const16(_, _)
+            | Self::Const(_, _)
+            | Self::ConstHigh16(_, _)
+            | Self::ConstWide16(_, _)
+            | Self::ConstWide32(_, _)
+            | Self::ConstWide(_, _)
+            | Self::ConstWideHigh16(_, _)
+            | Self::Goto(_)
+            | Self::Goto16(_)
+            | Self::Goto32(_)
+            | Self::PackedSwitch(_, _)
+            | Self::SparseSwitch(_, _)
+            | Self::CmplFloat(_, _, _)
+            | Self::CmpgFloat(_, _, _)
+            | Self::CmplDouble(_, _, _)
+            | Self::CmpgDouble(_, _, _)
+            | Self::CmpLong(_, _, _)
+            | Self::IfEq(_, _, _)
+            | Self::IfNe(_, _, _)
+            | Self::IfLt(_, _, _)
+            | Self::IfGe(_, _, _)
+            | Self::IfGt(_, _, _)
+            | Self::IfLe(_, _, _)
+            | Self::IfEqz(_, _)
+            | Self::IfNez(_, _)
+            | Self::IfLtz(_, _)
+            | Self::IfGez(_, _)
+            | Self::IfGtz(_, _)
+            | Self::IfLez(_, _)
+            | Self::NegInt(_, _)
+            | Self::NotInt(_, _)
+            | Self::NegLong(_, _)
+            | Self::NotLong(_, _)
+            | Self::NegFloat(_, _)
+            | Self::NegDouble(_, _)
+            | Self::IntToLong(_, _)
+            | Self::IntToFloat(_, _)
+            | Self::IntToDouble(_, _)
+            | Self::LongToInt(_, _)
+            | Self::LongToFloat(_, _)
+            | Self::LongToDouble(_, _)
+            | Self::FloatToInt(_, _)
+            | Self::FloatToLong(_, _)
+            | Self::FloatToDouble(_, _)
+            | Self::DoubleToInt(_, _)
+            | Self::DoubleToLong(_, _)
+            | Self::DoubleToFloat(_, _)
+            | Self::IntTobyte(_, _)
+            | Self::IntTochar(_, _)
+            | Self::IntToshort(_, _)
+            | Self::AddInt(_, _, _)
+            | Self::SubInt(_, _, _)
+            | Self::MulInt(_, _, _)
+            | Self::AndInt(_, _, _)
+            | Self::OrInt(_, _, _)
+            | Self::XorInt(_, _, _)
+            | Self::ShlInt(_, _, _)
+            | Self::ShrInt(_, _, _)
+            | Self::UshrInt(_, _, _)
+            | Self::AddLong(_, _, _)
+            | Self::SubLong(_, _, _)
+            | Self::MulLong(_, _, _)
+            | Self::AndLong(_, _, _)
+            | Self::OrLong(_, _, _)
+            | Self::XorLong(_, _, _)
+            | Self::ShlLong(_, _, _)
+            | Self::ShrLong(_, _, _)
+            | Self::UshrLong(_, _, _)
+            | Self::AddFloat(_, _, _)
+            | Self::SubFloat(_, _, _)
+            | Self::MulFloat(_, _, _)
+            | Self::DivFloat(_, _, _)
+            | Self::RemFloat(_, _, _)
+            | Self::AddDouble(_, _, _)
+            | Self::SubDouble(_, _, _)
+            | Self::MulDouble(_, _, _)
+            | Self::DivDouble(_, _, _)
+            | Self::RemDouble(_, _, _)
+            | Self::AddInt2(_, _)
+            | Self::SubInt2(_, _)
+            | Self::MulInt2(_, _)
+            | Self::AndInt2(_, _)
+            | Self::OrInt2(_, _)
+            | Self::XorInt2(_, _)
+            | Self::ShlInt2(_, _)
+            | Self::ShrInt2(_, _)
+            | Self::UShrInt2(_, _)
+            | Self::AddLong2(_, _)
+            | Self::SubLong2(_, _)
+            | Self::MulLong2(_, _)
+            | Self::AndLong2(_, _)
+            | Self::OrLong2(_, _)
+            | Self::XorLong2(_, _)
+            | Self::ShlLong2(_, _)
+            | Self::ShrLong2(_, _)
+            | Self::UShrLong2(_, _)
+            | Self::AddFloat2(_, _)
+            | Self::SubFloat2(_, _)
+            | Self::MulFloat2(_, _)
+            | Self::DivFloat2(_, _)
+            | Self::RemFloat2(_, _)
+            | Self::AddDouble2(_, _)
+            | Self::SubDouble2(_, _)
+            | Self::MulDouble2(_, _)
+            | Self::DivDouble2(_, _)
+            | Self::RemDouble2(_, _)
+            | Self::AddInt16(_, _, _)
+            | Self::RsubInt16(_, _, _)
+            | Self::MulInt16(_, _, _)
+            | Self::AndInt16(_, _, _)
+            | Self::OrInt16(_, _, _)
+            | Self::XorInt16(_, _, _)
+            | Self::AddInt8(_, _, _)
+            | Self::RsubInt8(_, _, _)
+            | Self::MulInt8(_, _, _)
+            | Self::AndInt8(_, _, _)
+            | Self::OrInt8(_, _, _)
+            | Self::XorInt8(_, _, _)
+            | Self::ShlInt8(_, _, _)
+            | Self::ShrInt8(_, _, _)
+            | Self::UshrInt8(_, _, _)
+            | Self::ReturnVoidNoBarrier
+            | Self::Breakpoint
+            | Self::ReturnVoidBarrier
+            | Self::PackedSwitchPayload { .. }
+            | Self::SparseSwitchPayload { .. }
+            | Self::FillArrayDataPayload { .. } => &[],
+        }
+    }
+
+    /// Whether the instruction may raise an exception, see [`throws`][`Self::throws`]
+    pub fn can_throw(&self) -> bool {
+        !self.throws().is_empty()
+    }
+}
@@ -1,4 +1,4 @@
-//! [Dalvik bytecode] instruction decoding and basic block analysis.
+//! [Dalvik bytecode] instruction decoding, encoding and basic block analysis.
 //!
 //! [Dalvik bytecode]: https://source.android.com/docs/core/runtime/dalvik-bytecode
 //!
@@ -6,14 +6,56 @@
 //! for printing the instruction mnemonics, however the best disassembly
 //! (closely matching baksmali) is possible only with dex metadata available,
 //! which can be provided through the [`PrettyPrint`] trait.
+//!
+//! # Serialization
+//!
+//! With the `serde` feature, [`Instruction`], [`ControlFlow`],
+//! [`BasicBlock`][`blocks::BasicBlock`] and
+//! [`NextBranch`][`blocks::NextBranch`] implement `Serialize` and
+//! `Deserialize`. Variant and field names are those of the Rust types, so the
+//! schema only changes along with the API. In JSON:
+//!
+//! - an [`Instruction`] is an object with the variant name in `op` and the
+//!   operands in `args`, as an array for tuple variants or an object for
+//!   struct variants. Operand-less instructions have no `args`.
+//!   ```json
+//!   {"op":"AddInt2","args":[1,2]}
+//!   {"op":"InvokeStatic","args":{"method":3,"nargs":1,"args":[0,0,0,0,0]}}
+//!   {"op":"ReturnVoid"}
+//!   ```
+//! - a [`ControlFlow`] or [`NextBranch`][`blocks::NextBranch`] is an object
+//!   with the variant name in `kind` and the offset or targets in `args`.
+//!   ```json
+//!   {"kind":"GoTo","args":-4}
+//!   {"kind":"Cond","args":{"t":8,"f":4}}
+//!   {"kind":"Terminate"}
+//!   ```
+//! - a [`BasicBlock`][`blocks::BasicBlock`] is an object with `instructions`
+//!   and `next`.
 
 #![warn(missing_docs)]
 
 #[cfg(test)]
 mod tests;
 
+pub mod assemble;
 pub mod blocks;
+pub mod builder;
 pub mod decode;
+pub mod encode;
+pub mod eval;
+pub mod exceptions;
+pub mod opcode;
+pub mod patch;
+pub mod pseudocode;
+pub mod references;
+pub mod registers;
+pub mod smali;
+pub mod sweep;
+pub mod tokens;
+
+use references::Reference;
+use tokens::Tokens;
 
 /// Dalvik Instruction
 ///
@@ -24,7 +66,20 @@ pub mod decode;
 /// Most of the enum variants here are simple tuples. The ordering of the
 /// contained values matches the order of the instruction mnemonic as read from
 /// left to right.
-#[derive(Debug, PartialEq, Eq)]
+///
+/// The `*Payload` variants are the inline data tables referenced by
+/// `packed-switch`, `sparse-switch` and `fill-array-data`. Their branch
+/// `targets` are relative to the address of the referencing switch
+/// instruction, not to the payload itself. `FillArrayDataPayload::data` holds
+/// the raw little-endian element bytes, `element_width` bytes per element.
+///
+/// The optimized variants (e.g. `IGetQuick`, `ExecuteInline`) only appear in
+/// odex and quickened vdex files, and are only decoded when requested through
+/// [`DecodeOptions`][`decode::DecodeOptions`]. Their indices are
+/// runtime-specific: field byte offsets, vtable slots and inline method
+/// numbers rather than dex constant pool indices.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(tag = "op", content = "args"))]
 #[allow(missing_docs)]
 pub enum Instruction {
     Nop,                                                       // 00
@@ -245,10 +300,65 @@ pub enum Instruction {
     ShlInt8(u8, u8, i8),                                       // e0
     ShrInt8(u8, u8, i8),                                       // e1
     UshrInt8(u8, u8, i8),                                      // e2
+
+    // Dex version 038+
+    InvokePolymorphic { method: u16, proto: u16, nargs: u8, args: [u8; 5] }, // fa
+    InvokePolymorphicRange { method: u16, proto: u16, args: Vec<u16> },      // fb
+    InvokeCustom { call_site: u16, nargs: u8, args: [u8; 5] },               // fc
+    InvokeCustomRange { call_site: u16, args: Vec<u16> },                    // fd
+    ConstMethodHandle(u8, u16),                                              // fe
+    ConstMethodType(u8, u16),                                                // ff
+
+    // Optimized opcodes, only decoded when targeting a runtime through
+    // `DecodeOptions::api_level`
+    ReturnVoidNoBarrier,                                          // art 73
+    IGetQuick(u8, u8, u16),                                       // dalvik f2, art e3
+    IGetWideQuick(u8, u8, u16),                                   // dalvik f3, art e4
+    IGetObjectQuick(u8, u8, u16),                                 // dalvik f4, art e5
+    IPutQuick(u8, u8, u16),                                       // dalvik f5, art e6
+    IPutWideQuick(u8, u8, u16),                                   // dalvik f6, art e7
+    IPutObjectQuick(u8, u8, u16),                                 // dalvik f7, art e8
+    InvokeVirtualQuick { vtable: u16, nargs: u8, args: [u8; 5] }, // dalvik f8, art e9
+    InvokeVirtualQuickRange { vtable: u16, args: Vec<u16> },      // dalvik f9, art ea
+    IPutBooleanQuick(u8, u8, u16),                                // art eb
+    IPutByteQuick(u8, u8, u16),                                   // art ec
+    IPutCharQuick(u8, u8, u16),                                   // art ed
+    IPutShortQuick(u8, u8, u16),                                  // art ee
+    IGetBooleanQuick(u8, u8, u16),                                // art ef
+    IGetByteQuick(u8, u8, u16),                                   // art f0
+    IGetCharQuick(u8, u8, u16),                                   // art f1
+    IGetShortQuick(u8, u8, u16),                                  // art f2
+    InvokeSuperQuick { vtable: u16, nargs: u8, args: [u8; 5] },   // dalvik fa
+    InvokeSuperQuickRange { vtable: u16, args: Vec<u16> },        // dalvik fb
+    IGetVolatile(u8, u8, u16),                                    // dalvik e3
+    IPutVolatile(u8, u8, u16),                                    // dalvik e4
+    SGetVolatile(u8, u16),                                        // dalvik e5
+    SPutVolatile(u8, u16),                                        // dalvik e6
+    IGetObjectVolatile(u8, u8, u16),                              // dalvik e7
+    IGetWideVolatile(u8, u8, u16),                                // dalvik e8
+    IPutWideVolatile(u8, u8, u16),                                // dalvik e9
+    SGetWideVolatile(u8, u16),                                    // dalvik ea
+    SPutWideVolatile(u8, u16),                                    // dalvik eb
+    Breakpoint,                                                   // dalvik ec
+    ThrowVerificationError(u8, u16),                              // dalvik ed
+    ExecuteInline { inline: u16, nargs: u8, args: [u8; 5] },      // dalvik ee
+    ExecuteInlineRange { inline: u16, args: Vec<u16> },           // dalvik ef
+    InvokeDirectEmpty { method: u16, nargs: u8, args: [u8; 5] },  // dalvik f0
+    InvokeObjectInitRange { method: u16, args: Vec<u16> },        // dalvik f0
+    ReturnVoidBarrier,                                            // dalvik f1
+    IPutObjectVolatile(u8, u8, u16),                              // dalvik fc
+    SGetObjectVolatile(u8, u16),                                  // dalvik fd
+    SPutObjectVolatile(u8, u16),                                  // dalvik fe
+
+    // Inline data tables, identified by a pseudo-opcode of 00
+    PackedSwitchPayload { first_key: i32, targets: Vec<i32> },  // 0100
+    SparseSwitchPayload { keys: Vec<i32>, targets: Vec<i32> },  // 0200
+    FillArrayDataPayload { element_width: u16, data: Vec<u8> }, // 0300
 }
 
 /// Describes the possible control flow effects of an [`Instruction`]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(tag = "kind", content = "args"))]
 pub enum ControlFlow {
     /// Terminates the method (throw or return)
     Terminate,
@@ -269,7 +379,10 @@ impl Instruction {
             | Self::Return(_)
             | Self::ReturnWide(_)
             | Self::ReturnObject(_)
-            | Self::Throw(_) => ControlFlow::Terminate,
+            | Self::Throw(_)
+            | Self::ReturnVoidNoBarrier
+            | Self::ReturnVoidBarrier
+            | Self::ThrowVerificationError(_, _) => ControlFlow::Terminate,
 
             Self::Goto(t) => ControlFlow::GoTo((*t).into()),
             Self::Goto16(t) => ControlFlow::GoTo((*t).into()),
@@ -293,301 +406,476 @@ impl Instruction {
     }
 }
 
+/// Comparison of a conditional branch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(missing_docs)]
+pub enum Condition {
+    Eq,
+    Ne,
+    Lt,
+    Ge,
+    Gt,
+    Le,
+}
+
+impl Condition {
+    /// The condition holding exactly when `self` does not
+    pub fn inverse(self) -> Self {
+        match self {
+            Condition::Eq => Condition::Ne,
+            Condition::Ne => Condition::Eq,
+            Condition::Lt => Condition::Ge,
+            Condition::Ge => Condition::Lt,
+            Condition::Gt => Condition::Le,
+            Condition::Le => Condition::Gt,
+        }
+    }
+}
+
+impl Instruction {
+    /// The `if-*` instruction comparing `a` with `b`, or with zero when `b` is
+    /// `None`
+    ///
+    /// ```
+    /// use dalvik::{Condition, Instruction};
+    ///
+    /// assert_eq!(Instruction::conditional(Condition::Lt, 1, None, 4), Instruction::IfLtz(1, 4));
+    /// assert_eq!(Instruction::IfGe(1, 2, 4).condition(), Some((Condition::Ge, 1, Some(2))));
+    /// ```
+    pub fn conditional(cond: Condition, a: u8, b: Option<u8>, off: i16) -> Self {
+        match (cond, b) {
+            (Condition::Eq, Some(b)) => Self::IfEq(a, b, off),
+            (Condition::Ne, Some(b)) => Self::IfNe(a, b, off),
+            (Condition::Lt, Some(b)) => Self::IfLt(a, b, off),
+            (Condition::Ge, Some(b)) => Self::IfGe(a, b, off),
+            (Condition::Gt, Some(b)) => Self::IfGt(a, b, off),
+            (Condition::Le, Some(b)) => Self::IfLe(a, b, off),
+            (Condition::Eq, None) => Self::IfEqz(a, off),
+            (Condition::Ne, None) => Self::IfNez(a, off),
+            (Condition::Lt, None) => Self::IfLtz(a, off),
+            (Condition::Ge, None) => Self::IfGez(a, off),
+            (Condition::Gt, None) => Self::IfGtz(a, off),
+            (Condition::Le, None) => Self::IfLez(a, off),
+        }
+    }
+
+    /// The comparison and registers of an `if-*` instruction, the inverse of
+    /// [`conditional`][`Self::conditional`]
+    pub fn condition(&self) -> Option<(Condition, u8, Option<u8>)> {
+        Some(match *self {
+            Self::IfEq(a, b, _) => (Condition::Eq, a, Some(b)),
+            Self::IfNe(a, b, _) => (Condition::Ne, a, Some(b)),
+            Self::IfLt(a, b, _) => (Condition::Lt, a, Some(b)),
+            Self::IfGe(a, b, _) => (Condition::Ge, a, Some(b)),
+            Self::IfGt(a, b, _) => (Condition::Gt, a, Some(b)),
+            Self::IfLe(a, b, _) => (Condition::Le, a, Some(b)),
+            Self::IfEqz(a, _) => (Condition::Eq, a, None),
+            Self::IfNez(a, _) => (Condition::Ne, a, None),
+            Self::IfLtz(a, _) => (Condition::Lt, a, None),
+            Self::IfGez(a, _) => (Condition::Ge, a, None),
+            Self::IfGtz(a, _) => (Condition::Gt, a, None),
+            Self::IfLez(a, _) => (Condition::Le, a, None),
+            _ => return None,
+        })
+    }
+}
+
 impl std::fmt::Display for Instruction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Instruction::Nop => f.write_str("nop"),
-            Instruction::Move(dst, src) => two_regs_display(f, "move", *dst, *src),
-            Instruction::MoveFrom16(dst, src) => two_regs_display(f, "move/from16", *dst, *src),
-            Instruction::Move16(dst, src) => two_regs_display(f, "move/16", *dst, *src),
-            Instruction::MoveWide(dst, src) => two_regs_display(f, "move-wide", *dst, *src),
-            Instruction::MoveWideFrom16(dst, src) => two_regs_display(f, "move-wide/from16", *dst, *src),
-            Instruction::MoveWide16(dst, src) => two_regs_display(f, "move-wide/16", *dst, *src),
-            Instruction::MoveObject(dst, src) => two_regs_display(f, "move-object", *dst, *src),
-            Instruction::MoveObjectFrom16(dst, src) => two_regs_display(f, "move-object/from16", *dst, *src),
-            Instruction::MoveObject16(dst, src) => two_regs_display(f, "move-object/16", *dst, *src),
-            Instruction::MoveResult(reg) => one_regs_display(f, "move-result", *reg),
-            Instruction::MoveResultWide(reg) => one_regs_display(f, "move-result-wide", *reg),
-            Instruction::MoveResultObject(reg) => one_regs_display(f, "move-result-object", *reg),
-            Instruction::MoveException(reg) => one_regs_display(f, "move-exception", *reg),
-            Instruction::ReturnVoid => f.write_str("return-void"),
-            Instruction::Return(reg) => one_regs_display(f, "return", *reg),
-            Instruction::ReturnWide(reg) => one_regs_display(f, "return-wide", *reg),
-            Instruction::ReturnObject(reg) => one_regs_display(f, "return-object", *reg),
-            Instruction::Const4(dst, src) => f.write_fmt(format_args!("const/4 v{dst}, {src:#x}")),
-            Instruction::Const16(dst, src) => f.write_fmt(format_args!("const/16 v{dst}, {src:#x}")),
-            Instruction::Const(dst, src) => f.write_fmt(format_args!("const v{dst}, {src:#x}")),
-            Instruction::ConstHigh16(dst, src) => f.write_fmt(format_args!("const/high16 v{dst}, {src:#x}0000")),
-            Instruction::ConstWide16(dst, src) => f.write_fmt(format_args!("const-wide/16 v{dst}, {src:#x}")),
-            Instruction::ConstWide32(dst, src) => f.write_fmt(format_args!("const-wide/32 v{dst}, {src:#x}")),
-            Instruction::ConstWide(dst, src) => f.write_fmt(format_args!("const-wide v{dst}, {src:#x}")),
-            Instruction::ConstWideHigh16(dst, src) => f.write_fmt(format_args!("const-wide/high16 v{dst}, {src:#x}")),
-            Instruction::ConstString(dst, idx) => f.write_fmt(format_args!("const-string v{dst}, string@{idx:x}")),
-            Instruction::ConstStringJumbo(dst, idx) => f.write_fmt(format_args!("const-string/jumbo v{dst}, string@{idx:x}")),
-            Instruction::ConstClass(dst, idx) => f.write_fmt(format_args!("const-class v{dst}, type@{idx:x}")),
-            Instruction::MonitorEnter(reg) => one_regs_display(f, "monitor-enter", *reg),
-            Instruction::MonitorExit(reg) => one_regs_display(f, "monitor-exit", *reg),
-            Instruction::CheckCast(reg, ty) => f.write_fmt(format_args!("check-cast v{reg}, type@{ty:x}")),
-            Instruction::InstanceOf(dst, src, ty) => f.write_fmt(format_args!("instance-of v{dst}, v{src}, type@{ty:x}")),
-            Instruction::ArrayLength(dst, src) => two_regs_display(f, "array-length", *dst, *src),
-            Instruction::NewInstance(reg, ty) => f.write_fmt(format_args!("new-instance v{reg}, type@{ty:x}")),
-            Instruction::NewArray(dst, size, ty) => f.write_fmt(format_args!("new-array v{dst}, v{size}, type@{ty:x}")),
-            Instruction::FilledNewArray { ty, nargs, args } => {
-                f.write_fmt(format_args!("filled-new-array {{"))?;
-                for (n, arg) in args[..*nargs as usize].iter().enumerate() {
-                    match n {
-                        0 => f.write_fmt(format_args!("v{arg}"))?,
-                        _ => f.write_fmt(format_args!(", v{arg}"))?,
-                    }
-                }
-                f.write_fmt(format_args!("}}, type@{ty:x}"))
+        self.tokens().iter().try_for_each(|token| f.write_str(&token.text))
+    }
+}
+
+impl Instruction {
+    /// The [`Display`][`std::fmt::Display`] text of the instruction, as
+    /// [`Token`][`tokens::Token`]s
+    pub fn tokens(&self) -> Vec<tokens::Token> {
+        let tokens = match self {
+            Instruction::Nop => Tokens::new("nop"),
+            Instruction::Move(dst, src) => two_regs_tokens("move", *dst, *src),
+            Instruction::MoveFrom16(dst, src) => two_regs_tokens("move/from16", *dst, *src),
+            Instruction::Move16(dst, src) => two_regs_tokens("move/16", *dst, *src),
+            Instruction::MoveWide(dst, src) => two_regs_tokens("move-wide", *dst, *src),
+            Instruction::MoveWideFrom16(dst, src) => two_regs_tokens("move-wide/from16", *dst, *src),
+            Instruction::MoveWide16(dst, src) => two_regs_tokens("move-wide/16", *dst, *src),
+            Instruction::MoveObject(dst, src) => two_regs_tokens("move-object", *dst, *src),
+            Instruction::MoveObjectFrom16(dst, src) => two_regs_tokens("move-object/from16", *dst, *src),
+            Instruction::MoveObject16(dst, src) => two_regs_tokens("move-object/16", *dst, *src),
+            Instruction::MoveResult(reg) => one_regs_tokens("move-result", *reg),
+            Instruction::MoveResultWide(reg) => one_regs_tokens("move-result-wide", *reg),
+            Instruction::MoveResultObject(reg) => one_regs_tokens("move-result-object", *reg),
+            Instruction::MoveException(reg) => one_regs_tokens("move-exception", *reg),
+            Instruction::ReturnVoid => Tokens::new("return-void"),
+            Instruction::Return(reg) => one_regs_tokens("return", *reg),
+            Instruction::ReturnWide(reg) => one_regs_tokens("return-wide", *reg),
+            Instruction::ReturnObject(reg) => one_regs_tokens("return-object", *reg),
+            Instruction::Const4(dst, src) => Tokens::new("const/4").register(*dst).literal(format!("{src:#x}")),
+            Instruction::Const16(dst, src) => Tokens::new("const/16").register(*dst).literal(format!("{src:#x}")),
+            Instruction::Const(dst, src) => Tokens::new("const").register(*dst).literal(format!("{src:#x}")),
+            Instruction::ConstHigh16(dst, src) => Tokens::new("const/high16").register(*dst).literal(format!("{src:#x}0000")),
+            Instruction::ConstWide16(dst, src) => Tokens::new("const-wide/16").register(*dst).literal(format!("{src:#x}")),
+            Instruction::ConstWide32(dst, src) => Tokens::new("const-wide/32").register(*dst).literal(format!("{src:#x}")),
+            Instruction::ConstWide(dst, src) => Tokens::new("const-wide").register(*dst).literal(format!("{src:#x}")),
+            Instruction::ConstWideHigh16(dst, src) => Tokens::new("const-wide/high16").register(*dst).literal(format!("{src:#x}")),
+            Instruction::ConstString(dst, idx) => Tokens::new("const-string").register(*dst).reference(Reference::String((*idx).into())),
+            Instruction::ConstStringJumbo(dst, idx) => Tokens::new("const-string/jumbo").register(*dst).reference(Reference::String(*idx)),
+            Instruction::ConstClass(dst, idx) => Tokens::new("const-class").register(*dst).reference(Reference::Type(*idx)),
+            Instruction::MonitorEnter(reg) => one_regs_tokens("monitor-enter", *reg),
+            Instruction::MonitorExit(reg) => one_regs_tokens("monitor-exit", *reg),
+            Instruction::CheckCast(reg, ty) => Tokens::new("check-cast").register(*reg).reference(Reference::Type(*ty)),
+            Instruction::InstanceOf(dst, src, ty) => Tokens::new("instance-of").register(*dst).register(*src).reference(Reference::Type(*ty)),
+            Instruction::ArrayLength(dst, src) => two_regs_tokens("array-length", *dst, *src),
+            Instruction::NewInstance(reg, ty) => Tokens::new("new-instance").register(*reg).reference(Reference::Type(*ty)),
+            Instruction::NewArray(dst, size, ty) => Tokens::new("new-array").register(*dst).register(*size).reference(Reference::Type(*ty)),
+            Instruction::FilledNewArray { ty, nargs, args } => Tokens::new("filled-new-array")
+                .registers(args[..*nargs as usize].iter().copied())
+                .reference(Reference::Type(*ty)),
+            Instruction::FilledNewArrayRange { ty, args } => Tokens::new("filled-new-array/range")
+                .registers(args.iter().copied())
+                .reference(Reference::Type(*ty)),
+            Instruction::FillArrayData(array, off) => Tokens::new("fill-array-data").register(*array).target(*off),
+            Instruction::Throw(reg) => one_regs_tokens("throw", *reg),
+            Instruction::Goto(off) => Tokens::new("goto").target(*off),
+            Instruction::Goto16(off) => Tokens::new("goto/16").target(*off),
+            Instruction::Goto32(off) => Tokens::new("goto/32").target(*off),
+            Instruction::PackedSwitch(reg, off) => Tokens::new("packed-switch").register(*reg).target(*off),
+            Instruction::SparseSwitch(reg, off) => Tokens::new("sparse-switch").register(*reg).target(*off),
+            Instruction::CmplFloat(dst, src1, src2) => three_regs_tokens("cmpl-float", *dst, *src1, *src2),
+            Instruction::CmpgFloat(dst, src1, src2) => three_regs_tokens("cmpg-float", *dst, *src1, *src2),
+            Instruction::CmplDouble(dst, src1, src2) => three_regs_tokens("cmpl-double", *dst, *src1, *src2),
+            Instruction::CmpgDouble(dst, src1, src2) => three_regs_tokens("cmpg-double", *dst, *src1, *src2),
+            Instruction::CmpLong(dst, src1, src2) => three_regs_tokens("cmp-long", *dst, *src1, *src2),
+            Instruction::IfEq(a, b, off) => Tokens::new("if-eq").register(*a).register(*b).target(*off),
+            Instruction::IfNe(a, b, off) => Tokens::new("if-ne").register(*a).register(*b).target(*off),
+            Instruction::IfLt(a, b, off) => Tokens::new("if-lt").register(*a).register(*b).target(*off),
+            Instruction::IfGe(a, b, off) => Tokens::new("if-ge").register(*a).register(*b).target(*off),
+            Instruction::IfGt(a, b, off) => Tokens::new("if-gt").register(*a).register(*b).target(*off),
+            Instruction::IfLe(a, b, off) => Tokens::new("if-le").register(*a).register(*b).target(*off),
+            Instruction::IfEqz(reg, off) => Tokens::new("if-eqz").register(*reg).target(*off),
+            Instruction::IfNez(reg, off) => Tokens::new("if-nez").register(*reg).target(*off),
+            Instruction::IfLtz(reg, off) => Tokens::new("if-ltz").register(*reg).target(*off),
+            Instruction::IfGez(reg, off) => Tokens::new("if-gez").register(*reg).target(*off),
+            Instruction::IfGtz(reg, off) => Tokens::new("if-gtz").register(*reg).target(*off),
+            Instruction::IfLez(reg, off) => Tokens::new("if-lez").register(*reg).target(*off),
+            Instruction::AGet(dst, src1, src2) => three_regs_tokens("aget", *dst, *src1, *src2),
+            Instruction::AGetWide(dst, src1, src2) => three_regs_tokens("aget-wide", *dst, *src1, *src2),
+            Instruction::AGetObject(dst, src1, src2) => three_regs_tokens("aget-object", *dst, *src1, *src2),
+            Instruction::AGetBoolean(dst, src1, src2) => three_regs_tokens("aget-boolean", *dst, *src1, *src2),
+            Instruction::AGetByte(dst, src1, src2) => three_regs_tokens("aget-byte", *dst, *src1, *src2),
+            Instruction::AGetChar(dst, src1, src2) => three_regs_tokens("aget-char", *dst, *src1, *src2),
+            Instruction::AGetShort(dst, src1, src2) => three_regs_tokens("aget-short", *dst, *src1, *src2),
+            Instruction::APut(dst, src1, src2) => three_regs_tokens("aput", *dst, *src1, *src2),
+            Instruction::APutWide(dst, src1, src2) => three_regs_tokens("aput-wide", *dst, *src1, *src2),
+            Instruction::APutObject(dst, src1, src2) => three_regs_tokens("aput-object", *dst, *src1, *src2),
+            Instruction::APutBoolean(dst, src1, src2) => three_regs_tokens("aput-boolean", *dst, *src1, *src2),
+            Instruction::APutByte(dst, src1, src2) => three_regs_tokens("aput-byte", *dst, *src1, *src2),
+            Instruction::APutChar(dst, src1, src2) => three_regs_tokens("aput-char", *dst, *src1, *src2),
+            Instruction::APutShort(dst, src1, src2) => three_regs_tokens("aput-short", *dst, *src1, *src2),
+            Instruction::IGet(dst, src, field) => igetters_tokens("iget", *dst, *src, *field),
+            Instruction::IGetWide(dst, src, field) => igetters_tokens("iget-wide", *dst, *src, *field),
+            Instruction::IGetObject(dst, src, field) => igetters_tokens("iget-object", *dst, *src, *field),
+            Instruction::IGetBoolean(dst, src, field) => igetters_tokens("iget-boolean", *dst, *src, *field),
+            Instruction::IGetByte(dst, src, field) => igetters_tokens("iget-byte", *dst, *src, *field),
+            Instruction::IGetChar(dst, src, field) => igetters_tokens("iget-char", *dst, *src, *field),
+            Instruction::IGetShort(dst, src, field) => igetters_tokens("iget-short", *dst, *src, *field),
+            Instruction::IPut(dst, src, field) => igetters_tokens("iput", *dst, *src, *field),
+            Instruction::IPutWide(dst, src, field) => igetters_tokens("iput-wide", *dst, *src, *field),
+            Instruction::IPutObject(dst, src, field) => igetters_tokens("iput-object", *dst, *src, *field),
+            Instruction::IPutBoolean(dst, src, field) => igetters_tokens("iput-boolean", *dst, *src, *field),
+            Instruction::IPutByte(dst, src, field) => igetters_tokens("iput-byte", *dst, *src, *field),
+            Instruction::IPutChar(dst, src, field) => igetters_tokens("iput-char", *dst, *src, *field),
+            Instruction::IPutShort(dst, src, field) => igetters_tokens("iput-short", *dst, *src, *field),
+            Instruction::SGet(dst, field) => sgetters_tokens("sget", *dst, *field),
+            Instruction::SGetWide(dst, field) => sgetters_tokens("sget-wide", *dst, *field),
+            Instruction::SGetObject(dst, field) => sgetters_tokens("sget-object", *dst, *field),
+            Instruction::SGetBoolean(dst, field) => sgetters_tokens("sget-boolean", *dst, *field),
+            Instruction::SGetByte(dst, field) => sgetters_tokens("sget-byte", *dst, *field),
+            Instruction::SGetChar(dst, field) => sgetters_tokens("sget-char", *dst, *field),
+            Instruction::SGetShort(dst, field) => sgetters_tokens("sget-short", *dst, *field),
+            Instruction::SPut(dst, field) => sgetters_tokens("sput", *dst, *field),
+            Instruction::SPutWide(dst, field) => sgetters_tokens("sput-wide", *dst, *field),
+            Instruction::SPutObject(dst, field) => sgetters_tokens("sput-object", *dst, *field),
+            Instruction::SPutBoolean(dst, field) => sgetters_tokens("sput-boolean", *dst, *field),
+            Instruction::SPutByte(dst, field) => sgetters_tokens("sput-byte", *dst, *field),
+            Instruction::SPutChar(dst, field) => sgetters_tokens("sput-char", *dst, *field),
+            Instruction::SPutShort(dst, field) => sgetters_tokens("sput-short", *dst, *field),
+            Instruction::InvokeVirtual { method, nargs, args } => invoke_tokens(args, nargs, *method, "virtual"),
+            Instruction::InvokeSuper { method, nargs, args } => invoke_tokens(args, nargs, *method, "super"),
+            Instruction::InvokeDirect { method, nargs, args } => invoke_tokens(args, nargs, *method, "direct"),
+            Instruction::InvokeStatic { method, nargs, args } => invoke_tokens(args, nargs, *method, "static"),
+            Instruction::InvokeInterface { method, nargs, args } => invoke_tokens(args, nargs, *method, "interface"),
+            Instruction::InvokeVirtualRange { method, args } => invoke_range_tokens(args, *method, "virtual"),
+            Instruction::InvokeSuperRange { method, args } => invoke_range_tokens(args, *method, "super"),
+            Instruction::InvokeDirectRange { method, args } => invoke_range_tokens(args, *method, "direct"),
+            Instruction::InvokeStaticRange { method, args } => invoke_range_tokens(args, *method, "static"),
+            Instruction::InvokeInterfaceRange { method, args } => invoke_range_tokens(args, *method, "interface"),
+            Instruction::NegInt(dst, src) => two_regs_tokens("neg-int", *dst, *src),
+            Instruction::NotInt(dst, src) => two_regs_tokens("not-int", *dst, *src),
+            Instruction::NegLong(dst, src) => two_regs_tokens("neg-long", *dst, *src),
+            Instruction::NotLong(dst, src) => two_regs_tokens("not-long", *dst, *src),
+            Instruction::NegFloat(dst, src) => two_regs_tokens("neg-float", *dst, *src),
+            Instruction::NegDouble(dst, src) => two_regs_tokens("neg-double", *dst, *src),
+            Instruction::IntToLong(dst, src) => two_regs_tokens("int-to-long", *dst, *src),
+            Instruction::IntToFloat(dst, src) => two_regs_tokens("int-to-float", *dst, *src),
+            Instruction::IntToDouble(dst, src) => two_regs_tokens("int-to-double", *dst, *src),
+            Instruction::LongToInt(dst, src) => two_regs_tokens("long-to-int", *dst, *src),
+            Instruction::LongToFloat(dst, src) => two_regs_tokens("long-to-float", *dst, *src),
+            Instruction::LongToDouble(dst, src) => two_regs_tokens("long-to-double", *dst, *src),
+            Instruction::FloatToInt(dst, src) => two_regs_tokens("float-to-int", *dst, *src),
+            Instruction::FloatToLong(dst, src) => two_regs_tokens("float-to-long", *dst, *src),
+            Instruction::FloatToDouble(dst, src) => two_regs_tokens("float-to-double", *dst, *src),
+            Instruction::DoubleToInt(dst, src) => two_regs_tokens("double-to-int", *dst, *src),
+            Instruction::DoubleToLong(dst, src) => two_regs_tokens("double-to-long", *dst, *src),
+            Instruction::DoubleToFloat(dst, src) => two_regs_tokens("double-to-float", *dst, *src),
+            Instruction::IntTobyte(dst, src) => two_regs_tokens("int-to-byte", *dst, *src),
+            Instruction::IntTochar(dst, src) => two_regs_tokens("int-to-char", *dst, *src),
+            Instruction::IntToshort(dst, src) => two_regs_tokens("int-to-short", *dst, *src),
+            Instruction::AddInt(dst, src1, src2) => three_regs_tokens("add-int", *dst, *src1, *src2),
+            Instruction::SubInt(dst, src1, src2) => three_regs_tokens("sub-int", *dst, *src1, *src2),
+            Instruction::MulInt(dst, src1, src2) => three_regs_tokens("mul-int", *dst, *src1, *src2),
+            Instruction::DivInt(dst, src1, src2) => three_regs_tokens("div-int", *dst, *src1, *src2),
+            Instruction::RemInt(dst, src1, src2) => three_regs_tokens("rem-int", *dst, *src1, *src2),
+            Instruction::AndInt(dst, src1, src2) => three_regs_tokens("and-int", *dst, *src1, *src2),
+            Instruction::OrInt(dst, src1, src2) => three_regs_tokens("or-int", *dst, *src1, *src2),
+            Instruction::XorInt(dst, src1, src2) => three_regs_tokens("xor-int", *dst, *src1, *src2),
+            Instruction::ShlInt(dst, src1, src2) => three_regs_tokens("shl-int", *dst, *src1, *src2),
+            Instruction::ShrInt(dst, src1, src2) => three_regs_tokens("shr-int", *dst, *src1, *src2),
+            Instruction::UshrInt(dst, src1, src2) => three_regs_tokens("ushr-int", *dst, *src1, *src2),
+            Instruction::AddLong(dst, src1, src2) => three_regs_tokens("add-long", *dst, *src1, *src2),
+            Instruction::SubLong(dst, src1, src2) => three_regs_tokens("sub-long", *dst, *src1, *src2),
+            Instruction::MulLong(dst, src1, src2) => three_regs_tokens("mul-long", *dst, *src1, *src2),
+            Instruction::DivLong(dst, src1, src2) => three_regs_tokens("div-long", *dst, *src1, *src2),
+            Instruction::RemLong(dst, src1, src2) => three_regs_tokens("rem-long", *dst, *src1, *src2),
+            Instruction::AndLong(dst, src1, src2) => three_regs_tokens("and-long", *dst, *src1, *src2),
+            Instruction::OrLong(dst, src1, src2) => three_regs_tokens("or-long", *dst, *src1, *src2),
+            Instruction::XorLong(dst, src1, src2) => three_regs_tokens("xor-long", *dst, *src1, *src2),
+            Instruction::ShlLong(dst, src1, src2) => three_regs_tokens("shl-long", *dst, *src1, *src2),
+            Instruction::ShrLong(dst, src1, src2) => three_regs_tokens("shr-long", *dst, *src1, *src2),
+            Instruction::UshrLong(dst, src1, src2) => three_regs_tokens("ushr-long", *dst, *src1, *src2),
+            Instruction::AddFloat(dst, src1, src2) => three_regs_tokens("add-float", *dst, *src1, *src2),
+            Instruction::SubFloat(dst, src1, src2) => three_regs_tokens("sub-float", *dst, *src1, *src2),
+            Instruction::MulFloat(dst, src1, src2) => three_regs_tokens("mul-float", *dst, *src1, *src2),
+            Instruction::DivFloat(dst, src1, src2) => three_regs_tokens("div-float", *dst, *src1, *src2),
+            Instruction::RemFloat(dst, src1, src2) => three_regs_tokens("rem-float", *dst, *src1, *src2),
+            Instruction::AddDouble(dst, src1, src2) => three_regs_tokens("add-double", *dst, *src1, *src2),
+            Instruction::SubDouble(dst, src1, src2) => three_regs_tokens("sub-double", *dst, *src1, *src2),
+            Instruction::MulDouble(dst, src1, src2) => three_regs_tokens("mul-double", *dst, *src1, *src2),
+            Instruction::DivDouble(dst, src1, src2) => three_regs_tokens("div-double", *dst, *src1, *src2),
+            Instruction::RemDouble(dst, src1, src2) => three_regs_tokens("rem-double", *dst, *src1, *src2),
+            Instruction::AddInt2(dst, src) => two_regs_tokens("add-int/2addr", *dst, *src),
+            Instruction::SubInt2(dst, src) => two_regs_tokens("sub-int/2addr", *dst, *src),
+            Instruction::MulInt2(dst, src) => two_regs_tokens("mul-int/2addr", *dst, *src),
+            Instruction::DivInt2(dst, src) => two_regs_tokens("div-int/2addr", *dst, *src),
+            Instruction::RemInt2(dst, src) => two_regs_tokens("rem-int/2addr", *dst, *src),
+            Instruction::AndInt2(dst, src) => two_regs_tokens("and-int/2addr", *dst, *src),
+            Instruction::OrInt2(dst, src) => two_regs_tokens("or-int/2addr", *dst, *src),
+            Instruction::XorInt2(dst, src) => two_regs_tokens("xor-int/2addr", *dst, *src),
+            Instruction::ShlInt2(dst, src) => two_regs_tokens("shl-int/2addr", *dst, *src),
+            Instruction::ShrInt2(dst, src) => two_regs_tokens("shr-int/2addr", *dst, *src),
+            Instruction::UShrInt2(dst, src) => two_regs_tokens("ushr-int/2addr", *dst, *src),
+            Instruction::AddLong2(dst, src) => two_regs_tokens("add-long/2addr", *dst, *src),
+            Instruction::SubLong2(dst, src) => two_regs_tokens("sub-long/2addr", *dst, *src),
+            Instruction::MulLong2(dst, src) => two_regs_tokens("mul-long/2addr", *dst, *src),
+            Instruction::DivLong2(dst, src) => two_regs_tokens("div-long/2addr", *dst, *src),
+            Instruction::RemLong2(dst, src) => two_regs_tokens("rem-long/2addr", *dst, *src),
+            Instruction::AndLong2(dst, src) => two_regs_tokens("and-long/2addr", *dst, *src),
+            Instruction::OrLong2(dst, src) => two_regs_tokens("or-long/2addr", *dst, *src),
+            Instruction::XorLong2(dst, src) => two_regs_tokens("xor-long/2addr", *dst, *src),
+            Instruction::ShlLong2(dst, src) => two_regs_tokens("shl-long/2addr", *dst, *src),
+            Instruction::ShrLong2(dst, src) => two_regs_tokens("shr-long/2addr", *dst, *src),
+            Instruction::UShrLong2(dst, src) => two_regs_tokens("ushr-long/2addr", *dst, *src),
+            Instruction::AddFloat2(dst, src) => two_regs_tokens("add-float/2addr", *dst, *src),
+            Instruction::SubFloat2(dst, src) => two_regs_tokens("sub-float/2addr", *dst, *src),
+            Instruction::MulFloat2(dst, src) => two_regs_tokens("mul-float/2addr", *dst, *src),
+            Instruction::DivFloat2(dst, src) => two_regs_tokens("div-float/2addr", *dst, *src),
+            Instruction::RemFloat2(dst, src) => two_regs_tokens("rem-float/2addr", *dst, *src),
+            Instruction::AddDouble2(dst, src) => two_regs_tokens("add-double/2addr", *dst, *src),
+            Instruction::SubDouble2(dst, src) => two_regs_tokens("sub-double/2addr", *dst, *src),
+            Instruction::MulDouble2(dst, src) => two_regs_tokens("mul-double/2addr", *dst, *src),
+            Instruction::DivDouble2(dst, src) => two_regs_tokens("div-double/2addr", *dst, *src),
+            Instruction::RemDouble2(dst, src) => two_regs_tokens("rem-double/2addr", *dst, *src),
+            Instruction::AddInt16(dst, src, lit) => Tokens::new("add-int/lit16").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::RsubInt16(dst, src, lit) => Tokens::new("rsub-int/lit16").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::MulInt16(dst, src, lit) => Tokens::new("mul-int/lit16").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::DivInt16(dst, src, lit) => Tokens::new("div-int/lit16").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::RemInt16(dst, src, lit) => Tokens::new("rem-int/lit16").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::AndInt16(dst, src, lit) => Tokens::new("and-int/lit16").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::OrInt16(dst, src, lit) => Tokens::new("or-int/lit16").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::XorInt16(dst, src, lit) => Tokens::new("xor-int/lit16").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::AddInt8(dst, src, lit) => Tokens::new("add-int/lit8").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::RsubInt8(dst, src, lit) => Tokens::new("rsub-int/lit8").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::MulInt8(dst, src, lit) => Tokens::new("mul-int/lit8").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::DivInt8(dst, src, lit) => Tokens::new("div-int/lit8").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::RemInt8(dst, src, lit) => Tokens::new("rem-int/lit8").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::AndInt8(dst, src, lit) => Tokens::new("and-int/lit8").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::OrInt8(dst, src, lit) => Tokens::new("or-int/lit8").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::XorInt8(dst, src, lit) => Tokens::new("xor-int/lit8").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::ShlInt8(dst, src, lit) => Tokens::new("shl-int/lit8").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::ShrInt8(dst, src, lit) => Tokens::new("shr-int/lit8").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::UshrInt8(dst, src, lit) => Tokens::new("ushr-int/lit8").register(*dst).register(*src).literal(format!("{lit:#x}")),
+            Instruction::InvokePolymorphic { method, proto, nargs, args } => {
+                invoke_tokens(args, nargs, *method, "polymorphic").reference(Reference::Proto(*proto))
             }
-            Instruction::FilledNewArrayRange { ty, args } => {
-                f.write_fmt(format_args!("filled-new-array/range {{"))?;
-                for (n, arg) in args.iter().enumerate() {
-                    match n {
-                        0 => f.write_fmt(format_args!("v{arg}"))?,
-                        _ => f.write_fmt(format_args!(", v{arg}"))?,
-                    }
+            Instruction::InvokePolymorphicRange { method, proto, args } => {
+                invoke_range_tokens(args, *method, "polymorphic").reference(Reference::Proto(*proto))
+            }
+            Instruction::InvokeCustom { call_site, nargs, args } => Tokens::new("invoke-custom")
+                .registers(args[..*nargs as usize].iter().copied())
+                .reference(Reference::CallSite(*call_site)),
+            Instruction::InvokeCustomRange { call_site, args } => Tokens::new("invoke-custom/range")
+                .registers(args.iter().copied())
+                .reference(Reference::CallSite(*call_site)),
+            Instruction::ConstMethodHandle(dst, idx) => Tokens::new("const-method-handle").register(*dst).reference(Reference::MethodHandle(*idx)),
+            Instruction::ConstMethodType(dst, idx) => Tokens::new("const-method-type").register(*dst).reference(Reference::Proto(*idx)),
+            Instruction::ReturnVoidNoBarrier => Tokens::new("return-void-no-barrier"),
+            Instruction::IGetQuick(dst, obj, offset) => Tokens::new("iget-quick").register(*dst).register(*obj).literal(format!("field@{offset:x}")),
+            Instruction::IGetWideQuick(dst, obj, offset) => Tokens::new("iget-wide-quick")
+                .register(*dst)
+                .register(*obj)
+                .literal(format!("field@{offset:x}")),
+            Instruction::IGetObjectQuick(dst, obj, offset) => Tokens::new("iget-object-quick")
+                .register(*dst)
+                .register(*obj)
+                .literal(format!("field@{offset:x}")),
+            Instruction::IPutQuick(dst, obj, offset) => Tokens::new("iput-quick").register(*dst).register(*obj).literal(format!("field@{offset:x}")),
+            Instruction::IPutWideQuick(dst, obj, offset) => Tokens::new("iput-wide-quick")
+                .register(*dst)
+                .register(*obj)
+                .literal(format!("field@{offset:x}")),
+            Instruction::IPutObjectQuick(dst, obj, offset) => Tokens::new("iput-object-quick")
+                .register(*dst)
+                .register(*obj)
+                .literal(format!("field@{offset:x}")),
+            Instruction::InvokeVirtualQuick { vtable, nargs, args } => optimized_invoke_tokens("invoke-virtual-quick", args, nargs, "vtable", *vtable),
+            Instruction::InvokeVirtualQuickRange { vtable, args } => optimized_invoke_range_tokens("invoke-virtual-quick/range", args, "vtable", *vtable),
+            Instruction::IPutBooleanQuick(dst, obj, offset) => Tokens::new("iput-boolean-quick")
+                .register(*dst)
+                .register(*obj)
+                .literal(format!("field@{offset:x}")),
+            Instruction::IPutByteQuick(dst, obj, offset) => Tokens::new("iput-byte-quick")
+                .register(*dst)
+                .register(*obj)
+                .literal(format!("field@{offset:x}")),
+            Instruction::IPutCharQuick(dst, obj, offset) => Tokens::new("iput-char-quick")
+                .register(*dst)
+                .register(*obj)
+                .literal(format!("field@{offset:x}")),
+            Instruction::IPutShortQuick(dst, obj, offset) => Tokens::new("iput-short-quick")
+                .register(*dst)
+                .register(*obj)
+                .literal(format!("field@{offset:x}")),
+            Instruction::IGetBooleanQuick(dst, obj, offset) => Tokens::new("iget-boolean-quick")
+                .register(*dst)
+                .register(*obj)
+                .literal(format!("field@{offset:x}")),
+            Instruction::IGetByteQuick(dst, obj, offset) => Tokens::new("iget-byte-quick")
+                .register(*dst)
+                .register(*obj)
+                .literal(format!("field@{offset:x}")),
+            Instruction::IGetCharQuick(dst, obj, offset) => Tokens::new("iget-char-quick")
+                .register(*dst)
+                .register(*obj)
+                .literal(format!("field@{offset:x}")),
+            Instruction::IGetShortQuick(dst, obj, offset) => Tokens::new("iget-short-quick")
+                .register(*dst)
+                .register(*obj)
+                .literal(format!("field@{offset:x}")),
+            Instruction::InvokeSuperQuick { vtable, nargs, args } => optimized_invoke_tokens("invoke-super-quick", args, nargs, "vtable", *vtable),
+            Instruction::InvokeSuperQuickRange { vtable, args } => optimized_invoke_range_tokens("invoke-super-quick/range", args, "vtable", *vtable),
+            Instruction::IGetVolatile(dst, src, field) => igetters_tokens("iget-volatile", *dst, *src, *field),
+            Instruction::IPutVolatile(dst, src, field) => igetters_tokens("iput-volatile", *dst, *src, *field),
+            Instruction::SGetVolatile(dst, field) => sgetters_tokens("sget-volatile", *dst, *field),
+            Instruction::SPutVolatile(dst, field) => sgetters_tokens("sput-volatile", *dst, *field),
+            Instruction::IGetObjectVolatile(dst, src, field) => igetters_tokens("iget-object-volatile", *dst, *src, *field),
+            Instruction::IGetWideVolatile(dst, src, field) => igetters_tokens("iget-wide-volatile", *dst, *src, *field),
+            Instruction::IPutWideVolatile(dst, src, field) => igetters_tokens("iput-wide-volatile", *dst, *src, *field),
+            Instruction::SGetWideVolatile(dst, field) => sgetters_tokens("sget-wide-volatile", *dst, *field),
+            Instruction::SPutWideVolatile(dst, field) => sgetters_tokens("sput-wide-volatile", *dst, *field),
+            Instruction::Breakpoint => Tokens::new("breakpoint"),
+            Instruction::ThrowVerificationError(kind, idx) => {
+                let tokens = Tokens::new("throw-verification-error").literal(format!("{kind:#x}"));
+                let text = format!("ref@{idx:x}");
+                match self.references().first() {
+                    Some(r) => tokens.push(tokens::TokenKind::Reference(*r), text),
+                    None => tokens.literal(text),
                 }
-                f.write_fmt(format_args!("}}, type@{ty:x}"))
             }
-            Instruction::FillArrayData(array, off) => f.write_fmt(format_args!("fill-array-data v{array}, {off:+}")),
-            Instruction::Throw(reg) => one_regs_display(f, "throw", *reg),
-            Instruction::Goto(off) => f.write_fmt(format_args!("goto {off:+}")),
-            Instruction::Goto16(off) => f.write_fmt(format_args!("goto/16 {off:+}")),
-            Instruction::Goto32(off) => f.write_fmt(format_args!("goto/32 {off:+}")),
-            Instruction::PackedSwitch(reg, off) => f.write_fmt(format_args!("packed-switch v{reg}, {off:+}")),
-            Instruction::SparseSwitch(reg, off) => f.write_fmt(format_args!("sparse-switch v{reg}, {off:+}")),
-            Instruction::CmplFloat(dst, src1, src2) => three_regs_display(f, "cmpl-float", *dst, *src1, *src2),
-            Instruction::CmpgFloat(dst, src1, src2) => three_regs_display(f, "cmpg-float", *dst, *src1, *src2),
-            Instruction::CmplDouble(dst, src1, src2) => three_regs_display(f, "cmpl-double", *dst, *src1, *src2),
-            Instruction::CmpgDouble(dst, src1, src2) => three_regs_display(f, "cmpg-double", *dst, *src1, *src2),
-            Instruction::CmpLong(dst, src1, src2) => three_regs_display(f, "cmp-long", *dst, *src1, *src2),
-            Instruction::IfEq(a, b, off) => f.write_fmt(format_args!("if-eq v{a}, v{b} {off:+}")),
-            Instruction::IfNe(a, b, off) => f.write_fmt(format_args!("if-ne v{a}, v{b} {off:+}")),
-            Instruction::IfLt(a, b, off) => f.write_fmt(format_args!("if-lt v{a}, v{b} {off:+}")),
-            Instruction::IfGe(a, b, off) => f.write_fmt(format_args!("if-ge v{a}, v{b} {off:+}")),
-            Instruction::IfGt(a, b, off) => f.write_fmt(format_args!("if-gt v{a}, v{b} {off:+}")),
-            Instruction::IfLe(a, b, off) => f.write_fmt(format_args!("if-le v{a}, v{b} {off:+}")),
-            Instruction::IfEqz(reg, off) => f.write_fmt(format_args!("if-eqz v{reg}, {off:+}")),
-            Instruction::IfNez(reg, off) => f.write_fmt(format_args!("if-nez v{reg}, {off:+}")),
-            Instruction::IfLtz(reg, off) => f.write_fmt(format_args!("if-ltz v{reg}, {off:+}")),
-            Instruction::IfGez(reg, off) => f.write_fmt(format_args!("if-gez v{reg}, {off:+}")),
-            Instruction::IfGtz(reg, off) => f.write_fmt(format_args!("if-gtz v{reg}, {off:+}")),
-            Instruction::IfLez(reg, off) => f.write_fmt(format_args!("if-lez v{reg}, {off:+}")),
-            Instruction::AGet(dst, src1, src2) => three_regs_display(f, "aget", *dst, *src1, *src2),
-            Instruction::AGetWide(dst, src1, src2) => three_regs_display(f, "aget-wide", *dst, *src1, *src2),
-            Instruction::AGetObject(dst, src1, src2) => three_regs_display(f, "aget-object", *dst, *src1, *src2),
-            Instruction::AGetBoolean(dst, src1, src2) => three_regs_display(f, "aget-boolean", *dst, *src1, *src2),
-            Instruction::AGetByte(dst, src1, src2) => three_regs_display(f, "aget-byte", *dst, *src1, *src2),
-            Instruction::AGetChar(dst, src1, src2) => three_regs_display(f, "aget-char", *dst, *src1, *src2),
-            Instruction::AGetShort(dst, src1, src2) => three_regs_display(f, "aget-short", *dst, *src1, *src2),
-            Instruction::APut(dst, src1, src2) => three_regs_display(f, "aput", *dst, *src1, *src2),
-            Instruction::APutWide(dst, src1, src2) => three_regs_display(f, "aput-wide", *dst, *src1, *src2),
-            Instruction::APutObject(dst, src1, src2) => three_regs_display(f, "aput-object", *dst, *src1, *src2),
-            Instruction::APutBoolean(dst, src1, src2) => three_regs_display(f, "aput-boolean", *dst, *src1, *src2),
-            Instruction::APutByte(dst, src1, src2) => three_regs_display(f, "aput-byte", *dst, *src1, *src2),
-            Instruction::APutChar(dst, src1, src2) => three_regs_display(f, "aput-char", *dst, *src1, *src2),
-            Instruction::APutShort(dst, src1, src2) => three_regs_display(f, "aput-short", *dst, *src1, *src2),
-            Instruction::IGet(dst, src, field) => igetters_display("iget", f, *dst, *src, *field),
-            Instruction::IGetWide(dst, src, field) => igetters_display("iget-wide", f, *dst, *src, *field),
-            Instruction::IGetObject(dst, src, field) => igetters_display("iget-object", f, *dst, *src, *field),
-            Instruction::IGetBoolean(dst, src, field) => igetters_display("iget-boolean", f, *dst, *src, *field),
-            Instruction::IGetByte(dst, src, field) => igetters_display("iget-byte", f, *dst, *src, *field),
-            Instruction::IGetChar(dst, src, field) => igetters_display("iget-char", f, *dst, *src, *field),
-            Instruction::IGetShort(dst, src, field) => igetters_display("iget-short", f, *dst, *src, *field),
-            Instruction::IPut(dst, src, field) => igetters_display("iput", f, *dst, *src, *field),
-            Instruction::IPutWide(dst, src, field) => igetters_display("iput-wide", f, *dst, *src, *field),
-            Instruction::IPutObject(dst, src, field) => igetters_display("iput-object", f, *dst, *src, *field),
-            Instruction::IPutBoolean(dst, src, field) => igetters_display("iput-boolean", f, *dst, *src, *field),
-            Instruction::IPutByte(dst, src, field) => igetters_display("iput-byte", f, *dst, *src, *field),
-            Instruction::IPutChar(dst, src, field) => igetters_display("iput-char", f, *dst, *src, *field),
-            Instruction::IPutShort(dst, src, field) => igetters_display("iput-short", f, *dst, *src, *field),
-            Instruction::SGet(dst, field) => sgetters_display("sget", f, *dst, *field),
-            Instruction::SGetWide(dst, field) => sgetters_display("sget-wide", f, *dst, *field),
-            Instruction::SGetObject(dst, field) => sgetters_display("sget-object", f, *dst, *field),
-            Instruction::SGetBoolean(dst, field) => sgetters_display("sget-boolean", f, *dst, *field),
-            Instruction::SGetByte(dst, field) => sgetters_display("sget-byte", f, *dst, *field),
-            Instruction::SGetChar(dst, field) => sgetters_display("sget-char", f, *dst, *field),
-            Instruction::SGetShort(dst, field) => sgetters_display("sget-short", f, *dst, *field),
-            Instruction::SPut(dst, field) => sgetters_display("sput", f, *dst, *field),
-            Instruction::SPutWide(dst, field) => sgetters_display("sput-wide", f, *dst, *field),
-            Instruction::SPutObject(dst, field) => sgetters_display("sput-object", f, *dst, *field),
-            Instruction::SPutBoolean(dst, field) => sgetters_display("sput-boolean", f, *dst, *field),
-            Instruction::SPutByte(dst, field) => sgetters_display("sput-byte", f, *dst, *field),
-            Instruction::SPutChar(dst, field) => sgetters_display("sput-char", f, *dst, *field),
-            Instruction::SPutShort(dst, field) => sgetters_display("sput-short", f, *dst, *field),
-            Instruction::InvokeVirtual { method, nargs, args } => invoke_display(f, args, nargs, *method, "virtual"),
-            Instruction::InvokeSuper { method, nargs, args } => invoke_display(f, args, nargs, *method, "super"),
-            Instruction::InvokeDirect { method, nargs, args } => invoke_display(f, args, nargs, *method, "direct"),
-            Instruction::InvokeStatic { method, nargs, args } => invoke_display(f, args, nargs, *method, "static"),
-            Instruction::InvokeInterface { method, nargs, args } => invoke_display(f, args, nargs, *method, "interface"),
-            Instruction::InvokeVirtualRange { method, args } => invoke_range_display(f, args, *method, "virtual"),
-            Instruction::InvokeSuperRange { method, args } => invoke_range_display(f, args, *method, "super"),
-            Instruction::InvokeDirectRange { method, args } => invoke_range_display(f, args, *method, "direct"),
-            Instruction::InvokeStaticRange { method, args } => invoke_range_display(f, args, *method, "static"),
-            Instruction::InvokeInterfaceRange { method, args } => invoke_range_display(f, args, *method, "interface"),
-            Instruction::NegInt(dst, src) => two_regs_display(f, "neg-int", *dst, *src),
-            Instruction::NotInt(dst, src) => two_regs_display(f, "not-int", *dst, *src),
-            Instruction::NegLong(dst, src) => two_regs_display(f, "neg-long", *dst, *src),
-            Instruction::NotLong(dst, src) => two_regs_display(f, "not-long", *dst, *src),
-            Instruction::NegFloat(dst, src) => two_regs_display(f, "neg-float", *dst, *src),
-            Instruction::NegDouble(dst, src) => two_regs_display(f, "neg-double", *dst, *src),
-            Instruction::IntToLong(dst, src) => two_regs_display(f, "int-to-long", *dst, *src),
-            Instruction::IntToFloat(dst, src) => two_regs_display(f, "int-to-float", *dst, *src),
-            Instruction::IntToDouble(dst, src) => two_regs_display(f, "int-to-double", *dst, *src),
-            Instruction::LongToInt(dst, src) => two_regs_display(f, "long-to-int", *dst, *src),
-            Instruction::LongToFloat(dst, src) => two_regs_display(f, "long-to-float", *dst, *src),
-            Instruction::LongToDouble(dst, src) => two_regs_display(f, "long-to-double", *dst, *src),
-            Instruction::FloatToInt(dst, src) => two_regs_display(f, "float-to-int", *dst, *src),
-            Instruction::FloatToLong(dst, src) => two_regs_display(f, "float-to-long", *dst, *src),
-            Instruction::FloatToDouble(dst, src) => two_regs_display(f, "float-to-double", *dst, *src),
-            Instruction::DoubleToInt(dst, src) => two_regs_display(f, "double-to-int", *dst, *src),
-            Instruction::DoubleToLong(dst, src) => two_regs_display(f, "double-to-long", *dst, *src),
-            Instruction::DoubleToFloat(dst, src) => two_regs_display(f, "double-to-float", *dst, *src),
-            Instruction::IntTobyte(dst, src) => two_regs_display(f, "int-to-byte", *dst, *src),
-            Instruction::IntTochar(dst, src) => two_regs_display(f, "int-to-char", *dst, *src),
-            Instruction::IntToshort(dst, src) => two_regs_display(f, "int-to-short", *dst, *src),
-            Instruction::AddInt(dst, src1, src2) => three_regs_display(f, "add-int", *dst, *src1, *src2),
-            Instruction::SubInt(dst, src1, src2) => three_regs_display(f, "sub-int", *dst, *src1, *src2),
-            Instruction::MulInt(dst, src1, src2) => three_regs_display(f, "mul-int", *dst, *src1, *src2),
-            Instruction::DivInt(dst, src1, src2) => three_regs_display(f, "div-int", *dst, *src1, *src2),
-            Instruction::RemInt(dst, src1, src2) => three_regs_display(f, "rem-int", *dst, *src1, *src2),
-            Instruction::AndInt(dst, src1, src2) => three_regs_display(f, "and-int", *dst, *src1, *src2),
-            Instruction::OrInt(dst, src1, src2) => three_regs_display(f, "or-int", *dst, *src1, *src2),
-            Instruction::XorInt(dst, src1, src2) => three_regs_display(f, "xor-int", *dst, *src1, *src2),
-            Instruction::ShlInt(dst, src1, src2) => three_regs_display(f, "shl-int", *dst, *src1, *src2),
-            Instruction::ShrInt(dst, src1, src2) => three_regs_display(f, "shr-int", *dst, *src1, *src2),
-            Instruction::UshrInt(dst, src1, src2) => three_regs_display(f, "ushr-int", *dst, *src1, *src2),
-            Instruction::AddLong(dst, src1, src2) => three_regs_display(f, "add-long", *dst, *src1, *src2),
-            Instruction::SubLong(dst, src1, src2) => three_regs_display(f, "sub-long", *dst, *src1, *src2),
-            Instruction::MulLong(dst, src1, src2) => three_regs_display(f, "mul-long", *dst, *src1, *src2),
-            Instruction::DivLong(dst, src1, src2) => three_regs_display(f, "div-long", *dst, *src1, *src2),
-            Instruction::RemLong(dst, src1, src2) => three_regs_display(f, "rem-long", *dst, *src1, *src2),
-            Instruction::AndLong(dst, src1, src2) => three_regs_display(f, "and-long", *dst, *src1, *src2),
-            Instruction::OrLong(dst, src1, src2) => three_regs_display(f, "or-long", *dst, *src1, *src2),
-            Instruction::XorLong(dst, src1, src2) => three_regs_display(f, "xor-long", *dst, *src1, *src2),
-            Instruction::ShlLong(dst, src1, src2) => three_regs_display(f, "shl-long", *dst, *src1, *src2),
-            Instruction::ShrLong(dst, src1, src2) => three_regs_display(f, "shr-long", *dst, *src1, *src2),
-            Instruction::UshrLong(dst, src1, src2) => three_regs_display(f, "ushr-long", *dst, *src1, *src2),
-            Instruction::AddFloat(dst, src1, src2) => three_regs_display(f, "add-float", *dst, *src1, *src2),
-            Instruction::SubFloat(dst, src1, src2) => three_regs_display(f, "sub-float", *dst, *src1, *src2),
-            Instruction::MulFloat(dst, src1, src2) => three_regs_display(f, "mul-float", *dst, *src1, *src2),
-            Instruction::DivFloat(dst, src1, src2) => three_regs_display(f, "div-float", *dst, *src1, *src2),
-            Instruction::RemFloat(dst, src1, src2) => three_regs_display(f, "rem-float", *dst, *src1, *src2),
-            Instruction::AddDouble(dst, src1, src2) => three_regs_display(f, "add-double", *dst, *src1, *src2),
-            Instruction::SubDouble(dst, src1, src2) => three_regs_display(f, "sub-double", *dst, *src1, *src2),
-            Instruction::MulDouble(dst, src1, src2) => three_regs_display(f, "mul-double", *dst, *src1, *src2),
-            Instruction::DivDouble(dst, src1, src2) => three_regs_display(f, "div-double", *dst, *src1, *src2),
-            Instruction::RemDouble(dst, src1, src2) => three_regs_display(f, "rem-double", *dst, *src1, *src2),
-            Instruction::AddInt2(dst, src) => two_regs_display(f, "add-int/2addr", *dst, *src),
-            Instruction::SubInt2(dst, src) => two_regs_display(f, "sub-int/2addr", *dst, *src),
-            Instruction::MulInt2(dst, src) => two_regs_display(f, "mul-int/2addr", *dst, *src),
-            Instruction::DivInt2(dst, src) => two_regs_display(f, "div-int/2addr", *dst, *src),
-            Instruction::RemInt2(dst, src) => two_regs_display(f, "rem-int/2addr", *dst, *src),
-            Instruction::AndInt2(dst, src) => two_regs_display(f, "and-int/2addr", *dst, *src),
-            Instruction::OrInt2(dst, src) => two_regs_display(f, "or-int/2addr", *dst, *src),
-            Instruction::XorInt2(dst, src) => two_regs_display(f, "xor-int/2addr", *dst, *src),
-            Instruction::ShlInt2(dst, src) => two_regs_display(f, "shl-int/2addr", *dst, *src),
-            Instruction::ShrInt2(dst, src) => two_regs_display(f, "shr-int/2addr", *dst, *src),
-            Instruction::UShrInt2(dst, src) => two_regs_display(f, "ushr-int/2addr", *dst, *src),
-            Instruction::AddLong2(dst, src) => two_regs_display(f, "add-long/2addr", *dst, *src),
-            Instruction::SubLong2(dst, src) => two_regs_display(f, "sub-long/2addr", *dst, *src),
-            Instruction::MulLong2(dst, src) => two_regs_display(f, "mul-long/2addr", *dst, *src),
-            Instruction::DivLong2(dst, src) => two_regs_display(f, "div-long/2addr", *dst, *src),
-            Instruction::RemLong2(dst, src) => two_regs_display(f, "rem-long/2addr", *dst, *src),
-            Instruction::AndLong2(dst, src) => two_regs_display(f, "and-long/2addr", *dst, *src),
-            Instruction::OrLong2(dst, src) => two_regs_display(f, "or-long/2addr", *dst, *src),
-            Instruction::XorLong2(dst, src) => two_regs_display(f, "xor-long/2addr", *dst, *src),
-            Instruction::ShlLong2(dst, src) => two_regs_display(f, "shl-long/2addr", *dst, *src),
-            Instruction::ShrLong2(dst, src) => two_regs_display(f, "shr-long/2addr", *dst, *src),
-            Instruction::UShrLong2(dst, src) => two_regs_display(f, "ushr-long/2addr", *dst, *src),
-            Instruction::AddFloat2(dst, src) => two_regs_display(f, "add-float/2addr", *dst, *src),
-            Instruction::SubFloat2(dst, src) => two_regs_display(f, "sub-float/2addr", *dst, *src),
-            Instruction::MulFloat2(dst, src) => two_regs_display(f, "mul-float/2addr", *dst, *src),
-            Instruction::DivFloat2(dst, src) => two_regs_display(f, "div-float/2addr", *dst, *src),
-            Instruction::RemFloat2(dst, src) => two_regs_display(f, "rem-float/2addr", *dst, *src),
-            Instruction::AddDouble2(dst, src) => two_regs_display(f, "add-double/2addr", *dst, *src),
-            Instruction::SubDouble2(dst, src) => two_regs_display(f, "sub-double/2addr", *dst, *src),
-            Instruction::MulDouble2(dst, src) => two_regs_display(f, "mul-double/2addr", *dst, *src),
-            Instruction::DivDouble2(dst, src) => two_regs_display(f, "div-double/2addr", *dst, *src),
-            Instruction::RemDouble2(dst, src) => two_regs_display(f, "rem-double/2addr", *dst, *src),
-            Instruction::AddInt16(dst, src, lit) => f.write_fmt(format_args!("add-int/lit16 v{dst}, v{src}, {lit:#x}")),
-            Instruction::RsubInt16(dst, src, lit) => f.write_fmt(format_args!("rsub-int/lit16 v{dst}, v{src}, {lit:#x}")),
-            Instruction::MulInt16(dst, src, lit) => f.write_fmt(format_args!("mul-int/lit16 v{dst}, v{src}, {lit:#x}")),
-            Instruction::DivInt16(dst, src, lit) => f.write_fmt(format_args!("div-int/lit16 v{dst}, v{src}, {lit:#x}")),
-            Instruction::RemInt16(dst, src, lit) => f.write_fmt(format_args!("rem-int/lit16 v{dst}, v{src}, {lit:#x}")),
-            Instruction::AndInt16(dst, src, lit) => f.write_fmt(format_args!("and-int/lit16 v{dst}, v{src}, {lit:#x}")),
-            Instruction::OrInt16(dst, src, lit) => f.write_fmt(format_args!("or-int/lit16 v{dst}, v{src}, {lit:#x}")),
-            Instruction::XorInt16(dst, src, lit) => f.write_fmt(format_args!("xor-int/lit16 v{dst}, v{src}, {lit:#x}")),
-            Instruction::AddInt8(dst, src, lit) => f.write_fmt(format_args!("add-int/lit8 v{dst}, v{src}, {lit:#x}")),
-            Instruction::RsubInt8(dst, src, lit) => f.write_fmt(format_args!("rsub-int/lit8 v{dst}, v{src}, {lit:#x}")),
-            Instruction::MulInt8(dst, src, lit) => f.write_fmt(format_args!("mul-int/lit8 v{dst}, v{src}, {lit:#x}")),
-            Instruction::DivInt8(dst, src, lit) => f.write_fmt(format_args!("div-int/lit8 v{dst}, v{src}, {lit:#x}")),
-            Instruction::RemInt8(dst, src, lit) => f.write_fmt(format_args!("rem-int/lit8 v{dst}, v{src}, {lit:#x}")),
-            Instruction::AndInt8(dst, src, lit) => f.write_fmt(format_args!("and-int/lit8 v{dst}, v{src}, {lit:#x}")),
-            Instruction::OrInt8(dst, src, lit) => f.write_fmt(format_args!("or-int/lit8 v{dst}, v{src}, {lit:#x}")),
-            Instruction::XorInt8(dst, src, lit) => f.write_fmt(format_args!("xor-int/lit8 v{dst}, v{src}, {lit:#x}")),
-            Instruction::ShlInt8(dst, src, lit) => f.write_fmt(format_args!("shl-int/lit8 v{dst}, v{src}, {lit:#x}")),
-            Instruction::ShrInt8(dst, src, lit) => f.write_fmt(format_args!("shr-int/lit8 v{dst}, v{src}, {lit:#x}")),
-            Instruction::UshrInt8(dst, src, lit) => f.write_fmt(format_args!("ushr-int/lit8 v{dst}, v{src}, {lit:#x}")),
-        }
+            Instruction::ExecuteInline { inline, nargs, args } => optimized_invoke_tokens("execute-inline", args, nargs, "inline", *inline),
+            Instruction::ExecuteInlineRange { inline, args } => optimized_invoke_range_tokens("execute-inline/range", args, "inline", *inline),
+            Instruction::InvokeDirectEmpty { method, nargs, args } => invoke_tokens(args, nargs, *method, "direct-empty"),
+            Instruction::InvokeObjectInitRange { method, args } => invoke_range_tokens(args, *method, "object-init"),
+            Instruction::ReturnVoidBarrier => Tokens::new("return-void-barrier"),
+            Instruction::IPutObjectVolatile(dst, src, field) => igetters_tokens("iput-object-volatile", *dst, *src, *field),
+            Instruction::SGetObjectVolatile(dst, field) => sgetters_tokens("sget-object-volatile", *dst, *field),
+            Instruction::SPutObjectVolatile(dst, field) => sgetters_tokens("sput-object-volatile", *dst, *field),
+            Instruction::PackedSwitchPayload { first_key, targets } => targets
+                .iter()
+                .fold(
+                    Tokens::new("packed-switch-payload").literal(format!("{first_key:#x}")).open(),
+                    |tokens, target| tokens.target(*target),
+                )
+                .close(),
+            Instruction::SparseSwitchPayload { keys, targets } => keys
+                .iter()
+                .zip(targets)
+                .fold(Tokens::new("sparse-switch-payload").open(), |tokens, (key, target)| {
+                    tokens.literal(format!("{key:#x}")).punctuation(": ").target(*target)
+                })
+                .close(),
+            Instruction::FillArrayDataPayload { element_width, data } => {
+                let tokens = Tokens::new("array-data-payload").literal(element_width.to_string()).open();
+                let tokens = data.chunks((*element_width as usize).clamp(1, 8)).fold(tokens, |tokens, element| {
+                    let mut bytes = [0u8; 8];
+                    bytes[..element.len()].copy_from_slice(element);
+                    tokens.literal(format!("{:#x}", u64::from_le_bytes(bytes)))
+                });
+                tokens.close()
+            }
+        };
+        tokens.into_vec()
     }
 }
 
-fn one_regs_display(f: &mut std::fmt::Formatter<'_>, verb: &str, reg: impl Into<u16>) -> Result<(), std::fmt::Error> {
-    let reg = reg.into();
-    f.write_fmt(format_args!("{verb} v{reg}"))
+fn one_regs_tokens(verb: &str, reg: impl Into<u16>) -> Tokens {
+    Tokens::new(verb).register(reg)
 }
 
-fn two_regs_display(f: &mut std::fmt::Formatter<'_>, verb: &str, dst: impl Into<u16>, src: impl Into<u16>) -> Result<(), std::fmt::Error> {
-    let dst = dst.into();
-    let src = src.into();
-    f.write_fmt(format_args!("{verb} v{dst}, v{src}"))
+fn two_regs_tokens(verb: &str, dst: impl Into<u16>, src: impl Into<u16>) -> Tokens {
+    Tokens::new(verb).register(dst).register(src)
 }
 
-fn three_regs_display(
-    f: &mut std::fmt::Formatter<'_>,
-    verb: &str,
-    dst: impl Into<u16>,
-    src1: impl Into<u16>,
-    src2: impl Into<u16>,
-) -> Result<(), std::fmt::Error> {
-    let dst = dst.into();
-    let src1 = src1.into();
-    let src2 = src2.into();
-    f.write_fmt(format_args!("{verb} v{dst}, v{src1}, v{src2}"))
+fn three_regs_tokens(verb: &str, dst: impl Into<u16>, src1: impl Into<u16>, src2: impl Into<u16>) -> Tokens {
+    Tokens::new(verb).register(dst).register(src1).register(src2)
 }
 
-fn igetters_display(verb: &str, f: &mut std::fmt::Formatter<'_>, dst: u8, src: u8, field: u16) -> Result<(), std::fmt::Error> {
-    f.write_fmt(format_args!("{verb} v{dst}, v{src}, field@{field:x}"))
+fn igetters_tokens(verb: &str, dst: u8, src: u8, field: u16) -> Tokens {
+    Tokens::new(verb).register(dst).register(src).reference(Reference::Field(field))
 }
 
-fn sgetters_display(verb: &str, f: &mut std::fmt::Formatter<'_>, dst: u8, field: u16) -> Result<(), std::fmt::Error> {
-    f.write_fmt(format_args!("{verb} v{dst}, field@{field:x}"))
+fn sgetters_tokens(verb: &str, dst: u8, field: u16) -> Tokens {
+    Tokens::new(verb).register(dst).reference(Reference::Field(field))
 }
 
-fn invoke_display(f: &mut std::fmt::Formatter<'_>, args: &[u8; 5], nargs: &u8, method: u16, kind: &'static str) -> Result<(), std::fmt::Error> {
-    f.write_fmt(format_args!("invoke-{kind} {{"))?;
-    for (n, arg) in args[..*nargs as usize].iter().enumerate() {
-        match n {
-            0 => f.write_fmt(format_args!("v{arg}"))?,
-            _ => f.write_fmt(format_args!(", v{arg}"))?,
-        }
-    }
-    f.write_fmt(format_args!("}}, method@{method:x}"))
+fn invoke_tokens(args: &[u8; 5], nargs: &u8, method: u16, kind: &'static str) -> Tokens {
+    Tokens::new(&format!("invoke-{kind}"))
+        .registers(args[..*nargs as usize].iter().copied())
+        .reference(Reference::Method(method))
 }
 
-fn invoke_range_display(f: &mut std::fmt::Formatter<'_>, args: &[u16], method: u16, kind: &'static str) -> Result<(), std::fmt::Error> {
-    f.write_fmt(format_args!("invoke-{kind}/range {{"))?;
-    for (n, arg) in args.iter().enumerate() {
-        match n {
-            0 => f.write_fmt(format_args!("v{arg}"))?,
-            _ => f.write_fmt(format_args!(", v{arg}"))?,
-        }
-    }
-    f.write_fmt(format_args!("}}, method@{method:x}"))
+fn optimized_invoke_tokens(verb: &str, args: &[u8; 5], nargs: &u8, kind: &'static str, index: u16) -> Tokens {
+    Tokens::new(verb)
+        .registers(args[..*nargs as usize].iter().copied())
+        .literal(format!("{kind}@{index:x}"))
+}
+
+fn optimized_invoke_range_tokens(verb: &str, args: &[u16], kind: &'static str, index: u16) -> Tokens {
+    Tokens::new(verb).registers(args.iter().copied()).literal(format!("{kind}@{index:x}"))
+}
+
+fn invoke_range_tokens(args: &[u16], method: u16, kind: &'static str) -> Tokens {
+    Tokens::new(&format!("invoke-{kind}/range"))
+        .registers(args.iter().copied())
+        .reference(Reference::Method(method))
 }
 
 /// Trait for pretty printing dalvik instructions such that they include method
@@ -604,121 +892,60 @@ pub trait PrettyPrint {
     fn string(&self, index: u32) -> String;
     /// Type lookup. Should return the encoded name of the type.
     fn type_name(&self, index: u16) -> String;
+    /// Prototype lookup. Should return the method descriptor, e.g. `(I)V`.
+    ///
+    /// Defaults to the raw index, as printed by [`Display`][`std::fmt::Display`].
+    fn proto(&self, index: u16) -> String {
+        format!("proto@{index:x}")
+    }
+    /// Call site lookup. Should return a description of the call site, e.g. its
+    /// bootstrap method and arguments.
+    ///
+    /// Defaults to the raw index, as printed by [`Display`][`std::fmt::Display`].
+    fn call_site(&self, index: u16) -> String {
+        format!("call_site@{index:x}")
+    }
+    /// Method handle lookup. Should return the handle kind and its target, e.g.
+    /// `invoke-static@Lcom/example/Foo;->bar()V`.
+    ///
+    /// Defaults to the raw index, as printed by [`Display`][`std::fmt::Display`].
+    fn method_handle(&self, index: u16) -> String {
+        format!("method_handle@{index:x}")
+    }
 
-    /// Pretty print the instruction
+    /// Pretty print the instruction as [`Token`][`tokens::Token`]s
     ///
-    /// Newline is not added to the end.
-    fn print(&self, inst: &Instruction) -> String {
-        match inst {
-            Instruction::ConstString(dst, idx) => format!("const-string v{dst}, \"{}\"", self.string((*idx).into())),
-            Instruction::ConstStringJumbo(dst, idx) => format!("const-string/jumbo v{dst}, \"{}\"", self.string(*idx)),
-            Instruction::ConstClass(dst, idx) => format!("const-class v{dst}, {}", self.type_name(*idx)),
-            Instruction::NewInstance(reg, ty) => format!("new-instance v{reg}, {}", self.type_name(*ty)),
-            Instruction::NewArray(dst, size, ty) => format!("new-array v{dst}, v{size}, {}", self.type_name(*ty)),
-            Instruction::FilledNewArray { ty, nargs, args } => {
-                let ty = self.type_name(*ty);
-
-                let mut s = format!("filled-new-array {{");
-                for (n, arg) in args[..*nargs as usize].iter().enumerate() {
-                    match n {
-                        0 => s.push_str(&format!("v{arg}")),
-                        _ => s.push_str(&format!(", v{arg}")),
-                    }
-                }
-                s.push_str(&format!("}}, {ty}"));
-                s
-            }
-            Instruction::FilledNewArrayRange { ty, args } => {
-                let ty = self.type_name(*ty);
-
-                let mut s = format!("filled-new-array/range {{");
-                for (n, arg) in args.iter().enumerate() {
-                    match n {
-                        0 => s.push_str(&format!("v{arg}")),
-                        _ => s.push_str(&format!(", v{arg}")),
-                    }
-                }
-                s.push_str(&format!("}}, {ty}"));
-                s
+    /// These are the [`Instruction::tokens`], with the text of constant pool
+    /// references looked up.
+    fn tokens(&self, inst: &Instruction) -> Vec<tokens::Token> {
+        let mut tokens = inst.tokens();
+        for token in &mut tokens {
+            if let tokens::TokenKind::Reference(r) = token.kind {
+                token.text = tokens::reference_text(self, r);
             }
-            Instruction::IGet(dst, src, field) => render_isgetters(self, "iget", *dst, Some(*src), *field),
-            Instruction::IGetWide(dst, src, field) => render_isgetters(self, "iget-wide", *dst, Some(*src), *field),
-            Instruction::IGetObject(dst, src, field) => render_isgetters(self, "iget-object", *dst, Some(*src), *field),
-            Instruction::IGetBoolean(dst, src, field) => render_isgetters(self, "iget-boolean", *dst, Some(*src), *field),
-            Instruction::IGetByte(dst, src, field) => render_isgetters(self, "iget-byte", *dst, Some(*src), *field),
-            Instruction::IGetChar(dst, src, field) => render_isgetters(self, "iget-char", *dst, Some(*src), *field),
-            Instruction::IGetShort(dst, src, field) => render_isgetters(self, "iget-short", *dst, Some(*src), *field),
-            Instruction::IPut(dst, src, field) => render_isgetters(self, "iput", *dst, Some(*src), *field),
-            Instruction::IPutWide(dst, src, field) => render_isgetters(self, "iput-wide", *dst, Some(*src), *field),
-            Instruction::IPutObject(dst, src, field) => render_isgetters(self, "iput-object", *dst, Some(*src), *field),
-            Instruction::IPutBoolean(dst, src, field) => render_isgetters(self, "iput-boolean", *dst, Some(*src), *field),
-            Instruction::IPutByte(dst, src, field) => render_isgetters(self, "iput-byte", *dst, Some(*src), *field),
-            Instruction::IPutChar(dst, src, field) => render_isgetters(self, "iput-char", *dst, Some(*src), *field),
-            Instruction::IPutShort(dst, src, field) => render_isgetters(self, "iput-short", *dst, Some(*src), *field),
-            Instruction::SGet(dst, field) => render_isgetters(self, "sget", *dst, None, *field),
-            Instruction::SGetWide(dst, field) => render_isgetters(self, "sget-wide", *dst, None, *field),
-            Instruction::SGetObject(dst, field) => render_isgetters(self, "sget-object", *dst, None, *field),
-            Instruction::SGetBoolean(dst, field) => render_isgetters(self, "sget-boolean", *dst, None, *field),
-            Instruction::SGetByte(dst, field) => render_isgetters(self, "sget-byte", *dst, None, *field),
-            Instruction::SGetChar(dst, field) => render_isgetters(self, "sget-char", *dst, None, *field),
-            Instruction::SGetShort(dst, field) => render_isgetters(self, "sget-short", *dst, None, *field),
-            Instruction::SPut(dst, field) => render_isgetters(self, "sput", *dst, None, *field),
-            Instruction::SPutWide(dst, field) => render_isgetters(self, "sput-wide", *dst, None, *field),
-            Instruction::SPutObject(dst, field) => render_isgetters(self, "sput-object", *dst, None, *field),
-            Instruction::SPutBoolean(dst, field) => render_isgetters(self, "sput-boolean", *dst, None, *field),
-            Instruction::SPutByte(dst, field) => render_isgetters(self, "sput-byte", *dst, None, *field),
-            Instruction::SPutChar(dst, field) => render_isgetters(self, "sput-char", *dst, None, *field),
-            Instruction::SPutShort(dst, field) => render_isgetters(self, "sput-short", *dst, None, *field),
-            Instruction::CheckCast(reg, ty) => format!("check-cast v{reg}, {}", self.type_name(*ty)),
-            Instruction::InstanceOf(dst, src, ty) => format!("instance-of v{dst}, v{src}, {}", self.type_name(*ty)),
-            Instruction::InvokeVirtual { method, nargs, args } => render_invoke(self, *method, args, *nargs, "virtual"),
-            Instruction::InvokeSuper { method, nargs, args } => render_invoke(self, *method, args, *nargs, "super"),
-            Instruction::InvokeStatic { method, nargs, args } => render_invoke(self, *method, args, *nargs, "static"),
-            Instruction::InvokeDirect { method, nargs, args } => render_invoke(self, *method, args, *nargs, "direct"),
-            Instruction::InvokeInterface { method, nargs, args } => render_invoke(self, *method, args, *nargs, "interface"),
-            Instruction::InvokeVirtualRange { method, args } => render_invoke_range(self, *method, args, "virtual"),
-            Instruction::InvokeSuperRange { method, args } => render_invoke_range(self, *method, args, "super"),
-            Instruction::InvokeStaticRange { method, args } => render_invoke_range(self, *method, args, "static"),
-            Instruction::InvokeDirectRange { method, args } => render_invoke_range(self, *method, args, "direct"),
-            Instruction::InvokeInterfaceRange { method, args } => render_invoke_range(self, *method, args, "interface"),
-            no_lookup => no_lookup.to_string(),
         }
+        tokens
     }
-}
 
-fn render_isgetters<T: PrettyPrint + ?Sized>(lookup: &T, verb: &str, dst: u8, src: Option<u8>, field: u16) -> String {
-    let (class, name, ty) = lookup.field(field);
-    let mut s = format!("{verb} v{dst}, ");
-    if let Some(src) = src {
-        s.push_str(&format!("{src}, "));
+    /// Pretty print the instruction
+    ///
+    /// Newline is not added to the end.
+    fn print(&self, inst: &Instruction) -> String {
+        self.tokens(inst).into_iter().map(|t| t.text).collect()
     }
-    s.push_str(&format!("{class}->{name}:{ty}"));
-    s
-}
 
-fn render_invoke<T: PrettyPrint + ?Sized>(lookup: &T, method: u16, args: &[u8; 5], nargs: u8, kind: &'static str) -> String {
-    let (class, name, params, ret) = lookup.method(method);
-
-    let mut s = format!("invoke-{kind} {{");
-    for (n, arg) in args[..nargs as usize].iter().enumerate() {
-        match n {
-            0 => s.push_str(&format!("v{arg}")),
-            _ => s.push_str(&format!(", v{arg}")),
-        }
+    /// Render the instruction as a Java-like statement, as
+    /// [`Token`][`tokens::Token`]s
+    ///
+    /// See [`pseudocode`].
+    fn pseudocode_tokens(&self, inst: &Instruction) -> Vec<tokens::Token> {
+        pseudocode::tokens(self, inst)
     }
-    s.push_str(&format!("}}, {class}->{name}({params}){ret}"));
-    s
-}
-fn render_invoke_range<T: PrettyPrint + ?Sized>(lookup: &T, method: u16, args: &[u16], kind: &'static str) -> String {
-    let (class, name, params, ret) = lookup.method(method);
-
-    let mut s = format!("invoke-{kind}/range {{");
-    for (n, arg) in args.iter().enumerate() {
-        match n {
-            0 => s.push_str(&format!("v{arg}")),
-            _ => s.push_str(&format!(", v{arg}")),
-        }
+
+    /// Render the instruction as a Java-like statement, e.g. `v0 = v1 + v2`
+    ///
+    /// Newline is not added to the end.
+    fn pseudocode(&self, inst: &Instruction) -> String {
+        self.pseudocode_tokens(inst).into_iter().map(|t| t.text).collect()
     }
-    s.push_str(&format!("}}, {class}->{name}({params}){ret}"));
-    s
 }
@@ -0,0 +1,904 @@
+//! Opcode metadata
+//!
+//! Every one of the 256 opcode slots is described by an [`Opcode`], including
+//! the unassigned ones, with its mnemonic, [instruction format], the kind of
+//! constant pool index it carries and ART-style [`Flags`] describing its
+//! control flow.
+//!
+//! The runtime-private opcodes of odex and quickened bytecode have their own
+//! [`Opcode`]s outside of the slot numbering, since the slot they occupy
+//! depends on the targeted runtime (see
+//! [`DecodeOptions`][`crate::decode::DecodeOptions`]).
+//!
+//! [instruction format]: https://source.android.com/docs/core/runtime/instruction-formats
+
+use crate::Instruction;
+
+/// [Instruction format] identifier
+///
+/// The first digit is the number of u16 codepoints, the second the number of
+/// registers, and the suffix the kind of extra data encoded.
+///
+/// [Instruction format]: https://source.android.com/docs/core/runtime/instruction-formats#formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(missing_docs)]
+#[non_exhaustive]
+pub enum Format {
+    F10x,
+    F12x,
+    F11n,
+    F11x,
+    F10t,
+    F20t,
+    F20bc,
+    F22x,
+    F21t,
+    F21s,
+    F21h,
+    F21c,
+    F23x,
+    F22b,
+    F22t,
+    F22s,
+    F22c,
+    F22cs,
+    F30t,
+    F32x,
+    F31i,
+    F31t,
+    F31c,
+    F35c,
+    F35ms,
+    F35mi,
+    F3rc,
+    F3rms,
+    F3rmi,
+    F45cc,
+    F4rcc,
+    F51l,
+}
+
+impl Format {
+    /// Format ID as written in the reference, e.g. `22c`
+    pub const fn id(self) -> &'static str {
+        match self {
+            Format::F10x => "10x",
+            Format::F12x => "12x",
+            Format::F11n => "11n",
+            Format::F11x => "11x",
+            Format::F10t => "10t",
+            Format::F20t => "20t",
+            Format::F20bc => "20bc",
+            Format::F22x => "22x",
+            Format::F21t => "21t",
+            Format::F21s => "21s",
+            Format::F21h => "21h",
+            Format::F21c => "21c",
+            Format::F23x => "23x",
+            Format::F22b => "22b",
+            Format::F22t => "22t",
+            Format::F22s => "22s",
+            Format::F22c => "22c",
+            Format::F22cs => "22cs",
+            Format::F30t => "30t",
+            Format::F32x => "32x",
+            Format::F31i => "31i",
+            Format::F31t => "31t",
+            Format::F31c => "31c",
+            Format::F35c => "35c",
+            Format::F35ms => "35ms",
+            Format::F35mi => "35mi",
+            Format::F3rc => "3rc",
+            Format::F3rms => "3rms",
+            Format::F3rmi => "3rmi",
+            Format::F45cc => "45cc",
+            Format::F4rcc => "4rcc",
+            Format::F51l => "51l",
+        }
+    }
+
+    /// Length in u16 codepoints of an instruction in this format
+    #[allow(clippy::len_without_is_empty)]
+    pub const fn len(self) -> usize {
+        (self.id().as_bytes()[0] - b'0') as usize
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// Kind of constant pool index (if any) carried by an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum IndexKind {
+    /// No index
+    None,
+    /// `string_ids` index
+    String,
+    /// `type_ids` index
+    Type,
+    /// `field_ids` index
+    Field,
+    /// `method_ids` index
+    Method,
+    /// `method_ids` index, followed by a `proto_ids` index
+    MethodAndProto,
+    /// `proto_ids` index
+    Proto,
+    /// `call_site_ids` index
+    CallSite,
+    /// `method_handles` index
+    MethodHandle,
+    /// An index whose kind is given by the instruction itself, as in
+    /// `throw-verification-error`
+    Varies,
+}
+
+/// Control flow properties of an opcode, mirroring ART's instruction flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Flags(u8);
+
+impl Flags {
+    /// No flags set
+    pub const NONE: Flags = Flags(0);
+    /// Conditional or unconditional branch
+    pub const BRANCH: Flags = Flags(1 << 0);
+    /// Flow can continue to the next instruction
+    pub const CONTINUE: Flags = Flags(1 << 1);
+    /// Switch statement
+    pub const SWITCH: Flags = Flags(1 << 2);
+    /// Could cause an exception to be thrown
+    pub const THROW: Flags = Flags(1 << 3);
+    /// Returns, no additional statements
+    pub const RETURN: Flags = Flags(1 << 4);
+    /// A flavor of invoke
+    pub const INVOKE: Flags = Flags(1 << 5);
+
+    /// Whether all of the flags in `other` are set
+    pub const fn contains(self, other: Flags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Raw bits of the flags
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+}
+
+impl std::ops::BitOr for Flags {
+    type Output = Flags;
+
+    fn bitor(self, rhs: Flags) -> Flags {
+        Flags(self.0 | rhs.0)
+    }
+}
+
+macro_rules! opcodes {
+    (
+        $($code:literal => $name:ident, $mnemonic:literal, $format:ident, $index:ident, $($flag:ident)|+;)*
+        optimized:
+        $($oname:ident, $omnemonic:literal, $oformat:ident, $oindex:ident, $($oflag:ident)|+;)*
+    ) => {
+        /// Dalvik opcode
+        ///
+        /// All 256 slots are represented, unassigned opcodes included, so that
+        /// any byte converts into an `Opcode`. Optimized opcodes follow, without
+        /// a slot of their own.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub enum Opcode {
+            $(
+                #[doc = concat!("`", $mnemonic, "`")]
+                $name,
+            )*
+            $(
+                #[doc = concat!("`", $omnemonic, "`, optimized")]
+                $oname,
+            )*
+        }
+
+        impl Opcode {
+            /// Look up the opcode of an instruction's low byte
+            pub const fn from_u8(op: u8) -> Opcode {
+                match op {
+                    $($code => Opcode::$name,)*
+                }
+            }
+
+            /// Slot of the opcode, or `None` for optimized opcodes whose slot
+            /// depends on the targeted runtime
+            pub const fn to_u8(self) -> Option<u8> {
+                match self {
+                    $(Opcode::$name => Some($code),)*
+                    $(Opcode::$oname => None,)*
+                }
+            }
+
+            /// Instruction mnemonic, e.g. `invoke-virtual/range`
+            pub const fn mnemonic(self) -> &'static str {
+                match self {
+                    $(Opcode::$name => $mnemonic,)*
+                    $(Opcode::$oname => $omnemonic,)*
+                }
+            }
+
+            /// Encoding format of the instruction
+            pub const fn format(self) -> Format {
+                match self {
+                    $(Opcode::$name => Format::$format,)*
+                    $(Opcode::$oname => Format::$oformat,)*
+                }
+            }
+
+            /// Kind of constant pool index carried by the instruction
+            pub const fn index_kind(self) -> IndexKind {
+                match self {
+                    $(Opcode::$name => IndexKind::$index,)*
+                    $(Opcode::$oname => IndexKind::$oindex,)*
+                }
+            }
+
+            /// Control flow flags of the instruction
+            pub const fn flags(self) -> Flags {
+                match self {
+                    $(Opcode::$name => Flags($(Flags::$flag.0)|+),)*
+                    $(Opcode::$oname => Flags($(Flags::$oflag.0)|+),)*
+                }
+            }
+        }
+    };
+}
+
+#[rustfmt::skip]
+opcodes! {
+    0x00 => Nop, "nop", F10x, None, CONTINUE;
+    0x01 => Move, "move", F12x, None, CONTINUE;
+    0x02 => MoveFrom16, "move/from16", F22x, None, CONTINUE;
+    0x03 => Move16, "move/16", F32x, None, CONTINUE;
+    0x04 => MoveWide, "move-wide", F12x, None, CONTINUE;
+    0x05 => MoveWideFrom16, "move-wide/from16", F22x, None, CONTINUE;
+    0x06 => MoveWide16, "move-wide/16", F32x, None, CONTINUE;
+    0x07 => MoveObject, "move-object", F12x, None, CONTINUE;
+    0x08 => MoveObjectFrom16, "move-object/from16", F22x, None, CONTINUE;
+    0x09 => MoveObject16, "move-object/16", F32x, None, CONTINUE;
+    0x0a => MoveResult, "move-result", F11x, None, CONTINUE;
+    0x0b => MoveResultWide, "move-result-wide", F11x, None, CONTINUE;
+    0x0c => MoveResultObject, "move-result-object", F11x, None, CONTINUE;
+    0x0d => MoveException, "move-exception", F11x, None, CONTINUE;
+    0x0e => ReturnVoid, "return-void", F10x, None, RETURN;
+    0x0f => Return, "return", F11x, None, RETURN;
+    0x10 => ReturnWide, "return-wide", F11x, None, RETURN;
+    0x11 => ReturnObject, "return-object", F11x, None, RETURN;
+    0x12 => Const4, "const/4", F11n, None, CONTINUE;
+    0x13 => Const16, "const/16", F21s, None, CONTINUE;
+    0x14 => Const, "const", F31i, None, CONTINUE;
+    0x15 => ConstHigh16, "const/high16", F21h, None, CONTINUE;
+    0x16 => ConstWide16, "const-wide/16", F21s, None, CONTINUE;
+    0x17 => ConstWide32, "const-wide/32", F31i, None, CONTINUE;
+    0x18 => ConstWide, "const-wide", F51l, None, CONTINUE;
+    0x19 => ConstWideHigh16, "const-wide/high16", F21h, None, CONTINUE;
+    0x1a => ConstString, "const-string", F21c, String, CONTINUE | THROW;
+    0x1b => ConstStringJumbo, "const-string/jumbo", F31c, String, CONTINUE | THROW;
+    0x1c => ConstClass, "const-class", F21c, Type, CONTINUE | THROW;
+    0x1d => MonitorEnter, "monitor-enter", F11x, None, CONTINUE | THROW;
+    0x1e => MonitorExit, "monitor-exit", F11x, None, CONTINUE | THROW;
+    0x1f => CheckCast, "check-cast", F21c, Type, CONTINUE | THROW;
+    0x20 => InstanceOf, "instance-of", F22c, Type, CONTINUE | THROW;
+    0x21 => ArrayLength, "array-length", F12x, None, CONTINUE | THROW;
+    0x22 => NewInstance, "new-instance", F21c, Type, CONTINUE | THROW;
+    0x23 => NewArray, "new-array", F22c, Type, CONTINUE | THROW;
+    0x24 => FilledNewArray, "filled-new-array", F35c, Type, CONTINUE | THROW;
+    0x25 => FilledNewArrayRange, "filled-new-array/range", F3rc, Type, CONTINUE | THROW;
+    0x26 => FillArrayData, "fill-array-data", F31t, None, CONTINUE | THROW;
+    0x27 => Throw, "throw", F11x, None, THROW;
+    0x28 => Goto, "goto", F10t, None, BRANCH;
+    0x29 => Goto16, "goto/16", F20t, None, BRANCH;
+    0x2a => Goto32, "goto/32", F30t, None, BRANCH;
+    0x2b => PackedSwitch, "packed-switch", F31t, None, CONTINUE | SWITCH;
+    0x2c => SparseSwitch, "sparse-switch", F31t, None, CONTINUE | SWITCH;
+    0x2d => CmplFloat, "cmpl-float", F23x, None, CONTINUE;
+    0x2e => CmpgFloat, "cmpg-float", F23x, None, CONTINUE;
+    0x2f => CmplDouble, "cmpl-double", F23x, None, CONTINUE;
+    0x30 => CmpgDouble, "cmpg-double", F23x, None, CONTINUE;
+    0x31 => CmpLong, "cmp-long", F23x, None, CONTINUE;
+    0x32 => IfEq, "if-eq", F22t, None, CONTINUE | BRANCH;
+    0x33 => IfNe, "if-ne", F22t, None, CONTINUE | BRANCH;
+    0x34 => IfLt, "if-lt", F22t, None, CONTINUE | BRANCH;
+    0x35 => IfGe, "if-ge", F22t, None, CONTINUE | BRANCH;
+    0x36 => IfGt, "if-gt", F22t, None, CONTINUE | BRANCH;
+    0x37 => IfLe, "if-le", F22t, None, CONTINUE | BRANCH;
+    0x38 => IfEqz, "if-eqz", F21t, None, CONTINUE | BRANCH;
+    0x39 => IfNez, "if-nez", F21t, None, CONTINUE | BRANCH;
+    0x3a => IfLtz, "if-ltz", F21t, None, CONTINUE | BRANCH;
+    0x3b => IfGez, "if-gez", F21t, None, CONTINUE | BRANCH;
+    0x3c => IfGtz, "if-gtz", F21t, None, CONTINUE | BRANCH;
+    0x3d => IfLez, "if-lez", F21t, None, CONTINUE | BRANCH;
+    0x3e => Unused3E, "unused-3e", F10x, None, NONE;
+    0x3f => Unused3F, "unused-3f", F10x, None, NONE;
+    0x40 => Unused40, "unused-40", F10x, None, NONE;
+    0x41 => Unused41, "unused-41", F10x, None, NONE;
+    0x42 => Unused42, "unused-42", F10x, None, NONE;
+    0x43 => Unused43, "unused-43", F10x, None, NONE;
+    0x44 => AGet, "aget", F23x, None, CONTINUE | THROW;
+    0x45 => AGetWide, "aget-wide", F23x, None, CONTINUE | THROW;
+    0x46 => AGetObject, "aget-object", F23x, None, CONTINUE | THROW;
+    0x47 => AGetBoolean, "aget-boolean", F23x, None, CONTINUE | THROW;
+    0x48 => AGetByte, "aget-byte", F23x, None, CONTINUE | THROW;
+    0x49 => AGetChar, "aget-char", F23x, None, CONTINUE | THROW;
+    0x4a => AGetShort, "aget-short", F23x, None, CONTINUE | THROW;
+    0x4b => APut, "aput", F23x, None, CONTINUE | THROW;
+    0x4c => APutWide, "aput-wide", F23x, None, CONTINUE | THROW;
+    0x4d => APutObject, "aput-object", F23x, None, CONTINUE | THROW;
+    0x4e => APutBoolean, "aput-boolean", F23x, None, CONTINUE | THROW;
+    0x4f => APutByte, "aput-byte", F23x, None, CONTINUE | THROW;
+    0x50 => APutChar, "aput-char", F23x, None, CONTINUE | THROW;
+    0x51 => APutShort, "aput-short", F23x, None, CONTINUE | THROW;
+    0x52 => IGet, "iget", F22c, Field, CONTINUE | THROW;
+    0x53 => IGetWide, "iget-wide", F22c, Field, CONTINUE | THROW;
+    0x54 => IGetObject, "iget-object", F22c, Field, CONTINUE | THROW;
+    0x55 => IGetBoolean, "iget-boolean", F22c, Field, CONTINUE | THROW;
+    0x56 => IGetByte, "iget-byte", F22c, Field, CONTINUE | THROW;
+    0x57 => IGetChar, "iget-char", F22c, Field, CONTINUE | THROW;
+    0x58 => IGetShort, "iget-short", F22c, Field, CONTINUE | THROW;
+    0x59 => IPut, "iput", F22c, Field, CONTINUE | THROW;
+    0x5a => IPutWide, "iput-wide", F22c, Field, CONTINUE | THROW;
+    0x5b => IPutObject, "iput-object", F22c, Field, CONTINUE | THROW;
+    0x5c => IPutBoolean, "iput-boolean", F22c, Field, CONTINUE | THROW;
+    0x5d => IPutByte, "iput-byte", F22c, Field, CONTINUE | THROW;
+    0x5e => IPutChar, "iput-char", F22c, Field, CONTINUE | THROW;
+    0x5f => IPutShort, "iput-short", F22c, Field, CONTINUE | THROW;
+    0x60 => SGet, "sget", F21c, Field, CONTINUE | THROW;
+    0x61 => SGetWide, "sget-wide", F21c, Field, CONTINUE | THROW;
+    0x62 => SGetObject, "sget-object", F21c, Field, CONTINUE | THROW;
+    0x63 => SGetBoolean, "sget-boolean", F21c, Field, CONTINUE | THROW;
+    0x64 => SGetByte, "sget-byte", F21c, Field, CONTINUE | THROW;
+    0x65 => SGetChar, "sget-char", F21c, Field, CONTINUE | THROW;
+    0x66 => SGetShort, "sget-short", F21c, Field, CONTINUE | THROW;
+    0x67 => SPut, "sput", F21c, Field, CONTINUE | THROW;
+    0x68 => SPutWide, "sput-wide", F21c, Field, CONTINUE | THROW;
+    0x69 => SPutObject, "sput-object", F21c, Field, CONTINUE | THROW;
+    0x6a => SPutBoolean, "sput-boolean", F21c, Field, CONTINUE | THROW;
+    0x6b => SPutByte, "sput-byte", F21c, Field, CONTINUE | THROW;
+    0x6c => SPutChar, "sput-char", F21c, Field, CONTINUE | THROW;
+    0x6d => SPutShort, "sput-short", F21c, Field, CONTINUE | THROW;
+    0x6e => InvokeVirtual, "invoke-virtual", F35c, Method, CONTINUE | THROW | INVOKE;
+    0x6f => InvokeSuper, "invoke-super", F35c, Method, CONTINUE | THROW | INVOKE;
+    0x70 => InvokeDirect, "invoke-direct", F35c, Method, CONTINUE | THROW | INVOKE;
+    0x71 => InvokeStatic, "invoke-static", F35c, Method, CONTINUE | THROW | INVOKE;
+    0x72 => InvokeInterface, "invoke-interface", F35c, Method, CONTINUE | THROW | INVOKE;
+    0x73 => Unused73, "unused-73", F10x, None, NONE;
+    0x74 => InvokeVirtualRange, "invoke-virtual/range", F3rc, Method, CONTINUE | THROW | INVOKE;
+    0x75 => InvokeSuperRange, "invoke-super/range", F3rc, Method, CONTINUE | THROW | INVOKE;
+    0x76 => InvokeDirectRange, "invoke-direct/range", F3rc, Method, CONTINUE | THROW | INVOKE;
+    0x77 => InvokeStaticRange, "invoke-static/range", F3rc, Method, CONTINUE | THROW | INVOKE;
+    0x78 => InvokeInterfaceRange, "invoke-interface/range", F3rc, Method, CONTINUE | THROW | INVOKE;
+    0x79 => Unused79, "unused-79", F10x, None, NONE;
+    0x7a => Unused7A, "unused-7a", F10x, None, NONE;
+    0x7b => NegInt, "neg-int", F12x, None, CONTINUE;
+    0x7c => NotInt, "not-int", F12x, None, CONTINUE;
+    0x7d => NegLong, "neg-long", F12x, None, CONTINUE;
+    0x7e => NotLong, "not-long", F12x, None, CONTINUE;
+    0x7f => NegFloat, "neg-float", F12x, None, CONTINUE;
+    0x80 => NegDouble, "neg-double", F12x, None, CONTINUE;
+    0x81 => IntToLong, "int-to-long", F12x, None, CONTINUE;
+    0x82 => IntToFloat, "int-to-float", F12x, None, CONTINUE;
+    0x83 => IntToDouble, "int-to-double", F12x, None, CONTINUE;
+    0x84 => LongToInt, "long-to-int", F12x, None, CONTINUE;
+    0x85 => LongToFloat, "long-to-float", F12x, None, CONTINUE;
+    0x86 => LongToDouble, "long-to-double", F12x, None, CONTINUE;
+    0x87 => FloatToInt, "float-to-int", F12x, None, CONTINUE;
+    0x88 => FloatToLong, "float-to-long", F12x, None, CONTINUE;
+    0x89 => FloatToDouble, "float-to-double", F12x, None, CONTINUE;
+    0x8a => DoubleToInt, "double-to-int", F12x, None, CONTINUE;
+    0x8b => DoubleToLong, "double-to-long", F12x, None, CONTINUE;
+    0x8c => DoubleToFloat, "double-to-float", F12x, None, CONTINUE;
+    0x8d => IntTobyte, "int-to-byte", F12x, None, CONTINUE;
+    0x8e => IntTochar, "int-to-char", F12x, None, CONTINUE;
+    0x8f => IntToshort, "int-to-short", F12x, None, CONTINUE;
+    0x90 => AddInt, "add-int", F23x, None, CONTINUE;
+    0x91 => SubInt, "sub-int", F23x, None, CONTINUE;
+    0x92 => MulInt, "mul-int", F23x, None, CONTINUE;
+    0x93 => DivInt, "div-int", F23x, None, CONTINUE | THROW;
+    0x94 => RemInt, "rem-int", F23x, None, CONTINUE | THROW;
+    0x95 => AndInt, "and-int", F23x, None, CONTINUE;
+    0x96 => OrInt, "or-int", F23x, None, CONTINUE;
+    0x97 => XorInt, "xor-int", F23x, None, CONTINUE;
+    0x98 => ShlInt, "shl-int", F23x, None, CONTINUE;
+    0x99 => ShrInt, "shr-int", F23x, None, CONTINUE;
+    0x9a => UshrInt, "ushr-int", F23x, None, CONTINUE;
+    0x9b => AddLong, "add-long", F23x, None, CONTINUE;
+    0x9c => SubLong, "sub-long", F23x, None, CONTINUE;
+    0x9d => MulLong, "mul-long", F23x, None, CONTINUE;
+    0x9e => DivLong, "div-long", F23x, None, CONTINUE | THROW;
+    0x9f => RemLong, "rem-long", F23x, None, CONTINUE | THROW;
+    0xa0 => AndLong, "and-long", F23x, None, CONTINUE;
+    0xa1 => OrLong, "or-long", F23x, None, CONTINUE;
+    0xa2 => XorLong, "xor-long", F23x, None, CONTINUE;
+    0xa3 => ShlLong, "shl-long", F23x, None, CONTINUE;
+    0xa4 => ShrLong, "shr-long", F23x, None, CONTINUE;
+    0xa5 => UshrLong, "ushr-long", F23x, None, CONTINUE;
+    0xa6 => AddFloat, "add-float", F23x, None, CONTINUE;
+    0xa7 => SubFloat, "sub-float", F23x, None, CONTINUE;
+    0xa8 => MulFloat, "mul-float", F23x, None, CONTINUE;
+    0xa9 => DivFloat, "div-float", F23x, None, CONTINUE;
+    0xaa => RemFloat, "rem-float", F23x, None, CONTINUE;
+    0xab => AddDouble, "add-double", F23x, None, CONTINUE;
+    0xac => SubDouble, "sub-double", F23x, None, CONTINUE;
+    0xad => MulDouble, "mul-double", F23x, None, CONTINUE;
+    0xae => DivDouble, "div-double", F23x, None, CONTINUE;
+    0xaf => RemDouble, "rem-double", F23x, None, CONTINUE;
+    0xb0 => AddInt2, "add-int/2addr", F12x, None, CONTINUE;
+    0xb1 => SubInt2, "sub-int/2addr", F12x, None, CONTINUE;
+    0xb2 => MulInt2, "mul-int/2addr", F12x, None, CONTINUE;
+    0xb3 => DivInt2, "div-int/2addr", F12x, None, CONTINUE | THROW;
+    0xb4 => RemInt2, "rem-int/2addr", F12x, None, CONTINUE | THROW;
+    0xb5 => AndInt2, "and-int/2addr", F12x, None, CONTINUE;
+    0xb6 => OrInt2, "or-int/2addr", F12x, None, CONTINUE;
+    0xb7 => XorInt2, "xor-int/2addr", F12x, None, CONTINUE;
+    0xb8 => ShlInt2, "shl-int/2addr", F12x, None, CONTINUE;
+    0xb9 => ShrInt2, "shr-int/2addr", F12x, None, CONTINUE;
+    0xba => UShrInt2, "ushr-int/2addr", F12x, None, CONTINUE;
+    0xbb => AddLong2, "add-long/2addr", F12x, None, CONTINUE;
+    0xbc => SubLong2, "sub-long/2addr", F12x, None, CONTINUE;
+    0xbd => MulLong2, "mul-long/2addr", F12x, None, CONTINUE;
+    0xbe => DivLong2, "div-long/2addr", F12x, None, CONTINUE | THROW;
+    0xbf => RemLong2, "rem-long/2addr", F12x, None, CONTINUE | THROW;
+    0xc0 => AndLong2, "and-long/2addr", F12x, None, CONTINUE;
+    0xc1 => OrLong2, "or-long/2addr", F12x, None, CONTINUE;
+    0xc2 => XorLong2, "xor-long/2addr", F12x, None, CONTINUE;
+    0xc3 => ShlLong2, "shl-long/2addr", F12x, None, CONTINUE;
+    0xc4 => ShrLong2, "shr-long/2addr", F12x, None, CONTINUE;
+    0xc5 => UShrLong2, "ushr-long/2addr", F12x, None, CONTINUE;
+    0xc6 => AddFloat2, "add-float/2addr", F12x, None, CONTINUE;
+    0xc7 => SubFloat2, "sub-float/2addr", F12x, None, CONTINUE;
+    0xc8 => MulFloat2, "mul-float/2addr", F12x, None, CONTINUE;
+    0xc9 => DivFloat2, "div-float/2addr", F12x, None, CONTINUE;
+    0xca => RemFloat2, "rem-float/2addr", F12x, None, CONTINUE;
+    0xcb => AddDouble2, "add-double/2addr", F12x, None, CONTINUE;
+    0xcc => SubDouble2, "sub-double/2addr", F12x, None, CONTINUE;
+    0xcd => MulDouble2, "mul-double/2addr", F12x, None, CONTINUE;
+    0xce => DivDouble2, "div-double/2addr", F12x, None, CONTINUE;
+    0xcf => RemDouble2, "rem-double/2addr", F12x, None, CONTINUE;
+    0xd0 => AddInt16, "add-int/lit16", F22s, None, CONTINUE;
+    0xd1 => RsubInt16, "rsub-int/lit16", F22s, None, CONTINUE;
+    0xd2 => MulInt16, "mul-int/lit16", F22s, None, CONTINUE;
+    0xd3 => DivInt16, "div-int/lit16", F22s, None, CONTINUE | THROW;
+    0xd4 => RemInt16, "rem-int/lit16", F22s, None, CONTINUE | THROW;
+    0xd5 => AndInt16, "and-int/lit16", F22s, None, CONTINUE;
+    0xd6 => OrInt16, "or-int/lit16", F22s, None, CONTINUE;
+    0xd7 => XorInt16, "xor-int/lit16", F22s, None, CONTINUE;
+    0xd8 => AddInt8, "add-int/lit8", F22b, None, CONTINUE;
+    0xd9 => RsubInt8, "rsub-int/lit8", F22b, None, CONTINUE;
+    0xda => MulInt8, "mul-int/lit8", F22b, None, CONTINUE;
+    0xdb => DivInt8, "div-int/lit8", F22b, None, CONTINUE | THROW;
+    0xdc => RemInt8, "rem-int/lit8", F22b, None, CONTINUE | THROW;
+    0xdd => AndInt8, "and-int/lit8", F22b, None, CONTINUE;
+    0xde => OrInt8, "or-int/lit8", F22b, None, CONTINUE;
+    0xdf => XorInt8, "xor-int/lit8", F22b, None, CONTINUE;
+    0xe0 => ShlInt8, "shl-int/lit8", F22b, None, CONTINUE;
+    0xe1 => ShrInt8, "shr-int/lit8", F22b, None, CONTINUE;
+    0xe2 => UshrInt8, "ushr-int/lit8", F22b, None, CONTINUE;
+    0xe3 => UnusedE3, "unused-e3", F10x, None, NONE;
+    0xe4 => UnusedE4, "unused-e4", F10x, None, NONE;
+    0xe5 => UnusedE5, "unused-e5", F10x, None, NONE;
+    0xe6 => UnusedE6, "unused-e6", F10x, None, NONE;
+    0xe7 => UnusedE7, "unused-e7", F10x, None, NONE;
+    0xe8 => UnusedE8, "unused-e8", F10x, None, NONE;
+    0xe9 => UnusedE9, "unused-e9", F10x, None, NONE;
+    0xea => UnusedEA, "unused-ea", F10x, None, NONE;
+    0xeb => UnusedEB, "unused-eb", F10x, None, NONE;
+    0xec => UnusedEC, "unused-ec", F10x, None, NONE;
+    0xed => UnusedED, "unused-ed", F10x, None, NONE;
+    0xee => UnusedEE, "unused-ee", F10x, None, NONE;
+    0xef => UnusedEF, "unused-ef", F10x, None, NONE;
+    0xf0 => UnusedF0, "unused-f0", F10x, None, NONE;
+    0xf1 => UnusedF1, "unused-f1", F10x, None, NONE;
+    0xf2 => UnusedF2, "unused-f2", F10x, None, NONE;
+    0xf3 => UnusedF3, "unused-f3", F10x, None, NONE;
+    0xf4 => UnusedF4, "unused-f4", F10x, None, NONE;
+    0xf5 => UnusedF5, "unused-f5", F10x, None, NONE;
+    0xf6 => UnusedF6, "unused-f6", F10x, None, NONE;
+    0xf7 => UnusedF7, "unused-f7", F10x, None, NONE;
+    0xf8 => UnusedF8, "unused-f8", F10x, None, NONE;
+    0xf9 => UnusedF9, "unused-f9", F10x, None, NONE;
+    0xfa => InvokePolymorphic, "invoke-polymorphic", F45cc, MethodAndProto, CONTINUE | THROW | INVOKE;
+    0xfb => InvokePolymorphicRange, "invoke-polymorphic/range", F4rcc, MethodAndProto, CONTINUE | THROW | INVOKE;
+    0xfc => InvokeCustom, "invoke-custom", F35c, CallSite, CONTINUE | THROW | INVOKE;
+    0xfd => InvokeCustomRange, "invoke-custom/range", F3rc, CallSite, CONTINUE | THROW | INVOKE;
+    0xfe => ConstMethodHandle, "const-method-handle", F21c, MethodHandle, CONTINUE | THROW;
+    0xff => ConstMethodType, "const-method-type", F21c, Proto, CONTINUE | THROW;
+    optimized:
+    ReturnVoidNoBarrier, "return-void-no-barrier", F10x, None, RETURN;
+    IGetQuick, "iget-quick", F22cs, None, CONTINUE | THROW;
+    IGetWideQuick, "iget-wide-quick", F22cs, None, CONTINUE | THROW;
+    IGetObjectQuick, "iget-object-quick", F22cs, None, CONTINUE | THROW;
+    IPutQuick, "iput-quick", F22cs, None, CONTINUE | THROW;
+    IPutWideQuick, "iput-wide-quick", F22cs, None, CONTINUE | THROW;
+    IPutObjectQuick, "iput-object-quick", F22cs, None, CONTINUE | THROW;
+    InvokeVirtualQuick, "invoke-virtual-quick", F35ms, None, CONTINUE | THROW | INVOKE;
+    InvokeVirtualQuickRange, "invoke-virtual-quick/range", F3rms, None, CONTINUE | THROW | INVOKE;
+    IPutBooleanQuick, "iput-boolean-quick", F22cs, None, CONTINUE | THROW;
+    IPutByteQuick, "iput-byte-quick", F22cs, None, CONTINUE | THROW;
+    IPutCharQuick, "iput-char-quick", F22cs, None, CONTINUE | THROW;
+    IPutShortQuick, "iput-short-quick", F22cs, None, CONTINUE | THROW;
+    IGetBooleanQuick, "iget-boolean-quick", F22cs, None, CONTINUE | THROW;
+    IGetByteQuick, "iget-byte-quick", F22cs, None, CONTINUE | THROW;
+    IGetCharQuick, "iget-char-quick", F22cs, None, CONTINUE | THROW;
+    IGetShortQuick, "iget-short-quick", F22cs, None, CONTINUE | THROW;
+    InvokeSuperQuick, "invoke-super-quick", F35ms, None, CONTINUE | THROW | INVOKE;
+    InvokeSuperQuickRange, "invoke-super-quick/range", F3rms, None, CONTINUE | THROW | INVOKE;
+    IGetVolatile, "iget-volatile", F22c, Field, CONTINUE | THROW;
+    IPutVolatile, "iput-volatile", F22c, Field, CONTINUE | THROW;
+    SGetVolatile, "sget-volatile", F21c, Field, CONTINUE | THROW;
+    SPutVolatile, "sput-volatile", F21c, Field, CONTINUE | THROW;
+    IGetObjectVolatile, "iget-object-volatile", F22c, Field, CONTINUE | THROW;
+    IGetWideVolatile, "iget-wide-volatile", F22c, Field, CONTINUE | THROW;
+    IPutWideVolatile, "iput-wide-volatile", F22c, Field, CONTINUE | THROW;
+    SGetWideVolatile, "sget-wide-volatile", F21c, Field, CONTINUE | THROW;
+    SPutWideVolatile, "sput-wide-volatile", F21c, Field, CONTINUE | THROW;
+    Breakpoint, "breakpoint", F10x, None, CONTINUE;
+    ThrowVerificationError, "throw-verification-error", F20bc, Varies, THROW;
+    ExecuteInline, "execute-inline", F35mi, None, CONTINUE | THROW;
+    ExecuteInlineRange, "execute-inline/range", F3rmi, None, CONTINUE | THROW;
+    InvokeDirectEmpty, "invoke-direct-empty", F35c, Method, CONTINUE | THROW | INVOKE;
+    InvokeObjectInitRange, "invoke-object-init/range", F3rc, Method, CONTINUE | THROW | INVOKE;
+    ReturnVoidBarrier, "return-void-barrier", F10x, None, RETURN;
+    IPutObjectVolatile, "iput-object-volatile", F22c, Field, CONTINUE | THROW;
+    SGetObjectVolatile, "sget-object-volatile", F21c, Field, CONTINUE | THROW;
+    SPutObjectVolatile, "sput-object-volatile", F21c, Field, CONTINUE | THROW;
+}
+
+impl Opcode {
+    /// Look up an assigned, non-optimized opcode by its mnemonic, e.g.
+    /// `invoke-virtual/range`
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Opcode> {
+        (0..=0xff).map(Opcode::from_u8).find(|op| !op.is_unused() && op.mnemonic() == mnemonic)
+    }
+
+    /// Whether the opcode slot is unassigned
+    pub const fn is_unused(self) -> bool {
+        matches!(self.to_u8(), Some(0x3e..=0x43 | 0x73 | 0x79..=0x7a | 0xe3..=0xf9))
+    }
+
+    /// Whether the opcode is private to a runtime, only found in odex and
+    /// quickened bytecode
+    pub const fn is_optimized(self) -> bool {
+        self.to_u8().is_none()
+    }
+
+    /// Earliest dex format version the opcode is valid in, e.g. `38` for the
+    /// `dex\n038\0` magic
+    ///
+    /// A dex file using opcodes newer than its own version is malformed: ART
+    /// refuses to verify it, but hand-crafted samples may still contain them.
+    pub const fn dex_version(self) -> u32 {
+        match self.to_u8() {
+            Some(0xfa..=0xfd) => 38,
+            Some(0xfe..=0xff) => 39,
+            _ => 35,
+        }
+    }
+
+    /// Shorthand for `flags().contains(Flags::THROW)`
+    pub const fn can_throw(self) -> bool {
+        self.flags().contains(Flags::THROW)
+    }
+
+    /// Shorthand for `flags().contains(Flags::CONTINUE)`
+    pub const fn can_continue(self) -> bool {
+        self.flags().contains(Flags::CONTINUE)
+    }
+
+    /// Shorthand for `flags().contains(Flags::BRANCH)`
+    pub const fn is_branch(self) -> bool {
+        self.flags().contains(Flags::BRANCH)
+    }
+
+    /// Shorthand for `flags().contains(Flags::SWITCH)`
+    pub const fn is_switch(self) -> bool {
+        self.flags().contains(Flags::SWITCH)
+    }
+
+    /// Shorthand for `flags().contains(Flags::INVOKE)`
+    pub const fn is_invoke(self) -> bool {
+        self.flags().contains(Flags::INVOKE)
+    }
+
+    /// Shorthand for `flags().contains(Flags::RETURN)`
+    pub const fn is_return(self) -> bool {
+        self.flags().contains(Flags::RETURN)
+    }
+}
+
+impl From<u8> for Opcode {
+    fn from(op: u8) -> Self {
+        Opcode::from_u8(op)
+    }
+}
+
+impl std::fmt::Display for Opcode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.mnemonic())
+    }
+}
+
+impl Instruction {
+    /// Get the [`Opcode`] of the instruction
+    ///
+    /// The inline payload tables share the `nop` opcode, distinguished only by
+    /// their high byte.
+    ///
+    /// Optimized instructions get their own optimized opcode, see
+    /// [`Opcode::is_optimized`].
+    pub fn opcode(&self) -> Opcode {
+        match self {
+            Instruction::Nop => Opcode::Nop,
+            Instruction::Move(_, _) => Opcode::Move,
+            Instruction::MoveFrom16(_, _) => Opcode::MoveFrom16,
+            Instruction::Move16(_, _) => Opcode::Move16,
+            Instruction::MoveWide(_, _) => Opcode::MoveWide,
+            Instruction::MoveWideFrom16(_, _) => Opcode::MoveWideFrom16,
+            Instruction::MoveWide16(_, _) => Opcode::MoveWide16,
+            Instruction::MoveObject(_, _) => Opcode::MoveObject,
+            Instruction::MoveObjectFrom16(_, _) => Opcode::MoveObjectFrom16,
+            Instruction::MoveObject16(_, _) => Opcode::MoveObject16,
+            Instruction::MoveResult(_) => Opcode::MoveResult,
+            Instruction::MoveResultWide(_) => Opcode::MoveResultWide,
+            Instruction::MoveResultObject(_) => Opcode::MoveResultObject,
+            Instruction::MoveException(_) => Opcode::MoveException,
+            Instruction::ReturnVoid => Opcode::ReturnVoid,
+            Instruction::Return(_) => Opcode::Return,
+            Instruction::ReturnWide(_) => Opcode::ReturnWide,
+            Instruction::ReturnObject(_) => Opcode::ReturnObject,
+            Instruction::Const4(_, _) => Opcode::Const4,
+            Instruction::Const16(_, _) => Opcode::Const16,
+            Instruction::Const(_, _) => Opcode::Const,
+            Instruction::ConstHigh16(_, _) => Opcode::ConstHigh16,
+            Instruction::ConstWide16(_, _) => Opcode::ConstWide16,
+            Instruction::ConstWide32(_, _) => Opcode::ConstWide32,
+            Instruction::ConstWide(_, _) => Opcode::ConstWide,
+            Instruction::ConstWideHigh16(_, _) => Opcode::ConstWideHigh16,
+            Instruction::ConstString(_, _) => Opcode::ConstString,
+            Instruction::ConstStringJumbo(_, _) => Opcode::ConstStringJumbo,
+            Instruction::ConstClass(_, _) => Opcode::ConstClass,
+            Instruction::MonitorEnter(_) => Opcode::MonitorEnter,
+            Instruction::MonitorExit(_) => Opcode::MonitorExit,
+            Instruction::CheckCast(_, _) => Opcode::CheckCast,
+            Instruction::InstanceOf(_, _, _) => Opcode::InstanceOf,
+            Instruction::ArrayLength(_, _) => Opcode::ArrayLength,
+            Instruction::NewInstance(_, _) => Opcode::NewInstance,
+            Instruction::NewArray(_, _, _) => Opcode::NewArray,
+            Instruction::FilledNewArray { .. } => Opcode::FilledNewArray,
+            Instruction::FilledNewArrayRange { .. } => Opcode::FilledNewArrayRange,
+            Instruction::FillArrayData(_, _) => Opcode::FillArrayData,
+            Instruction::Throw(_) => Opcode::Throw,
+            Instruction::Goto(_) => Opcode::Goto,
+            Instruction::Goto16(_) => Opcode::Goto16,
+            Instruction::Goto32(_) => Opcode::Goto32,
+            Instruction::PackedSwitch(_, _) => Opcode::PackedSwitch,
+            Instruction::SparseSwitch(_, _) => Opcode::SparseSwitch,
+            Instruction::CmplFloat(_, _, _) => Opcode::CmplFloat,
+            Instruction::CmpgFloat(_, _, _) => Opcode::CmpgFloat,
+            Instruction::CmplDouble(_, _, _) => Opcode::CmplDouble,
+            Instruction::CmpgDouble(_, _, _) => Opcode::CmpgDouble,
+            Instruction::CmpLong(_, _, _) => Opcode::CmpLong,
+            Instruction::IfEq(_, _, _) => Opcode::IfEq,
+            Instruction::IfNe(_, _, _) => Opcode::IfNe,
+            Instruction::IfLt(_, _, _) => Opcode::IfLt,
+            Instruction::IfGe(_, _, _) => Opcode::IfGe,
+            Instruction::IfGt(_, _, _) => Opcode::IfGt,
+            Instruction::IfLe(_, _, _) => Opcode::IfLe,
+            Instruction::IfEqz(_, _) => Opcode::IfEqz,
+            Instruction::IfNez(_, _) => Opcode::IfNez,
+            Instruction::IfLtz(_, _) => Opcode::IfLtz,
+            Instruction::IfGez(_, _) => Opcode::IfGez,
+            Instruction::IfGtz(_, _) => Opcode::IfGtz,
+            Instruction::IfLez(_, _) => Opcode::IfLez,
+            Instruction::AGet(_, _, _) => Opcode::AGet,
+            Instruction::AGetWide(_, _, _) => Opcode::AGetWide,
+            Instruction::AGetObject(_, _, _) => Opcode::AGetObject,
+            Instruction::AGetBoolean(_, _, _) => Opcode::AGetBoolean,
+            Instruction::AGetByte(_, _, _) => Opcode::AGetByte,
+            Instruction::AGetChar(_, _, _) => Opcode::AGetChar,
+            Instruction::AGetShort(_, _, _) => Opcode::AGetShort,
+            Instruction::APut(_, _, _) => Opcode::APut,
+            Instruction::APutWide(_, _, _) => Opcode::APutWide,
+            Instruction::APutObject(_, _, _) => Opcode::APutObject,
+            Instruction::APutBoolean(_, _, _) => Opcode::APutBoolean,
+            Instruction::APutByte(_, _, _) => Opcode::APutByte,
+            Instruction::APutChar(_, _, _) => Opcode::APutChar,
+            Instruction::APutShort(_, _, _) => Opcode::APutShort,
+            Instruction::IGet(_, _, _) => Opcode::IGet,
+            Instruction::IGetWide(_, _, _) => Opcode::IGetWide,
+            Instruction::IGetObject(_, _, _) => Opcode::IGetObject,
+            Instruction::IGetBoolean(_, _, _) => Opcode::IGetBoolean,
+            Instruction::IGetByte(_, _, _) => Opcode::IGetByte,
+            Instruction::IGetChar(_, _, _) => Opcode::IGetChar,
+            Instruction::IGetShort(_, _, _) => Opcode::IGetShort,
+            Instruction::IPut(_, _, _) => Opcode::IPut,
+            Instruction::IPutWide(_, _, _) => Opcode::IPutWide,
+            Instruction::IPutObject(_, _, _) => Opcode::IPutObject,
+            Instruction::IPutBoolean(_, _, _) => Opcode::IPutBoolean,
+            Instruction::IPutByte(_, _, _) => Opcode::IPutByte,
+            Instruction::IPutChar(_, _, _) => Opcode::IPutChar,
+            Instruction::IPutShort(_, _, _) => Opcode::IPutShort,
+            Instruction::SGet(_, _) => Opcode::SGet,
+            Instruction::SGetWide(_, _) => Opcode::SGetWide,
+            Instruction::SGetObject(_, _) => Opcode::SGetObject,
+            Instruction::SGetBoolean(_, _) => Opcode::SGetBoolean,
+            Instruction::SGetByte(_, _) => Opcode::SGetByte,
+            Instruction::SGetChar(_, _) => Opcode::SGetChar,
+            Instruction::SGetShort(_, _) => Opcode::SGetShort,
+            Instruction::SPut(_, _) => Opcode::SPut,
+            Instruction::SPutWide(_, _) => Opcode::SPutWide,
+            Instruction::SPutObject(_, _) => Opcode::SPutObject,
+            Instruction::SPutBoolean(_, _) => Opcode::SPutBoolean,
+            Instruction::SPutByte(_, _) => Opcode::SPutByte,
+            Instruction::SPutChar(_, _) => Opcode::SPutChar,
+            Instruction::SPutShort(_, _) => Opcode::SPutShort,
+            Instruction::InvokeVirtual { .. } => Opcode::InvokeVirtual,
+            Instruction::InvokeSuper { .. } => Opcode::InvokeSuper,
+            Instruction::InvokeDirect { .. } => Opcode::InvokeDirect,
+            Instruction::InvokeStatic { .. } => Opcode::InvokeStatic,
+            Instruction::InvokeInterface { .. } => Opcode::InvokeInterface,
+            Instruction::InvokeVirtualRange { .. } => Opcode::InvokeVirtualRange,
+            Instruction::InvokeSuperRange { .. } => Opcode::InvokeSuperRange,
+            Instruction::InvokeDirectRange { .. } => Opcode::InvokeDirectRange,
+            Instruction::InvokeStaticRange { .. } => Opcode::InvokeStaticRange,
+            Instruction::InvokeInterfaceRange { .. } => Opcode::InvokeInterfaceRange,
+            Instruction::NegInt(_, _) => Opcode::NegInt,
+            Instruction::NotInt(_, _) => Opcode::NotInt,
+            Instruction::NegLong(_, _) => Opcode::NegLong,
+            Instruction::NotLong(_, _) => Opcode::NotLong,
+            Instruction::NegFloat(_, _) => Opcode::NegFloat,
+            Instruction::NegDouble(_, _) => Opcode::NegDouble,
+            Instruction::IntToLong(_, _) => Opcode::IntToLong,
+            Instruction::IntToFloat(_, _) => Opcode::IntToFloat,
+            Instruction::IntToDouble(_, _) => Opcode::IntToDouble,
+            Instruction::LongToInt(_, _) => Opcode::LongToInt,
+            Instruction::LongToFloat(_, _) => Opcode::LongToFloat,
+            Instruction::LongToDouble(_, _) => Opcode::LongToDouble,
+            Instruction::FloatToInt(_, _) => Opcode::FloatToInt,
+            Instruction::FloatToLong(_, _) => Opcode::FloatToLong,
+            Instruction::FloatToDouble(_, _) => Opcode::FloatToDouble,
+            Instruction::DoubleToInt(_, _) => Opcode::DoubleToInt,
+            Instruction::DoubleToLong(_, _) => Opcode::DoubleToLong,
+            Instruction::DoubleToFloat(_, _) => Opcode::DoubleToFloat,
+            Instruction::IntTobyte(_, _) => Opcode::IntTobyte,
+            Instruction::IntTochar(_, _) => Opcode::IntTochar,
+            Instruction::IntToshort(_, _) => Opcode::IntToshort,
+            Instruction::AddInt(_, _, _) => Opcode::AddInt,
+            Instruction::SubInt(_, _, _) => Opcode::SubInt,
+            Instruction::MulInt(_, _, _) => Opcode::MulInt,
+            Instruction::DivInt(_, _, _) => Opcode::DivInt,
+            Instruction::RemInt(_, _, _) => Opcode::RemInt,
+            Instruction::AndInt(_, _, _) => Opcode::AndInt,
+            Instruction::OrInt(_, _, _) => Opcode::OrInt,
+            Instruction::XorInt(_, _, _) => Opcode::XorInt,
+            Instruction::ShlInt(_, _, _) => Opcode::ShlInt,
+            Instruction::ShrInt(_, _, _) => Opcode::ShrInt,
+            Instruction::UshrInt(_, _, _) => Opcode::UshrInt,
+            Instruction::AddLong(_, _, _) => Opcode::AddLong,
+            Instruction::SubLong(_, _, _) => Opcode::SubLong,
+            Instruction::MulLong(_, _, _) => Opcode::MulLong,
+            Instruction::DivLong(_, _, _) => Opcode::DivLong,
+            Instruction::RemLong(_, _, _) => Opcode::RemLong,
+            Instruction::AndLong(_, _, _) => Opcode::AndLong,
+            Instruction::OrLong(_, _, _) => Opcode::OrLong,
+            Instruction::XorLong(_, _, _) => Opcode::XorLong,
+            Instruction::ShlLong(_, _, _) => Opcode::ShlLong,
+            Instruction::ShrLong(_, _, _) => Opcode::ShrLong,
+            Instruction::UshrLong(_, _, _) => Opcode::UshrLong,
+            Instruction::AddFloat(_, _, _) => Opcode::AddFloat,
+            Instruction::SubFloat(_, _, _) => Opcode::SubFloat,
+            Instruction::MulFloat(_, _, _) => Opcode::MulFloat,
+            Instruction::DivFloat(_, _, _) => Opcode::DivFloat,
+            Instruction::RemFloat(_, _, _) => Opcode::RemFloat,
+            Instruction::AddDouble(_, _, _) => Opcode::AddDouble,
+            Instruction::SubDouble(_, _, _) => Opcode::SubDouble,
+            Instruction::MulDouble(_, _, _) => Opcode::MulDouble,
+            Instruction::DivDouble(_, _, _) => Opcode::DivDouble,
+            Instruction::RemDouble(_, _, _) => Opcode::RemDouble,
+            Instruction::AddInt2(_, _) => Opcode::AddInt2,
+            Instruction::SubInt2(_, _) => Opcode::SubInt2,
+            Instruction::MulInt2(_, _) => Opcode::MulInt2,
+            Instruction::DivInt2(_, _) => Opcode::DivInt2,
+            Instruction::RemInt2(_, _) => Opcode::RemInt2,
+            Instruction::AndInt2(_, _) => Opcode::AndInt2,
+            Instruction::OrInt2(_, _) => Opcode::OrInt2,
+            Instruction::XorInt2(_, _) => Opcode::XorInt2,
+            Instruction::ShlInt2(_, _) => Opcode::ShlInt2,
+            Instruction::ShrInt2(_, _) => Opcode::ShrInt2,
+            Instruction::UShrInt2(_, _) => Opcode::UShrInt2,
+            Instruction::AddLong2(_, _) => Opcode::AddLong2,
+            Instruction::SubLong2(_, _) => Opcode::SubLong2,
+            Instruction::MulLong2(_, _) => Opcode::MulLong2,
+            Instruction::DivLong2(_, _) => Opcode::DivLong2,
+            Instruction::RemLong2(_, _) => Opcode::RemLong2,
+            Instruction::AndLong2(_, _) => Opcode::AndLong2,
+            Instruction::OrLong2(_, _) => Opcode::OrLong2,
+            Instruction::XorLong2(_, _) => Opcode::XorLong2,
+            Instruction::ShlLong2(_, _) => Opcode::ShlLong2,
+            Instruction::ShrLong2(_, _) => Opcode::ShrLong2,
+            Instruction::UShrLong2(_, _) => Opcode::UShrLong2,
+            Instruction::AddFloat2(_, _) => Opcode::AddFloat2,
+            Instruction::SubFloat2(_, _) => Opcode::SubFloat2,
+            Instruction::MulFloat2(_, _) => Opcode::MulFloat2,
+            Instruction::DivFloat2(_, _) => Opcode::DivFloat2,
+            Instruction::RemFloat2(_, _) => Opcode::RemFloat2,
+            Instruction::AddDouble2(_, _) => Opcode::AddDouble2,
+            Instruction::SubDouble2(_, _) => Opcode::SubDouble2,
+            Instruction::MulDouble2(_, _) => Opcode::MulDouble2,
+            Instruction::DivDouble2(_, _) => Opcode::DivDouble2,
+            Instruction::RemDouble2(_, _) => Opcode::RemDouble2,
+            Instruction::AddInt16(_, _, _) => Opcode::AddInt16,
+            Instruction::RsubInt16(_, _, _) => Opcode::RsubInt16,
+            Instruction::MulInt16(_, _, _) => Opcode::MulInt16,
+            Instruction::DivInt16(_, _, _) => Opcode::DivInt16,
+            Instruction::RemInt16(_, _, _) => Opcode::RemInt16,
+            Instruction::AndInt16(_, _, _) => Opcode::AndInt16,
+            Instruction::OrInt16(_, _, _) => Opcode::OrInt16,
+            Instruction::XorInt16(_, _, _) => Opcode::XorInt16,
+            Instruction::AddInt8(_, _, _) => Opcode::AddInt8,
+            Instruction::RsubInt8(_, _, _) => Opcode::RsubInt8,
+            Instruction::MulInt8(_, _, _) => Opcode::MulInt8,
+            Instruction::DivInt8(_, _, _) => Opcode::DivInt8,
+            Instruction::RemInt8(_, _, _) => Opcode::RemInt8,
+            Instruction::AndInt8(_, _, _) => Opcode::AndInt8,
+            Instruction::OrInt8(_, _, _) => Opcode::OrInt8,
+            Instruction::XorInt8(_, _, _) => Opcode::XorInt8,
+            Instruction::ShlInt8(_, _, _) => Opcode::ShlInt8,
+            Instruction::ShrInt8(_, _, _) => Opcode::ShrInt8,
+            Instruction::UshrInt8(_, _, _) => Opcode::UshrInt8,
+            Instruction::InvokePolymorphic { .. } => Opcode::InvokePolymorphic,
+            Instruction::InvokePolymorphicRange { .. } => Opcode::InvokePolymorphicRange,
+            Instruction::InvokeCustom { .. } => Opcode::InvokeCustom,
+            Instruction::InvokeCustomRange { .. } => Opcode::InvokeCustomRange,
+            Instruction::ConstMethodHandle(_, _) => Opcode::ConstMethodHandle,
+            Instruction::ConstMethodType(_, _) => Opcode::ConstMethodType,
+            Instruction::ReturnVoidNoBarrier => Opcode::ReturnVoidNoBarrier,
+            Instruction::IGetQuick(_, _, _) => Opcode::IGetQuick,
+            Instruction::IGetWideQuick(_, _, _) => Opcode::IGetWideQuick,
+            Instruction::IGetObjectQuick(_, _, _) => Opcode::IGetObjectQuick,
+            Instruction::IPutQuick(_, _, _) => Opcode::IPutQuick,
+            Instruction::IPutWideQuick(_, _, _) => Opcode::IPutWideQuick,
+            Instruction::IPutObjectQuick(_, _, _) => Opcode::IPutObjectQuick,
+            Instruction::InvokeVirtualQuick { .. } => Opcode::InvokeVirtualQuick,
+            Instruction::InvokeVirtualQuickRange { .. } => Opcode::InvokeVirtualQuickRange,
+            Instruction::IPutBooleanQuick(_, _, _) => Opcode::IPutBooleanQuick,
+            Instruction::IPutByteQuick(_, _, _) => Opcode::IPutByteQuick,
+            Instruction::IPutCharQuick(_, _, _) => Opcode::IPutCharQuick,
+            Instruction::IPutShortQuick(_, _, _) => Opcode::IPutShortQuick,
+            Instruction::IGetBooleanQuick(_, _, _) => Opcode::IGetBooleanQuick,
+            Instruction::IGetByteQuick(_, _, _) => Opcode::IGetByteQuick,
+            Instruction::IGetCharQuick(_, _, _) => Opcode::IGetCharQuick,
+            Instruction::IGetShortQuick(_, _, _) => Opcode::IGetShortQuick,
+            Instruction::InvokeSuperQuick { .. } => Opcode::InvokeSuperQuick,
+            Instruction::InvokeSuperQuickRange { .. } => Opcode::InvokeSuperQuickRange,
+            Instruction::IGetVolatile(_, _, _) => Opcode::IGetVolatile,
+            Instruction::IPutVolatile(_, _, _) => Opcode::IPutVolatile,
+            Instruction::SGetVolatile(_, _) => Opcode::SGetVolatile,
+            Instruction::SPutVolatile(_, _) => Opcode::SPutVolatile,
+            Instruction::IGetObjectVolatile(_, _, _) => Opcode::IGetObjectVolatile,
+            Instruction::IGetWideVolatile(_, _, _) => Opcode::IGetWideVolatile,
+            Instruction::IPutWideVolatile(_, _, _) => Opcode::IPutWideVolatile,
+            Instruction::SGetWideVolatile(_, _) => Opcode::SGetWideVolatile,
+            Instruction::SPutWideVolatile(_, _) => Opcode::SPutWideVolatile,
+            Instruction::Breakpoint => Opcode::Breakpoint,
+            Instruction::ThrowVerificationError(_, _) => Opcode::ThrowVerificationError,
+            Instruction::ExecuteInline { .. } => Opcode::ExecuteInline,
+            Instruction::ExecuteInlineRange { .. } => Opcode::ExecuteInlineRange,
+            Instruction::InvokeDirectEmpty { .. } => Opcode::InvokeDirectEmpty,
+            Instruction::InvokeObjectInitRange { .. } => Opcode::InvokeObjectInitRange,
+            Instruction::ReturnVoidBarrier => Opcode::ReturnVoidBarrier,
+            Instruction::IPutObjectVolatile(_, _, _) => Opcode::IPutObjectVolatile,
+            Instruction::SGetObjectVolatile(_, _) => Opcode::SGetObjectVolatile,
+            Instruction::SPutObjectVolatile(_, _) => Opcode::SPutObjectVolatile,
+            Instruction::PackedSwitchPayload { .. } => Opcode::Nop,
+            Instruction::SparseSwitchPayload { .. } => Opcode::Nop,
+            Instruction::FillArrayDataPayload { .. } => Opcode::Nop,
+        }
+    }
+}
@@ -0,0 +1,428 @@
+//! Bytecode patching
+//!
+//! [`Editor`] inserts, deletes and replaces instructions of an existing method
+//! body at their original addresses, then lays the method out again:
+//!  - branch offsets, switch and array data references and switch payload
+//!    targets of the original instructions are recomputed. `goto` grows to
+//!    `goto/16` or `goto/32`, and conditional branches out of 16-bit range
+//!    become an inverted branch over a `goto/32`.
+//!  - payloads stay 32-bit aligned, with the original `nop` padding recomputed
+//!  - try ranges and catch handler addresses are remapped
+//!
+//! A branch to an address lands on the code inserted before it, so code
+//! inserted at the start of a basic block runs on every path into the block,
+//! and inside the same try block. Branches to a deleted instruction land on
+//! the one following it. Inserted instructions are laid out as given: their
+//! offsets are relative to their new address and are not adjusted.
+//!
+//! ```
+//! use dalvik::patch::Editor;
+//! use dalvik::Instruction;
+//!
+//! // if-eqz v0, +3; return-void; return v0
+//! let code = [0x0038, 0x0003, 0x000e, 0x000f];
+//! let mut editor = Editor::new(&code, vec![]).unwrap();
+//! editor.insert(2, [Instruction::Const4(0, 1)]).unwrap();
+//!
+//! // if-eqz v0, +4; const/4 v0, 1; return-void; return v0
+//! let (code, _) = editor.finish().unwrap();
+//! assert_eq!(code, [0x0038, 0x0004, 0x1012, 0x000e, 0x000f]);
+//! ```
+
+use std::collections::HashMap;
+
+use crate::{
+    decode::{self, Decoder},
+    ControlFlow, Instruction,
+};
+
+/// Patching error
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Error {
+    /// The method body could not be decoded, or one of its branches does not
+    /// land on an instruction
+    Decode(decode::Error),
+    /// The address is not the start of an original instruction, or the end of
+    /// the method where only insertion is possible
+    NotAnInstruction(usize),
+    /// The payload referenced by the instruction at that original address was
+    /// deleted or replaced
+    MissingPayload(usize),
+    /// A try block covers more than 65535 codepoints after patching
+    TryTooLarge,
+    /// An instruction could not be encoded
+    Encode(crate::encode::Error),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Decode(e) => f.write_fmt(format_args!("decoding failed: {e}")),
+            Error::NotAnInstruction(addr) => f.write_fmt(format_args!("no instruction at {addr:#x}")),
+            Error::MissingPayload(addr) => f.write_fmt(format_args!("payload referenced at {addr:#x} was removed")),
+            Error::TryTooLarge => f.write_str("try block too large"),
+            Error::Encode(e) => f.write_fmt(format_args!("encoding failed: {e}")),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A try block of a method, as a dex `try_item` with its catch handlers
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TryBlock {
+    /// Address of the first covered codepoint
+    pub start_addr: u32,
+    /// Number of codepoints covered
+    pub insn_count: u16,
+    /// `(type_idx, addr)` of each typed catch handler, in order
+    pub handlers: Vec<(u32, u32)>,
+    /// Address of the catch-all handler, if any
+    pub catch_all_addr: Option<u32>,
+}
+
+/// Editor of a method body, addressed by the original instruction addresses
+#[derive(Debug, Clone)]
+pub struct Editor {
+    slots: Vec<Slot>,
+    tries: Vec<TryBlock>,
+    /// Instructions inserted at the end of the method
+    tail: Vec<Instruction>,
+    len: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Slot {
+    addr: usize,
+    inst: Instruction,
+    target: Target,
+    before: Vec<Instruction>,
+    replacement: Option<Vec<Instruction>>,
+    /// Alignment nop before a payload, recomputed on layout
+    padding: bool,
+}
+
+/// Original targets of an instruction, as slot indices
+#[derive(Debug, Clone)]
+enum Target {
+    None,
+    Branch(usize),
+    Payload(usize),
+    Cases { referrer: usize, targets: Vec<usize> },
+}
+
+impl Editor {
+    /// Decode a method's bytecode and its try blocks for editing
+    ///
+    /// Fails if the bytecode cannot be decoded, or if a branch, try boundary
+    /// or catch handler does not land on an instruction.
+    pub fn new(bytecode: &[u16], tries: Vec<TryBlock>) -> Result<Self, Error> {
+        let mut slots = Vec::new();
+        for decoded in Decoder::new(bytecode) {
+            let (addr, inst) = decoded.map_err(|(_, e)| Error::Decode(e))?;
+            slots.push(Slot {
+                addr,
+                inst,
+                target: Target::None,
+                before: Vec::new(),
+                replacement: None,
+                padding: false,
+            });
+        }
+        let mut editor = Editor {
+            slots,
+            tries,
+            tail: Vec::new(),
+            len: bytecode.len(),
+        };
+
+        let mut referrers = HashMap::new();
+        for i in 0..editor.slots.len() {
+            let Slot { addr, inst, .. } = &editor.slots[i];
+            let target = match (inst, inst.control_flow()) {
+                (Instruction::PackedSwitch(_, off) | Instruction::SparseSwitch(_, off) | Instruction::FillArrayData(_, off), _) => {
+                    let payload = editor.resolve(*addr, *off)?;
+                    referrers.entry(payload).or_insert(i);
+                    Target::Payload(payload)
+                }
+                (_, ControlFlow::GoTo(off)) => Target::Branch(editor.resolve(*addr, off)?),
+                (_, ControlFlow::Branch(off)) => Target::Branch(editor.resolve(*addr, off.into())?),
+                _ => continue,
+            };
+            editor.slots[i].target = target;
+        }
+
+        // switch targets are relative to the switch, not the payload
+        for i in 0..editor.slots.len() {
+            let slot = &editor.slots[i];
+            let (Instruction::PackedSwitchPayload { targets, .. } | Instruction::SparseSwitchPayload { targets, .. }) = &slot.inst else {
+                continue;
+            };
+            let Some(&referrer) = referrers.get(&i) else {
+                continue;
+            };
+            let from = editor.slots[referrer].addr;
+            let targets = targets.iter().map(|t| editor.resolve(from, *t)).collect::<Result<_, _>>()?;
+            editor.slots[i].target = Target::Cases { referrer, targets };
+        }
+
+        let mut landed = vec![false; editor.slots.len() + 1];
+        for slot in &editor.slots {
+            match &slot.target {
+                Target::Branch(target) => landed[*target] = true,
+                Target::Cases { targets, .. } => targets.iter().for_each(|t| landed[*t] = true),
+                _ => {}
+            }
+        }
+        for t in &editor.tries {
+            let start = t.start_addr as usize;
+            let handlers = t.handlers.iter().map(|(_, addr)| addr).chain(&t.catch_all_addr);
+            for addr in [start, start + t.insn_count as usize].into_iter().chain(handlers.map(|a| *a as usize)) {
+                landed[editor.index(addr).ok_or(Error::NotAnInstruction(addr))?] = true;
+            }
+        }
+
+        // a nop aligning a payload is padding, unless code lands on it
+        for i in 1..editor.slots.len() {
+            let nop = &editor.slots[i - 1];
+            if editor.slots[i].inst.is_payload() && nop.inst == Instruction::Nop && nop.addr % 2 == 1 && !landed[i - 1] {
+                editor.slots[i - 1].padding = true;
+            }
+        }
+
+        Ok(editor)
+    }
+
+    /// Insert instructions before the one at `addr`, or at the end of the
+    /// method if `addr` is its length
+    ///
+    /// Branches to `addr` land on the first inserted instruction. Repeated
+    /// insertions at the same address are laid out in order.
+    pub fn insert(&mut self, addr: usize, insts: impl IntoIterator<Item = Instruction>) -> Result<&mut Self, Error> {
+        match self.index(addr) {
+            Some(i) if i == self.slots.len() => self.tail.extend(insts),
+            Some(i) => self.slots[i].before.extend(insts),
+            None => return Err(Error::NotAnInstruction(addr)),
+        }
+        Ok(self)
+    }
+
+    /// Delete the instruction at `addr`
+    ///
+    /// Branches to it land on the instruction following it.
+    pub fn delete(&mut self, addr: usize) -> Result<&mut Self, Error> {
+        self.replace(addr, [])
+    }
+
+    /// Replace the instruction at `addr`
+    ///
+    /// Branches to it land on the code inserted before it, if any, or on the
+    /// first replacement instruction.
+    pub fn replace(&mut self, addr: usize, insts: impl IntoIterator<Item = Instruction>) -> Result<&mut Self, Error> {
+        match self.index(addr) {
+            Some(i) if i < self.slots.len() => self.slots[i].replacement = Some(insts.into_iter().collect()),
+            _ => return Err(Error::NotAnInstruction(addr)),
+        }
+        Ok(self)
+    }
+
+    /// Lay out the patched method, returning its bytecode and try blocks
+    ///
+    /// Try blocks left empty by deletions are dropped.
+    pub fn finish(self) -> Result<(Vec<u16>, Vec<TryBlock>), Error> {
+        // original encodings only ever grow, so the relaxation terminates
+        let mut sizes: Vec<usize> = self.slots.iter().map(|s| s.inst.len()).collect();
+        let layout = loop {
+            let layout = self.layout(&sizes);
+            let mut stable = true;
+            for (i, slot) in self.slots.iter().enumerate() {
+                let Target::Branch(target) = slot.target else {
+                    continue;
+                };
+                if slot.replacement.is_some() {
+                    continue;
+                }
+                let off = layout.branch(target, i);
+                let size = match slot.inst.control_flow() {
+                    ControlFlow::GoTo(_) if off == 0 => 3,
+                    ControlFlow::GoTo(_) if i8::try_from(off).is_ok() => 1,
+                    ControlFlow::GoTo(_) if i16::try_from(off).is_ok() => 2,
+                    ControlFlow::GoTo(_) => 3,
+                    _ if off != 0 && i16::try_from(off).is_ok() => 2,
+                    _ => 5,
+                };
+                if size > sizes[i] {
+                    sizes[i] = size;
+                    stable = false;
+                }
+            }
+            if stable {
+                break layout;
+            }
+        };
+
+        let mut tries = Vec::with_capacity(self.tries.len());
+        for t in &self.tries {
+            let start = layout.start[self.index(t.start_addr as usize).unwrap()];
+            let end = layout.start[self.index((t.start_addr + u32::from(t.insn_count)) as usize).unwrap()];
+            if start == end {
+                continue;
+            }
+            let handler = |addr: u32| layout.start[self.index(addr as usize).unwrap()] as u32;
+            tries.push(TryBlock {
+                start_addr: start as u32,
+                insn_count: (end - start).try_into().map_err(|_| Error::TryTooLarge)?,
+                handlers: t.handlers.iter().map(|(ty, addr)| (*ty, handler(*addr))).collect(),
+                catch_all_addr: t.catch_all_addr.map(handler),
+            });
+        }
+
+        for slot in &self.slots {
+            if let (None, Target::Payload(payload)) = (&slot.replacement, &slot.target) {
+                if self.slots[*payload].replacement.is_some() {
+                    return Err(Error::MissingPayload(slot.addr));
+                }
+            }
+        }
+
+        let mut insts = Vec::new();
+        let mut addr = 0;
+        for (i, slot) in self.slots.into_iter().enumerate() {
+            for inst in slot.before {
+                emit(&mut insts, &mut addr, inst);
+            }
+            let mut inst = match (slot.replacement, slot.padding) {
+                (Some(replacement), _) => {
+                    for inst in replacement {
+                        emit(&mut insts, &mut addr, inst);
+                    }
+                    continue;
+                }
+                (None, true) => continue,
+                (None, false) => slot.inst,
+            };
+
+            let from = layout.at[i] as i32;
+            match (slot.target, &mut inst) {
+                (Target::Branch(target), _) => {
+                    let off = layout.branch(target, i);
+                    inst = match (sizes[i], inst.condition()) {
+                        (2, Some((cond, a, b))) => Instruction::conditional(cond, a, b, off as i16),
+                        (_, Some((cond, a, b))) => {
+                            // branch over a goto/32 to the target
+                            emit(&mut insts, &mut addr, Instruction::conditional(cond.inverse(), a, b, 5));
+                            Instruction::Goto32(off - 2)
+                        }
+                        (1, None) => Instruction::Goto(off as i8),
+                        (2, None) => Instruction::Goto16(off as i16),
+                        (_, None) => Instruction::Goto32(off),
+                    };
+                }
+                (Target::Payload(payload), Instruction::PackedSwitch(_, off) | Instruction::SparseSwitch(_, off) | Instruction::FillArrayData(_, off)) => {
+                    *off = layout.at[payload] as i32 - from
+                }
+                (
+                    Target::Cases { referrer, targets },
+                    Instruction::PackedSwitchPayload { targets: offs, .. } | Instruction::SparseSwitchPayload { targets: offs, .. },
+                ) => {
+                    let from = layout.at[referrer] as i32;
+                    for (off, target) in offs.iter_mut().zip(targets) {
+                        *off = layout.start[target] as i32 - from;
+                    }
+                }
+                _ => {}
+            }
+            emit(&mut insts, &mut addr, inst);
+        }
+        for inst in self.tail {
+            emit(&mut insts, &mut addr, inst);
+        }
+
+        let mut units = Vec::with_capacity(addr);
+        for inst in &insts {
+            inst.encode(&mut units).map_err(Error::Encode)?;
+        }
+        Ok((units, tries))
+    }
+
+    // slot index of an original instruction, or the slot count for the end
+    fn index(&self, addr: usize) -> Option<usize> {
+        if addr == self.len {
+            return Some(self.slots.len());
+        }
+        self.slots.binary_search_by_key(&addr, |s| s.addr).ok()
+    }
+
+    // slot index of the target of an original branch, which must land on an
+    // instruction
+    fn resolve(&self, from: usize, off: i32) -> Result<usize, Error> {
+        let bad = Error::Decode(decode::Error::BadBranchTarget {
+            from,
+            to: from as isize + off as isize,
+        });
+        let to = from.checked_add_signed(off as isize).ok_or(bad.clone())?;
+        match self.index(to) {
+            Some(i) if i < self.slots.len() => Ok(i),
+            _ => Err(bad),
+        }
+    }
+
+    // new addresses, given the sizes of the original instructions
+    fn layout(&self, sizes: &[usize]) -> Layout {
+        let mut layout = Layout {
+            start: Vec::with_capacity(self.slots.len() + 1),
+            at: Vec::with_capacity(self.slots.len()),
+        };
+        let mut addr = 0;
+        let place = |addr: &mut usize, inst: &Instruction, size: usize| {
+            *addr += usize::from(inst.is_payload() && *addr % 2 == 1);
+            let at = *addr;
+            *addr += size;
+            at
+        };
+        for (slot, size) in self.slots.iter().zip(sizes) {
+            layout.start.push(addr);
+            for inst in &slot.before {
+                place(&mut addr, inst, inst.len());
+            }
+            match &slot.replacement {
+                Some(replacement) => {
+                    layout.at.push(addr);
+                    for inst in replacement {
+                        place(&mut addr, inst, inst.len());
+                    }
+                }
+                None if slot.padding => layout.at.push(addr),
+                None => layout.at.push(place(&mut addr, &slot.inst, *size)),
+            }
+        }
+        layout.start.push(addr);
+
+        layout
+    }
+}
+
+struct Layout {
+    /// New address of each slot including its insertions, and of the end
+    start: Vec<usize>,
+    /// New address of each original instruction
+    at: Vec<usize>,
+}
+
+impl Layout {
+    // offset of a branch from the original instruction in slot `from`
+    fn branch(&self, to: usize, from: usize) -> i32 {
+        self.start[to] as i32 - self.at[from] as i32
+    }
+}
+
+// append an instruction, aligning payloads
+fn emit(insts: &mut Vec<Instruction>, addr: &mut usize, inst: Instruction) {
+    if inst.is_payload() && *addr % 2 == 1 {
+        insts.push(Instruction::Nop);
+        *addr += 1;
+    }
+    *addr += inst.len();
+    insts.push(inst);
+}
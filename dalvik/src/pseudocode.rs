@@ -0,0 +1,512 @@
+//! Java-like rendering of instructions
+//!
+//! [`PrettyPrint::pseudocode`] renders an instruction as a statement, e.g.
+//! `v0 = v1 + v2`, `v3 = v2.count` or `if (v16 != 0) goto +1025`, for
+//! readers not fluent in smali. Like [`PrettyPrint::tokens`] it is available as
+//! [`Token`]s, so branch targets can be resolved with
+//! [`DisplayAt`][`crate::tokens::DisplayAt`]. Instructions with no Java
+//! equivalent, like `monitor-enter` or the payloads, keep their mnemonic form.
+//!
+//! On its own, an invoke renders as a call and the `move-result` after it as
+//! `v0 = result`. [`statements`] renders a sequence of instructions instead,
+//! joining the two into `v0 = v4.compute(v5, v6)`.
+
+use crate::{
+    references::Reference,
+    tokens::{Token, TokenKind},
+    Condition, ControlFlow, Instruction, PrettyPrint,
+};
+
+/// Java name of a type descriptor, without its package
+fn java_type(desc: &str) -> String {
+    if let Some(elem) = desc.strip_prefix('[') {
+        return format!("{}[]", java_type(elem));
+    }
+    let name = match desc {
+        "V" => "void",
+        "Z" => "boolean",
+        "B" => "byte",
+        "S" => "short",
+        "C" => "char",
+        "I" => "int",
+        "J" => "long",
+        "F" => "float",
+        "D" => "double",
+        _ => match desc.strip_prefix('L').and_then(|d| d.strip_suffix(';')) {
+            Some(class) => class.rsplit('/').next().unwrap_or(class),
+            None => desc,
+        },
+    };
+    name.to_string()
+}
+
+/// Whether each parameter of a method descriptor's parameter list is wide
+fn wide_params(params: &str) -> Vec<bool> {
+    let mut wide = Vec::new();
+    let mut chars = params.chars();
+    while let Some(mut c) = chars.next() {
+        let array = c == '[';
+        while c == '[' {
+            c = chars.next().unwrap_or(c);
+        }
+        if c == 'L' {
+            chars.by_ref().find(|c| *c == ';');
+        }
+        wide.push(!array && matches!(c, 'J' | 'D'));
+    }
+    wide
+}
+
+struct Statement<'a, T: ?Sized> {
+    lookup: &'a T,
+    tokens: Vec<Token>,
+}
+
+impl<T: PrettyPrint + ?Sized> Statement<'_, T> {
+    fn push(&mut self, kind: TokenKind, text: impl Into<String>) -> &mut Self {
+        self.tokens.push(Token { kind, text: text.into() });
+        self
+    }
+
+    fn punct(&mut self, text: &str) -> &mut Self {
+        self.push(TokenKind::Punctuation, text)
+    }
+
+    fn keyword(&mut self, text: &str) -> &mut Self {
+        self.push(TokenKind::Keyword, text)
+    }
+
+    fn literal(&mut self, text: impl Into<String>) -> &mut Self {
+        self.push(TokenKind::Literal, text)
+    }
+
+    fn reg(&mut self, reg: impl Into<u16>) -> &mut Self {
+        let reg = reg.into();
+        self.push(TokenKind::Register(reg), format!("v{reg}"))
+    }
+
+    fn target(&mut self, off: i32) -> &mut Self {
+        self.push(TokenKind::Target(off), format!("{off:+}"))
+    }
+
+    fn assign(&mut self, dst: impl Into<u16>) -> &mut Self {
+        self.reg(dst).punct(" = ")
+    }
+
+    fn ty(&mut self, idx: u16) -> &mut Self {
+        let name = java_type(&self.lookup.type_name(idx));
+        self.push(TokenKind::Reference(Reference::Type(idx)), name)
+    }
+
+    fn field(&mut self, obj: Option<u8>, idx: u16) -> &mut Self {
+        let (class, name, _) = self.lookup.field(idx);
+        let text = match obj {
+            Some(obj) => {
+                self.reg(obj).punct(".");
+                name
+            }
+            None => format!("{}.{name}", java_type(&class)),
+        };
+        self.push(TokenKind::Reference(Reference::Field(idx)), text)
+    }
+
+    fn unary(&mut self, dst: u8, op: &str, src: u8) -> &mut Self {
+        self.assign(dst).punct(op).reg(src)
+    }
+
+    fn binary(&mut self, dst: u8, a: u8, op: &str, b: u8) -> &mut Self {
+        self.assign(dst).reg(a).punct(op).reg(b)
+    }
+
+    fn binary_lit(&mut self, dst: u8, a: u8, op: &str, lit: i16) -> &mut Self {
+        self.assign(dst).reg(a).punct(op).literal(lit.to_string())
+    }
+
+    fn args(&mut self, regs: impl IntoIterator<Item = u16>) -> &mut Self {
+        self.punct("(");
+        for (n, reg) in regs.into_iter().enumerate() {
+            if n > 0 {
+                self.punct(", ");
+            }
+            self.reg(reg);
+        }
+        self.punct(")")
+    }
+
+    fn invoke(&mut self, kind: Invoke, method: u16, regs: &[u16]) -> &mut Self {
+        let (class, name, params, _) = self.lookup.method(method);
+        let reference = TokenKind::Reference(Reference::Method(method));
+        let args = match kind {
+            Invoke::Static => {
+                self.push(reference, format!("{}.{name}", java_type(&class)));
+                regs
+            }
+            Invoke::Super if !regs.is_empty() => {
+                self.keyword("super").punct(".").push(reference, name);
+                &regs[1..]
+            }
+            _ if !regs.is_empty() => {
+                self.reg(regs[0]).punct(".").push(reference, name);
+                &regs[1..]
+            }
+            _ => {
+                self.push(reference, name);
+                regs
+            }
+        };
+
+        // only the first register of a wide argument is shown
+        let wide = wide_params(&params);
+        match wide.iter().map(|w| 1 + *w as usize).sum::<usize>() == args.len() {
+            true => {
+                let mut args = args.iter().copied();
+                let mut firsts = Vec::new();
+                for w in wide {
+                    firsts.extend(args.next());
+                    if w {
+                        args.next();
+                    }
+                }
+                self.args(firsts)
+            }
+            false => self.args(args.iter().copied()),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Invoke {
+    Instance,
+    Super,
+    Static,
+}
+
+pub(crate) fn tokens<T: PrettyPrint + ?Sized>(lookup: &T, inst: &Instruction) -> Vec<Token> {
+    use Instruction::*;
+
+    let mut s = Statement { lookup, tokens: Vec::new() };
+    let regs = |nargs: u8, args: &[u8; 5]| -> Vec<u16> { args[..nargs as usize].iter().map(|r| (*r).into()).collect() };
+
+    match inst {
+        Move(dst, src) | MoveWide(dst, src) | MoveObject(dst, src) => s.assign(*dst).reg(*src),
+        MoveFrom16(dst, src) | MoveWideFrom16(dst, src) | MoveObjectFrom16(dst, src) => s.assign(*dst).reg(*src),
+        Move16(dst, src) | MoveWide16(dst, src) | MoveObject16(dst, src) => s.assign(*dst).reg(*src),
+        MoveResult(dst) | MoveResultWide(dst) | MoveResultObject(dst) => s.assign(*dst).keyword("result"),
+        MoveException(dst) => s.assign(*dst).keyword("exception"),
+        ReturnVoid | ReturnVoidNoBarrier | ReturnVoidBarrier => s.keyword("return"),
+        Return(reg) | ReturnWide(reg) | ReturnObject(reg) => s.keyword("return").punct(" ").reg(*reg),
+
+        Const4(dst, lit) => s.assign(*dst).literal(lit.to_string()),
+        Const16(dst, lit) => s.assign(*dst).literal(lit.to_string()),
+        Const(dst, lit) => s.assign(*dst).literal((*lit as i32).to_string()),
+        ConstHigh16(dst, lit) => s.assign(*dst).literal(((*lit as i32) << 16).to_string()),
+        ConstWide16(dst, lit) => s.assign(*dst).literal(format!("{lit}L")),
+        ConstWide32(dst, lit) => s.assign(*dst).literal(format!("{}L", *lit as i32)),
+        ConstWide(dst, lit) => s.assign(*dst).literal(format!("{}L", *lit as i64)),
+        ConstWideHigh16(dst, lit) => s.assign(*dst).literal(format!("{}L", (*lit as i64) << 48)),
+        ConstString(dst, idx) => {
+            let text = format!("\"{}\"", crate::tokens::escape(&lookup.string((*idx).into())));
+            s.assign(*dst).push(TokenKind::Reference(Reference::String((*idx).into())), text)
+        }
+        ConstStringJumbo(dst, idx) => {
+            let text = format!("\"{}\"", crate::tokens::escape(&lookup.string(*idx)));
+            s.assign(*dst).push(TokenKind::Reference(Reference::String(*idx)), text)
+        }
+        ConstClass(dst, ty) => s.assign(*dst).ty(*ty).punct(".").keyword("class"),
+        ConstMethodHandle(dst, idx) => s
+            .assign(*dst)
+            .push(TokenKind::Reference(Reference::MethodHandle(*idx)), lookup.method_handle(*idx)),
+        ConstMethodType(dst, idx) => s.assign(*dst).push(TokenKind::Reference(Reference::Proto(*idx)), lookup.proto(*idx)),
+
+        CheckCast(reg, ty) => s.assign(*reg).punct("(").ty(*ty).punct(") ").reg(*reg),
+        InstanceOf(dst, src, ty) => s.assign(*dst).reg(*src).punct(" ").keyword("instanceof").punct(" ").ty(*ty),
+        ArrayLength(dst, src) => s.assign(*dst).reg(*src).punct(".").keyword("length"),
+        NewInstance(dst, ty) => s.assign(*dst).keyword("new").punct(" ").ty(*ty),
+        NewArray(dst, size, ty) => {
+            // the element type of the array type, with the size in its brackets
+            let name = java_type(&lookup.type_name(*ty));
+            let elem = name.strip_suffix("[]").unwrap_or(&name).to_string();
+            s.assign(*dst)
+                .keyword("new")
+                .punct(" ")
+                .push(TokenKind::Reference(Reference::Type(*ty)), elem)
+                .punct("[")
+                .reg(*size)
+                .punct("]")
+        }
+        FilledNewArray { ty, nargs, args } => {
+            let regs = regs(*nargs, args);
+            s.keyword("result").punct(" = ").keyword("new").punct(" ").ty(*ty).punct(" {");
+            for (n, reg) in regs.into_iter().enumerate() {
+                if n > 0 {
+                    s.punct(", ");
+                }
+                s.reg(reg);
+            }
+            s.punct("}")
+        }
+        FilledNewArrayRange { ty, args } => {
+            s.keyword("result").punct(" = ").keyword("new").punct(" ").ty(*ty).punct(" {");
+            for (n, reg) in args.iter().enumerate() {
+                if n > 0 {
+                    s.punct(", ");
+                }
+                s.reg(*reg);
+            }
+            s.punct("}")
+        }
+        Throw(reg) => s.keyword("throw").punct(" ").reg(*reg),
+
+        Goto(off) => s.keyword("goto").punct(" ").target((*off).into()),
+        Goto16(off) => s.keyword("goto").punct(" ").target((*off).into()),
+        Goto32(off) => s.keyword("goto").punct(" ").target(*off),
+        PackedSwitch(reg, off) | SparseSwitch(reg, off) => s.keyword("switch").punct(" (").reg(*reg).punct(") ").target(*off),
+
+        CmplFloat(dst, a, b) | CmplDouble(dst, a, b) => s.assign(*dst).keyword("cmpl").args([(*a).into(), (*b).into()]),
+        CmpgFloat(dst, a, b) | CmpgDouble(dst, a, b) => s.assign(*dst).keyword("cmpg").args([(*a).into(), (*b).into()]),
+        CmpLong(dst, a, b) => s.assign(*dst).keyword("cmp").args([(*a).into(), (*b).into()]),
+
+        AGet(dst, arr, idx)
+        | AGetWide(dst, arr, idx)
+        | AGetObject(dst, arr, idx)
+        | AGetBoolean(dst, arr, idx)
+        | AGetByte(dst, arr, idx)
+        | AGetChar(dst, arr, idx)
+        | AGetShort(dst, arr, idx) => s.assign(*dst).reg(*arr).punct("[").reg(*idx).punct("]"),
+        APut(src, arr, idx)
+        | APutWide(src, arr, idx)
+        | APutObject(src, arr, idx)
+        | APutBoolean(src, arr, idx)
+        | APutByte(src, arr, idx)
+        | APutChar(src, arr, idx)
+        | APutShort(src, arr, idx) => s.reg(*arr).punct("[").reg(*idx).punct("] = ").reg(*src),
+
+        IGet(dst, obj, field)
+        | IGetWide(dst, obj, field)
+        | IGetObject(dst, obj, field)
+        | IGetBoolean(dst, obj, field)
+        | IGetByte(dst, obj, field)
+        | IGetChar(dst, obj, field)
+        | IGetShort(dst, obj, field)
+        | IGetVolatile(dst, obj, field)
+        | IGetObjectVolatile(dst, obj, field)
+        | IGetWideVolatile(dst, obj, field) => s.assign(*dst).field(Some(*obj), *field),
+        IPut(src, obj, field)
+        | IPutWide(src, obj, field)
+        | IPutObject(src, obj, field)
+        | IPutBoolean(src, obj, field)
+        | IPutByte(src, obj, field)
+        | IPutChar(src, obj, field)
+        | IPutShort(src, obj, field)
+        | IPutVolatile(src, obj, field)
+        | IPutWideVolatile(src, obj, field)
+        | IPutObjectVolatile(src, obj, field) => s.field(Some(*obj), *field).punct(" = ").reg(*src),
+        SGet(dst, field)
+        | SGetWide(dst, field)
+        | SGetObject(dst, field)
+        | SGetBoolean(dst, field)
+        | SGetByte(dst, field)
+        | SGetChar(dst, field)
+        | SGetShort(dst, field)
+        | SGetVolatile(dst, field)
+        | SGetWideVolatile(dst, field)
+        | SGetObjectVolatile(dst, field) => s.assign(*dst).field(None, *field),
+        SPut(src, field)
+        | SPutWide(src, field)
+        | SPutObject(src, field)
+        | SPutBoolean(src, field)
+        | SPutByte(src, field)
+        | SPutChar(src, field)
+        | SPutShort(src, field)
+        | SPutVolatile(src, field)
+        | SPutWideVolatile(src, field)
+        | SPutObjectVolatile(src, field) => s.field(None, *field).punct(" = ").reg(*src),
+        IGetQuick(dst, obj, off)
+        | IGetWideQuick(dst, obj, off)
+        | IGetObjectQuick(dst, obj, off)
+        | IGetBooleanQuick(dst, obj, off)
+        | IGetByteQuick(dst, obj, off)
+        | IGetCharQuick(dst, obj, off)
+        | IGetShortQuick(dst, obj, off) => s.assign(*dst).reg(*obj).punct(".").literal(format!("field@{off:x}")),
+        IPutQuick(src, obj, off)
+        | IPutWideQuick(src, obj, off)
+        | IPutObjectQuick(src, obj, off)
+        | IPutBooleanQuick(src, obj, off)
+        | IPutByteQuick(src, obj, off)
+        | IPutCharQuick(src, obj, off)
+        | IPutShortQuick(src, obj, off) => s.reg(*obj).punct(".").literal(format!("field@{off:x}")).punct(" = ").reg(*src),
+
+        InvokeVirtual { method, nargs, args }
+        | InvokeDirect { method, nargs, args }
+        | InvokeInterface { method, nargs, args }
+        | InvokeDirectEmpty { method, nargs, args }
+        | InvokePolymorphic { method, nargs, args, .. } => s.invoke(Invoke::Instance, *method, &regs(*nargs, args)),
+        InvokeVirtualRange { method, args }
+        | InvokeDirectRange { method, args }
+        | InvokeInterfaceRange { method, args }
+        | InvokeObjectInitRange { method, args }
+        | InvokePolymorphicRange { method, args, .. } => s.invoke(Invoke::Instance, *method, args),
+        InvokeSuper { method, nargs, args } => s.invoke(Invoke::Super, *method, &regs(*nargs, args)),
+        InvokeSuperRange { method, args } => s.invoke(Invoke::Super, *method, args),
+        InvokeStatic { method, nargs, args } => s.invoke(Invoke::Static, *method, &regs(*nargs, args)),
+        InvokeStaticRange { method, args } => s.invoke(Invoke::Static, *method, args),
+        InvokeCustom { call_site, nargs, args } => s
+            .push(TokenKind::Reference(Reference::CallSite(*call_site)), lookup.call_site(*call_site))
+            .args(regs(*nargs, args)),
+        InvokeCustomRange { call_site, args } => s
+            .push(TokenKind::Reference(Reference::CallSite(*call_site)), lookup.call_site(*call_site))
+            .args(args.iter().copied()),
+
+        NegInt(dst, src) | NegLong(dst, src) | NegFloat(dst, src) | NegDouble(dst, src) => s.unary(*dst, "-", *src),
+        NotInt(dst, src) | NotLong(dst, src) => s.unary(*dst, "~", *src),
+        IntToLong(dst, src) | FloatToLong(dst, src) | DoubleToLong(dst, src) => s.unary(*dst, "(long) ", *src),
+        IntToFloat(dst, src) | LongToFloat(dst, src) | DoubleToFloat(dst, src) => s.unary(*dst, "(float) ", *src),
+        IntToDouble(dst, src) | LongToDouble(dst, src) | FloatToDouble(dst, src) => s.unary(*dst, "(double) ", *src),
+        LongToInt(dst, src) | FloatToInt(dst, src) | DoubleToInt(dst, src) => s.unary(*dst, "(int) ", *src),
+        IntTobyte(dst, src) => s.unary(*dst, "(byte) ", *src),
+        IntTochar(dst, src) => s.unary(*dst, "(char) ", *src),
+        IntToshort(dst, src) => s.unary(*dst, "(short) ", *src),
+
+        AddInt(d, a, b) | AddLong(d, a, b) | AddFloat(d, a, b) | AddDouble(d, a, b) => s.binary(*d, *a, " + ", *b),
+        SubInt(d, a, b) | SubLong(d, a, b) | SubFloat(d, a, b) | SubDouble(d, a, b) => s.binary(*d, *a, " - ", *b),
+        MulInt(d, a, b) | MulLong(d, a, b) | MulFloat(d, a, b) | MulDouble(d, a, b) => s.binary(*d, *a, " * ", *b),
+        DivInt(d, a, b) | DivLong(d, a, b) | DivFloat(d, a, b) | DivDouble(d, a, b) => s.binary(*d, *a, " / ", *b),
+        RemInt(d, a, b) | RemLong(d, a, b) | RemFloat(d, a, b) | RemDouble(d, a, b) => s.binary(*d, *a, " % ", *b),
+        AndInt(d, a, b) | AndLong(d, a, b) => s.binary(*d, *a, " & ", *b),
+        OrInt(d, a, b) | OrLong(d, a, b) => s.binary(*d, *a, " | ", *b),
+        XorInt(d, a, b) | XorLong(d, a, b) => s.binary(*d, *a, " ^ ", *b),
+        ShlInt(d, a, b) | ShlLong(d, a, b) => s.binary(*d, *a, " << ", *b),
+        ShrInt(d, a, b) | ShrLong(d, a, b) => s.binary(*d, *a, " >> ", *b),
+        UshrInt(d, a, b) | UshrLong(d, a, b) => s.binary(*d, *a, " >>> ", *b),
+
+        AddInt2(a, b) | AddLong2(a, b) | AddFloat2(a, b) | AddDouble2(a, b) => s.binary(*a, *a, " + ", *b),
+        SubInt2(a, b) | SubLong2(a, b) | SubFloat2(a, b) | SubDouble2(a, b) => s.binary(*a, *a, " - ", *b),
+        MulInt2(a, b) | MulLong2(a, b) | MulFloat2(a, b) | MulDouble2(a, b) => s.binary(*a, *a, " * ", *b),
+        DivInt2(a, b) | DivLong2(a, b) | DivFloat2(a, b) | DivDouble2(a, b) => s.binary(*a, *a, " / ", *b),
+        RemInt2(a, b) | RemLong2(a, b) | RemFloat2(a, b) | RemDouble2(a, b) => s.binary(*a, *a, " % ", *b),
+        AndInt2(a, b) | AndLong2(a, b) => s.binary(*a, *a, " & ", *b),
+        OrInt2(a, b) | OrLong2(a, b) => s.binary(*a, *a, " | ", *b),
+        XorInt2(a, b) | XorLong2(a, b) => s.binary(*a, *a, " ^ ", *b),
+        ShlInt2(a, b) | ShlLong2(a, b) => s.binary(*a, *a, " << ", *b),
+        ShrInt2(a, b) | ShrLong2(a, b) => s.binary(*a, *a, " >> ", *b),
+        UShrInt2(a, b) | UShrLong2(a, b) => s.binary(*a, *a, " >>> ", *b),
+
+        AddInt16(d, a, lit) => s.binary_lit(*d, *a, " + ", *lit),
+        MulInt16(d, a, lit) => s.binary_lit(*d, *a, " * ", *lit),
+        DivInt16(d, a, lit) => s.binary_lit(*d, *a, " / ", *lit),
+        RemInt16(d, a, lit) => s.binary_lit(*d, *a, " % ", *lit),
+        AndInt16(d, a, lit) => s.binary_lit(*d, *a, " & ", *lit),
+        OrInt16(d, a, lit) => s.binary_lit(*d, *a, " | ", *lit),
+        XorInt16(d, a, lit) => s.binary_lit(*d, *a, " ^ ", *lit),
+        AddInt8(d, a, lit) => s.binary_lit(*d, *a, " + ", (*lit).into()),
+        MulInt8(d, a, lit) => s.binary_lit(*d, *a, " * ", (*lit).into()),
+        DivInt8(d, a, lit) => s.binary_lit(*d, *a, " / ", (*lit).into()),
+        RemInt8(d, a, lit) => s.binary_lit(*d, *a, " % ", (*lit).into()),
+        AndInt8(d, a, lit) => s.binary_lit(*d, *a, " & ", (*lit).into()),
+        OrInt8(d, a, lit) => s.binary_lit(*d, *a, " | ", (*lit).into()),
+        XorInt8(d, a, lit) => s.binary_lit(*d, *a, " ^ ", (*lit).into()),
+        ShlInt8(d, a, lit) => s.binary_lit(*d, *a, " << ", (*lit).into()),
+        ShrInt8(d, a, lit) => s.binary_lit(*d, *a, " >> ", (*lit).into()),
+        UshrInt8(d, a, lit) => s.binary_lit(*d, *a, " >>> ", (*lit).into()),
+        RsubInt16(d, a, lit) => s.assign(*d).literal(lit.to_string()).punct(" - ").reg(*a),
+        RsubInt8(d, a, lit) => s.assign(*d).literal(lit.to_string()).punct(" - ").reg(*a),
+
+        _ => match (inst.condition(), inst.control_flow()) {
+            (Some((cond, a, b)), ControlFlow::Branch(off)) => {
+                let op = match cond {
+                    Condition::Eq => " == ",
+                    Condition::Ne => " != ",
+                    Condition::Lt => " < ",
+                    Condition::Ge => " >= ",
+                    Condition::Gt => " > ",
+                    Condition::Le => " <= ",
+                };
+                s.keyword("if").punct(" (").reg(a).punct(op);
+                match b {
+                    Some(b) => s.reg(b),
+                    None => s.literal("0"),
+                };
+                s.punct(") ").keyword("goto").punct(" ").target(off.into())
+            }
+            // no Java equivalent
+            _ => return lookup.tokens(inst),
+        },
+    };
+
+    s.tokens
+}
+
+/// Whether the pseudo-code of the instruction is a call, whose result the
+/// following `move-result` assigns
+fn is_call(inst: &Instruction) -> bool {
+    use Instruction::*;
+
+    matches!(
+        inst,
+        InvokeVirtual { .. }
+            | InvokeSuper { .. }
+            | InvokeDirect { .. }
+            | InvokeStatic { .. }
+            | InvokeInterface { .. }
+            | InvokeVirtualRange { .. }
+            | InvokeSuperRange { .. }
+            | InvokeDirectRange { .. }
+            | InvokeStaticRange { .. }
+            | InvokeInterfaceRange { .. }
+            | InvokePolymorphic { .. }
+            | InvokePolymorphicRange { .. }
+            | InvokeCustom { .. }
+            | InvokeCustomRange { .. }
+            | InvokeDirectEmpty { .. }
+            | InvokeObjectInitRange { .. }
+    )
+}
+
+/// Render consecutive instructions as statements, each invoke followed by a
+/// `move-result` making up a single assignment
+///
+/// Returns the index in `insts` of the first instruction of each statement,
+/// along with its tokens.
+///
+/// ```
+/// use dalvik::pseudocode::statements;
+/// use dalvik::{Instruction, PrettyPrint};
+///
+/// # struct Dex;
+/// # impl PrettyPrint for Dex {
+/// #     fn method(&self, index: u16) -> (String, String, String, String) {
+/// #         ("Lcom/example/Foo;".into(), "size".into(), "".into(), "I".into())
+/// #     }
+/// #     fn field(&self, index: u16) -> (String, String, String) { unimplemented!() }
+/// #     fn string(&self, index: u32) -> String { unimplemented!() }
+/// #     fn type_name(&self, index: u16) -> String { unimplemented!() }
+/// # }
+/// let insts = [
+///     Instruction::InvokeVirtual { method: 0, nargs: 1, args: [1, 0, 0, 0, 0] },
+///     Instruction::MoveResult(0),
+///     Instruction::Return(0),
+/// ];
+/// let lines: Vec<_> = statements(&Dex, &insts)
+///     .into_iter()
+///     .map(|(i, tokens)| (i, tokens.into_iter().map(|t| t.text).collect::<String>()))
+///     .collect();
+/// assert_eq!(lines, [(0, "v0 = v1.size()".to_string()), (2, "return v0".to_string())]);
+/// ```
+pub fn statements<T: PrettyPrint + ?Sized>(lookup: &T, insts: &[Instruction]) -> Vec<(usize, Vec<Token>)> {
+    let mut statements = Vec::with_capacity(insts.len());
+    let mut i = 0;
+    while i < insts.len() {
+        let mut s = Statement { lookup, tokens: Vec::new() };
+        let result = match insts.get(i + 1) {
+            Some(Instruction::MoveResult(dst) | Instruction::MoveResultWide(dst) | Instruction::MoveResultObject(dst)) if is_call(&insts[i]) => Some(*dst),
+            _ => None,
+        };
+        if let Some(dst) = result {
+            s.assign(dst);
+        }
+        s.tokens.extend(tokens(lookup, &insts[i]));
+        statements.push((i, s.tokens));
+        i += 1 + result.is_some() as usize;
+    }
+    statements
+}
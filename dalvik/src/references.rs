@@ -0,0 +1,280 @@
+//! Constant pool references of instructions
+
+use crate::{encode::Error, Instruction};
+
+/// An index into one of the dex constant pools, referenced by an [`Instruction`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Reference {
+    /// `string_ids` index
+    String(u32),
+    /// `type_ids` index
+    Type(u16),
+    /// `field_ids` index
+    Field(u16),
+    /// `method_ids` index
+    Method(u16),
+    /// `proto_ids` index
+    Proto(u16),
+    /// `call_site_ids` index
+    CallSite(u16),
+    /// `method_handles` index
+    MethodHandle(u16),
+}
+
+impl Reference {
+    /// The index, whatever the pool
+    pub fn index(self) -> u32 {
+        match self {
+            Reference::String(idx) => idx,
+            Reference::Type(idx)
+            | Reference::Field(idx)
+            | Reference::Method(idx)
+            | Reference::Proto(idx)
+            | Reference::CallSite(idx)
+            | Reference::MethodHandle(idx) => idx.into(),
+        }
+    }
+
+    /// Name of the pool, as printed before the index
+    pub(crate) fn pool(self) -> &'static str {
+        match self {
+            Reference::String(_) => "string",
+            Reference::Type(_) => "type",
+            Reference::Field(_) => "field",
+            Reference::Method(_) => "method",
+            Reference::Proto(_) => "proto",
+            Reference::CallSite(_) => "call_site",
+            Reference::MethodHandle(_) => "method_handle",
+        }
+    }
+
+    fn with_index(self, idx: u32) -> Self {
+        // the caller checks the range
+        match self {
+            Reference::String(_) => Reference::String(idx),
+            Reference::Type(_) => Reference::Type(idx as u16),
+            Reference::Field(_) => Reference::Field(idx as u16),
+            Reference::Method(_) => Reference::Method(idx as u16),
+            Reference::Proto(_) => Reference::Proto(idx as u16),
+            Reference::CallSite(_) => Reference::CallSite(idx as u16),
+            Reference::MethodHandle(_) => Reference::MethodHandle(idx as u16),
+        }
+    }
+}
+
+impl std::fmt::Display for Reference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}@{:x}", self.pool(), self.index()))
+    }
+}
+
+impl Instruction {
+    /// Constant pool indices the instruction references, in operand order
+    ///
+    /// Runtime-specific indices of the optimized instructions (field offsets,
+    /// vtable slots and inline method numbers) are not constant pool
+    /// references, so are not included.
+    pub fn references(&self) -> Vec<Reference> {
+        match self {
+            Self::ConstString(_, idx) => vec![Reference::String((*idx).into())],
+            Self::ConstStringJumbo(_, idx) => vec![Reference::String(*idx)],
+
+            Self::ConstClass(_, idx)
+            | Self::CheckCast(_, idx)
+            | Self::InstanceOf(_, _, idx)
+            | Self::NewInstance(_, idx)
+            | Self::NewArray(_, _, idx)
+            | Self::FilledNewArray { ty: idx, .. }
+            | Self::FilledNewArrayRange { ty: idx, .. } => vec![Reference::Type(*idx)],
+
+            Self::IGet(_, _, idx)
+            | Self::IGetWide(_, _, idx)
+            | Self::IGetObject(_, _, idx)
+            | Self::IGetBoolean(_, _, idx)
+            | Self::IGetByte(_, _, idx)
+            | Self::IGetChar(_, _, idx)
+            | Self::IGetShort(_, _, idx)
+            | Self::IPut(_, _, idx)
+            | Self::IPutWide(_, _, idx)
+            | Self::IPutObject(_, _, idx)
+            | Self::IPutBoolean(_, _, idx)
+            | Self::IPutByte(_, _, idx)
+            | Self::IPutChar(_, _, idx)
+            | Self::IPutShort(_, _, idx)
+            | Self::SGet(_, idx)
+            | Self::SGetWide(_, idx)
+            | Self::SGetObject(_, idx)
+            | Self::SGetBoolean(_, idx)
+            | Self::SGetByte(_, idx)
+            | Self::SGetChar(_, idx)
+            | Self::SGetShort(_, idx)
+            | Self::SPut(_, idx)
+            | Self::SPutWide(_, idx)
+            | Self::SPutObject(_, idx)
+            | Self::SPutBoolean(_, idx)
+            | Self::SPutByte(_, idx)
+            | Self::SPutChar(_, idx)
+            | Self::SPutShort(_, idx)
+            | Self::IGetVolatile(_, _, idx)
+            | Self::IPutVolatile(_, _, idx)
+            | Self::SGetVolatile(_, idx)
+            | Self::SPutVolatile(_, idx)
+            | Self::IGetObjectVolatile(_, _, idx)
+            | Self::IGetWideVolatile(_, _, idx)
+            | Self::IPutWideVolatile(_, _, idx)
+            | Self::SGetWideVolatile(_, idx)
+            | Self::SPutWideVolatile(_, idx)
+            | Self::IPutObjectVolatile(_, _, idx)
+            | Self::SGetObjectVolatile(_, idx)
+            | Self::SPutObjectVolatile(_, idx) => vec![Reference::Field(*idx)],
+
+            Self::InvokeVirtual { method, .. }
+            | Self::InvokeSuper { method, .. }
+            | Self::InvokeDirect { method, .. }
+            | Self::InvokeStatic { method, .. }
+            | Self::InvokeInterface { method, .. }
+            | Self::InvokeVirtualRange { method, .. }
+            | Self::InvokeSuperRange { method, .. }
+            | Self::InvokeDirectRange { method, .. }
+            | Self::InvokeStaticRange { method, .. }
+            | Self::InvokeInterfaceRange { method, .. }
+            | Self::InvokeDirectEmpty { method, .. }
+            | Self::InvokeObjectInitRange { method, .. } => vec![Reference::Method(*method)],
+
+            Self::InvokePolymorphic { method, proto, .. } | Self::InvokePolymorphicRange { method, proto, .. } => {
+                vec![Reference::Method(*method), Reference::Proto(*proto)]
+            }
+            Self::InvokeCustom { call_site, .. } | Self::InvokeCustomRange { call_site, .. } => vec![Reference::CallSite(*call_site)],
+            Self::ConstMethodHandle(_, idx) => vec![Reference::MethodHandle(*idx)],
+            Self::ConstMethodType(_, idx) => vec![Reference::Proto(*idx)],
+
+            // the kind of reference is in the top two bits
+            Self::ThrowVerificationError(kind, idx) => match kind >> 6 {
+                1 => vec![Reference::Type(*idx)],
+                2 => vec![Reference::Field(*idx)],
+                3 => vec![Reference::Method(*idx)],
+                _ => vec![],
+            },
+
+            _ => vec![],
+        }
+    }
+
+    /// Rewrite the constant pool indices the instruction references
+    ///
+    /// `f` is called with each of [`references`][`Self::references`] and
+    /// returns the new index into the same pool. A `const-string` becomes a
+    /// `const-string/jumbo` when the new string index needs it, which changes
+    /// the length of the instruction. Fails without modifying the instruction
+    /// if any other new index does not fit in 16 bits.
+    ///
+    /// ```
+    /// use dalvik::references::Reference;
+    /// use dalvik::Instruction;
+    ///
+    /// let mut inst = Instruction::ConstString(0, 0x10);
+    /// inst.map_references(|r| r.index() + 0x10000).unwrap();
+    /// assert_eq!(inst, Instruction::ConstStringJumbo(0, 0x10010));
+    /// assert_eq!(inst.references(), [Reference::String(0x10010)]);
+    /// ```
+    pub fn map_references(&mut self, mut f: impl FnMut(Reference) -> u32) -> Result<(), Error> {
+        let mut refs = self.references();
+        for r in &mut refs {
+            let idx = f(*r);
+            if !matches!(r, Reference::String(_)) && idx > u16::MAX.into() {
+                return Err(Error::OutOfRange { value: idx.into(), bits: 16 });
+            }
+            *r = r.with_index(idx);
+        }
+
+        if let (Self::ConstString(dst, _), [Reference::String(new)]) = (&*self, refs.as_slice()) {
+            *self = match u16::try_from(*new) {
+                Ok(new) => Self::ConstString(*dst, new),
+                Err(_) => Self::ConstStringJumbo(*dst, *new),
+            };
+            return Ok(());
+        }
+
+        let index = |r: Reference| r.index() as u16;
+        match (self, refs.as_slice()) {
+            (Self::ConstStringJumbo(_, idx), [Reference::String(new)]) => *idx = *new,
+
+            (Self::InvokePolymorphic { method, proto, .. } | Self::InvokePolymorphicRange { method, proto, .. }, [new_method, new_proto]) => {
+                *method = index(*new_method);
+                *proto = index(*new_proto);
+            }
+
+            (
+                Self::ConstClass(_, idx)
+                | Self::CheckCast(_, idx)
+                | Self::InstanceOf(_, _, idx)
+                | Self::NewInstance(_, idx)
+                | Self::NewArray(_, _, idx)
+                | Self::FilledNewArray { ty: idx, .. }
+                | Self::FilledNewArrayRange { ty: idx, .. }
+                | Self::IGet(_, _, idx)
+                | Self::IGetWide(_, _, idx)
+                | Self::IGetObject(_, _, idx)
+                | Self::IGetBoolean(_, _, idx)
+                | Self::IGetByte(_, _, idx)
+                | Self::IGetChar(_, _, idx)
+                | Self::IGetShort(_, _, idx)
+                | Self::IPut(_, _, idx)
+                | Self::IPutWide(_, _, idx)
+                | Self::IPutObject(_, _, idx)
+                | Self::IPutBoolean(_, _, idx)
+                | Self::IPutByte(_, _, idx)
+                | Self::IPutChar(_, _, idx)
+                | Self::IPutShort(_, _, idx)
+                | Self::SGet(_, idx)
+                | Self::SGetWide(_, idx)
+                | Self::SGetObject(_, idx)
+                | Self::SGetBoolean(_, idx)
+                | Self::SGetByte(_, idx)
+                | Self::SGetChar(_, idx)
+                | Self::SGetShort(_, idx)
+                | Self::SPut(_, idx)
+                | Self::SPutWide(_, idx)
+                | Self::SPutObject(_, idx)
+                | Self::SPutBoolean(_, idx)
+                | Self::SPutByte(_, idx)
+                | Self::SPutChar(_, idx)
+                | Self::SPutShort(_, idx)
+                | Self::IGetVolatile(_, _, idx)
+                | Self::IPutVolatile(_, _, idx)
+                | Self::SGetVolatile(_, idx)
+                | Self::SPutVolatile(_, idx)
+                | Self::IGetObjectVolatile(_, _, idx)
+                | Self::IGetWideVolatile(_, _, idx)
+                | Self::IPutWideVolatile(_, _, idx)
+                | Self::SGetWideVolatile(_, idx)
+                | Self::SPutWideVolatile(_, idx)
+                | Self::IPutObjectVolatile(_, _, idx)
+                | Self::SGetObjectVolatile(_, idx)
+                | Self::SPutObjectVolatile(_, idx)
+                | Self::InvokeVirtual { method: idx, .. }
+                | Self::InvokeSuper { method: idx, .. }
+                | Self::InvokeDirect { method: idx, .. }
+                | Self::InvokeStatic { method: idx, .. }
+                | Self::InvokeInterface { method: idx, .. }
+                | Self::InvokeVirtualRange { method: idx, .. }
+                | Self::InvokeSuperRange { method: idx, .. }
+                | Self::InvokeDirectRange { method: idx, .. }
+                | Self::InvokeStaticRange { method: idx, .. }
+                | Self::InvokeInterfaceRange { method: idx, .. }
+                | Self::InvokeDirectEmpty { method: idx, .. }
+                | Self::InvokeObjectInitRange { method: idx, .. }
+                | Self::InvokeCustom { call_site: idx, .. }
+                | Self::InvokeCustomRange { call_site: idx, .. }
+                | Self::ConstMethodHandle(_, idx)
+                | Self::ConstMethodType(_, idx)
+                | Self::ThrowVerificationError(_, idx),
+                [new],
+            ) => *idx = index(*new),
+
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
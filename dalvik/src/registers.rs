@@ -0,0 +1,873 @@
+//! Register operands of instructions: what they read and write, the kind of
+//! value they hold, and renumbering
+
+use crate::{encode::Error, Instruction};
+
+/// A register read or written by an [`Instruction`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Register {
+    /// Virtual register `vN`
+    V(u16),
+    /// The implicit result register, written by invokes and
+    /// `filled-new-array` and read by the `move-result*` following them
+    Result,
+}
+
+impl std::fmt::Display for Register {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Register::V(r) => f.write_fmt(format_args!("v{r}")),
+            Register::Result => f.write_str("result"),
+        }
+    }
+}
+
+/// Kind of value an instruction expects in, or stores to, a register operand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    /// An `int`, `boolean`, `byte`, `char` or `short`
+    Int,
+    /// A `float`
+    Float,
+    /// A `long`, in a register pair
+    Long,
+    /// A `double`, in a register pair
+    Double,
+    /// An object or array reference
+    Reference,
+    /// An untyped 32-bit value, e.g. the operands of `move` or `aput`. For
+    /// `if-eq`, `if-ne`, `if-eqz` and `if-nez` this may also be a reference.
+    Any,
+    /// An untyped 64-bit value in a register pair, e.g. the operands of
+    /// `move-wide` or `aput-wide`
+    AnyWide,
+    /// Depends on a method prototype or array type rather than the
+    /// instruction: invoke arguments and results, and `filled-new-array`
+    /// elements. Wide arguments are given as both registers of their pair.
+    Unknown,
+}
+
+impl Category {
+    /// Whether the value occupies a register pair, in which case the operand
+    /// names the first register of the pair
+    pub fn is_wide(self) -> bool {
+        matches!(self, Category::Long | Category::Double | Category::AnyWide)
+    }
+}
+
+/// Whether an operand is read or written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Role {
+    /// The register is read
+    Source,
+    /// The register is written
+    Destination,
+}
+
+/// A register operand of an [`Instruction`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Operand {
+    /// The register, the first of the pair for wide values
+    pub register: Register,
+    /// Kind of value held in the register
+    pub category: Category,
+    /// Whether the register is read or written
+    pub role: Role,
+}
+
+impl Instruction {
+    /// Registers read by the instruction, in operand order
+    ///
+    /// Wide (long and double) operands contribute both registers of their
+    /// pair, e.g. `v4, v5` for `return-wide v4`.
+    pub fn uses(&self) -> Vec<Register> {
+        self.registers(Role::Source)
+    }
+
+    /// Registers written by the instruction, both registers of a pair for wide
+    /// results
+    pub fn defs(&self) -> Vec<Register> {
+        self.registers(Role::Destination)
+    }
+
+    fn registers(&self, role: Role) -> Vec<Register> {
+        let mut regs = Vec::new();
+        for op in self.operands().into_iter().filter(|op| op.role == role) {
+            regs.push(op.register);
+            if let (Register::V(r), true) = (op.register, op.category.is_wide()) {
+                regs.push(Register::V(r.wrapping_add(1)));
+            }
+        }
+        regs
+    }
+
+    /// Register operands of the instruction, with the kind of value each holds
+    ///
+    /// Destinations are listed before sources, otherwise operands are in
+    /// mnemonic order. The first operand of a `/2addr` instruction is both a
+    /// destination and a source, and is listed as both.
+    ///
+    /// ```
+    /// use dalvik::registers::{Category, Operand, Register, Role};
+    /// use dalvik::Instruction;
+    ///
+    /// let ops = Instruction::IntToLong(0, 2).operands();
+    /// assert_eq!(
+    ///     ops,
+    ///     [
+    ///         Operand { register: Register::V(0), category: Category::Long, role: Role::Destination },
+    ///         Operand { register: Register::V(2), category: Category::Int, role: Role::Source },
+    ///     ]
+    /// );
+    /// ```
+    pub fn operands(&self) -> Vec<Operand> {
+        use Category::{Any, AnyWide, Double, Float, Int, Long, Reference, Unknown};
+
+        let mut ops = Operands(Vec::with_capacity(3));
+        match self {
+            Self::Nop
+            | Self::ReturnVoid
+            | Self::Goto(_)
+            | Self::Goto16(_)
+            | Self::Goto32(_)
+            | Self::ReturnVoidNoBarrier
+            | Self::ReturnVoidBarrier
+            | Self::Breakpoint
+            | Self::ThrowVerificationError(_, _)
+            | Self::PackedSwitchPayload { .. }
+            | Self::SparseSwitchPayload { .. }
+            | Self::FillArrayDataPayload { .. } => &mut ops,
+
+            Self::Move(dst, src) => ops.dst(Any, *dst).src(Any, *src),
+            Self::MoveFrom16(dst, src) => ops.dst(Any, *dst).src(Any, *src),
+            Self::Move16(dst, src) => ops.dst(Any, *dst).src(Any, *src),
+            Self::MoveWide(dst, src) => ops.dst(AnyWide, *dst).src(AnyWide, *src),
+            Self::MoveWideFrom16(dst, src) => ops.dst(AnyWide, *dst).src(AnyWide, *src),
+            Self::MoveWide16(dst, src) => ops.dst(AnyWide, *dst).src(AnyWide, *src),
+            Self::MoveObject(dst, src) => ops.dst(Reference, *dst).src(Reference, *src),
+            Self::MoveObjectFrom16(dst, src) => ops.dst(Reference, *dst).src(Reference, *src),
+            Self::MoveObject16(dst, src) => ops.dst(Reference, *dst).src(Reference, *src),
+
+            Self::MoveResult(dst) => ops.dst(Any, *dst).src_result(Any),
+            Self::MoveResultWide(dst) => ops.dst(AnyWide, *dst).src_result(AnyWide),
+            Self::MoveResultObject(dst) => ops.dst(Reference, *dst).src_result(Reference),
+
+            Self::Const4(dst, _) | Self::Const16(dst, _) | Self::Const(dst, _) | Self::ConstHigh16(dst, _) => ops.dst(Any, *dst),
+            Self::ConstWide16(dst, _) | Self::ConstWide32(dst, _) | Self::ConstWide(dst, _) | Self::ConstWideHigh16(dst, _) => ops.dst(AnyWide, *dst),
+
+            Self::MoveException(dst)
+            | Self::ConstString(dst, _)
+            | Self::ConstStringJumbo(dst, _)
+            | Self::ConstClass(dst, _)
+            | Self::NewInstance(dst, _)
+            | Self::ConstMethodHandle(dst, _)
+            | Self::ConstMethodType(dst, _) => ops.dst(Reference, *dst),
+
+            Self::SGet(dst, _) | Self::SGetVolatile(dst, _) => ops.dst(Any, *dst),
+            Self::SGetWide(dst, _) | Self::SGetWideVolatile(dst, _) => ops.dst(AnyWide, *dst),
+            Self::SGetObject(dst, _) | Self::SGetObjectVolatile(dst, _) => ops.dst(Reference, *dst),
+            Self::SGetBoolean(dst, _) | Self::SGetByte(dst, _) | Self::SGetChar(dst, _) | Self::SGetShort(dst, _) => ops.dst(Int, *dst),
+
+            Self::SPut(src, _) | Self::SPutVolatile(src, _) => ops.src(Any, *src),
+            Self::SPutWide(src, _) | Self::SPutWideVolatile(src, _) => ops.src(AnyWide, *src),
+            Self::SPutObject(src, _) | Self::SPutObjectVolatile(src, _) => ops.src(Reference, *src),
+            Self::SPutBoolean(src, _) | Self::SPutByte(src, _) | Self::SPutChar(src, _) | Self::SPutShort(src, _) => ops.src(Int, *src),
+
+            Self::Return(src) => ops.src(Any, *src),
+            Self::ReturnWide(src) => ops.src(AnyWide, *src),
+
+            Self::ReturnObject(src)
+            | Self::MonitorEnter(src)
+            | Self::MonitorExit(src)
+            | Self::CheckCast(src, _)
+            | Self::FillArrayData(src, _)
+            | Self::Throw(src) => ops.src(Reference, *src),
+
+            Self::PackedSwitch(src, _) | Self::SparseSwitch(src, _) => ops.src(Int, *src),
+
+            // equality also compares references, to each other or null
+            Self::IfEq(a, b, _) | Self::IfNe(a, b, _) => ops.src(Any, *a).src(Any, *b),
+            Self::IfLt(a, b, _) | Self::IfGe(a, b, _) | Self::IfGt(a, b, _) | Self::IfLe(a, b, _) => ops.src(Int, *a).src(Int, *b),
+            Self::IfEqz(src, _) | Self::IfNez(src, _) => ops.src(Any, *src),
+            Self::IfLtz(src, _) | Self::IfGez(src, _) | Self::IfGtz(src, _) | Self::IfLez(src, _) => ops.src(Int, *src),
+
+            Self::InstanceOf(dst, src, _) | Self::ArrayLength(dst, src) => ops.dst(Int, *dst).src(Reference, *src),
+            Self::NewArray(dst, size, _) => ops.dst(Reference, *dst).src(Int, *size),
+
+            Self::NegInt(dst, src) | Self::NotInt(dst, src) | Self::IntTobyte(dst, src) | Self::IntTochar(dst, src) | Self::IntToshort(dst, src) => {
+                ops.dst(Int, *dst).src(Int, *src)
+            }
+            Self::NegLong(dst, src) | Self::NotLong(dst, src) => ops.dst(Long, *dst).src(Long, *src),
+            Self::NegFloat(dst, src) => ops.dst(Float, *dst).src(Float, *src),
+            Self::NegDouble(dst, src) => ops.dst(Double, *dst).src(Double, *src),
+            Self::IntToLong(dst, src) => ops.dst(Long, *dst).src(Int, *src),
+            Self::IntToFloat(dst, src) => ops.dst(Float, *dst).src(Int, *src),
+            Self::IntToDouble(dst, src) => ops.dst(Double, *dst).src(Int, *src),
+            Self::LongToInt(dst, src) => ops.dst(Int, *dst).src(Long, *src),
+            Self::LongToFloat(dst, src) => ops.dst(Float, *dst).src(Long, *src),
+            Self::LongToDouble(dst, src) => ops.dst(Double, *dst).src(Long, *src),
+            Self::FloatToInt(dst, src) => ops.dst(Int, *dst).src(Float, *src),
+            Self::FloatToLong(dst, src) => ops.dst(Long, *dst).src(Float, *src),
+            Self::FloatToDouble(dst, src) => ops.dst(Double, *dst).src(Float, *src),
+            Self::DoubleToInt(dst, src) => ops.dst(Int, *dst).src(Double, *src),
+            Self::DoubleToLong(dst, src) => ops.dst(Long, *dst).src(Double, *src),
+            Self::DoubleToFloat(dst, src) => ops.dst(Float, *dst).src(Double, *src),
+
+            Self::IGet(dst, obj, _) | Self::IGetQuick(dst, obj, _) | Self::IGetVolatile(dst, obj, _) => ops.dst(Any, *dst).src(Reference, *obj),
+            Self::IGetWide(dst, obj, _) | Self::IGetWideQuick(dst, obj, _) | Self::IGetWideVolatile(dst, obj, _) => ops.dst(AnyWide, *dst).src(Reference, *obj),
+            Self::IGetObject(dst, obj, _) | Self::IGetObjectQuick(dst, obj, _) | Self::IGetObjectVolatile(dst, obj, _) => {
+                ops.dst(Reference, *dst).src(Reference, *obj)
+            }
+            Self::IGetBoolean(dst, obj, _)
+            | Self::IGetByte(dst, obj, _)
+            | Self::IGetChar(dst, obj, _)
+            | Self::IGetShort(dst, obj, _)
+            | Self::IGetBooleanQuick(dst, obj, _)
+            | Self::IGetByteQuick(dst, obj, _)
+            | Self::IGetCharQuick(dst, obj, _)
+            | Self::IGetShortQuick(dst, obj, _) => ops.dst(Int, *dst).src(Reference, *obj),
+
+            Self::IPut(src, obj, _) | Self::IPutQuick(src, obj, _) | Self::IPutVolatile(src, obj, _) => ops.src(Any, *src).src(Reference, *obj),
+            Self::IPutWide(src, obj, _) | Self::IPutWideQuick(src, obj, _) | Self::IPutWideVolatile(src, obj, _) => ops.src(AnyWide, *src).src(Reference, *obj),
+            Self::IPutObject(src, obj, _) | Self::IPutObjectQuick(src, obj, _) | Self::IPutObjectVolatile(src, obj, _) => {
+                ops.src(Reference, *src).src(Reference, *obj)
+            }
+            Self::IPutBoolean(src, obj, _)
+            | Self::IPutByte(src, obj, _)
+            | Self::IPutChar(src, obj, _)
+            | Self::IPutShort(src, obj, _)
+            | Self::IPutBooleanQuick(src, obj, _)
+            | Self::IPutByteQuick(src, obj, _)
+            | Self::IPutCharQuick(src, obj, _)
+            | Self::IPutShortQuick(src, obj, _) => ops.src(Int, *src).src(Reference, *obj),
+
+            Self::AGet(dst, arr, idx) => ops.dst(Any, *dst).src(Reference, *arr).src(Int, *idx),
+            Self::AGetWide(dst, arr, idx) => ops.dst(AnyWide, *dst).src(Reference, *arr).src(Int, *idx),
+            Self::AGetObject(dst, arr, idx) => ops.dst(Reference, *dst).src(Reference, *arr).src(Int, *idx),
+            Self::AGetBoolean(dst, arr, idx) | Self::AGetByte(dst, arr, idx) | Self::AGetChar(dst, arr, idx) | Self::AGetShort(dst, arr, idx) => {
+                ops.dst(Int, *dst).src(Reference, *arr).src(Int, *idx)
+            }
+
+            Self::APut(src, arr, idx) => ops.src(Any, *src).src(Reference, *arr).src(Int, *idx),
+            Self::APutWide(src, arr, idx) => ops.src(AnyWide, *src).src(Reference, *arr).src(Int, *idx),
+            Self::APutObject(src, arr, idx) => ops.src(Reference, *src).src(Reference, *arr).src(Int, *idx),
+            Self::APutBoolean(src, arr, idx) | Self::APutByte(src, arr, idx) | Self::APutChar(src, arr, idx) | Self::APutShort(src, arr, idx) => {
+                ops.src(Int, *src).src(Reference, *arr).src(Int, *idx)
+            }
+
+            Self::CmplFloat(dst, a, b) | Self::CmpgFloat(dst, a, b) => ops.dst(Int, *dst).src(Float, *a).src(Float, *b),
+            Self::CmplDouble(dst, a, b) | Self::CmpgDouble(dst, a, b) => ops.dst(Int, *dst).src(Double, *a).src(Double, *b),
+            Self::CmpLong(dst, a, b) => ops.dst(Int, *dst).src(Long, *a).src(Long, *b),
+
+            Self::AddInt(dst, a, b)
+            | Self::SubInt(dst, a, b)
+            | Self::MulInt(dst, a, b)
+            | Self::DivInt(dst, a, b)
+            | Self::RemInt(dst, a, b)
+            | Self::AndInt(dst, a, b)
+            | Self::OrInt(dst, a, b)
+            | Self::XorInt(dst, a, b)
+            | Self::ShlInt(dst, a, b)
+            | Self::ShrInt(dst, a, b)
+            | Self::UshrInt(dst, a, b) => ops.dst(Int, *dst).src(Int, *a).src(Int, *b),
+
+            Self::AddLong(dst, a, b)
+            | Self::SubLong(dst, a, b)
+            | Self::MulLong(dst, a, b)
+            | Self::DivLong(dst, a, b)
+            | Self::RemLong(dst, a, b)
+            | Self::AndLong(dst, a, b)
+            | Self::OrLong(dst, a, b)
+            | Self::XorLong(dst, a, b) => ops.dst(Long, *dst).src(Long, *a).src(Long, *b),
+
+            // the shift distance is an int
+            Self::ShlLong(dst, a, b) | Self::ShrLong(dst, a, b) | Self::UshrLong(dst, a, b) => ops.dst(Long, *dst).src(Long, *a).src(Int, *b),
+
+            Self::AddFloat(dst, a, b) | Self::SubFloat(dst, a, b) | Self::MulFloat(dst, a, b) | Self::DivFloat(dst, a, b) | Self::RemFloat(dst, a, b) => {
+                ops.dst(Float, *dst).src(Float, *a).src(Float, *b)
+            }
+
+            Self::AddDouble(dst, a, b) | Self::SubDouble(dst, a, b) | Self::MulDouble(dst, a, b) | Self::DivDouble(dst, a, b) | Self::RemDouble(dst, a, b) => {
+                ops.dst(Double, *dst).src(Double, *a).src(Double, *b)
+            }
+
+            Self::AddInt2(a, b)
+            | Self::SubInt2(a, b)
+            | Self::MulInt2(a, b)
+            | Self::DivInt2(a, b)
+            | Self::RemInt2(a, b)
+            | Self::AndInt2(a, b)
+            | Self::OrInt2(a, b)
+            | Self::XorInt2(a, b)
+            | Self::ShlInt2(a, b)
+            | Self::ShrInt2(a, b)
+            | Self::UShrInt2(a, b) => ops.dst(Int, *a).src(Int, *a).src(Int, *b),
+
+            Self::AddLong2(a, b)
+            | Self::SubLong2(a, b)
+            | Self::MulLong2(a, b)
+            | Self::DivLong2(a, b)
+            | Self::RemLong2(a, b)
+            | Self::AndLong2(a, b)
+            | Self::OrLong2(a, b)
+            | Self::XorLong2(a, b) => ops.dst(Long, *a).src(Long, *a).src(Long, *b),
+
+            Self::ShlLong2(a, b) | Self::ShrLong2(a, b) | Self::UShrLong2(a, b) => ops.dst(Long, *a).src(Long, *a).src(Int, *b),
+
+            Self::AddFloat2(a, b) | Self::SubFloat2(a, b) | Self::MulFloat2(a, b) | Self::DivFloat2(a, b) | Self::RemFloat2(a, b) => {
+                ops.dst(Float, *a).src(Float, *a).src(Float, *b)
+            }
+
+            Self::AddDouble2(a, b) | Self::SubDouble2(a, b) | Self::MulDouble2(a, b) | Self::DivDouble2(a, b) | Self::RemDouble2(a, b) => {
+                ops.dst(Double, *a).src(Double, *a).src(Double, *b)
+            }
+
+            Self::AddInt16(dst, src, _)
+            | Self::RsubInt16(dst, src, _)
+            | Self::MulInt16(dst, src, _)
+            | Self::DivInt16(dst, src, _)
+            | Self::RemInt16(dst, src, _)
+            | Self::AndInt16(dst, src, _)
+            | Self::OrInt16(dst, src, _)
+            | Self::XorInt16(dst, src, _) => ops.dst(Int, *dst).src(Int, *src),
+
+            Self::AddInt8(dst, src, _)
+            | Self::RsubInt8(dst, src, _)
+            | Self::MulInt8(dst, src, _)
+            | Self::DivInt8(dst, src, _)
+            | Self::RemInt8(dst, src, _)
+            | Self::AndInt8(dst, src, _)
+            | Self::OrInt8(dst, src, _)
+            | Self::XorInt8(dst, src, _)
+            | Self::ShlInt8(dst, src, _)
+            | Self::ShrInt8(dst, src, _)
+            | Self::UshrInt8(dst, src, _) => ops.dst(Int, *dst).src(Int, *src),
+
+            Self::FilledNewArray { nargs, args, .. } => {
+                ops.dst_result(Reference);
+                ops.srcs(Unknown, &args[..usize::from(*nargs).min(5)])
+            }
+            Self::FilledNewArrayRange { args, .. } => ops.dst_result(Reference).srcs(Unknown, args),
+
+            Self::InvokeVirtual { nargs, args, .. }
+            | Self::InvokeSuper { nargs, args, .. }
+            | Self::InvokeDirect { nargs, args, .. }
+            | Self::InvokeStatic { nargs, args, .. }
+            | Self::InvokeInterface { nargs, args, .. }
+            | Self::InvokePolymorphic { nargs, args, .. }
+            | Self::InvokeCustom { nargs, args, .. }
+            | Self::InvokeVirtualQuick { nargs, args, .. }
+            | Self::InvokeSuperQuick { nargs, args, .. }
+            | Self::ExecuteInline { nargs, args, .. }
+            | Self::InvokeDirectEmpty { nargs, args, .. } => {
+                ops.dst_result(Unknown);
+                ops.srcs(Unknown, &args[..usize::from(*nargs).min(5)])
+            }
+
+            Self::InvokeVirtualRange { args, .. }
+            | Self::InvokeSuperRange { args, .. }
+            | Self::InvokeDirectRange { args, .. }
+            | Self::InvokeStaticRange { args, .. }
+            | Self::InvokeInterfaceRange { args, .. }
+            | Self::InvokePolymorphicRange { args, .. }
+            | Self::InvokeCustomRange { args, .. }
+            | Self::InvokeVirtualQuickRange { args, .. }
+            | Self::InvokeSuperQuickRange { args, .. }
+            | Self::ExecuteInlineRange { args, .. }
+            | Self::InvokeObjectInitRange { args, .. } => ops.dst_result(Unknown).srcs(Unknown, args),
+        };
+
+        ops.0
+    }
+}
+
+impl Instruction {
+    /// Renumber every register operand through `f`
+    ///
+    /// `f` is called once per register field: wide operands are renumbered
+    /// through the first register of their pair, while invoke arguments list
+    /// both registers of a pair. Fails without modifying the instruction if a
+    /// new number does not fit its field, e.g. `v16` in `move vA, vB`, or if a
+    /// range of registers is no longer contiguous.
+    ///
+    /// See [`map_registers_widening`][`Self::map_registers_widening`] to move
+    /// to a wider encoding instead.
+    pub fn map_registers(&mut self, mut f: impl FnMut(u16) -> u16) -> Result<(), Error> {
+        let mut fields = self.register_fields();
+        let mut renumbered = Vec::with_capacity(fields.len());
+        for field in &fields {
+            let new: Vec<u16> = match field {
+                Field::Nibble(reg) | Field::Byte(reg) => vec![f((**reg).into())],
+                Field::Short(reg) => vec![f(**reg)],
+                Field::Nibbles(regs) => regs.iter().map(|reg| f((*reg).into())).collect(),
+                Field::Range(regs) => regs.iter().map(|reg| f(*reg)).collect(),
+            };
+            let bits = match field {
+                Field::Nibble(_) | Field::Nibbles(_) => 4,
+                Field::Byte(_) => 8,
+                Field::Short(_) | Field::Range(_) => 16,
+            };
+            if let Some(value) = new.iter().find(|reg| u32::from(**reg) >> bits != 0) {
+                return Err(Error::OutOfRange { value: (*value).into(), bits });
+            }
+            if matches!(field, Field::Range(_)) && new.windows(2).any(|w| w[0].checked_add(1) != Some(w[1])) {
+                return Err(Error::Malformed);
+            }
+            renumbered.push(new);
+        }
+
+        for (field, new) in fields.iter_mut().zip(renumbered) {
+            match field {
+                Field::Nibble(reg) | Field::Byte(reg) => **reg = new[0] as u8,
+                Field::Short(reg) => **reg = new[0],
+                Field::Nibbles(regs) => regs.iter_mut().zip(new).for_each(|(reg, new)| *reg = new as u8),
+                Field::Range(regs) => **regs = new,
+            }
+        }
+        Ok(())
+    }
+
+    /// Renumber every register operand through `f` as with
+    /// [`map_registers`][`Self::map_registers`], moving to the
+    /// [`widened`][`Self::widened`] encodings as needed for the new numbers
+    ///
+    /// `f` may be called more than once for the same register. Fails if no
+    /// encoding fits, e.g. `if-eq` with `v16`, or `invoke-virtual` arguments
+    /// that do not form a contiguous range for `invoke-virtual/range`.
+    ///
+    /// ```
+    /// use dalvik::Instruction;
+    ///
+    /// let mut inst = Instruction::Move(0, 1);
+    /// inst.map_registers_widening(|r| r + 0xff).unwrap();
+    /// assert_eq!(inst, Instruction::MoveFrom16(0xff, 0x100));
+    /// ```
+    pub fn map_registers_widening(&mut self, mut f: impl FnMut(u16) -> u16) -> Result<(), Error> {
+        loop {
+            match self.map_registers(&mut f) {
+                Err(Error::OutOfRange { value, bits }) => match self.widened() {
+                    Some(wider) => *self = wider,
+                    None => return Err(Error::OutOfRange { value, bits }),
+                },
+                result => return result,
+            }
+        }
+    }
+
+    /// The same instruction in the next encoding with wider register fields,
+    /// if any: `move` to `move/from16` to `move/16`, `const/4` to `const/16`,
+    /// `/2addr` to three-register arithmetic, `/lit16` to `/lit8` if the
+    /// literal fits, and invokes and `filled-new-array` to their `/range`
+    /// forms
+    pub fn widened(&self) -> Option<Instruction> {
+        Some(match self {
+            Self::Move(dst, src) => Self::MoveFrom16(*dst, (*src).into()),
+            Self::MoveFrom16(dst, src) => Self::Move16((*dst).into(), *src),
+            Self::MoveWide(dst, src) => Self::MoveWideFrom16(*dst, (*src).into()),
+            Self::MoveWideFrom16(dst, src) => Self::MoveWide16((*dst).into(), *src),
+            Self::MoveObject(dst, src) => Self::MoveObjectFrom16(*dst, (*src).into()),
+            Self::MoveObjectFrom16(dst, src) => Self::MoveObject16((*dst).into(), *src),
+            Self::Const4(dst, lit) => Self::Const16(*dst, (*lit).into()),
+
+            Self::AddInt2(a, b) => Self::AddInt(*a, *a, *b),
+            Self::SubInt2(a, b) => Self::SubInt(*a, *a, *b),
+            Self::MulInt2(a, b) => Self::MulInt(*a, *a, *b),
+            Self::DivInt2(a, b) => Self::DivInt(*a, *a, *b),
+            Self::RemInt2(a, b) => Self::RemInt(*a, *a, *b),
+            Self::AndInt2(a, b) => Self::AndInt(*a, *a, *b),
+            Self::OrInt2(a, b) => Self::OrInt(*a, *a, *b),
+            Self::XorInt2(a, b) => Self::XorInt(*a, *a, *b),
+            Self::ShlInt2(a, b) => Self::ShlInt(*a, *a, *b),
+            Self::ShrInt2(a, b) => Self::ShrInt(*a, *a, *b),
+            Self::UShrInt2(a, b) => Self::UshrInt(*a, *a, *b),
+            Self::AddLong2(a, b) => Self::AddLong(*a, *a, *b),
+            Self::SubLong2(a, b) => Self::SubLong(*a, *a, *b),
+            Self::MulLong2(a, b) => Self::MulLong(*a, *a, *b),
+            Self::DivLong2(a, b) => Self::DivLong(*a, *a, *b),
+            Self::RemLong2(a, b) => Self::RemLong(*a, *a, *b),
+            Self::AndLong2(a, b) => Self::AndLong(*a, *a, *b),
+            Self::OrLong2(a, b) => Self::OrLong(*a, *a, *b),
+            Self::XorLong2(a, b) => Self::XorLong(*a, *a, *b),
+            Self::ShlLong2(a, b) => Self::ShlLong(*a, *a, *b),
+            Self::ShrLong2(a, b) => Self::ShrLong(*a, *a, *b),
+            Self::UShrLong2(a, b) => Self::UshrLong(*a, *a, *b),
+            Self::AddFloat2(a, b) => Self::AddFloat(*a, *a, *b),
+            Self::SubFloat2(a, b) => Self::SubFloat(*a, *a, *b),
+            Self::MulFloat2(a, b) => Self::MulFloat(*a, *a, *b),
+            Self::DivFloat2(a, b) => Self::DivFloat(*a, *a, *b),
+            Self::RemFloat2(a, b) => Self::RemFloat(*a, *a, *b),
+            Self::AddDouble2(a, b) => Self::AddDouble(*a, *a, *b),
+            Self::SubDouble2(a, b) => Self::SubDouble(*a, *a, *b),
+            Self::MulDouble2(a, b) => Self::MulDouble(*a, *a, *b),
+            Self::DivDouble2(a, b) => Self::DivDouble(*a, *a, *b),
+            Self::RemDouble2(a, b) => Self::RemDouble(*a, *a, *b),
+
+            Self::AddInt16(dst, src, lit) => Self::AddInt8(*dst, *src, i8::try_from(*lit).ok()?),
+            Self::RsubInt16(dst, src, lit) => Self::RsubInt8(*dst, *src, i8::try_from(*lit).ok()?),
+            Self::MulInt16(dst, src, lit) => Self::MulInt8(*dst, *src, i8::try_from(*lit).ok()?),
+            Self::DivInt16(dst, src, lit) => Self::DivInt8(*dst, *src, i8::try_from(*lit).ok()?),
+            Self::RemInt16(dst, src, lit) => Self::RemInt8(*dst, *src, i8::try_from(*lit).ok()?),
+            Self::AndInt16(dst, src, lit) => Self::AndInt8(*dst, *src, i8::try_from(*lit).ok()?),
+            Self::OrInt16(dst, src, lit) => Self::OrInt8(*dst, *src, i8::try_from(*lit).ok()?),
+            Self::XorInt16(dst, src, lit) => Self::XorInt8(*dst, *src, i8::try_from(*lit).ok()?),
+
+            Self::FilledNewArray { ty, nargs, args } => Self::FilledNewArrayRange {
+                ty: *ty,
+                args: range(args, *nargs),
+            },
+            Self::InvokeVirtual { method, nargs, args } => Self::InvokeVirtualRange {
+                method: *method,
+                args: range(args, *nargs),
+            },
+            Self::InvokeSuper { method, nargs, args } => Self::InvokeSuperRange {
+                method: *method,
+                args: range(args, *nargs),
+            },
+            Self::InvokeDirect { method, nargs, args } => Self::InvokeDirectRange {
+                method: *method,
+                args: range(args, *nargs),
+            },
+            Self::InvokeStatic { method, nargs, args } => Self::InvokeStaticRange {
+                method: *method,
+                args: range(args, *nargs),
+            },
+            Self::InvokeInterface { method, nargs, args } => Self::InvokeInterfaceRange {
+                method: *method,
+                args: range(args, *nargs),
+            },
+            Self::InvokePolymorphic { method, proto, nargs, args } => Self::InvokePolymorphicRange {
+                method: *method,
+                proto: *proto,
+                args: range(args, *nargs),
+            },
+            Self::InvokeCustom { call_site, nargs, args } => Self::InvokeCustomRange {
+                call_site: *call_site,
+                args: range(args, *nargs),
+            },
+
+            _ => return None,
+        })
+    }
+
+    fn register_fields(&mut self) -> Vec<Field<'_>> {
+        use Field::{Byte, Nibble, Nibbles, Range, Short};
+
+        match self {
+            Self::Move(a, b)
+            | Self::MoveWide(a, b)
+            | Self::MoveObject(a, b)
+            | Self::ArrayLength(a, b)
+            | Self::NegInt(a, b)
+            | Self::NotInt(a, b)
+            | Self::NegLong(a, b)
+            | Self::NotLong(a, b)
+            | Self::NegFloat(a, b)
+            | Self::NegDouble(a, b)
+            | Self::IntToLong(a, b)
+            | Self::IntToFloat(a, b)
+            | Self::IntToDouble(a, b)
+            | Self::LongToInt(a, b)
+            | Self::LongToFloat(a, b)
+            | Self::LongToDouble(a, b)
+            | Self::FloatToInt(a, b)
+            | Self::FloatToLong(a, b)
+            | Self::FloatToDouble(a, b)
+            | Self::DoubleToInt(a, b)
+            | Self::DoubleToLong(a, b)
+            | Self::DoubleToFloat(a, b)
+            | Self::IntTobyte(a, b)
+            | Self::IntTochar(a, b)
+            | Self::IntToshort(a, b)
+            | Self::AddInt2(a, b)
+            | Self::SubInt2(a, b)
+            | Self::MulInt2(a, b)
+            | Self::DivInt2(a, b)
+            | Self::RemInt2(a, b)
+            | Self::AndInt2(a, b)
+            | Self::OrInt2(a, b)
+            | Self::XorInt2(a, b)
+            | Self::ShlInt2(a, b)
+            | Self::ShrInt2(a, b)
+            | Self::UShrInt2(a, b)
+            | Self::AddLong2(a, b)
+            | Self::SubLong2(a, b)
+            | Self::MulLong2(a, b)
+            | Self::DivLong2(a, b)
+            | Self::RemLong2(a, b)
+            | Self::AndLong2(a, b)
+            | Self::OrLong2(a, b)
+            | Self::XorLong2(a, b)
+            | Self::ShlLong2(a, b)
+            | Self::ShrLong2(a, b)
+            | Self::UShrLong2(a, b)
+            | Self::AddFloat2(a, b)
+            | Self::SubFloat2(a, b)
+            | Self::MulFloat2(a, b)
+            | Self::DivFloat2(a, b)
+            | Self::RemFloat2(a, b)
+            | Self::AddDouble2(a, b)
+            | Self::SubDouble2(a, b)
+            | Self::MulDouble2(a, b)
+            | Self::DivDouble2(a, b)
+            | Self::RemDouble2(a, b) => vec![Nibble(a), Nibble(b)],
+
+            Self::InstanceOf(a, b, _)
+            | Self::NewArray(a, b, _)
+            | Self::IfEq(a, b, _)
+            | Self::IfNe(a, b, _)
+            | Self::IfLt(a, b, _)
+            | Self::IfGe(a, b, _)
+            | Self::IfGt(a, b, _)
+            | Self::IfLe(a, b, _)
+            | Self::IGet(a, b, _)
+            | Self::IGetWide(a, b, _)
+            | Self::IGetObject(a, b, _)
+            | Self::IGetBoolean(a, b, _)
+            | Self::IGetByte(a, b, _)
+            | Self::IGetChar(a, b, _)
+            | Self::IGetShort(a, b, _)
+            | Self::IPut(a, b, _)
+            | Self::IPutWide(a, b, _)
+            | Self::IPutObject(a, b, _)
+            | Self::IPutBoolean(a, b, _)
+            | Self::IPutByte(a, b, _)
+            | Self::IPutChar(a, b, _)
+            | Self::IPutShort(a, b, _)
+            | Self::IGetQuick(a, b, _)
+            | Self::IGetWideQuick(a, b, _)
+            | Self::IGetObjectQuick(a, b, _)
+            | Self::IPutQuick(a, b, _)
+            | Self::IPutWideQuick(a, b, _)
+            | Self::IPutObjectQuick(a, b, _)
+            | Self::IPutBooleanQuick(a, b, _)
+            | Self::IPutByteQuick(a, b, _)
+            | Self::IPutCharQuick(a, b, _)
+            | Self::IPutShortQuick(a, b, _)
+            | Self::IGetBooleanQuick(a, b, _)
+            | Self::IGetByteQuick(a, b, _)
+            | Self::IGetCharQuick(a, b, _)
+            | Self::IGetShortQuick(a, b, _)
+            | Self::IGetVolatile(a, b, _)
+            | Self::IPutVolatile(a, b, _)
+            | Self::IGetObjectVolatile(a, b, _)
+            | Self::IGetWideVolatile(a, b, _)
+            | Self::IPutWideVolatile(a, b, _)
+            | Self::IPutObjectVolatile(a, b, _) => vec![Nibble(a), Nibble(b)],
+
+            Self::AddInt16(a, b, _)
+            | Self::RsubInt16(a, b, _)
+            | Self::MulInt16(a, b, _)
+            | Self::DivInt16(a, b, _)
+            | Self::RemInt16(a, b, _)
+            | Self::AndInt16(a, b, _)
+            | Self::OrInt16(a, b, _)
+            | Self::XorInt16(a, b, _) => vec![Nibble(a), Nibble(b)],
+
+            Self::Const4(a, _) => vec![Nibble(a)],
+
+            Self::MoveFrom16(a, b) | Self::MoveWideFrom16(a, b) | Self::MoveObjectFrom16(a, b) => vec![Byte(a), Short(b)],
+            Self::Move16(a, b) | Self::MoveWide16(a, b) | Self::MoveObject16(a, b) => vec![Short(a), Short(b)],
+
+            Self::MoveResult(a)
+            | Self::MoveResultWide(a)
+            | Self::MoveResultObject(a)
+            | Self::MoveException(a)
+            | Self::Return(a)
+            | Self::ReturnWide(a)
+            | Self::ReturnObject(a)
+            | Self::MonitorEnter(a)
+            | Self::MonitorExit(a)
+            | Self::Throw(a)
+            | Self::Const16(a, _)
+            | Self::ConstHigh16(a, _)
+            | Self::ConstWide16(a, _)
+            | Self::ConstWideHigh16(a, _)
+            | Self::ConstString(a, _)
+            | Self::ConstClass(a, _)
+            | Self::CheckCast(a, _)
+            | Self::NewInstance(a, _)
+            | Self::ConstMethodHandle(a, _)
+            | Self::ConstMethodType(a, _)
+            | Self::IfEqz(a, _)
+            | Self::IfNez(a, _)
+            | Self::IfLtz(a, _)
+            | Self::IfGez(a, _)
+            | Self::IfGtz(a, _)
+            | Self::IfLez(a, _)
+            | Self::SGet(a, _)
+            | Self::SGetWide(a, _)
+            | Self::SGetObject(a, _)
+            | Self::SGetBoolean(a, _)
+            | Self::SGetByte(a, _)
+            | Self::SGetChar(a, _)
+            | Self::SGetShort(a, _)
+            | Self::SPut(a, _)
+            | Self::SPutWide(a, _)
+            | Self::SPutObject(a, _)
+            | Self::SPutBoolean(a, _)
+            | Self::SPutByte(a, _)
+            | Self::SPutChar(a, _)
+            | Self::SPutShort(a, _)
+            | Self::SGetVolatile(a, _)
+            | Self::SPutVolatile(a, _)
+            | Self::SGetWideVolatile(a, _)
+            | Self::SPutWideVolatile(a, _)
+            | Self::SGetObjectVolatile(a, _)
+            | Self::SPutObjectVolatile(a, _) => vec![Byte(a)],
+
+            Self::Const(a, _) | Self::ConstWide32(a, _) | Self::ConstStringJumbo(a, _) => vec![Byte(a)],
+            Self::ConstWide(a, _) => vec![Byte(a)],
+            Self::FillArrayData(a, _) | Self::PackedSwitch(a, _) | Self::SparseSwitch(a, _) => vec![Byte(a)],
+
+            Self::CmplFloat(a, b, c)
+            | Self::CmpgFloat(a, b, c)
+            | Self::CmplDouble(a, b, c)
+            | Self::CmpgDouble(a, b, c)
+            | Self::CmpLong(a, b, c)
+            | Self::AGet(a, b, c)
+            | Self::AGetWide(a, b, c)
+            | Self::AGetObject(a, b, c)
+            | Self::AGetBoolean(a, b, c)
+            | Self::AGetByte(a, b, c)
+            | Self::AGetChar(a, b, c)
+            | Self::AGetShort(a, b, c)
+            | Self::APut(a, b, c)
+            | Self::APutWide(a, b, c)
+            | Self::APutObject(a, b, c)
+            | Self::APutBoolean(a, b, c)
+            | Self::APutByte(a, b, c)
+            | Self::APutChar(a, b, c)
+            | Self::APutShort(a, b, c)
+            | Self::AddInt(a, b, c)
+            | Self::SubInt(a, b, c)
+            | Self::MulInt(a, b, c)
+            | Self::DivInt(a, b, c)
+            | Self::RemInt(a, b, c)
+            | Self::AndInt(a, b, c)
+            | Self::OrInt(a, b, c)
+            | Self::XorInt(a, b, c)
+            | Self::ShlInt(a, b, c)
+            | Self::ShrInt(a, b, c)
+            | Self::UshrInt(a, b, c)
+            | Self::AddLong(a, b, c)
+            | Self::SubLong(a, b, c)
+            | Self::MulLong(a, b, c)
+            | Self::DivLong(a, b, c)
+            | Self::RemLong(a, b, c)
+            | Self::AndLong(a, b, c)
+            | Self::OrLong(a, b, c)
+            | Self::XorLong(a, b, c)
+            | Self::ShlLong(a, b, c)
+            | Self::ShrLong(a, b, c)
+            | Self::UshrLong(a, b, c)
+            | Self::AddFloat(a, b, c)
+            | Self::SubFloat(a, b, c)
+            | Self::MulFloat(a, b, c)
+            | Self::DivFloat(a, b, c)
+            | Self::RemFloat(a, b, c)
+            | Self::AddDouble(a, b, c)
+            | Self::SubDouble(a, b, c)
+            | Self::MulDouble(a, b, c)
+            | Self::DivDouble(a, b, c)
+            | Self::RemDouble(a, b, c) => vec![Byte(a), Byte(b), Byte(c)],
+
+            Self::AddInt8(a, b, _)
+            | Self::RsubInt8(a, b, _)
+            | Self::MulInt8(a, b, _)
+            | Self::DivInt8(a, b, _)
+            | Self::RemInt8(a, b, _)
+            | Self::AndInt8(a, b, _)
+            | Self::OrInt8(a, b, _)
+            | Self::XorInt8(a, b, _)
+            | Self::ShlInt8(a, b, _)
+            | Self::ShrInt8(a, b, _)
+            | Self::UshrInt8(a, b, _) => vec![Byte(a), Byte(b)],
+
+            Self::FilledNewArray { nargs, args, .. }
+            | Self::InvokeVirtual { nargs, args, .. }
+            | Self::InvokeSuper { nargs, args, .. }
+            | Self::InvokeDirect { nargs, args, .. }
+            | Self::InvokeStatic { nargs, args, .. }
+            | Self::InvokeInterface { nargs, args, .. }
+            | Self::InvokePolymorphic { nargs, args, .. }
+            | Self::InvokeCustom { nargs, args, .. }
+            | Self::InvokeVirtualQuick { nargs, args, .. }
+            | Self::InvokeSuperQuick { nargs, args, .. }
+            | Self::ExecuteInline { nargs, args, .. }
+            | Self::InvokeDirectEmpty { nargs, args, .. } => vec![Nibbles(&mut args[..usize::from(*nargs).min(5)])],
+
+            Self::FilledNewArrayRange { args, .. }
+            | Self::InvokeVirtualRange { args, .. }
+            | Self::InvokeSuperRange { args, .. }
+            | Self::InvokeDirectRange { args, .. }
+            | Self::InvokeStaticRange { args, .. }
+            | Self::InvokeInterfaceRange { args, .. }
+            | Self::InvokePolymorphicRange { args, .. }
+            | Self::InvokeCustomRange { args, .. }
+            | Self::InvokeVirtualQuickRange { args, .. }
+            | Self::InvokeSuperQuickRange { args, .. }
+            | Self::ExecuteInlineRange { args, .. }
+            | Self::InvokeObjectInitRange { args, .. } => vec![Range(args)],
+
+            Self::Nop
+            | Self::ReturnVoid
+            | Self::Goto(_)
+            | Self::Goto16(_)
+            | Self::Goto32(_)
+            | Self::ReturnVoidNoBarrier
+            | Self::ReturnVoidBarrier
+            | Self::Breakpoint
+            | Self::ThrowVerificationError(_, _)
+            | Self::PackedSwitchPayload { .. }
+            | Self::SparseSwitchPayload { .. }
+            | Self::FillArrayDataPayload { .. } => vec![],
+        }
+    }
+}
+
+/// A register field of an encoded instruction
+enum Field<'a> {
+    Nibble(&'a mut u8),
+    Byte(&'a mut u8),
+    Short(&'a mut u16),
+    /// Arguments of a non-range invoke
+    Nibbles(&'a mut [u8]),
+    /// Contiguous arguments of a range invoke
+    Range(&'a mut Vec<u16>),
+}
+
+fn range(args: &[u8; 5], nargs: u8) -> Vec<u16> {
+    args[..usize::from(nargs).min(5)].iter().map(|arg| u16::from(*arg)).collect()
+}
+
+struct Operands(Vec<Operand>);
+
+impl Operands {
+    fn push(&mut self, role: Role, category: Category, register: Register) -> &mut Self {
+        self.0.push(Operand { register, category, role });
+        self
+    }
+
+    fn dst(&mut self, category: Category, reg: impl Into<u16>) -> &mut Self {
+        self.push(Role::Destination, category, Register::V(reg.into()))
+    }
+
+    fn src(&mut self, category: Category, reg: impl Into<u16>) -> &mut Self {
+        self.push(Role::Source, category, Register::V(reg.into()))
+    }
+
+    fn srcs<R: Copy + Into<u16>>(&mut self, category: Category, regs: &[R]) -> &mut Self {
+        for reg in regs {
+            self.src(category, *reg);
+        }
+        self
+    }
+
+    fn dst_result(&mut self, category: Category) -> &mut Self {
+        self.push(Role::Destination, category, Register::Result)
+    }
+
+    fn src_result(&mut self, category: Category) -> &mut Self {
+        self.push(Role::Source, category, Register::Result)
+    }
+}
@@ -0,0 +1,346 @@
+//! Smali method listings
+//!
+//! [`listing`] renders a whole method body the way baksmali does, with branch
+//! offsets replaced by labels, try blocks as `.catch` and `.catchall`
+//! directives and payloads as `.packed-switch`, `.sparse-switch` and
+//! `.array-data` blocks, so that it can be reassembled by smali or by
+//! [`assemble_method`][`crate::assemble::assemble_method`].
+//!
+//! ```
+//! use dalvik::smali::listing;
+//! use dalvik::PrettyPrint;
+//!
+//! # struct Dex;
+//! # impl PrettyPrint for Dex {
+//! #     fn method(&self, index: u16) -> (String, String, String, String) { unimplemented!() }
+//! #     fn field(&self, index: u16) -> (String, String, String) { unimplemented!() }
+//! #     fn string(&self, index: u32) -> String { unimplemented!() }
+//! #     fn type_name(&self, index: u16) -> String { unimplemented!() }
+//! # }
+//! // if-eqz v0, +3; return-void; return v0
+//! let code = [0x0038, 0x0003, 0x000e, 0x000f];
+//! let smali = listing(&Dex, 1, &code, &[]).unwrap();
+//! let lines: Vec<_> = smali.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+//! assert_eq!(lines, [".registers 1", "if-eqz v0, :cond_0", "return-void", ":cond_0", "return v0"]);
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::{
+    decode::{self, Decoder},
+    patch::TryBlock,
+    references::Reference,
+    tokens::{escape, TokenKind},
+    ControlFlow, Instruction, PrettyPrint,
+};
+
+/// Listing error
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Error {
+    /// The method body could not be decoded, or one of its branches does not
+    /// land on an instruction
+    Decode(decode::Error),
+    /// A try boundary or catch handler does not land on an instruction
+    NotAnInstruction(usize),
+    /// The switch or `fill-array-data` at that address does not reference a
+    /// payload of its kind
+    MissingPayload(usize),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Decode(e) => f.write_fmt(format_args!("decoding failed: {e}")),
+            Error::NotAnInstruction(addr) => f.write_fmt(format_args!("no instruction at {addr:#x}")),
+            Error::MissingPayload(addr) => f.write_fmt(format_args!("no payload for the instruction at {addr:#x}")),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Label kinds, named and numbered as by baksmali
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Kind {
+    TryStart,
+    TryEnd,
+    Catch,
+    CatchAll,
+    Cond,
+    Goto,
+    PSwitch,
+    SSwitch,
+    PSwitchData,
+    SSwitchData,
+    Array,
+}
+
+impl Kind {
+    fn prefix(self) -> &'static str {
+        match self {
+            Kind::TryStart => "try_start",
+            Kind::TryEnd => "try_end",
+            Kind::Catch => "catch",
+            Kind::CatchAll => "catchall",
+            Kind::Cond => "cond",
+            Kind::Goto => "goto",
+            Kind::PSwitch => "pswitch",
+            Kind::SSwitch => "sswitch",
+            Kind::PSwitchData => "pswitch_data",
+            Kind::SSwitchData => "sswitch_data",
+            Kind::Array => "array",
+        }
+    }
+}
+
+/// Render a method body as smali, from `.registers` up to but excluding
+/// `.end method`
+///
+/// `tries` are the method's try blocks, in the order of the dex `tries` table.
+/// Instructions are rendered from [`PrettyPrint::tokens`], with branch
+/// targets replaced by labels and string literals escaped. Switch payloads that
+/// no switch references keep their case offsets, as they have no switch to
+/// label them from.
+///
+/// Fails if the bytecode cannot be decoded, or if a branch, try boundary or
+/// catch handler does not land on an instruction.
+pub fn listing<T: PrettyPrint + ?Sized>(lookup: &T, registers: u16, bytecode: &[u16], tries: &[TryBlock]) -> Result<String, Error> {
+    let insts = Decoder::new(bytecode).collect::<Result<Vec<_>, _>>().map_err(|(_, e)| Error::Decode(e))?;
+    let index: HashMap<usize, usize> = insts.iter().enumerate().map(|(i, (addr, _))| (*addr, i)).collect();
+
+    let resolve = |from: usize, off: i32| {
+        let to = from as isize + off as isize;
+        match index.get(&(to as usize)) {
+            Some(i) if to >= 0 => Ok(*i),
+            _ => Err(Error::Decode(decode::Error::BadBranchTarget { from, to })),
+        }
+    };
+
+    let mut marks = BTreeSet::new();
+    // payload index to the address of the first switch referencing it
+    let mut referrers = HashMap::new();
+    for (addr, inst) in &insts {
+        let (kind, off) = match (inst, inst.control_flow()) {
+            (Instruction::PackedSwitch(_, off), _) => (Kind::PSwitchData, *off),
+            (Instruction::SparseSwitch(_, off), _) => (Kind::SSwitchData, *off),
+            (Instruction::FillArrayData(_, off), _) => (Kind::Array, *off),
+            (_, ControlFlow::GoTo(off)) => (Kind::Goto, off),
+            (_, ControlFlow::Branch(off)) => (Kind::Cond, off.into()),
+            _ => continue,
+        };
+        let i = resolve(*addr, off)?;
+        let (to, target) = &insts[i];
+        let targets = match (kind, target) {
+            (Kind::PSwitchData, Instruction::PackedSwitchPayload { targets, .. }) => Some((Kind::PSwitch, targets)),
+            (Kind::SSwitchData, Instruction::SparseSwitchPayload { targets, .. }) => Some((Kind::SSwitch, targets)),
+            (Kind::Array, Instruction::FillArrayDataPayload { .. }) => None,
+            (Kind::Goto | Kind::Cond, _) => None,
+            _ => return Err(Error::MissingPayload(*addr)),
+        };
+        marks.insert((kind, *to));
+
+        if let Some((case, targets)) = targets {
+            if referrers.contains_key(&i) {
+                continue;
+            }
+            referrers.insert(i, *addr);
+            for off in targets {
+                marks.insert((case, insts[resolve(*addr, *off)?].0));
+            }
+        }
+    }
+
+    // unreferenced switch payloads are kept, so that the method keeps its size
+    for (i, (addr, inst)) in insts.iter().enumerate() {
+        let kind = match inst {
+            Instruction::PackedSwitchPayload { .. } => Kind::PSwitchData,
+            Instruction::SparseSwitchPayload { .. } => Kind::SSwitchData,
+            _ => continue,
+        };
+        if !referrers.contains_key(&i) {
+            marks.insert((kind, *addr));
+        }
+    }
+
+    let on_inst = |addr: usize| match index.contains_key(&addr) {
+        true => Ok(addr),
+        false => Err(Error::NotAnInstruction(addr)),
+    };
+    for t in tries {
+        let start = t.start_addr as usize;
+        let end = start + t.insn_count as usize;
+        marks.insert((Kind::TryStart, on_inst(start)?));
+        marks.insert((Kind::TryEnd, if end == bytecode.len() { end } else { on_inst(end)? }));
+        for (_, addr) in &t.handlers {
+            marks.insert((Kind::Catch, on_inst(*addr as usize)?));
+        }
+        if let Some(addr) = t.catch_all_addr {
+            marks.insert((Kind::CatchAll, on_inst(addr as usize)?));
+        }
+    }
+
+    // labels are numbered by address, separately for each kind
+    let mut names = HashMap::new();
+    let mut at: BTreeMap<usize, Vec<&str>> = BTreeMap::new();
+    let mut last = None;
+    let mut n = 0;
+    for &(kind, addr) in &marks {
+        if last != Some(kind) {
+            last = Some(kind);
+            n = 0;
+        }
+        names.insert((kind, addr), format!(":{}_{n}", kind.prefix()));
+        n += 1;
+    }
+    for (kind, addr) in &marks {
+        if *kind != Kind::TryEnd {
+            at.entry(*addr).or_default().push(&names[&(*kind, *addr)]);
+        }
+    }
+    let label = |kind: Kind, addr: usize| names[&(kind, addr)].as_str();
+    // switch cases are relative to the first switch referencing the payload,
+    // and left as offsets when there is none
+    let case = |kind: Kind, from: Option<&usize>, off: i32| match from {
+        Some(from) => label(kind, (*from as isize + off as isize) as usize).to_string(),
+        None => format!("{off:+}"),
+    };
+
+    let mut out = format!("    .registers {registers}\n");
+    let ends = insts.iter().map(|(addr, _)| *addr).chain([bytecode.len()]);
+    let insts = insts.iter().map(Some).chain([None]);
+    for (addr, inst) in ends.zip(insts) {
+        if names.contains_key(&(Kind::TryEnd, addr)) {
+            let end = label(Kind::TryEnd, addr);
+            out.push_str(&format!("    {end}\n"));
+            for t in tries.iter().filter(|t| t.start_addr as usize + t.insn_count as usize == addr) {
+                let start = label(Kind::TryStart, t.start_addr as usize);
+                for (ty, handler) in &t.handlers {
+                    let ty = lookup.type_name(*ty as u16);
+                    let catch = label(Kind::Catch, *handler as usize);
+                    out.push_str(&format!("    .catch {ty} {{{start} .. {end}}} {catch}\n"));
+                }
+                if let Some(handler) = t.catch_all_addr {
+                    let catch = label(Kind::CatchAll, handler as usize);
+                    out.push_str(&format!("    .catchall {{{start} .. {end}}} {catch}\n"));
+                }
+            }
+        }
+
+        let Some((_, inst)) = inst else {
+            break;
+        };
+        out.push('\n');
+        for name in at.get(&addr).into_iter().flatten() {
+            out.push_str(&format!("    {name}\n"));
+        }
+
+        // the kind of label branch targets become
+        let branch = match (inst, inst.control_flow()) {
+            (Instruction::PackedSwitch(_, _), _) => Some(Kind::PSwitchData),
+            (Instruction::SparseSwitch(_, _), _) => Some(Kind::SSwitchData),
+            (Instruction::FillArrayData(_, _), _) => Some(Kind::Array),
+            (_, ControlFlow::GoTo(_)) => Some(Kind::Goto),
+            (_, ControlFlow::Branch(_)) => Some(Kind::Cond),
+            _ => None,
+        };
+        match inst {
+            Instruction::PackedSwitchPayload { first_key, targets } => {
+                let from = referrers.get(&index[&addr]);
+                out.push_str(&format!("    .packed-switch {}\n", hex((*first_key).into(), "")));
+                for off in targets {
+                    out.push_str(&format!("        {}\n", case(Kind::PSwitch, from, *off)));
+                }
+                out.push_str("    .end packed-switch\n");
+            }
+            Instruction::SparseSwitchPayload { keys, targets } => {
+                let from = referrers.get(&index[&addr]);
+                out.push_str("    .sparse-switch\n");
+                for (key, off) in keys.iter().zip(targets) {
+                    out.push_str(&format!("        {} -> {}\n", hex((*key).into(), ""), case(Kind::SSwitch, from, *off)));
+                }
+                out.push_str("    .end sparse-switch\n");
+            }
+            Instruction::FillArrayDataPayload { element_width, data } => {
+                out.push_str(&format!("    .array-data {element_width}\n"));
+                let width = (*element_width as usize).clamp(1, 8);
+                for element in data.chunks(width) {
+                    let mut bytes = [0u8; 8];
+                    bytes[..element.len()].copy_from_slice(element);
+                    // sign extend from the element width
+                    let shift = 64 - 8 * element.len() as u32;
+                    let value = (i64::from_le_bytes(bytes) << shift) >> shift;
+                    let suffix = match width {
+                        1 => "t",
+                        2 => "s",
+                        8 => "L",
+                        _ => "",
+                    };
+                    out.push_str(&format!("        {}\n", hex(value, suffix)));
+                }
+                out.push_str("    .end array-data\n");
+            }
+            _ => {
+                let literal = literal(inst);
+                let text: String = lookup
+                    .tokens(inst)
+                    .into_iter()
+                    .map(|token| match (token.kind, branch) {
+                        (TokenKind::Target(off), Some(kind)) => label(kind, (addr as isize + off as isize) as usize).to_string(),
+                        (TokenKind::Reference(Reference::String(idx)), _) => format!("\"{}\"", escape(&lookup.string(idx))),
+                        (TokenKind::Literal, _) => literal.clone().unwrap_or(token.text),
+                        _ => token.text,
+                    })
+                    .collect();
+                out.push_str(&format!("    {text}\n"));
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+// the constant of a const or literal arithmetic instruction as written by
+// baksmali: signed, with high16 values shifted into place and an `L` suffix on
+// wide values
+fn literal(inst: &Instruction) -> Option<String> {
+    use Instruction::*;
+    Some(match *inst {
+        Const4(_, lit)
+        | AddInt8(_, _, lit)
+        | RsubInt8(_, _, lit)
+        | MulInt8(_, _, lit)
+        | DivInt8(_, _, lit)
+        | RemInt8(_, _, lit)
+        | AndInt8(_, _, lit)
+        | OrInt8(_, _, lit)
+        | XorInt8(_, _, lit)
+        | ShlInt8(_, _, lit)
+        | ShrInt8(_, _, lit)
+        | UshrInt8(_, _, lit) => hex(lit.into(), ""),
+        Const16(_, lit)
+        | AddInt16(_, _, lit)
+        | RsubInt16(_, _, lit)
+        | MulInt16(_, _, lit)
+        | DivInt16(_, _, lit)
+        | RemInt16(_, _, lit)
+        | AndInt16(_, _, lit)
+        | OrInt16(_, _, lit)
+        | XorInt16(_, _, lit) => hex(lit.into(), ""),
+        Const(_, lit) => hex((lit as i32).into(), ""),
+        ConstHigh16(_, lit) => hex(i64::from(lit) << 16, ""),
+        ConstWide16(_, lit) => hex(lit.into(), "L"),
+        ConstWide32(_, lit) => hex((lit as i32).into(), "L"),
+        ConstWide(_, lit) => hex(lit as i64, "L"),
+        ConstWideHigh16(_, lit) => hex((lit as i64) << 48, "L"),
+        _ => return None,
+    })
+}
+
+// signed hex literal, as written by baksmali
+fn hex(value: i64, suffix: &str) -> String {
+    match value < 0 {
+        true => format!("-{:#x}{suffix}", value.unsigned_abs()),
+        false => format!("{value:#x}{suffix}"),
+    }
+}
@@ -0,0 +1,215 @@
+//! Recovery-mode disassembly for obfuscated bytecode
+//!
+//! [`decode_all`][`crate::decode::decode_all`] gives up at the first malformed
+//! instruction, which is the point of junk code units and fake payload idents
+//! inserted by packers. [`disassemble`] instead splits a method's bytecode into
+//! interleaved code and data [`Region`]s:
+//!
+//!  1. instructions reachable from the entry point and the given entries (e.g.
+//!     catch handlers) are decoded first, following branches, switch tables and
+//!     array data references
+//!  2. the remaining gaps are linearly swept, decoding plausible instructions
+//!     and marking everything else as data, one codepoint at a time until the
+//!     sweep resynchronizes
+//!
+//! Reachable instructions always win: the sweep never decodes an instruction
+//! overlapping one found in the first pass.
+
+use std::collections::BTreeMap;
+
+use crate::{decode::DecodeOptions, ControlFlow, Instruction};
+
+/// A span of a method's bytecode, either decoded or left as raw data
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Region {
+    /// A decoded instruction
+    Code {
+        /// Address of the instruction, in u16 codepoints
+        address: usize,
+        /// The decoded instruction
+        instruction: Instruction,
+        /// Whether the instruction is reachable from an entry point, rather
+        /// than just found by the linear sweep
+        reachable: bool,
+    },
+    /// Codepoints that could not be decoded into a plausible instruction
+    Data {
+        /// Address of the first codepoint
+        address: usize,
+        /// The raw codepoints
+        units: Vec<u16>,
+    },
+}
+
+impl Region {
+    /// Address of the region, in u16 codepoints
+    pub fn address(&self) -> usize {
+        match self {
+            Region::Code { address, .. } | Region::Data { address, .. } => *address,
+        }
+    }
+
+    /// Length of the region, in u16 codepoints
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        match self {
+            Region::Code { instruction, .. } => instruction.len(),
+            Region::Data { units, .. } => units.len(),
+        }
+    }
+}
+
+impl std::fmt::Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Region::Code { address, instruction, .. } => f.write_fmt(format_args!("{address:04x}: {instruction}")),
+            Region::Data { address, units } => {
+                f.write_fmt(format_args!("{address:04x}: .data"))?;
+                for (i, unit) in units.iter().enumerate() {
+                    let sep = if i == 0 { " " } else { ", " };
+                    f.write_fmt(format_args!("{sep}{unit:#06x}"))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Disassemble a method's bytecode into [`Region`]s ordered by address,
+/// covering every codepoint exactly once
+///
+/// `entries` should be all known entrypoints besides address 0, for example
+/// the catch handler offsets of the method. Unlike
+/// [`basic_blocks`][`crate::blocks::basic_blocks`] this never fails: paths
+/// running into undecodable bytecode or out of bounds simply end there.
+pub fn disassemble(bytecode: &[u16], entries: &[usize]) -> Vec<Region> {
+    disassemble_with(bytecode, entries, &DecodeOptions::default())
+}
+
+/// [`disassemble`] with the given [`DecodeOptions`], e.g. to recover odex or
+/// quickened bytecode
+pub fn disassemble_with(bytecode: &[u16], entries: &[usize], options: &DecodeOptions) -> Vec<Region> {
+    let mut claimed = vec![false; bytecode.len()];
+    let mut reachable = BTreeMap::new();
+
+    // depth first from the entry point, which wins any overlap
+    let mut search_next: Vec<usize> = entries.iter().rev().copied().collect();
+    search_next.push(0);
+
+    while let Some(addr) = search_next.pop() {
+        let Some(inst) = decode_at(bytecode, addr, &claimed, options) else {
+            continue;
+        };
+        let len = inst.len();
+        claimed[addr..addr + len].fill(true);
+
+        // control flow never legitimately reaches a payload
+        if inst.is_payload() {
+            reachable.insert(addr, inst);
+            continue;
+        }
+
+        match inst.control_flow() {
+            ControlFlow::FallThrough => search_next.push(addr + len),
+            ControlFlow::GoTo(t) => search_next.extend(target(bytecode, addr, t)),
+            ControlFlow::Branch(t) => {
+                search_next.push(addr + len);
+                search_next.extend(target(bytecode, addr, t.into()));
+            }
+            ControlFlow::Terminate => {}
+        }
+
+        // claim the referenced payload, and follow switch cases, even when
+        // another switch already claimed the same payload
+        if let Instruction::PackedSwitch(_, off) | Instruction::SparseSwitch(_, off) | Instruction::FillArrayData(_, off) = inst {
+            let payload = target(bytecode, addr, off).and_then(|p| match reachable.get(&p) {
+                Some(payload) if payload.is_payload() => Some((p, payload.clone())),
+                _ => Some((p, decode_at(bytecode, p, &claimed, options)?)),
+            });
+            if let Some((payload_addr, payload)) = payload {
+                let targets = match (&inst, &payload) {
+                    (Instruction::PackedSwitch(..), Instruction::PackedSwitchPayload { targets, .. })
+                    | (Instruction::SparseSwitch(..), Instruction::SparseSwitchPayload { targets, .. }) => targets.as_slice(),
+                    (Instruction::FillArrayData(..), Instruction::FillArrayDataPayload { .. }) => &[],
+                    // not the payload the instruction expects
+                    _ => {
+                        reachable.insert(addr, inst);
+                        continue;
+                    }
+                };
+                search_next.extend(targets.iter().filter_map(|t| target(bytecode, addr, *t)));
+                claimed[payload_addr..payload_addr + payload.len()].fill(true);
+                reachable.insert(payload_addr, payload);
+            }
+        }
+
+        reachable.insert(addr, inst);
+    }
+
+    let mut regions = Vec::new();
+    let mut addr = 0;
+    while addr < bytecode.len() {
+        if let Some(instruction) = reachable.remove(&addr) {
+            let address = addr;
+            addr += instruction.len();
+            regions.push(Region::Code {
+                address,
+                instruction,
+                reachable: true,
+            });
+            continue;
+        }
+
+        if let Some(instruction) = decode_at(bytecode, addr, &claimed, options).filter(|i| plausible(bytecode, addr, i)) {
+            let address = addr;
+            addr += instruction.len();
+            regions.push(Region::Code {
+                address,
+                instruction,
+                reachable: false,
+            });
+            continue;
+        }
+
+        match regions.last_mut() {
+            Some(Region::Data { units, .. }) => units.push(bytecode[addr]),
+            _ => regions.push(Region::Data {
+                address: addr,
+                units: vec![bytecode[addr]],
+            }),
+        }
+        addr += 1;
+    }
+
+    regions
+}
+
+// decode the instruction at addr, unless it overlaps an already decoded one
+fn decode_at(bytecode: &[u16], addr: usize, claimed: &[bool], options: &DecodeOptions) -> Option<Instruction> {
+    let mut rest = bytecode.get(addr..)?;
+    let inst = crate::decode::decode_one_with(&mut rest, options).ok()?;
+    if claimed[addr..addr + inst.len()].contains(&true) {
+        return None;
+    }
+
+    Some(inst)
+}
+
+// resolve a relative branch, if it lands within the bytecode
+fn target(bytecode: &[u16], from: usize, offset: i32) -> Option<usize> {
+    let to = from.checked_add_signed(offset as isize)?;
+    (to < bytecode.len()).then_some(to)
+}
+
+// whether an unreachable instruction could be genuine code: real branches and
+// payload references stay within the method
+fn plausible(bytecode: &[u16], addr: usize, inst: &Instruction) -> bool {
+    let offset = match (inst.control_flow(), inst) {
+        (ControlFlow::GoTo(t), _) => t,
+        (ControlFlow::Branch(t), _) => t.into(),
+        (_, Instruction::PackedSwitch(_, off) | Instruction::SparseSwitch(_, off) | Instruction::FillArrayData(_, off)) => *off,
+        _ => return true,
+    };
+
+    target(bytecode, addr, offset).is_some()
+}
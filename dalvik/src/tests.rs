@@ -55,3 +55,1396 @@ fn return_() {
 fn iget_object() {
     decode_and_display(&[0x2054, 0xbeef], &["iget-object v0, v2, field@beef"]);
 }
+
+#[test]
+fn packed_switch_payload() {
+    let payload = [0x0100, 0x0002, 0x0001, 0x0000, 0x0005, 0x0000, 0xfff9, 0xffff];
+    decode_and_display(&payload, &["packed-switch-payload 0x1, {+5, -7}"]);
+    assert_eq!(decode_all(&payload).unwrap()[0].len(), payload.len());
+}
+
+#[test]
+fn sparse_switch_payload() {
+    let payload = [0x0200, 0x0002, 0x0003, 0x0000, 0x0010, 0x0000, 0x0008, 0x0000, 0x000b, 0x0000];
+    decode_and_display(&payload, &["sparse-switch-payload {0x3: +8, 0x10: +11}"]);
+    assert_eq!(decode_all(&payload).unwrap()[0].len(), payload.len());
+}
+
+#[test]
+fn fill_array_data_payload() {
+    // odd number of bytes, padded to a full code unit
+    let payload = [0x0300, 0x0001, 0x0003, 0x0000, 0x0201, 0x0003];
+    decode_and_display(&payload, &["array-data-payload 1, {0x1, 0x2, 0x3}"]);
+    assert_eq!(decode_all(&payload).unwrap()[0].len(), payload.len());
+
+    let payload = [0x0300, 0x0004, 0x0002, 0x0000, 0xbeef, 0xdead, 0x0001, 0x0000];
+    decode_and_display(&payload, &["array-data-payload 4, {0xdeadbeef, 0x1}"]);
+}
+
+#[test]
+fn payload_after_code() {
+    // packed-switch v0, +3; return-void; packed-switch-payload
+    let ins = [0x002b, 0x0003, 0x0000, 0x000e, 0x0100, 0x0001, 0x0000, 0x0000, 0x0004, 0x0000];
+    decode_and_display(&ins, &["packed-switch v0, +3", "return-void", "packed-switch-payload 0x0, {+4}"]);
+}
+
+#[test]
+fn truncated_payload() {
+    let payload = [0x0200, 0x0002, 0x0003, 0x0000, 0x0010, 0x0000, 0x0008];
+    assert!(matches!(decode_all(&payload), Err(decode::Error::Truncated)));
+}
+
+#[test]
+fn invoke_polymorphic() {
+    decode_and_display(&[0x30fa, 0x0011, 0x4321, 0x0022], &["invoke-polymorphic {v1, v2, v3}, method@11, proto@22"]);
+}
+
+#[test]
+fn invoke_polymorphic_range() {
+    decode_and_display(&[0x02fb, 0x0011, 0x0007, 0x0022], &["invoke-polymorphic/range {v7, v8}, method@11, proto@22"]);
+}
+
+#[test]
+fn invoke_custom() {
+    decode_and_display(&[0x10fc, 0x0003, 0x0005], &["invoke-custom {v5}, call_site@3"]);
+    decode_and_display(&[0x03fd, 0x0003, 0x0004], &["invoke-custom/range {v4, v5, v6}, call_site@3"]);
+}
+
+#[test]
+fn const_method_handle_and_type() {
+    decode_and_display(
+        &[0x00fe, 0x0010, 0x01ff, 0x0020],
+        &["const-method-handle v0, method_handle@10", "const-method-type v1, proto@20"],
+    );
+}
+
+#[test]
+fn empty_bytecode() {
+    assert_eq!(decode::decode_one(&mut &[][..]), Err(decode::Error::Truncated));
+}
+
+#[test]
+fn unknown_opcode() {
+    // nop; return-void; unused 0x3e
+    let err = decode_all(&[0x0000, 0x000e, 0x003e]).unwrap_err();
+    assert_eq!(err, decode::Error::UnknownOpcode { opcode: 0x3e, offset: 2 });
+}
+
+#[test]
+fn invoke_too_many_args() {
+    assert_eq!(decode_all(&[0x6071, 0x4455, 0x0030]), Err(decode::Error::Encoding));
+}
+
+#[test]
+fn invoke_range_overflow() {
+    assert_eq!(decode_all(&[0x0274, 0x0001, 0xffff]), Err(decode::Error::Encoding));
+}
+
+#[test]
+fn bad_branch_target() {
+    // nop; goto -2
+    let err = blocks::basic_blocks(&[0x0000, 0xfe28], &[]).unwrap_err();
+    assert_eq!(err, decode::Error::BadBranchTarget { from: 1, to: -1 });
+}
+
+#[test]
+fn falls_off_the_end() {
+    // if-eqz v0, +2 branching past the end; nop falling off the end
+    assert_eq!(
+        blocks::basic_blocks(&[0x0038, 0x0002], &[]).unwrap_err(),
+        decode::Error::BadBranchTarget { from: 0, to: 2 }
+    );
+    assert_eq!(blocks::basic_blocks(&[0x0000], &[]).unwrap_err(), decode::Error::Truncated);
+}
+
+#[test]
+fn decoder_addresses() {
+    // const-string v3, string@1234; goto +3; nop (padding); packed-switch-payload
+    let ins = [0x031a, 0x1234, 0x0328, 0x0000, 0x0100, 0x0000, 0x0000, 0x0000];
+    let decoded: Vec<_> = decode::Decoder::new(&ins).map(|r| r.unwrap()).map(|(addr, i)| (addr, i.is_payload())).collect();
+    assert_eq!(decoded, [(0, false), (2, false), (3, false), (4, true)]);
+}
+
+#[test]
+fn decoder_error_address() {
+    let mut decoder = decode::Decoder::new(&[0x000e, 0x0073, 0x000e]);
+    assert_eq!(decoder.next(), Some(Ok((0, Instruction::ReturnVoid))));
+    assert_eq!(decoder.next(), Some(Err((1, decode::Error::UnknownOpcode { opcode: 0x73, offset: 1 }))));
+    assert_eq!(decoder.next(), None);
+}
+
+#[test]
+fn decode_bytes() {
+    use decode::{decode_all_bytes, Endian};
+
+    // invoke-static {v0, v3}, method@4455; return v3
+    let le = [0x71, 0x20, 0x55, 0x44, 0x30, 0x00, 0x0f, 0x03];
+    let be = [0x20, 0x71, 0x44, 0x55, 0x00, 0x30, 0x03, 0x0f];
+    let expected = decode_all(&[0x2071, 0x4455, 0x0030, 0x030f]).unwrap();
+    assert_eq!(decode_all_bytes(&le, Endian::Little).unwrap(), expected);
+    assert_eq!(decode_all_bytes(&be, Endian::Big).unwrap(), expected);
+
+    // a dangling byte is not a whole codepoint
+    assert_eq!(decode_all_bytes(&le[..7], Endian::Little), Err(decode::Error::Truncated));
+}
+
+#[test]
+fn decode_one_bytes() {
+    let mut bytes = &[0x1a, 0x03, 0x34, 0x12, 0x0e, 0x00][..];
+    let inst = decode::decode_one_bytes(&mut bytes, decode::Endian::Little).unwrap();
+    assert_eq!(inst, Instruction::ConstString(3, 0x1234));
+    assert_eq!(bytes, [0x0e, 0x00]);
+}
+
+#[test]
+fn opcode_metadata() {
+    use opcode::{Flags, Format, IndexKind, Opcode};
+
+    let op = Opcode::from_u8(0x74);
+    assert_eq!(op, Opcode::InvokeVirtualRange);
+    assert_eq!(op.mnemonic(), "invoke-virtual/range");
+    assert_eq!(op.format(), Format::F3rc);
+    assert_eq!(op.index_kind(), IndexKind::Method);
+    assert_eq!(op.flags(), Flags::CONTINUE | Flags::THROW | Flags::INVOKE);
+
+    assert_eq!(Opcode::from_u8(0xfa).format().to_string(), "45cc");
+    assert!(Opcode::from_u8(0x73).is_unused());
+    assert!(Opcode::Goto.is_branch() && !Opcode::Goto.can_continue());
+    assert!(Opcode::DivInt2.can_throw() && !Opcode::DivFloat2.can_throw());
+}
+
+#[test]
+fn opcode_matches_decoding() {
+    // every decodable opcode agrees with the metadata table on its length
+    for op in 0..=0xffu8 {
+        let opcode = opcode::Opcode::from_u8(op);
+        assert_eq!(opcode.to_u8(), Some(op));
+        let bytecode = [op as u16, 0, 0, 0, 0];
+        match decode::decode_one(&mut &bytecode[..]) {
+            Ok(inst) => {
+                assert_eq!(inst.opcode(), opcode);
+                assert_eq!(inst.len(), opcode.format().len(), "{opcode}");
+            }
+            Err(_) => assert!(opcode.is_unused(), "{opcode}"),
+        }
+    }
+}
+
+#[test]
+fn optimized_opcode_metadata() {
+    use decode::DecodeOptions;
+    use opcode::Opcode;
+
+    // optimized instructions are described by their own entries, whatever the
+    // slot they were decoded from
+    for api_level in [10, 19, 26] {
+        let options = DecodeOptions::new().with_api_level(api_level);
+        for op in 0..=0xffu8 {
+            let bytecode = [op as u16, 0, 0, 0, 0];
+            let Ok(inst) = decode::decode_one_with(&mut &bytecode[..], &options) else {
+                continue;
+            };
+            let opcode = inst.opcode();
+            assert_eq!(inst.len(), opcode.format().len(), "{opcode}");
+            assert!(inst.to_string().starts_with(opcode.mnemonic()), "{inst}");
+            assert_eq!(opcode.is_optimized(), opcode.to_u8() != Some(op), "{opcode}");
+        }
+    }
+
+    let op = Instruction::InvokeSuperQuick {
+        vtable: 0,
+        nargs: 0,
+        args: [0; 5],
+    }
+    .opcode();
+    assert_eq!(op, Opcode::InvokeSuperQuick);
+    assert_eq!(op.format().id(), "35ms");
+    assert!(op.is_invoke() && op.to_u8().is_none());
+    assert!(!Instruction::IGetVolatile(0, 1, 2).opcode().is_unused());
+}
+
+#[test]
+fn odex_opcodes() {
+    use decode::{decode_all_with, DecodeOptions};
+
+    let dalvik = DecodeOptions::new().with_api_level(19);
+    // iget-quick v0, v1, [obj+8]; execute-inline {v2}, inline@3; return-void-barrier
+    let ins = decode_all_with(&[0x10f2, 0x0008, 0x10ee, 0x0003, 0x0002, 0x00f1], &dalvik).unwrap();
+    let ins: Vec<String> = ins.iter().map(|i| i.to_string()).collect();
+    assert_eq!(ins, ["iget-quick v0, v1, field@8", "execute-inline {v2}, inline@3", "return-void-barrier"]);
+
+    // f0 changed meaning in API 14
+    let old = DecodeOptions::new().with_api_level(10);
+    let inst = decode::decode_one_with(&mut &[0x10f0, 0x0007, 0x0004][..], &old).unwrap();
+    assert_eq!(
+        inst,
+        Instruction::InvokeDirectEmpty {
+            method: 7,
+            nargs: 1,
+            args: [4, 0, 0, 0, 0]
+        }
+    );
+}
+
+#[test]
+fn quickened_opcodes() {
+    use decode::{decode_all_with, DecodeOptions};
+
+    let art = DecodeOptions::new().with_api_level(26);
+    let ins = decode_all_with(&[0x10e3, 0x0008, 0x0073], &art).unwrap();
+    assert_eq!(ins, [Instruction::IGetQuick(0, 1, 8), Instruction::ReturnVoidNoBarrier]);
+    assert_eq!(ins[1].control_flow(), ControlFlow::Terminate);
+
+    // neither standard dex nor runtimes without quickening accept them
+    let err = decode::Error::UnknownOpcode { opcode: 0xe3, offset: 0 };
+    assert_eq!(decode_all(&[0x10e3, 0x0008]), Err(err.clone()));
+    let unquickened = DecodeOptions::new().with_api_level(31);
+    assert_eq!(decode_all_with(&[0x10e3, 0x0008], &unquickened), Err(err));
+}
+
+#[test]
+fn dex_version() {
+    use decode::{decode_all_with, DecodeOptions};
+    use opcode::Opcode;
+
+    assert_eq!(Opcode::InvokePolymorphic.dex_version(), 38);
+    assert_eq!(Opcode::ConstMethodType.dex_version(), 39);
+    assert_eq!(Opcode::ReturnVoid.dex_version(), 35);
+
+    // return-void; const-method-handle v0, method_handle@1
+    let bytecode = [0x000e, 0x00fe, 0x0001];
+    let v38 = DecodeOptions::new().with_dex_version(38);
+    let err = decode::Error::UnsupportedOpcode {
+        opcode: 0xfe,
+        offset: 1,
+        version: 38,
+    };
+    assert_eq!(decode_all_with(&bytecode, &v38), Err(err.clone()));
+    assert_eq!(err.to_string(), "opcode 0xfe at 0x1 is not valid in dex version 038");
+
+    let v39 = DecodeOptions::new().with_dex_version(39);
+    assert_eq!(decode_all_with(&bytecode, &v39), decode_all(&bytecode));
+}
+
+#[test]
+fn sweep_junk() {
+    use sweep::{disassemble, Region};
+
+    // goto +2; <unassigned 3e>; return-void
+    let regions = disassemble(&[0x0228, 0x003e, 0x000e], &[]);
+    let listing: Vec<String> = regions.iter().map(|r| r.to_string()).collect();
+    assert_eq!(listing, ["0000: goto +2", "0001: .data 0x003e", "0002: return-void"]);
+    assert!(matches!(regions[2], Region::Code { reachable: true, .. }));
+}
+
+#[test]
+fn sweep_fake_payload() {
+    use sweep::{disassemble, Region};
+
+    // return-void; a packed-switch-payload ident with an impossible size;
+    // <unassigned f0>; const/4 v0, 0x0
+    let bytecode = [0x000e, 0x0100, 0xfff0, 0x0012];
+    let regions = disassemble(&bytecode, &[]);
+    assert_eq!(
+        regions[1],
+        Region::Data {
+            address: 1,
+            units: vec![0x0100, 0xfff0]
+        }
+    );
+    assert_eq!(
+        regions[2],
+        Region::Code {
+            address: 3,
+            instruction: Instruction::Const4(0, 0),
+            reachable: false
+        }
+    );
+
+    // unless a catch handler starts there
+    let regions = disassemble(&bytecode, &[3]);
+    assert!(matches!(regions[2], Region::Code { reachable: true, .. }));
+}
+
+#[test]
+fn sweep_follows_switches() {
+    use sweep::{disassemble, Region};
+
+    // packed-switch v0, +6; return-void; return-void; <unassigned 3f>;
+    // packed-switch-payload 0x0, {+4}
+    let bytecode = [0x002b, 0x0006, 0x0000, 0x000e, 0x000e, 0x003f, 0x0100, 0x0001, 0x0000, 0x0000, 0x0004, 0x0000];
+    let regions = disassemble(&bytecode, &[]);
+    let addrs: Vec<(usize, bool)> = regions
+        .iter()
+        .map(|r| (r.address(), matches!(r, Region::Code { reachable: true, .. })))
+        .collect();
+    assert_eq!(addrs, [(0, true), (3, true), (4, true), (5, false), (6, true)]);
+
+    // a second switch reusing a payload already claimed by the first:
+    // packed-switch v0, +10; return-void; packed-switch v0, +6; return-void;
+    // return-void; nop; packed-switch-payload 0x0, {+4}
+    let bytecode = [
+        0x002b, 0x000a, 0x0000, 0x000e, 0x002b, 0x0006, 0x0000, 0x000e, 0x000e, 0x0000, 0x0100, 0x0001, 0x0000, 0x0000, 0x0004, 0x0000,
+    ];
+    let regions = disassemble(&bytecode, &[]);
+    let addrs: Vec<(usize, bool)> = regions
+        .iter()
+        .map(|r| (r.address(), matches!(r, Region::Code { reachable: true, .. })))
+        .collect();
+    assert_eq!(addrs, [(0, true), (3, true), (4, true), (7, true), (8, true), (9, false), (10, true)]);
+}
+
+#[test]
+fn sweep_with_options() {
+    use decode::DecodeOptions;
+    use sweep::{disassemble, disassemble_with, Region};
+
+    // iget-quick v0, v1, field@8; return v0
+    let bytecode = [0x10f2, 0x0008, 0x000f];
+    assert!(matches!(disassemble(&bytecode, &[])[0], Region::Data { .. }));
+    let regions = disassemble_with(&bytecode, &[], &DecodeOptions::new().with_api_level(19));
+    let listing: Vec<String> = regions.iter().map(|r| r.to_string()).collect();
+    assert_eq!(listing, ["0000: iget-quick v0, v1, field@8", "0002: return v0"]);
+}
+
+#[test]
+fn array_length() {
+    // array-length vA, vB: the length of the array in vB goes to vA
+    decode_and_display(&[0x1021], &["array-length v0, v1"]);
+    assert_eq!(decode_all(&[0x1021]), Ok(vec![Instruction::ArrayLength(0, 1)]));
+}
+
+#[test]
+fn encode_round_trip() {
+    use decode::{decode_one_with, DecodeOptions};
+
+    let targets = [
+        DecodeOptions::default(),
+        DecodeOptions::new().with_api_level(10),
+        DecodeOptions::new().with_api_level(19),
+        DecodeOptions::new().with_api_level(26),
+    ];
+    let operands = [
+        [0x0000, 0x0000, 0x0000, 0x0000],
+        [0x1234, 0x5678, 0x9abc, 0xdef0],
+        [0xff00, 0xffff, 0x0010, 0x0000],
+    ];
+    for options in &targets {
+        for op in 0..=0xffu16 {
+            for [aa, b, c, d] in operands {
+                let bytecode = [op | aa, b, c, d, 0x0000];
+                let Ok(inst) = decode_one_with(&mut &bytecode[..], options) else {
+                    continue;
+                };
+                let mut encoded = Vec::new();
+                inst.encode_with(&mut encoded, options).unwrap();
+                assert_eq!(encoded, bytecode[..inst.len()], "{inst}");
+                assert_eq!(decode_one_with(&mut &encoded[..], options), Ok(inst));
+            }
+        }
+    }
+
+    // payloads
+    for ins in [
+        &[0x0100, 0x0002, 0x0001, 0x0000, 0x0005, 0x0000, 0xfff9, 0xffff][..],
+        &[0x0200, 0x0001, 0x0003, 0x0000, 0x0008, 0x0000],
+        &[0x0300, 0x0001, 0x0003, 0x0000, 0x0201, 0x0003],
+    ] {
+        let inst = decode::decode_one(&mut &ins[..]).unwrap();
+        let mut encoded = Vec::new();
+        inst.encode(&mut encoded).unwrap();
+        assert_eq!(encoded, ins);
+    }
+}
+
+#[test]
+fn encode_errors() {
+    use encode::Error;
+
+    let mut out = vec![0x000e];
+    assert_eq!(Instruction::Move(16, 0).encode(&mut out), Err(Error::OutOfRange { value: 16, bits: 4 }));
+    assert_eq!(Instruction::Const4(0, 8).encode(&mut out), Err(Error::OutOfRange { value: 8, bits: 4 }));
+    let gap = Instruction::InvokeStaticRange { method: 0, args: vec![1, 3] };
+    assert_eq!(gap.encode(&mut out), Err(Error::Malformed));
+    let sparse = Instruction::SparseSwitchPayload {
+        keys: vec![1],
+        targets: vec![],
+    };
+    assert_eq!(sparse.encode(&mut out), Err(Error::Malformed));
+
+    // optimized variants need a runtime that has them, and shadow standard opcodes
+    assert_eq!(Instruction::IGetQuick(0, 1, 8).encode(&mut out), Err(Error::Unsupported));
+    let dalvik = decode::DecodeOptions::new().with_api_level(19);
+    let custom = Instruction::InvokeCustomRange { call_site: 0, args: vec![] };
+    assert_eq!(custom.encode_with(&mut out, &dalvik), Err(Error::Unsupported));
+    assert_eq!(out, [0x000e]);
+
+    Instruction::Const4(0, -8).encode(&mut out).unwrap();
+    assert_eq!(out, [0x000e, 0x8012]);
+}
+
+#[test]
+fn assemble_display_round_trip() {
+    let operands = [
+        [0x0000, 0x0000, 0x0000, 0x0000],
+        [0x1234, 0x5678, 0x0abc, 0xdef0],
+        [0xff00, 0xffff, 0x0010, 0x0000],
+    ];
+    for op in 0..=0xffu16 {
+        for [aa, b, c, d] in operands {
+            let bytecode = [op | aa, b, c, d, 0x0000];
+            let Ok(inst) = decode::decode_one(&mut &bytecode[..]) else {
+                continue;
+            };
+            let text = inst.to_string();
+            let assembled = assemble::assemble(&text, &()).unwrap_or_else(|e| panic!("{text}: {e}"));
+            assert_eq!(assembled.len(), 1);
+            assert_eq!(assembled[0].to_string(), text);
+            // unused slots of a register list are not printed
+            let list = matches!(inst.opcode().format(), opcode::Format::F35c | opcode::Format::F45cc);
+            if !list || [aa, b, c, d] == [0; 4] {
+                assert_eq!(assembled[0], inst, "{text}");
+            }
+        }
+    }
+
+    for text in [
+        "packed-switch-payload 0x1, {+5, -7}",
+        "sparse-switch-payload {0x3: +8, 0xfffffff0: +11}",
+        "array-data-payload 2, {0x1, 0xffff}",
+    ] {
+        let assembled = assemble::assemble(text, &()).unwrap();
+        assert_eq!(assembled[0].to_string(), text);
+    }
+}
+
+#[test]
+fn assemble_labels_and_payloads() {
+    let text = "
+        const/4 v0, 0x1     # selector
+        packed-switch v0, :pswitch_data_0
+    :pswitch_0
+        goto :pswitch_0
+    :pswitch_data_0
+        .packed-switch 0x0
+            :pswitch_0
+            :pswitch_0
+        .end packed-switch
+    ";
+    let insts = assemble::assemble(text, &()).unwrap();
+    assert_eq!(
+        insts,
+        [
+            Instruction::Const4(0, 1),
+            Instruction::PackedSwitch(0, 5),
+            Instruction::Goto(0),
+            // aligns the payload at 5 to 6
+            Instruction::Nop,
+            Instruction::PackedSwitchPayload {
+                first_key: 0,
+                targets: vec![3, 3]
+            },
+        ]
+    );
+
+    let units = assemble::assemble_units(".array-data 4\n    0x1 -0x2\n.end array-data", &()).unwrap();
+    assert_eq!(units, [0x0300, 0x0004, 0x0002, 0x0000, 0x0001, 0x0000, 0xfffe, 0xffff]);
+}
+
+#[test]
+fn assemble_symbols() {
+    struct Symbols;
+    impl assemble::Resolve for Symbols {
+        fn string(&self, contents: &str) -> Option<u32> {
+            (contents == "hi \"there\"").then_some(7)
+        }
+        fn method(&self, class: &str, name: &str, params: &str, ret: &str) -> Option<u16> {
+            (class == "Ljava/lang/Object;" && name == "<init>" && params.is_empty() && ret == "V").then_some(2)
+        }
+    }
+
+    let text = "const-string v0, \"hi \\\"there\\\"\"\ninvoke-direct {v1}, Ljava/lang/Object;-><init>()V";
+    let insts = assemble::assemble(text, &Symbols).unwrap();
+    assert_eq!(insts[0], Instruction::ConstString(0, 7));
+    assert_eq!(
+        insts[1],
+        Instruction::InvokeDirect {
+            method: 2,
+            nargs: 1,
+            args: [1, 0, 0, 0, 0]
+        }
+    );
+}
+
+#[test]
+fn assemble_errors() {
+    use assemble::{assemble, Error, ErrorKind};
+
+    let err = |line, column, kind| Err(Error { line, column, kind });
+    assert_eq!(assemble("nop\nmove v16, v0", &()), err(2, 6, ErrorKind::OutOfRange));
+    assert_eq!(assemble("  mov v1, v0", &()), err(1, 3, ErrorKind::UnknownMnemonic("mov".into())));
+    assert_eq!(assemble("goto :nowhere", &()), err(1, 6, ErrorKind::UnknownLabel("nowhere".into())));
+    assert_eq!(assemble("return v0 v1", &()), err(1, 11, ErrorKind::Expected("end of line")));
+    assert_eq!(assemble("const/4 v0, 0x10", &()), err(1, 13, ErrorKind::OutOfRange));
+    let unresolved = ErrorKind::Unresolved("Lfoo;".into());
+    assert_eq!(assemble("const-class v0, Lfoo;", &()), err(1, 17, unresolved));
+    assert_eq!(assemble(".locals 2", &()), err(1, 1, ErrorKind::UnknownDirective(".locals".into())));
+    let empty = ":a\n.catchall {:a .. :a} :a\nreturn-void";
+    assert_eq!(assemble(empty, &()), err(2, 18, ErrorKind::EmptyTry));
+    let reversed = ErrorKind::Expected("a last register no lower than the first");
+    assert_eq!(assemble("invoke-static/range {v5 .. v3}, method@1", &()), err(1, 28, reversed));
+
+    // a goto/16 is needed past 127 codepoints
+    let far = format!("goto :end\n{}:end\nreturn-void", "nop\n".repeat(128));
+    assert_eq!(assemble(&far, &()), err(1, 6, ErrorKind::OutOfRange));
+}
+
+#[test]
+fn builder_relaxation() {
+    use builder::{CodeBuilder, Condition};
+
+    // goto grows past 127 codepoints, and never encodes a zero offset in less
+    // than goto/32
+    let mut code = CodeBuilder::new();
+    let (end, top) = (code.new_label(), code.new_label());
+    code.goto(end);
+    for _ in 0..128 {
+        code.push(Instruction::Nop);
+    }
+    code.bind(end).bind(top).goto(top);
+    let insts = code.build().unwrap();
+    assert_eq!(insts[0], Instruction::Goto16(130));
+    assert_eq!(insts[129], Instruction::Goto32(0));
+
+    // conditional branches past 16 bits become an inverted branch over a goto/32
+    let mut code = CodeBuilder::new();
+    let end = code.new_label();
+    code.if_testz(Condition::Eq, 3, end);
+    for _ in 0..40000 {
+        code.push(Instruction::Nop);
+    }
+    code.bind(end).push(Instruction::ReturnVoid);
+    let insts = code.build().unwrap();
+    assert_eq!(insts[..2], [Instruction::IfNez(3, 5), Instruction::Goto32(40003)]);
+
+    let mut code = CodeBuilder::new();
+    code.push(Instruction::Nop);
+    let units = code.build_units();
+    assert_eq!(units, Ok(vec![0x0000]));
+}
+
+#[test]
+fn builder_selection() {
+    use builder::{CodeBuilder, MoveKind};
+
+    let mut code = CodeBuilder::new();
+    code.constant(15, -8)
+        .constant(16, 7)
+        .constant(0, -0x8000)
+        .constant(0, 0x7fff0000)
+        .constant(0, 0x12345)
+        .constant_wide(0, -1)
+        .constant_wide(0, 0x12345)
+        .constant_wide(0, 0x4000 << 48)
+        .constant_wide(0, 1 << 32)
+        .mov(MoveKind::Single, 15, 15)
+        .mov(MoveKind::Wide, 16, 0x100)
+        .mov(MoveKind::Object, 0x100, 0);
+    assert_eq!(
+        code.build().unwrap(),
+        [
+            Instruction::Const4(15, -8),
+            Instruction::Const16(16, 7),
+            Instruction::Const16(0, -0x8000),
+            Instruction::ConstHigh16(0, 0x7fff),
+            Instruction::Const(0, 0x12345),
+            Instruction::ConstWide16(0, -1),
+            Instruction::ConstWide32(0, 0x12345),
+            Instruction::ConstWideHigh16(0, 0x4000),
+            Instruction::ConstWide(0, 1 << 32),
+            Instruction::Move(15, 15),
+            Instruction::MoveWideFrom16(16, 0x100),
+            Instruction::MoveObject16(0x100, 0),
+        ]
+    );
+}
+
+#[test]
+fn builder_payloads() {
+    use builder::{CodeBuilder, Condition, Error};
+
+    let mut code = CodeBuilder::new();
+    let case = code.new_label();
+    code.fill_array_data(1, 1, vec![1, 2, 3])
+        .packed_switch(0, 10, &[case])
+        .bind(case)
+        .push(Instruction::ReturnVoid);
+    assert_eq!(
+        code.build().unwrap(),
+        [
+            Instruction::FillArrayData(1, 8),
+            Instruction::PackedSwitch(0, 11),
+            Instruction::ReturnVoid,
+            // aligns the array data at 7 to 8
+            Instruction::Nop,
+            Instruction::FillArrayDataPayload {
+                element_width: 1,
+                data: vec![1, 2, 3]
+            },
+            Instruction::PackedSwitchPayload {
+                first_key: 10,
+                targets: vec![3]
+            },
+        ]
+    );
+
+    let mut code = CodeBuilder::new();
+    let label = code.new_label();
+    code.goto(label);
+    assert_eq!(code.build(), Err(Error::UnboundLabel));
+
+    let mut code = CodeBuilder::new();
+    let label = code.new_label();
+    code.bind(label).bind(label).goto(label);
+    assert_eq!(code.build(), Err(Error::DuplicateLabel));
+
+    // labels do not carry over between builders
+    let mut other = CodeBuilder::new();
+    let label = other.new_label();
+    let mut code = CodeBuilder::new();
+    code.bind(label).push(Instruction::ReturnVoid);
+    assert_eq!(code.build(), Err(Error::ForeignLabel));
+    let mut code = CodeBuilder::new();
+    code.new_label();
+    code.goto(label);
+    assert_eq!(code.build(), Err(Error::ForeignLabel));
+
+    let mut code = CodeBuilder::new();
+    let label = code.new_label();
+    code.bind(label).if_test(Condition::Lt, 0, 16, label);
+    assert_eq!(code.build(), Err(Error::OutOfRange));
+}
+
+#[test]
+fn patch_branches() {
+    use patch::Editor;
+
+    // nop; goto -1, growing to goto/16
+    let mut editor = Editor::new(&[0x0000, 0xff28], vec![]).unwrap();
+    editor.insert(1, (0..200).map(|_| Instruction::Nop)).unwrap();
+    let (code, _) = editor.finish().unwrap();
+    assert_eq!(code.len(), 203);
+    assert_eq!(code[201..], [0x0029, 0xff37]);
+
+    // if-eqz v0, +3; nop; return-void, relaxing to an inverted branch
+    let mut editor = Editor::new(&[0x0038, 0x0003, 0x0000, 0x000e], vec![]).unwrap();
+    editor.insert(2, (0..40000).map(|_| Instruction::Nop)).unwrap();
+    let (code, _) = editor.finish().unwrap();
+    assert_eq!(code[..5], [0x0039, 0x0005, 0x002a, 0x9c44, 0x0000]);
+    assert_eq!(code.len(), 40007);
+
+    // branches to deleted and replaced instructions land on what follows
+    // if-eqz v3, +2; goto +1; nop; return-void
+    let mut editor = Editor::new(&[0x0338, 0x0002, 0x0128, 0x0000, 0x000e], vec![]).unwrap();
+    editor.delete(2).unwrap().replace(3, [Instruction::Const16(0, 1)]).unwrap();
+    let (code, _) = editor.finish().unwrap();
+    assert_eq!(code, [0x0338, 0x0002, 0x0013, 0x0001, 0x000e]);
+}
+
+#[test]
+fn patch_switch_and_tries() {
+    use patch::{Editor, Error, TryBlock};
+
+    // packed-switch v0, +6; return-void; return-void; nop; packed-switch-payload
+    let code = [0x002b, 0x0006, 0x0000, 0x000e, 0x000e, 0x0000, 0x0100, 0x0001, 0x0000, 0x0000, 0x0003, 0x0000];
+    let tries = vec![TryBlock {
+        start_addr: 0,
+        insn_count: 3,
+        handlers: vec![(7, 4)],
+        catch_all_addr: None,
+    }];
+
+    let mut editor = Editor::new(&code, tries.clone()).unwrap();
+    editor.insert(0, [Instruction::Nop]).unwrap().insert(3, [Instruction::Const4(0, 1)]).unwrap();
+    let (patched, patched_tries) = editor.finish().unwrap();
+    assert_eq!(
+        patched,
+        [0x0000, 0x002b, 0x0007, 0x0000, 0x1012, 0x000e, 0x000e, 0x0000, 0x0100, 0x0001, 0x0000, 0x0000, 0x0003, 0x0000]
+    );
+    assert_eq!(
+        patched_tries,
+        [TryBlock {
+            start_addr: 0,
+            insn_count: 4,
+            handlers: vec![(7, 6)],
+            catch_all_addr: None,
+        }]
+    );
+
+    // the padding is dropped once the payload is aligned without it
+    let mut editor = Editor::new(&code, tries.clone()).unwrap();
+    editor.delete(4).unwrap();
+    let (patched, patched_tries) = editor.finish().unwrap();
+    assert_eq!(patched[..5], [0x002b, 0x0004, 0x0000, 0x000e, 0x0100]);
+    assert_eq!(patched_tries[0].handlers, [(7, 4)]);
+
+    // a nop that a handler lands on is code, not padding
+    // fill-array-data v0, +4; nop; array-data 2 x 1 {5}
+    let array = [0x0026, 0x0004, 0x0000, 0x0000, 0x0300, 0x0002, 0x0001, 0x0000, 0x0005];
+    let handler = vec![TryBlock {
+        start_addr: 0,
+        insn_count: 3,
+        handlers: vec![],
+        catch_all_addr: Some(3),
+    }];
+    let mut editor = Editor::new(&array, handler).unwrap();
+    editor.insert(0, [Instruction::Nop]).unwrap();
+    let (patched, patched_tries) = editor.finish().unwrap();
+    assert_eq!(patched[..7], [0x0000, 0x0026, 0x0005, 0x0000, 0x0000, 0x0000, 0x0300]);
+    assert_eq!(patched_tries[0].catch_all_addr, Some(4));
+
+    let mut editor = Editor::new(&code, tries.clone()).unwrap();
+    editor.delete(6).unwrap();
+    assert_eq!(editor.finish(), Err(Error::MissingPayload(0)));
+    assert_eq!(Editor::new(&code, tries).unwrap().insert(1, []).err(), Some(Error::NotAnInstruction(1)));
+
+    let err = Editor::new(&[0x0228, 0x0000], vec![]).unwrap_err();
+    assert_eq!(err, Error::Decode(decode::Error::BadBranchTarget { from: 0, to: 2 }));
+}
+
+#[test]
+fn uses_and_defs() {
+    use registers::Register::{Result, V};
+
+    let inst = Instruction::AddLong(0, 2, 4);
+    assert_eq!((inst.uses(), inst.defs()), (vec![V(2), V(3), V(4), V(5)], vec![V(0), V(1)]));
+    let inst = Instruction::ShlLong2(6, 8);
+    assert_eq!((inst.uses(), inst.defs()), (vec![V(6), V(7), V(8)], vec![V(6), V(7)]));
+    let inst = Instruction::APutWide(1, 3, 4);
+    assert_eq!((inst.uses(), inst.defs()), (vec![V(1), V(2), V(3), V(4)], vec![]));
+    let inst = Instruction::CmpLong(0, 1, 3);
+    assert_eq!((inst.uses(), inst.defs()), (vec![V(1), V(2), V(3), V(4)], vec![V(0)]));
+    let inst = Instruction::IntToDouble(0, 9);
+    assert_eq!((inst.uses(), inst.defs()), (vec![V(9)], vec![V(0), V(1)]));
+
+    // invoke results go through the implicit result register
+    let inst = Instruction::InvokeVirtual {
+        method: 0,
+        nargs: 2,
+        args: [4, 5, 0, 0, 0],
+    };
+    assert_eq!((inst.uses(), inst.defs()), (vec![V(4), V(5)], vec![Result]));
+    let inst = Instruction::InvokeStaticRange {
+        method: 0,
+        args: vec![0x100, 0x101, 0x102],
+    };
+    assert_eq!((inst.uses(), inst.defs()), (vec![V(0x100), V(0x101), V(0x102)], vec![Result]));
+    let inst = Instruction::MoveResultWide(2);
+    assert_eq!((inst.uses(), inst.defs()), (vec![Result], vec![V(2), V(3)]));
+    assert_eq!(Instruction::ReturnWide(7).uses(), [V(7), V(8)]);
+    assert_eq!(Instruction::Goto(1).uses(), []);
+}
+
+#[test]
+fn operand_categories() {
+    use registers::{Category, Register, Role};
+
+    let categories = |inst: Instruction| -> Vec<(Category, Role)> { inst.operands().into_iter().map(|op| (op.category, op.role)).collect() };
+    let (src, dst) = (Role::Source, Role::Destination);
+
+    assert_eq!(
+        categories(Instruction::AddInt2(0, 1)),
+        [(Category::Int, dst), (Category::Int, src), (Category::Int, src)]
+    );
+    assert_eq!(
+        categories(Instruction::AddFloat2(0, 1)),
+        [(Category::Float, dst), (Category::Float, src), (Category::Float, src)]
+    );
+    assert_eq!(
+        categories(Instruction::AddLong2(0, 2)),
+        [(Category::Long, dst), (Category::Long, src), (Category::Long, src)]
+    );
+    assert_eq!(
+        categories(Instruction::UShrLong2(0, 2)),
+        [(Category::Long, dst), (Category::Long, src), (Category::Int, src)]
+    );
+    assert_eq!(categories(Instruction::Move(0, 1)), [(Category::Any, dst), (Category::Any, src)]);
+    assert_eq!(categories(Instruction::MoveWide16(0, 2)), [(Category::AnyWide, dst), (Category::AnyWide, src)]);
+    assert_eq!(
+        categories(Instruction::APutChar(0, 1, 2)),
+        [(Category::Int, src), (Category::Reference, src), (Category::Int, src)]
+    );
+    assert_eq!(
+        categories(Instruction::CmplDouble(0, 2, 4)),
+        [(Category::Int, dst), (Category::Double, src), (Category::Double, src)]
+    );
+    assert_eq!(categories(Instruction::IfLtz(0, 2)), [(Category::Int, src)]);
+    assert_eq!(categories(Instruction::IfEqz(0, 2)), [(Category::Any, src)]);
+
+    let ops = Instruction::FilledNewArrayRange { ty: 0, args: vec![3, 4] }.operands();
+    assert_eq!(ops[0].register, Register::Result);
+    assert_eq!((ops[0].category, ops[0].role), (Category::Reference, dst));
+    assert_eq!(ops[1..].iter().map(|op| op.register).collect::<Vec<_>>(), [Register::V(3), Register::V(4)]);
+}
+
+#[test]
+fn implicit_exceptions() {
+    use decode::DecodeOptions;
+    use exceptions::Exception::*;
+
+    assert_eq!(Instruction::IGet(0, 1, 2).throws(), [Linkage, NullPointer]);
+    assert_eq!(Instruction::MonitorEnter(0).throws(), [NullPointer]);
+    assert_eq!(Instruction::RemLong2(0, 2).throws(), [Arithmetic]);
+    assert_eq!(Instruction::DivInt16(0, 1, 0).throws(), [Arithmetic]);
+    assert_eq!(Instruction::APut(0, 1, 2).throws(), [NullPointer, ArrayIndexOutOfBounds]);
+    assert_eq!(Instruction::APutObject(0, 1, 2).throws(), [NullPointer, ArrayIndexOutOfBounds, ArrayStore]);
+    assert_eq!(Instruction::CheckCast(0, 1).throws(), [Linkage, ClassCast]);
+    assert_eq!(Instruction::Throw(0).throws(), [NullPointer, Any]);
+    let invoke = Instruction::InvokeStatic {
+        method: 0,
+        nargs: 0,
+        args: [0; 5],
+    };
+    assert_eq!(invoke.throws(), [Linkage, Any]);
+
+    assert!(!Instruction::DivFloat(0, 1, 2).can_throw());
+    assert!(!Instruction::DivInt8(0, 1, 2).can_throw());
+    assert!(!Instruction::Return(0).can_throw());
+    assert_eq!(Instruction::ConstString(0, 1).throws(), [OutOfMemory]);
+    assert_eq!(Instruction::SGetWideVolatile(0, 1).throws(), [Linkage]);
+
+    // agrees with the opcode metadata, divisions by a zero literal included
+    for options in [
+        DecodeOptions::new(),
+        DecodeOptions::new().with_api_level(19),
+        DecodeOptions::new().with_api_level(26),
+    ] {
+        for op in 0..=0xffu16 {
+            let Ok(inst) = decode::decode_one_with(&mut &[op, 0, 0, 0, 0][..], &options) else {
+                continue;
+            };
+            assert_eq!(inst.can_throw(), inst.opcode().can_throw(), "{inst}");
+        }
+    }
+}
+
+#[test]
+fn references() {
+    use references::Reference;
+
+    let poly = Instruction::InvokePolymorphicRange {
+        method: 3,
+        proto: 4,
+        args: vec![0, 1],
+    };
+    assert_eq!(poly.references(), [Reference::Method(3), Reference::Proto(4)]);
+    assert_eq!(Instruction::SPutWide(0, 9).references(), [Reference::Field(9)]);
+    assert_eq!(Instruction::ConstMethodType(0, 2).references(), [Reference::Proto(2)]);
+    assert_eq!(Instruction::IGetQuick(0, 1, 8).references(), []);
+    assert_eq!(Instruction::ThrowVerificationError(0x81, 5).references(), [Reference::Field(5)]);
+
+    // renumber types, leaving other pools alone
+    let mut inst = Instruction::NewArray(0, 1, 7);
+    inst.map_references(|r| match r {
+        Reference::Type(idx) => u32::from(idx) * 2,
+        r => r.index(),
+    })
+    .unwrap();
+    assert_eq!(inst, Instruction::NewArray(0, 1, 14));
+
+    let mut poly = poly;
+    poly.map_references(|r| r.index() + 1).unwrap();
+    assert_eq!(poly.references(), [Reference::Method(4), Reference::Proto(5)]);
+
+    let mut inst = Instruction::IGet(0, 1, 2);
+    let err = inst.map_references(|_| 0x10000);
+    assert_eq!(err, Err(encode::Error::OutOfRange { value: 0x10000, bits: 16 }));
+    assert_eq!(inst, Instruction::IGet(0, 1, 2));
+}
+
+#[test]
+fn map_registers() {
+    let mut inst = Instruction::AGetWide(0, 1, 2);
+    inst.map_registers(|r| r + 10).unwrap();
+    assert_eq!(inst, Instruction::AGetWide(10, 11, 12));
+
+    let mut inst = Instruction::InvokeStaticRange {
+        method: 1,
+        args: vec![3, 4, 5],
+    };
+    inst.map_registers(|r| r * 2 - 3).unwrap_err();
+    inst.map_registers(|r| r + 0x100).unwrap();
+    assert_eq!(
+        inst.uses(),
+        [registers::Register::V(0x103), registers::Register::V(0x104), registers::Register::V(0x105)]
+    );
+
+    // no silent truncation, and the instruction is left alone
+    let mut inst = Instruction::IfEq(1, 2, 5);
+    assert_eq!(inst.map_registers(|r| r + 14), Err(encode::Error::OutOfRange { value: 16, bits: 4 }));
+    assert_eq!(inst, Instruction::IfEq(1, 2, 5));
+    assert_eq!(inst.map_registers_widening(|r| r + 14), Err(encode::Error::OutOfRange { value: 16, bits: 4 }));
+
+    let mut inst = Instruction::Move(1, 2);
+    inst.map_registers_widening(|r| r << 8).unwrap();
+    assert_eq!(inst, Instruction::Move16(0x100, 0x200));
+
+    let mut inst = Instruction::AddInt2(1, 2);
+    inst.map_registers_widening(|r| r + 0x10).unwrap();
+    assert_eq!(inst, Instruction::AddInt(0x11, 0x11, 0x12));
+
+    let mut inst = Instruction::InvokeVirtual {
+        method: 7,
+        nargs: 2,
+        args: [0, 1, 0, 0, 0],
+    };
+    inst.map_registers_widening(|r| r + 0x20).unwrap();
+    assert_eq!(
+        inst,
+        Instruction::InvokeVirtualRange {
+            method: 7,
+            args: vec![0x20, 0x21]
+        }
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_schema() {
+    use serde_json::json;
+
+    let block = blocks::BasicBlock {
+        instructions: vec![
+            Instruction::AddInt2(1, 2),
+            Instruction::InvokeStatic {
+                method: 3,
+                nargs: 1,
+                args: [0, 0, 0, 0, 0],
+            },
+            Instruction::ReturnVoid,
+        ],
+        next: blocks::NextBranch::Cond { t: 8, f: 4 },
+    };
+    let value = serde_json::to_value(&block).unwrap();
+    assert_eq!(
+        value,
+        json!({
+            "instructions": [
+                {"op": "AddInt2", "args": [1, 2]},
+                {"op": "InvokeStatic", "args": {"method": 3, "nargs": 1, "args": [0, 0, 0, 0, 0]}},
+                {"op": "ReturnVoid"},
+            ],
+            "next": {"kind": "Cond", "args": {"t": 8, "f": 4}},
+        })
+    );
+    assert_eq!(serde_json::from_value::<blocks::BasicBlock>(value).unwrap(), block);
+
+    assert_eq!(serde_json::to_value(ControlFlow::GoTo(-4)).unwrap(), json!({"kind": "GoTo", "args": -4}));
+    assert_eq!(serde_json::to_value(ControlFlow::Terminate).unwrap(), json!({"kind": "Terminate"}));
+}
+
+#[test]
+fn evaluate() {
+    use eval::{Error, Value};
+    use exceptions::Exception;
+
+    let regs = |reg| match reg {
+        0 => Some(Value::Int(i32::MIN)),
+        1 => Some(Value::Int(-1)),
+        2 => Some(Value::Long(-8)),
+        4 => Some(Value::Float(f32::NAN)),
+        5 => Some(Value::Float(-1e20)),
+        6 => Some(Value::Double(f64::INFINITY)),
+        8 => Some(Value::Int(0x3f80_0000)),
+        _ => None,
+    };
+
+    // wrapping arithmetic and masked shift distances
+    assert_eq!(Instruction::DivInt(9, 0, 1).evaluate(regs), Ok(Value::Int(i32::MIN)));
+    assert_eq!(Instruction::RemInt2(0, 1).evaluate(regs), Ok(Value::Int(0)));
+    assert_eq!(Instruction::NegInt(9, 0).evaluate(regs), Ok(Value::Int(i32::MIN)));
+    assert_eq!(Instruction::ShlLong(9, 2, 1).evaluate(regs), Ok(Value::Long(0)));
+    assert_eq!(Instruction::UshrLong(9, 2, 1).evaluate(regs), Ok(Value::Long(1)));
+    assert_eq!(Instruction::ShrInt8(9, 1, 40).evaluate(regs), Ok(Value::Int(-1)));
+    assert_eq!(Instruction::UshrInt8(9, 1, 28).evaluate(regs), Ok(Value::Int(0xf)));
+    assert_eq!(Instruction::RsubInt16(9, 1, 10).evaluate(regs), Ok(Value::Int(11)));
+    assert_eq!(Instruction::IntTochar(9, 1).evaluate(regs), Ok(Value::Int(0xffff)));
+    assert_eq!(Instruction::IntTobyte(9, 0).evaluate(regs), Ok(Value::Int(0)));
+
+    // saturating conversions
+    assert_eq!(Instruction::FloatToInt(9, 4).evaluate(regs), Ok(Value::Int(0)));
+    assert_eq!(Instruction::FloatToLong(9, 5).evaluate(regs), Ok(Value::Long(i64::MIN)));
+    assert_eq!(Instruction::DoubleToInt(9, 6).evaluate(regs), Ok(Value::Int(i32::MAX)));
+
+    // NaN ordering
+    assert_eq!(Instruction::CmplFloat(9, 4, 5).evaluate(regs), Ok(Value::Int(-1)));
+    assert_eq!(Instruction::CmpgFloat(9, 4, 5).evaluate(regs), Ok(Value::Int(1)));
+    assert_eq!(Instruction::CmpgFloat(9, 5, 8).evaluate(regs), Ok(Value::Int(-1)));
+    assert_eq!(Instruction::CmpLong(9, 2, 2).evaluate(regs), Ok(Value::Int(0)));
+
+    // narrow registers are untyped
+    assert_eq!(Instruction::AddFloat2(8, 8).evaluate(regs), Ok(Value::Float(2.0)));
+    assert!(matches!(Instruction::RemFloat(9, 5, 4).evaluate(regs), Ok(Value::Float(v)) if v.is_nan()));
+
+    assert_eq!(
+        Instruction::DivLong2(2, 2).evaluate(|_| Some(Value::Long(0))),
+        Err(Error::Throws(Exception::Arithmetic))
+    );
+    assert_eq!(Instruction::RemInt16(9, 1, 0).evaluate(regs), Err(Error::Throws(Exception::Arithmetic)));
+    assert_eq!(Instruction::AddLong(9, 2, 1).evaluate(regs), Err(Error::Width(1)));
+    assert_eq!(Instruction::AddInt(9, 1, 3).evaluate(regs), Err(Error::Unknown(3)));
+    assert_eq!(Instruction::Move(9, 1).evaluate(regs), Err(Error::Unsupported));
+}
+
+struct Dex;
+
+impl PrettyPrint for Dex {
+    fn method(&self, index: u16) -> (String, String, String, String) {
+        let (name, params, ret) = match index {
+            0 => ("<init>", "", "V"),
+            _ => ("compute", "IJ", "Ljava/lang/String;"),
+        };
+        ("Lcom/example/Foo;".into(), name.into(), params.into(), ret.into())
+    }
+
+    fn field(&self, _: u16) -> (String, String, String) {
+        ("Lcom/example/Foo;".into(), "count".into(), "I".into())
+    }
+
+    fn string(&self, _: u32) -> String {
+        "say \"hi\"\n".into()
+    }
+
+    fn type_name(&self, index: u16) -> String {
+        match index {
+            0 => "Ljava/lang/String;".into(),
+            2 => "[I".into(),
+            _ => "Ljava/lang/Exception;".into(),
+        }
+    }
+}
+
+#[test]
+fn smali_listing() {
+    use builder::{CodeBuilder, Condition};
+    use patch::TryBlock;
+
+    let mut code = CodeBuilder::new();
+    let [first, second, handler, ret] = [(); 4].map(|_| code.new_label());
+    code.push(Instruction::ConstString(0, 0))
+        .packed_switch(1, -1, &[first, second])
+        .sparse_switch(1, &[(-5, second), (7, ret)])
+        .bind(first)
+        .fill_array_data(2, 2, vec![0xff, 0xff, 1, 0])
+        .bind(second)
+        .if_testz(Condition::Eq, 1, ret)
+        .goto(ret)
+        .bind(handler)
+        .push(Instruction::MoveException(3))
+        .bind(ret)
+        .push(Instruction::Const4(0, -1))
+        .push(Instruction::ConstWide(0, 0x1_0000_0000))
+        .push(Instruction::ConstWideHigh16(0, 0x4000))
+        .push(Instruction::ConstHigh16(0, -0x8000))
+        .push(Instruction::AddInt8(0, 1, -1))
+        .push(Instruction::ReturnVoid);
+    let code = code.build_units().unwrap();
+    let tries = [TryBlock {
+        start_addr: 0,
+        insn_count: 8,
+        handlers: vec![(1, 14)],
+        catch_all_addr: Some(14),
+    }];
+    let expected = "    .registers 4
+
+    :try_start_0
+    const-string v0, \"say \\\"hi\\\"\\n\"
+
+    packed-switch v1, :pswitch_data_0
+
+    sparse-switch v1, :sswitch_data_0
+    :try_end_0
+    .catch Ljava/lang/Exception; {:try_start_0 .. :try_end_0} :catch_0
+    .catchall {:try_start_0 .. :try_end_0} :catchall_0
+
+    :pswitch_0
+    fill-array-data v2, :array_0
+
+    :pswitch_1
+    :sswitch_0
+    if-eqz v1, :cond_0
+
+    goto :goto_0
+
+    :catch_0
+    :catchall_0
+    move-exception v3
+
+    :cond_0
+    :goto_0
+    :sswitch_1
+    const/4 v0, -0x1
+
+    const-wide v0, 0x100000000L
+
+    const-wide/high16 v0, 0x4000000000000000L
+
+    const/high16 v0, -0x80000000
+
+    add-int/lit8 v0, v1, -0x1
+
+    return-void
+
+    :pswitch_data_0
+    .packed-switch -0x1
+        :pswitch_0
+        :pswitch_1
+    .end packed-switch
+
+    :sswitch_data_0
+    .sparse-switch
+        -0x5 -> :sswitch_0
+        0x7 -> :sswitch_1
+    .end sparse-switch
+
+    :array_0
+    .array-data 2
+        -0x1s
+        0x1s
+    .end array-data
+";
+    assert_eq!(smali::listing(&Dex, 4, &code, &tries).unwrap(), expected);
+
+    // and reassembles to the same method
+    struct Symbols;
+    impl assemble::Resolve for Symbols {
+        fn string(&self, contents: &str) -> Option<u32> {
+            (contents == "say \"hi\"\n").then_some(0)
+        }
+        fn type_index(&self, descriptor: &str) -> Option<u16> {
+            (descriptor == "Ljava/lang/Exception;").then_some(1)
+        }
+    }
+    let method = assemble::assemble_method(expected, &Symbols).unwrap();
+    assert_eq!(method.registers, Some(4));
+    assert_eq!(method.insns, code);
+    assert_eq!(method.tries, tries);
+
+    // unreferenced payloads keep their offsets, and their size
+    // return-void; nop; sparse-switch-payload {0x3: +4}
+    let code = [0x000e, 0x0000, 0x0200, 0x0001, 0x0003, 0x0000, 0x0004, 0x0000];
+    let expected = "    .registers 0
+
+    return-void
+
+    nop
+
+    :sswitch_data_0
+    .sparse-switch
+        0x3 -> +4
+    .end sparse-switch
+";
+    assert_eq!(smali::listing(&Dex, 0, &code, &[]).unwrap(), expected);
+    assert_eq!(assemble::assemble_method(expected, &()).unwrap().insns, code);
+}
+
+#[test]
+fn tokens() {
+    use references::Reference;
+    use tokens::{Token, TokenKind};
+
+    let token = |kind, text: &str| Token { kind, text: text.to_string() };
+    let inst = Instruction::InvokeVirtual {
+        method: 1,
+        nargs: 2,
+        args: [4, 5, 0, 0, 0],
+    };
+    assert_eq!(
+        Dex.tokens(&inst),
+        [
+            token(TokenKind::Mnemonic, "invoke-virtual"),
+            token(TokenKind::Punctuation, " {"),
+            token(TokenKind::Register(4), "v4"),
+            token(TokenKind::Punctuation, ", "),
+            token(TokenKind::Register(5), "v5"),
+            token(TokenKind::Punctuation, "}, "),
+            token(TokenKind::Reference(Reference::Method(1)), "Lcom/example/Foo;->compute(IJ)Ljava/lang/String;"),
+        ]
+    );
+    assert_eq!(Dex.print(&inst), "invoke-virtual {v4, v5}, Lcom/example/Foo;->compute(IJ)Ljava/lang/String;");
+
+    // two-register branches separate the target with a comma, as in smali
+    assert_eq!(Instruction::IfEq(9, 14, 27518).to_string(), "if-eq v9, v14, +27518");
+    assert_eq!(Instruction::IfLe(0, 1, -3).to_string(), "if-le v0, v1, -3");
+    assert_eq!(Instruction::IfEqz(9, 4).to_string(), "if-eqz v9, +4");
+
+    // field offsets of optimized instructions are not references
+    let kinds: Vec<_> = Instruction::IGetQuick(1, 2, 8).tokens().into_iter().map(|t| t.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::Mnemonic,
+            TokenKind::Punctuation,
+            TokenKind::Register(1),
+            TokenKind::Punctuation,
+            TokenKind::Register(2),
+            TokenKind::Punctuation,
+            TokenKind::Literal
+        ]
+    );
+
+    let inst = Instruction::SparseSwitchPayload {
+        keys: vec![-1, 3],
+        targets: vec![5, -2],
+    };
+    let kinds: Vec<_> = inst.tokens().into_iter().filter(|t| t.kind != TokenKind::Punctuation).map(|t| t.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::Mnemonic,
+            TokenKind::Literal,
+            TokenKind::Target(5),
+            TokenKind::Literal,
+            TokenKind::Target(-2)
+        ]
+    );
+
+    // the reference kind of a verification error is in its top bits
+    let inst = Instruction::ThrowVerificationError(0x41, 3);
+    assert_eq!(inst.tokens()[4], token(TokenKind::Reference(Reference::Type(3)), "ref@3"));
+    assert_eq!(Dex.print(&inst), "throw-verification-error 0x41, Ljava/lang/Exception;");
+    assert_eq!(Instruction::ThrowVerificationError(0x01, 3).tokens()[4].kind, TokenKind::Literal);
+}
+
+#[test]
+fn display_at() {
+    use std::collections::HashMap;
+    use tokens::DisplayAt;
+
+    assert_eq!(Instruction::PackedSwitch(2, 0x10).display_at(0x1e).to_string(), "packed-switch v2, 0x002e");
+    assert_eq!(Instruction::FillArrayData(0, 6).display_at(0).to_string(), "fill-array-data v0, 0x0006");
+    assert_eq!(Instruction::Goto32(0x10000).display_at(0x4).to_string(), "goto/32 0x10004");
+    // no address to go back to
+    assert_eq!(Instruction::Goto(-6).display_at(2).to_string(), "goto -6");
+    // not a branch
+    assert_eq!(Instruction::Const4(0, 1).display_at(0x20).to_string(), "const/4 v0, 0x1");
+
+    let labels = HashMap::from([(0x20, "L1".to_string())]);
+    let inst = Instruction::PackedSwitchPayload {
+        first_key: 0,
+        targets: vec![0x10, 0x12],
+    };
+    assert_eq!(
+        inst.display_at(0x10).with_labels(&labels).to_string(),
+        "packed-switch-payload 0x0, {L1, 0x0022}"
+    );
+
+    let inst = Instruction::IfEqz(0, 4);
+    let at = DisplayAt::new(Dex.tokens(&inst), 0x1c).with_labels(&labels);
+    assert_eq!(at.to_string(), "if-eqz v0, L1");
+}
+
+#[test]
+fn register_names() {
+    use tokens::{DisplayAt, RegisterNames};
+
+    // static compute(IJ) with 3 locals: int in p0, long in p1/p2
+    let names = RegisterNames::new(6, 3, true);
+    assert_eq!(names.with_this(), names);
+    let inst = Instruction::InvokeStaticRange {
+        method: 1,
+        args: vec![3, 4, 5],
+    };
+    assert_eq!(
+        DisplayAt::new(Dex.tokens(&inst), 0).with_registers(names).to_string(),
+        "invoke-static/range {p0, p1, p2}, Lcom/example/Foo;->compute(IJ)Ljava/lang/String;"
+    );
+    let inst = Instruction::AddLong2(0, 4);
+    assert_eq!(inst.display_at(0).with_registers(names).to_string(), "add-long/2addr v0, p1");
+
+    // instance method without locals
+    let names = RegisterNames::new(1, 1, false).with_this();
+    assert_eq!(names.name(0), "this");
+    assert_eq!(
+        Instruction::ReturnObject(0).display_at(0).with_registers(names).to_string(),
+        "return-object this"
+    );
+}
+
+#[test]
+fn pseudocode() {
+    use std::collections::HashMap;
+    use tokens::DisplayAt;
+
+    let cases = [
+        (Instruction::AddInt(0, 1, 2), "v0 = v1 + v2"),
+        (Instruction::ShrLong2(4, 6), "v4 = v4 >> v6"),
+        (Instruction::RsubInt8(0, 1, -3), "v0 = -3 - v1"),
+        (Instruction::IntTochar(1, 1), "v1 = (char) v1"),
+        (Instruction::ConstWide16(0, -1), "v0 = -1L"),
+        (Instruction::IGet(3, 2, 0), "v3 = v2.count"),
+        (Instruction::SPut(0, 0), "Foo.count = v0"),
+        (Instruction::APutObject(0, 1, 2), "v1[v2] = v0"),
+        (Instruction::CheckCast(1, 0), "v1 = (String) v1"),
+        (Instruction::NewArray(0, 1, 2), "v0 = new int[v1]"),
+        (Instruction::ConstString(0, 0), "v0 = \"say \\\"hi\\\"\\n\""),
+        (Instruction::IfNez(16, 1025), "if (v16 != 0) goto +1025"),
+        (Instruction::MonitorEnter(0), "monitor-enter v0"),
+        (
+            // the long argument takes v6 and v7
+            Instruction::InvokeVirtual {
+                method: 1,
+                nargs: 4,
+                args: [4, 5, 6, 7, 0],
+            },
+            "v4.compute(v5, v6)",
+        ),
+        (
+            Instruction::InvokeStaticRange {
+                method: 1,
+                args: vec![5, 6, 7],
+            },
+            "Foo.compute(v5, v6)",
+        ),
+        (
+            Instruction::InvokeSuper {
+                method: 0,
+                nargs: 1,
+                args: [0, 0, 0, 0, 0],
+            },
+            "super.<init>()",
+        ),
+    ];
+    for (inst, expected) in cases {
+        assert_eq!(Dex.pseudocode(&inst), expected);
+    }
+
+    let labels = HashMap::from([(0x20, "L1".to_string())]);
+    let inst = Instruction::IfLt(0, 1, 0x10);
+    let at = DisplayAt::new(Dex.pseudocode_tokens(&inst), 0x10).with_labels(&labels);
+    assert_eq!(at.to_string(), "if (v0 < v1) goto L1");
+
+    // a call and its move-result make up one statement
+    let insts = [
+        Instruction::InvokeVirtual {
+            method: 1,
+            nargs: 3,
+            args: [4, 5, 6, 0, 0],
+        },
+        Instruction::MoveResultObject(0),
+        Instruction::MoveResult(1),
+        Instruction::Return(1),
+    ];
+    let statements: Vec<_> = pseudocode::statements(&Dex, &insts)
+        .into_iter()
+        .map(|(i, tokens)| (i, tokens.into_iter().map(|t| t.text).collect::<String>()))
+        .collect();
+    assert_eq!(
+        statements,
+        [
+            (0, "v0 = v4.compute(v5, v6)".to_string()),
+            (2, "v1 = result".to_string()),
+            (3, "return v1".to_string())
+        ]
+    );
+}
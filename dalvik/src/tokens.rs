@@ -0,0 +1,325 @@
+//! Structured instruction rendering
+//!
+//! [`Instruction::tokens`] and [`PrettyPrint::tokens`] render instructions as
+//! typed [`Token`]s, so that plain text, HTML, DOT labels or ANSI colored output
+//! are all produced from the same rendering.
+//! [`Display`][`std::fmt::Display`] and [`PrettyPrint::print`] are the
+//! concatenated texts of these tokens. [`Instruction::display_at`] renders them with
+//! absolute branch targets instead, and [`RegisterNames`] names parameter
+//! registers `pN`.
+//!
+//! ```
+//! use dalvik::tokens::{Token, TokenKind};
+//! use dalvik::Instruction;
+//!
+//! let html: String = Instruction::IfEqz(3, 8)
+//!     .tokens()
+//!     .into_iter()
+//!     .map(|Token { kind, text }| match kind {
+//!         TokenKind::Register(_) => format!("<var>{text}</var>"),
+//!         TokenKind::Target(_) => format!("<a>{text}</a>"),
+//!         _ => text,
+//!     })
+//!     .collect();
+//! assert_eq!(html, "if-eqz <var>v3</var>, <a>+8</a>");
+//! ```
+
+use std::collections::HashMap;
+
+use crate::{references::Reference, Instruction, PrettyPrint};
+
+/// A piece of rendered instruction text
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Token {
+    /// What the text stands for
+    pub kind: TokenKind,
+    /// The rendered text
+    pub text: String,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Kind of a [`Token`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TokenKind {
+    /// Instruction mnemonic, e.g. `invoke-virtual/range`
+    Mnemonic,
+    /// Pseudo-code keyword, e.g. `return`
+    Keyword,
+    /// A register, given by its number
+    Register(u16),
+    /// A literal value, or a runtime-specific index like a vtable slot
+    Literal,
+    /// A branch, switch case or payload reference, given by its offset
+    /// relative to the branching instruction
+    Target(i32),
+    /// A string, type, field, method or other constant pool reference
+    Reference(Reference),
+    /// Separators, braces and whitespace between the other tokens
+    Punctuation,
+}
+
+/// Builder of the tokens of an instruction, separating its operands with `, `
+pub(crate) struct Tokens(Vec<Token>);
+
+impl Tokens {
+    pub(crate) fn new(mnemonic: &str) -> Self {
+        Tokens(vec![Token {
+            kind: TokenKind::Mnemonic,
+            text: mnemonic.to_string(),
+        }])
+    }
+
+    pub(crate) fn into_vec(self) -> Vec<Token> {
+        self.0
+    }
+
+    /// Append punctuation, merged with the punctuation before it
+    pub(crate) fn punctuation(mut self, text: &str) -> Self {
+        match self.0.last_mut() {
+            Some(Token {
+                kind: TokenKind::Punctuation,
+                text: last,
+            }) => last.push_str(text),
+            _ => self.0.push(Token {
+                kind: TokenKind::Punctuation,
+                text: text.to_string(),
+            }),
+        }
+        self
+    }
+
+    // the separator before the next operand
+    fn separate(self) -> Self {
+        match self.0.last() {
+            Some(Token { kind: TokenKind::Mnemonic, .. }) => self.punctuation(" "),
+            Some(Token {
+                kind: TokenKind::Punctuation,
+                text,
+            }) if text.ends_with(['{', ' ']) => self,
+            _ => self.punctuation(", "),
+        }
+    }
+
+    pub(crate) fn push(self, kind: TokenKind, text: String) -> Self {
+        let mut tokens = self.separate();
+        tokens.0.push(Token { kind, text });
+        tokens
+    }
+
+    pub(crate) fn register(self, reg: impl Into<u16>) -> Self {
+        let reg = reg.into();
+        self.push(TokenKind::Register(reg), format!("v{reg}"))
+    }
+
+    pub(crate) fn literal(self, text: String) -> Self {
+        self.push(TokenKind::Literal, text)
+    }
+
+    pub(crate) fn target(self, off: impl Into<i32>) -> Self {
+        let off = off.into();
+        self.push(TokenKind::Target(off), format!("{off:+}"))
+    }
+
+    pub(crate) fn reference(self, reference: Reference) -> Self {
+        self.push(TokenKind::Reference(reference), reference.to_string())
+    }
+
+    /// Start a `{...}` list operand
+    pub(crate) fn open(self) -> Self {
+        self.separate().punctuation("{")
+    }
+
+    pub(crate) fn close(self) -> Self {
+        self.punctuation("}")
+    }
+
+    /// A `{vA, vB, ...}` register list operand
+    pub(crate) fn registers<R: Into<u16>>(self, regs: impl IntoIterator<Item = R>) -> Self {
+        regs.into_iter().fold(self.open(), Self::register).close()
+    }
+}
+
+/// Register naming of a method, rendering its parameter registers as `pN` like
+/// baksmali
+///
+/// Parameters are passed in the last `ins_size` of the method's registers,
+/// starting with `this` for instance methods. A wide parameter takes two
+/// consecutive `pN`.
+///
+/// ```
+/// use dalvik::tokens::RegisterNames;
+/// use dalvik::Instruction;
+///
+/// // an instance method taking a long, with 2 locals
+/// let names = RegisterNames::new(5, 3, false);
+/// assert_eq!(names.name(1), "v1");
+/// assert_eq!(names.name(2), "p0");
+/// assert_eq!(names.name(4), "p2");
+///
+/// let inst = Instruction::IPutWide(3, 2, 0);
+/// assert_eq!(inst.display_at(0).with_registers(names).to_string(), "iput-wide p1, p0, field@0");
+/// assert_eq!(inst.display_at(0).with_registers(names.with_this()).to_string(), "iput-wide p1, this, field@0");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RegisterNames {
+    first_param: u16,
+    instance: bool,
+    this: bool,
+}
+
+impl RegisterNames {
+    /// Naming for a method with `registers_size` registers, of which
+    /// `ins_size` are parameters
+    pub fn new(registers_size: u16, ins_size: u16, is_static: bool) -> Self {
+        RegisterNames {
+            first_param: registers_size.saturating_sub(ins_size),
+            instance: !is_static && ins_size > 0,
+            this: false,
+        }
+    }
+
+    /// Name `p0` of an instance method `this`
+    pub fn with_this(self) -> Self {
+        RegisterNames { this: self.instance, ..self }
+    }
+
+    /// Name of the register, `vN`, `pN` or `this`
+    pub fn name(&self, reg: u16) -> String {
+        match reg.checked_sub(self.first_param) {
+            Some(0) if self.this => "this".to_string(),
+            Some(param) => format!("p{param}"),
+            None => format!("v{reg}"),
+        }
+    }
+
+    /// Rename the [`TokenKind::Register`] tokens
+    pub fn rename(&self, tokens: &mut [Token]) {
+        for token in tokens {
+            if let TokenKind::Register(reg) = token.kind {
+                token.text = self.name(reg);
+            }
+        }
+    }
+}
+
+/// Display adapter rendering branch targets as absolute addresses, see
+/// [`Instruction::display_at`]
+#[derive(Debug, Clone)]
+pub struct DisplayAt<'a> {
+    tokens: Vec<Token>,
+    addr: usize,
+    labels: Option<&'a HashMap<usize, String>>,
+}
+
+impl DisplayAt<'_> {
+    /// Display `tokens` of the instruction at `addr`, e.g. those of
+    /// [`PrettyPrint::tokens`]
+    pub fn new(tokens: Vec<Token>, addr: usize) -> Self {
+        DisplayAt { tokens, addr, labels: None }
+    }
+
+    /// Render registers with `names`
+    pub fn with_registers(mut self, names: RegisterNames) -> Self {
+        names.rename(&mut self.tokens);
+        self
+    }
+
+    /// Render targets found in `labels` as the label instead of the address
+    pub fn with_labels(self, labels: &HashMap<usize, String>) -> DisplayAt<'_> {
+        DisplayAt {
+            tokens: self.tokens,
+            addr: self.addr,
+            labels: Some(labels),
+        }
+    }
+}
+
+impl std::fmt::Display for DisplayAt<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for token in &self.tokens {
+            let TokenKind::Target(off) = token.kind else {
+                f.write_str(&token.text)?;
+                continue;
+            };
+            match self.addr.checked_add_signed(off as isize) {
+                Some(to) => match self.labels.and_then(|labels| labels.get(&to)) {
+                    Some(label) => f.write_str(label)?,
+                    None => f.write_fmt(format_args!("{to:#06x}"))?,
+                },
+                None => f.write_str(&token.text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Instruction {
+    /// Display the instruction located at `addr`, with branch, switch and
+    /// array data targets as absolute addresses like `dexdump -d`
+    ///
+    /// Switch payload targets are relative to the switch, so for payloads
+    /// `addr` is that of the switch referencing it.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use dalvik::Instruction;
+    ///
+    /// assert_eq!(Instruction::Goto(-6).display_at(0x50).to_string(), "goto 0x004a");
+    ///
+    /// let labels = HashMap::from([(0x4a, ":loop".to_string())]);
+    /// let inst = Instruction::IfNez(16, -6);
+    /// assert_eq!(inst.display_at(0x50).with_labels(&labels).to_string(), "if-nez v16, :loop");
+    /// ```
+    pub fn display_at(&self, addr: usize) -> DisplayAt<'static> {
+        DisplayAt::new(self.tokens(), addr)
+    }
+}
+
+/// The [`PrettyPrint::print`] text of a constant pool reference
+pub(crate) fn reference_text<T: PrettyPrint + ?Sized>(lookup: &T, reference: Reference) -> String {
+    match reference {
+        Reference::String(idx) => format!("\"{}\"", lookup.string(idx)),
+        Reference::Type(idx) => lookup.type_name(idx),
+        Reference::Field(idx) => {
+            let (class, name, ty) = lookup.field(idx);
+            format!("{class}->{name}:{ty}")
+        }
+        Reference::Method(idx) => {
+            let (class, name, params, ret) = lookup.method(idx);
+            format!("{class}->{name}({params}){ret}")
+        }
+        Reference::Proto(idx) => lookup.proto(idx),
+        Reference::CallSite(idx) => lookup.call_site(idx),
+        Reference::MethodHandle(idx) => lookup.method_handle(idx),
+    }
+}
+
+/// String literal contents in smali syntax, with quotes, backslashes and
+/// control characters escaped
+pub(crate) fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '"' | '\'' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' '..='~' => out.push(c),
+            _ => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    out.push_str(&format!("\\u{unit:04x}"));
+                }
+            }
+        }
+    }
+    out
+}
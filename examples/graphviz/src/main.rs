@@ -1,7 +1,7 @@
 use std::{collections::HashSet, path::PathBuf};
 
 use clap::Parser;
-use dex::{jtype::TypeId, Endian};
+use dex::jtype::TypeId;
 
 #[derive(Debug, Parser)]
 struct Args {
@@ -14,6 +14,10 @@ struct Args {
 
     /// Method to dump with try/catch indentation
     method: String,
+
+    /// Show Java-like statements instead of smali
+    #[arg(long)]
+    pseudocode: bool,
 }
 
 impl Args {
@@ -31,12 +35,9 @@ fn main() {
     let args = Args::parse().normalize();
 
     let dex_bytes = std::fs::read(args.file).unwrap();
+    // `dex` byte swaps code items according to the header's endian tag, so
+    // `insns` are native u16 codepoints regardless of the file's byte order
     let dex = dex::DexReader::from_vec(&dex_bytes).unwrap();
-    assert_eq!(
-        dex.get_endian(),
-        Endian::Little,
-        "Big Endian dex file encountered. TODO: idk if bytecodes are swapped in big endian modes or not"
-    );
 
     let class = dex.find_class_by_name(&args.class).unwrap().unwrap();
 
@@ -47,10 +48,10 @@ fn main() {
         .find(|m| m.name().as_ref() == args.method)
         .unwrap();
 
-    dump_graphviz(method, &dex, &dex_bytes);
+    dump_graphviz(method, &dex, &dex_bytes, args.pseudocode);
 }
 
-fn dump_graphviz<T: AsRef<[u8]>>(method: &dex::method::Method, dex: &dex::Dex<T>, bytes: &[u8]) {
+fn dump_graphviz<T: AsRef<[u8]>>(method: &dex::method::Method, dex: &dex::Dex<T>, bytes: &[u8], pseudocode: bool) {
     let Some(code) = method.code() else {
         return;
     };
@@ -74,13 +75,22 @@ fn dump_graphviz<T: AsRef<[u8]>>(method: &dex::method::Method, dex: &dex::Dex<T>
         .collect::<Vec<_>>();
     catch_addrs.sort_unstable();
 
-    let basic_blocks = dalvik::blocks::basic_blocks(bytecode, &catch_addrs);
+    let basic_blocks = dalvik::blocks::basic_blocks(bytecode, &catch_addrs).unwrap();
 
     let mut disassembly = String::new();
     for (id, bb) in &basic_blocks {
         disassembly.push_str(&format!("    {id} [label=\""));
-        for inst in &bb.instructions {
-            disassembly.push_str(&mylookup.print(&inst).replace('"', "\\\""));
+        let lines = match pseudocode {
+            true => dalvik::pseudocode::statements(&mylookup, &bb.instructions)
+                .into_iter()
+                .map(|(_, tokens)| tokens)
+                .collect(),
+            false => bb.instructions.iter().map(|inst| mylookup.tokens(inst)).collect::<Vec<_>>(),
+        };
+        for tokens in lines {
+            for token in tokens {
+                disassembly.push_str(&escape_dot(&token.text));
+            }
             disassembly.push_str("\\l");
         }
         disassembly.push_str("\"]");
@@ -106,7 +116,7 @@ fn dump_graphviz<T: AsRef<[u8]>>(method: &dex::method::Method, dex: &dex::Dex<T>
             println!("    catch{c} [label=\"catch {exception}\"]");
 
             // draw all edges to this catch node
-            for (addr, _block) in &basic_blocks {
+            for addr in basic_blocks.keys() {
                 if *addr >= first_addr && *addr < last_addr {
                     println!("    {addr} -> catch{c} [style=dashed]");
                 }
@@ -139,6 +149,11 @@ fn dump_graphviz<T: AsRef<[u8]>>(method: &dex::method::Method, dex: &dex::Dex<T>
     println!("}}");
 }
 
+// escape text for a quoted DOT label
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
 struct MyLookup<'a, T> {
     dex: &'a dex::Dex<T>,
     bytes: &'a [u8],
@@ -172,8 +187,22 @@ impl<'a, T: AsRef<[u8]>> dalvik::PrettyPrint for MyLookup<'a, T> {
         (class.to_string(), name.to_string(), ty.to_string())
     }
 
+    fn proto(&self, index: u16) -> String {
+        let proto = self.dex.get_proto_item(index.into()).unwrap();
+        let params = param_type_ids(self.bytes, proto.params_off());
+        let mut paramstr = String::new();
+        for p in params {
+            let param = self.dex.get_type(p).unwrap();
+            paramstr.push_str(&param.to_string());
+        }
+
+        let ret = self.dex.get_type(proto.return_type()).unwrap();
+
+        format!("({paramstr}){ret}")
+    }
+
     fn string(&self, index: u32) -> String {
-        self.dex.get_string(index.into()).unwrap().to_string()
+        self.dex.get_string(index).unwrap().to_string()
     }
 
     fn type_name(&self, index: u16) -> String {